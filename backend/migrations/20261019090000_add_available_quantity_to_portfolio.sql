@@ -0,0 +1,15 @@
+-- Shares held against open sell orders are reserved by lowering available_quantity,
+-- mirroring how available_balance works for cash on the users table.
+ALTER TABLE portfolio ADD COLUMN available_quantity DECIMAL NOT NULL DEFAULT 0;
+
+UPDATE portfolio p SET available_quantity = GREATEST(
+    p.quantity - COALESCE((
+        SELECT SUM(o.quantity) FROM orders o
+        WHERE o.user_id = p.user_id AND o.ticker = p.ticker
+          AND o.order_type = 'SELL' AND o.status = 'PENDING'
+    ), 0),
+    0
+);
+
+ALTER TABLE portfolio ADD CONSTRAINT portfolio_available_quantity_check
+    CHECK (available_quantity >= 0 AND available_quantity <= quantity);
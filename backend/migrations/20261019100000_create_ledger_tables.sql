@@ -0,0 +1,82 @@
+CREATE TYPE journal_entry_type AS ENUM (
+    'OPENING_BALANCE', 'DEPOSIT', 'WITHDRAWAL', 'RESERVATION', 'TRADE_BUY', 'TRADE_SELL',
+    'FEE', 'LOAN_DISBURSEMENT', 'LOAN_REPAYMENT', 'RESET'
+);
+CREATE TYPE ledger_account AS ENUM (
+    'USER_CASH', 'USER_RESERVED', 'EXTERNAL_BANK', 'MARKET', 'FEES', 'LOANS', 'EQUITY'
+);
+
+CREATE TABLE journal_entries (
+    entry_id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(user_id) ON DELETE RESTRICT,
+    entry_type journal_entry_type NOT NULL,
+    reference_id UUID,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE journal_lines (
+    line_id UUID PRIMARY KEY,
+    entry_id UUID NOT NULL REFERENCES journal_entries(entry_id) ON DELETE RESTRICT,
+    account ledger_account NOT NULL,
+    amount DECIMAL(15, 4) NOT NULL,
+    CONSTRAINT journal_line_amount_check CHECK (amount <> 0)
+);
+
+CREATE INDEX idx_journal_entries_user_id ON journal_entries(user_id, created_at);
+CREATE INDEX idx_journal_lines_entry_id ON journal_lines(entry_id);
+
+-- The ledger is append-only: corrections are made with new entries, never by editing old ones.
+CREATE FUNCTION reject_ledger_mutation() RETURNS TRIGGER AS $$
+BEGIN
+    RAISE EXCEPTION 'ledger tables are append-only';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER journal_entries_append_only BEFORE UPDATE OR DELETE ON journal_entries
+    FOR EACH ROW EXECUTE FUNCTION reject_ledger_mutation();
+CREATE TRIGGER journal_lines_append_only BEFORE UPDATE OR DELETE ON journal_lines
+    FOR EACH ROW EXECUTE FUNCTION reject_ledger_mutation();
+
+-- users.balance and users.available_balance become a cache of the ledger, so new users start
+-- at zero and are funded through an OPENING_BALANCE entry.
+ALTER TABLE users ALTER COLUMN balance SET DEFAULT 0;
+ALTER TABLE users ALTER COLUMN available_balance SET DEFAULT 0;
+
+-- Open the ledger with each existing user's current balances. Cash held for a pending buy is
+-- opened as available cash and then reserved against its order, so the reservation can be
+-- released when the order fills or is cancelled; anything else already reserved is opened as is.
+CREATE TEMPORARY TABLE pending_reservations AS
+SELECT gen_random_uuid() AS entry_id, user_id, order_id, quantity * price_per_share AS amount
+FROM orders
+WHERE status = 'PENDING' AND order_type = 'BUY' AND quantity * price_per_share <> 0;
+
+CREATE TEMPORARY TABLE opening_entries AS
+SELECT gen_random_uuid() AS entry_id, u.user_id,
+       COALESCE(u.available_balance, 0) + COALESCE(r.amount, 0) AS cash,
+       COALESCE(u.balance, 0) - COALESCE(u.available_balance, 0) - COALESCE(r.amount, 0) AS reserved
+FROM users u
+LEFT JOIN (
+    SELECT user_id, SUM(amount) AS amount FROM pending_reservations GROUP BY user_id
+) r ON r.user_id = u.user_id;
+
+INSERT INTO journal_entries (entry_id, user_id, entry_type)
+SELECT entry_id, user_id, 'OPENING_BALANCE' FROM opening_entries WHERE cash <> 0 OR reserved <> 0;
+
+INSERT INTO journal_lines (line_id, entry_id, account, amount)
+SELECT gen_random_uuid(), entry_id, 'USER_CASH'::ledger_account, cash FROM opening_entries WHERE cash <> 0
+UNION ALL
+SELECT gen_random_uuid(), entry_id, 'USER_RESERVED', reserved FROM opening_entries WHERE reserved <> 0
+UNION ALL
+SELECT gen_random_uuid(), entry_id, 'EQUITY', -(cash + reserved) FROM opening_entries WHERE cash + reserved <> 0;
+
+INSERT INTO journal_entries (entry_id, user_id, entry_type, reference_id, created_at)
+SELECT entry_id, user_id, 'RESERVATION', order_id, NOW() + INTERVAL '1 microsecond'
+FROM pending_reservations;
+
+INSERT INTO journal_lines (line_id, entry_id, account, amount)
+SELECT gen_random_uuid(), entry_id, 'USER_CASH'::ledger_account, -amount FROM pending_reservations
+UNION ALL
+SELECT gen_random_uuid(), entry_id, 'USER_RESERVED', amount FROM pending_reservations;
+
+DROP TABLE opening_entries;
+DROP TABLE pending_reservations;
@@ -0,0 +1,13 @@
+CREATE TABLE idempotency_keys (
+    user_id UUID NOT NULL REFERENCES users(user_id),
+    idempotency_key VARCHAR(255) NOT NULL,
+    request_hash CHAR(64) NOT NULL,
+    --null until the first request with this key has finished
+    response_status SMALLINT,
+    response_content_type TEXT,
+    response_body BYTEA,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, idempotency_key)
+);
+
+CREATE INDEX idx_idempotency_keys_created_at ON idempotency_keys(created_at);
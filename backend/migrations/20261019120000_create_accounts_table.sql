@@ -0,0 +1,48 @@
+-- Users can hold several sub-accounts (e.g. "long-term", "day trading"), each with its own cash,
+-- positions, orders and history. Every user keeps a default account whose account_id is the
+-- user's own id, so existing data, loans and the system user carry over unchanged.
+CREATE TABLE accounts (
+    account_id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(user_id) ON DELETE CASCADE,
+    name VARCHAR(64) NOT NULL,
+    balance DECIMAL(15, 4) NOT NULL DEFAULT 0,
+    available_balance DECIMAL(15, 4) NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT unique_user_account_name UNIQUE (user_id, name)
+);
+
+CREATE INDEX idx_accounts_user_id ON accounts(user_id);
+
+INSERT INTO accounts (account_id, user_id, name, balance, available_balance, created_at)
+SELECT user_id, user_id, 'default', COALESCE(balance, 0), COALESCE(available_balance, 0),
+       COALESCE(created_at, NOW())
+FROM users;
+
+-- cash now lives on the account
+ALTER TABLE users DROP COLUMN balance;
+ALTER TABLE users DROP COLUMN available_balance;
+
+ALTER TABLE portfolio RENAME COLUMN user_id TO account_id;
+ALTER TABLE portfolio DROP CONSTRAINT portfolio_user_id_fkey;
+ALTER TABLE portfolio ADD CONSTRAINT portfolio_account_id_fkey
+    FOREIGN KEY (account_id) REFERENCES accounts(account_id) ON DELETE CASCADE;
+ALTER TABLE portfolio RENAME CONSTRAINT unique_user_ticker TO unique_account_ticker;
+ALTER INDEX idx_userid_portfolio RENAME TO idx_portfolio_account_id;
+
+ALTER TABLE orders RENAME COLUMN user_id TO account_id;
+ALTER TABLE orders DROP CONSTRAINT orders_user_id_fkey;
+ALTER TABLE orders ADD CONSTRAINT orders_account_id_fkey
+    FOREIGN KEY (account_id) REFERENCES accounts(account_id) ON DELETE RESTRICT;
+ALTER INDEX idx_orders_user_id RENAME TO idx_orders_account_id;
+
+ALTER TABLE transactions RENAME COLUMN user_id TO account_id;
+
+ALTER TABLE journal_entries RENAME COLUMN user_id TO account_id;
+ALTER TABLE journal_entries DROP CONSTRAINT journal_entries_user_id_fkey;
+ALTER TABLE journal_entries ADD CONSTRAINT journal_entries_account_id_fkey
+    FOREIGN KEY (account_id) REFERENCES accounts(account_id) ON DELETE RESTRICT;
+ALTER INDEX idx_journal_entries_user_id RENAME TO idx_journal_entries_account_id;
+
+-- transfers between a user's accounts clear through their own ledger account
+ALTER TYPE journal_entry_type ADD VALUE 'TRANSFER';
+ALTER TYPE ledger_account ADD VALUE 'TRANSFERS';
@@ -0,0 +1,29 @@
+-- admin-only endpoints (reconciliation, corporate actions) check this flag
+ALTER TABLE users ADD COLUMN is_admin BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TYPE reconciliation_break_type AS ENUM (
+    'POSITION_QUANTITY', 'RESERVED_QUANTITY', 'CASH_BALANCE', 'AVAILABLE_CASH',
+    'MISSING_FROM_ORDER_BOOK', 'STALE_IN_ORDER_BOOK', 'ORDER_BOOK_QUANTITY', 'SYSTEM_INVENTORY'
+);
+
+CREATE TABLE reconciliation_reports (
+    report_id UUID PRIMARY KEY,
+    auto_repair BOOLEAN NOT NULL,
+    started_at TIMESTAMPTZ NOT NULL,
+    completed_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE TABLE reconciliation_breaks (
+    break_id UUID PRIMARY KEY,
+    report_id UUID NOT NULL REFERENCES reconciliation_reports(report_id) ON DELETE CASCADE,
+    break_type reconciliation_break_type NOT NULL,
+    account_id UUID,
+    ticker VARCHAR(255),
+    order_id UUID,
+    expected DECIMAL NOT NULL,
+    actual DECIMAL NOT NULL,
+    repaired BOOLEAN NOT NULL DEFAULT FALSE
+);
+
+CREATE INDEX idx_reconciliation_reports_started_at ON reconciliation_reports(started_at DESC);
+CREATE INDEX idx_reconciliation_breaks_report_id ON reconciliation_breaks(report_id);
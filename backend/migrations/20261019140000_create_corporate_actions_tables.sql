@@ -0,0 +1,44 @@
+-- Cash dividends and (reverse) splits scheduled by an admin and applied on their effective date.
+-- A split turns old_shares into new_shares, e.g. 2-for-1 is new_shares = 2, old_shares = 1.
+CREATE TYPE corporate_action_type AS ENUM ('DIVIDEND', 'SPLIT', 'REVERSE_SPLIT');
+CREATE TYPE corporate_action_status AS ENUM ('SCHEDULED', 'PROCESSED', 'CANCELLED');
+
+CREATE TABLE corporate_actions (
+    action_id UUID PRIMARY KEY,
+    ticker VARCHAR(16) NOT NULL,
+    action_type corporate_action_type NOT NULL,
+    effective_date DATE NOT NULL,
+    dividend_per_share DECIMAL(15, 4),
+    new_shares INTEGER,
+    old_shares INTEGER,
+    status corporate_action_status NOT NULL DEFAULT 'SCHEDULED',
+    created_by UUID REFERENCES users(user_id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    processed_at TIMESTAMPTZ,
+    CONSTRAINT corporate_action_terms_check CHECK (
+        (action_type = 'DIVIDEND' AND dividend_per_share > 0
+            AND new_shares IS NULL AND old_shares IS NULL)
+        OR (action_type = 'SPLIT' AND dividend_per_share IS NULL
+            AND old_shares > 0 AND new_shares > old_shares)
+        OR (action_type = 'REVERSE_SPLIT' AND dividend_per_share IS NULL
+            AND new_shares > 0 AND new_shares < old_shares)
+    )
+);
+
+CREATE INDEX idx_corporate_actions_due ON corporate_actions(effective_date) WHERE status = 'SCHEDULED';
+
+-- what each action did to an account, so positions still reconcile against transactions
+CREATE TABLE corporate_action_adjustments (
+    action_id UUID NOT NULL REFERENCES corporate_actions(action_id) ON DELETE RESTRICT,
+    account_id UUID NOT NULL REFERENCES accounts(account_id) ON DELETE CASCADE,
+    ticker VARCHAR(16) NOT NULL,
+    quantity_before DECIMAL NOT NULL,
+    quantity_after DECIMAL NOT NULL,
+    cash_amount DECIMAL(15, 4) NOT NULL DEFAULT 0,
+    PRIMARY KEY (action_id, account_id)
+);
+
+CREATE INDEX idx_corporate_action_adjustments_account ON corporate_action_adjustments(account_id, ticker);
+
+ALTER TYPE journal_entry_type ADD VALUE 'DIVIDEND';
+ALTER TYPE ledger_account ADD VALUE 'DIVIDENDS';
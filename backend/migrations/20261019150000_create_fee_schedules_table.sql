@@ -0,0 +1,33 @@
+-- Commission schedules assigned per user tier. Rates are fractions of notional; a negative
+-- maker_rate is a rebate paid to the order that was resting in the book.
+CREATE TABLE fee_schedules (
+    tier VARCHAR(32) PRIMARY KEY,
+    flat_fee DECIMAL(15, 4) NOT NULL DEFAULT 0,
+    per_share_fee DECIMAL(15, 6) NOT NULL DEFAULT 0,
+    taker_rate DECIMAL(10, 6) NOT NULL DEFAULT 0,
+    maker_rate DECIMAL(10, 6) NOT NULL DEFAULT 0,
+    minimum_fee DECIMAL(15, 4) NOT NULL DEFAULT 0,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT fee_schedule_amounts_check CHECK (
+        flat_fee >= 0 AND per_share_fee >= 0 AND taker_rate >= 0 AND minimum_fee >= 0
+            AND maker_rate <= taker_rate
+    )
+);
+
+INSERT INTO fee_schedules (tier, flat_fee, per_share_fee, taker_rate, maker_rate, minimum_fee) VALUES
+    ('STANDARD', 0, 0.005, 0, 0, 1.00),
+    ('ACTIVE_TRADER', 0, 0, 0.0005, -0.0002, 0.50),
+    ('COMMISSION_FREE', 0, 0, 0, 0, 0);
+
+ALTER TABLE users ADD COLUMN fee_tier VARCHAR(32) NOT NULL DEFAULT 'STANDARD'
+    REFERENCES fee_schedules(tier) ON UPDATE CASCADE;
+
+CREATE TYPE liquidity AS ENUM ('MAKER', 'TAKER');
+
+-- fees are charged per fill and kept apart from the execution price
+ALTER TABLE transactions ADD COLUMN order_id UUID;
+ALTER TABLE transactions ADD COLUMN liquidity liquidity;
+ALTER TABLE transactions ADD COLUMN fee DECIMAL(15, 4) NOT NULL DEFAULT 0;
+CREATE INDEX idx_transactions_order_id ON transactions(order_id);
+
+ALTER TABLE portfolio ADD COLUMN total_fees DECIMAL(15, 4) NOT NULL DEFAULT 0;
@@ -0,0 +1,27 @@
+-- Idle cash earns interest at tiered annual rates. Each tier's rate applies to the part of the
+-- balance above its min_balance and below the next tier's, like tax brackets.
+CREATE TABLE interest_rate_tiers (
+    min_balance DECIMAL(15, 4) PRIMARY KEY,
+    annual_rate DECIMAL(8, 6) NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT interest_rate_tier_check CHECK (min_balance >= 0 AND annual_rate >= 0)
+);
+
+INSERT INTO interest_rate_tiers (min_balance, annual_rate) VALUES
+    (0, 0.005),
+    (10000, 0.02),
+    (100000, 0.035);
+
+-- one accrual per account and day, so a rerun of the job can't pay the same day twice
+CREATE TABLE interest_accruals (
+    accrual_id UUID PRIMARY KEY,
+    account_id UUID NOT NULL REFERENCES accounts(account_id) ON DELETE CASCADE,
+    accrual_date DATE NOT NULL,
+    balance DECIMAL(15, 4) NOT NULL,
+    amount DECIMAL(15, 4) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT unique_account_accrual_date UNIQUE (account_id, accrual_date)
+);
+
+ALTER TYPE journal_entry_type ADD VALUE 'INTEREST';
+ALTER TYPE ledger_account ADD VALUE 'INTEREST';
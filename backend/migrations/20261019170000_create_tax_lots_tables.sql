@@ -0,0 +1,96 @@
+-- Each buy opens a tax lot and each sell closes quantity out of lots, chosen by the account's
+-- cost basis method, so the position's cost and the realized P&L follow the shares sold.
+CREATE TYPE cost_basis_method AS ENUM ('FIFO', 'LIFO', 'AVERAGE_COST', 'SPECIFIC_LOT');
+
+ALTER TABLE accounts ADD COLUMN cost_basis_method cost_basis_method NOT NULL DEFAULT 'FIFO';
+
+CREATE TABLE tax_lots (
+    lot_id UUID PRIMARY KEY,
+    account_id UUID NOT NULL REFERENCES accounts(account_id) ON DELETE CASCADE,
+    ticker VARCHAR(16) NOT NULL,
+    order_id UUID,
+    acquired_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    quantity DECIMAL NOT NULL,
+    remaining_quantity DECIMAL NOT NULL,
+    -- cost of the shares still in the lot
+    cost_basis DECIMAL(15, 4) NOT NULL,
+    CONSTRAINT tax_lot_quantity_check CHECK (remaining_quantity >= 0)
+);
+
+CREATE INDEX idx_tax_lots_open ON tax_lots(account_id, ticker, acquired_at) WHERE remaining_quantity > 0;
+
+-- positions held before lots were tracked become a single lot each
+INSERT INTO tax_lots (lot_id, account_id, ticker, acquired_at, quantity, remaining_quantity, cost_basis)
+SELECT gen_random_uuid(), account_id, ticker, created_at, quantity, quantity, total_money_spent
+FROM portfolio;
+
+-- lots a sell order closes first, in priority order. Written before the order is, so no FK.
+CREATE TABLE order_lot_selections (
+    order_id UUID NOT NULL,
+    lot_id UUID NOT NULL REFERENCES tax_lots(lot_id) ON DELETE CASCADE,
+    priority INTEGER NOT NULL,
+    PRIMARY KEY (order_id, lot_id)
+);
+
+-- one row per lot closed by a sell fill
+CREATE TABLE realized_gains (
+    realization_id UUID PRIMARY KEY,
+    account_id UUID NOT NULL REFERENCES accounts(account_id) ON DELETE CASCADE,
+    ticker VARCHAR(16) NOT NULL,
+    order_id UUID NOT NULL,
+    lot_id UUID REFERENCES tax_lots(lot_id) ON DELETE SET NULL,
+    acquired_at TIMESTAMPTZ,
+    quantity DECIMAL NOT NULL,
+    cost_basis DECIMAL(15, 4) NOT NULL,
+    proceeds DECIMAL(15, 4) NOT NULL,
+    realized_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_realized_gains_account ON realized_gains(account_id, realized_at);
+
+ALTER TABLE portfolio ADD COLUMN realized_pnl DECIMAL(15, 4) NOT NULL DEFAULT 0;
+ALTER TABLE transactions ADD COLUMN realized_pnl DECIMAL(15, 4);
+
+-- sells made before lots were tracked are realized against the position's average cost at the
+-- time, which is how positions were costed then, so tax reports still cover those years
+DO $$
+DECLARE
+    fill RECORD;
+    position_key TEXT;
+    held DECIMAL;
+    cost DECIMAL;
+    sold_cost DECIMAL(15, 4);
+    proceeds DECIMAL(15, 4);
+BEGIN
+    FOR fill IN
+        SELECT transaction_id, account_id, ticker, order_type, quantity, price_per_share,
+               COALESCE(order_id, transaction_id) AS order_id,
+               COALESCE(executed_at, NOW()) AS executed_at
+        FROM transactions
+        ORDER BY account_id, ticker, executed_at, transaction_id
+    LOOP
+        IF position_key IS DISTINCT FROM fill.account_id || fill.ticker THEN
+            position_key := fill.account_id || fill.ticker;
+            held := 0;
+            cost := 0;
+        END IF;
+        IF fill.order_type = 'BUY' THEN
+            held := held + fill.quantity;
+            cost := cost + fill.quantity * fill.price_per_share;
+            CONTINUE;
+        END IF;
+        sold_cost := CASE WHEN held > 0 THEN cost * LEAST(fill.quantity, held) / held ELSE 0 END;
+        proceeds := fill.quantity * fill.price_per_share;
+        held := GREATEST(held - fill.quantity, 0);
+        cost := cost - sold_cost;
+        IF fill.quantity > 0 THEN
+            INSERT INTO realized_gains (realization_id, account_id, ticker, order_id, quantity,
+                cost_basis, proceeds, realized_at)
+            VALUES (gen_random_uuid(), fill.account_id, fill.ticker, fill.order_id, fill.quantity,
+                sold_cost, proceeds, fill.executed_at);
+        END IF;
+        UPDATE transactions SET realized_pnl = proceeds - sold_cost
+        WHERE transaction_id = fill.transaction_id;
+    END LOOP;
+END;
+$$;
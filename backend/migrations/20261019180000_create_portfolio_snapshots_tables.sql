@@ -0,0 +1,25 @@
+-- What each account held and was worth at the end of each day, so history doesn't have to
+-- replay every trade against the price history on each request. Cash includes what is
+-- reserved for open buys.
+CREATE TABLE portfolio_snapshots (
+    account_id UUID NOT NULL REFERENCES accounts(account_id) ON DELETE CASCADE,
+    snapshot_date DATE NOT NULL,
+    cash DECIMAL NOT NULL,
+    positions_value DECIMAL NOT NULL,
+    total_value DECIMAL NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (account_id, snapshot_date)
+);
+
+-- quantities and prices are in the shares of the day, before any later split
+CREATE TABLE portfolio_snapshot_positions (
+    account_id UUID NOT NULL,
+    snapshot_date DATE NOT NULL,
+    ticker VARCHAR(16) NOT NULL,
+    quantity DECIMAL NOT NULL,
+    price DECIMAL NOT NULL,
+    market_value DECIMAL NOT NULL,
+    PRIMARY KEY (account_id, snapshot_date, ticker),
+    FOREIGN KEY (account_id, snapshot_date)
+        REFERENCES portfolio_snapshots(account_id, snapshot_date) ON DELETE CASCADE
+);
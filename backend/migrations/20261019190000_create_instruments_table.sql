@@ -0,0 +1,15 @@
+-- Reference data about what each ticker is, used to group holdings by sector and asset class.
+CREATE TYPE asset_class AS ENUM ('EQUITY', 'ETF', 'FUND', 'BOND', 'COMMODITY', 'CRYPTO');
+
+CREATE TABLE instruments (
+    ticker VARCHAR(16) PRIMARY KEY,
+    name VARCHAR(128) NOT NULL,
+    asset_class asset_class NOT NULL DEFAULT 'EQUITY',
+    sector VARCHAR(64),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+INSERT INTO instruments (ticker, name, asset_class, sector) VALUES
+    ('AAPL', 'Apple Inc.', 'EQUITY', 'Information Technology'),
+    ('GOOGL', 'Alphabet Inc.', 'EQUITY', 'Communication Services'),
+    ('MSFT', 'Microsoft Corporation', 'EQUITY', 'Information Technology');
@@ -0,0 +1,46 @@
+-- Orders are sized in whole lots of the instrument.
+ALTER TABLE instruments ADD COLUMN lot_size DECIMAL(15, 4) NOT NULL DEFAULT 1;
+ALTER TABLE instruments ADD CONSTRAINT instrument_lot_size_check CHECK (lot_size > 0);
+
+-- The weight each account wants in each ticker. What the weights leave over is held as cash.
+CREATE TABLE target_allocations (
+    account_id UUID NOT NULL REFERENCES accounts(account_id) ON DELETE CASCADE,
+    ticker VARCHAR(16) NOT NULL,
+    weight DECIMAL(7, 6) NOT NULL,
+    PRIMARY KEY (account_id, ticker),
+    CONSTRAINT target_allocation_weight_check CHECK (weight > 0 AND weight <= 1)
+);
+
+-- With auto_rebalance on, a proposal is placed without confirmation whenever a weight has
+-- drifted further than the threshold from its target.
+ALTER TABLE accounts ADD COLUMN rebalance_drift_threshold DECIMAL(7, 6);
+ALTER TABLE accounts ADD COLUMN auto_rebalance BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TYPE rebalance_proposal_status AS ENUM ('PROPOSED', 'SUBMITTED');
+
+CREATE TABLE rebalance_proposals (
+    proposal_id UUID PRIMARY KEY,
+    account_id UUID NOT NULL REFERENCES accounts(account_id) ON DELETE CASCADE,
+    status rebalance_proposal_status NOT NULL DEFAULT 'PROPOSED',
+    total_value DECIMAL NOT NULL,
+    drift DECIMAL(7, 6) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL,
+    submitted_at TIMESTAMPTZ
+);
+
+CREATE INDEX idx_rebalance_proposals_account_id ON rebalance_proposals(account_id, created_at DESC);
+
+-- order_id or error is filled in for each order once the proposal is submitted
+CREATE TABLE rebalance_proposal_orders (
+    proposal_id UUID NOT NULL REFERENCES rebalance_proposals(proposal_id) ON DELETE CASCADE,
+    ticker VARCHAR(16) NOT NULL,
+    order_type order_type NOT NULL,
+    quantity DECIMAL(15, 4) NOT NULL,
+    price_per_share DECIMAL(15, 4) NOT NULL,
+    current_weight DECIMAL(7, 6) NOT NULL,
+    target_weight DECIMAL(7, 6) NOT NULL,
+    order_id UUID,
+    error TEXT,
+    PRIMARY KEY (proposal_id, ticker)
+);
@@ -0,0 +1,19 @@
+-- Named lists of tickers a user follows. Lists and the tickers in them are kept in the order
+-- the user put them in.
+CREATE TABLE watchlists (
+    watchlist_id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(user_id) ON DELETE CASCADE,
+    name VARCHAR(64) NOT NULL,
+    position INTEGER NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    CONSTRAINT watchlist_name_unique UNIQUE (user_id, name)
+);
+
+CREATE INDEX idx_watchlists_user_id ON watchlists(user_id, position);
+
+CREATE TABLE watchlist_items (
+    watchlist_id UUID NOT NULL REFERENCES watchlists(watchlist_id) ON DELETE CASCADE,
+    ticker VARCHAR(16) NOT NULL,
+    position INTEGER NOT NULL,
+    PRIMARY KEY (watchlist_id, ticker)
+);
@@ -0,0 +1,51 @@
+CREATE TYPE alert_condition AS ENUM (
+    'PRICE_ABOVE',
+    'PRICE_BELOW',
+    'PERCENT_MOVE',
+    'VOLUME_SPIKE',
+    'ORDER_FILLED'
+);
+
+-- A condition on a ticker the user wants to hear about. One-shot alerts go inactive once they
+-- trigger. Recurring alerts are disarmed when they trigger and armed again once the condition
+-- stops holding, so a price sitting above a level notifies once rather than on every tick.
+CREATE TABLE alerts (
+    alert_id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(user_id) ON DELETE CASCADE,
+    ticker VARCHAR(16) NOT NULL,
+    condition alert_condition NOT NULL,
+    threshold DECIMAL(20, 6),
+    recurring BOOLEAN NOT NULL DEFAULT FALSE,
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    armed BOOLEAN NOT NULL DEFAULT TRUE,
+    trigger_count INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_triggered_at TIMESTAMPTZ,
+    CONSTRAINT alert_threshold_check CHECK (
+        (condition = 'ORDER_FILLED' AND threshold IS NULL)
+        OR (condition <> 'ORDER_FILLED' AND threshold > 0)
+    )
+);
+
+CREATE INDEX idx_alerts_user_id ON alerts(user_id, created_at);
+CREATE INDEX idx_alerts_active_ticker ON alerts(ticker, condition) WHERE active;
+
+-- The inbox alerts deliver to. Notifications outlive the alert that raised them.
+CREATE TABLE notifications (
+    notification_id UUID PRIMARY KEY,
+    user_id UUID NOT NULL REFERENCES users(user_id) ON DELETE CASCADE,
+    alert_id UUID REFERENCES alerts(alert_id) ON DELETE SET NULL,
+    ticker VARCHAR(16) NOT NULL,
+    condition alert_condition NOT NULL,
+    message TEXT NOT NULL,
+    price DECIMAL(15, 4),
+    order_id UUID,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    read_at TIMESTAMPTZ
+);
+
+CREATE INDEX idx_notifications_user_id ON notifications(user_id, created_at DESC);
+CREATE INDEX idx_notifications_unread ON notifications(user_id) WHERE read_at IS NULL;
+-- an order filled alert notifies about each order once
+CREATE UNIQUE INDEX idx_notifications_alert_order ON notifications(alert_id, order_id)
+    WHERE order_id IS NOT NULL;
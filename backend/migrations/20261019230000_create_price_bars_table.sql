@@ -0,0 +1,23 @@
+CREATE TYPE bar_interval AS ENUM (
+    'MINUTE',
+    'FIVE_MINUTES',
+    'FIFTEEN_MINUTES',
+    'HOUR',
+    'DAY'
+);
+
+-- OHLCV bars built from the matcher's fills, one row per ticker, interval and bucket. The daily
+-- bar is also folded into stock_prices so the last trade drives quotes.
+CREATE TABLE price_bars (
+    ticker VARCHAR(16) NOT NULL,
+    interval bar_interval NOT NULL,
+    bucket_start TIMESTAMPTZ NOT NULL,
+    open DECIMAL(15, 4) NOT NULL,
+    high DECIMAL(15, 4) NOT NULL,
+    low DECIMAL(15, 4) NOT NULL,
+    close DECIMAL(15, 4) NOT NULL,
+    volume DECIMAL(20, 4) NOT NULL,
+    trade_count INTEGER NOT NULL,
+    last_trade_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (ticker, interval, bucket_start)
+);
@@ -0,0 +1,4 @@
+-- a claimed key's lease is renewed while its request is still running, so a retry only takes
+-- the key over once nothing has renewed it for the whole lease
+ALTER TABLE idempotency_keys ADD COLUMN renewed_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+UPDATE idempotency_keys SET renewed_at = created_at;
@@ -0,0 +1,21 @@
+-- a position sold out keeps its row at zero shares, with the P&L it realized and the fees it
+-- paid, instead of being deleted
+ALTER TABLE portfolio DROP CONSTRAINT portfolio_quantity_check;
+ALTER TABLE portfolio ADD CONSTRAINT portfolio_quantity_check CHECK (quantity >= 0);
+
+-- positions closed before this have their rows brought back from the realized gains and fees
+-- recorded for them
+INSERT INTO portfolio (portfolio_id, account_id, ticker, quantity, available_quantity,
+    total_money_spent, total_fees, realized_pnl, created_at)
+SELECT gen_random_uuid(), g.account_id, g.ticker, 0, 0, 0,
+    COALESCE((SELECT SUM(t.fee) FROM transactions t
+        WHERE t.account_id = g.account_id AND t.ticker = g.ticker), 0),
+    g.realized_pnl, g.first_realized_at
+FROM (
+    SELECT account_id, ticker, SUM(proceeds - cost_basis) AS realized_pnl,
+        MIN(realized_at) AS first_realized_at
+    FROM realized_gains GROUP BY account_id, ticker
+) g
+WHERE NOT EXISTS (
+    SELECT 1 FROM portfolio p WHERE p.account_id = g.account_id AND p.ticker = g.ticker
+);
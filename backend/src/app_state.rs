@@ -1,14 +1,29 @@
 use crate::authentication::basic_client::AuthorizationClient;
 use crate::models::errors::trade_error::TradeError;
 use crate::services::account_management_service::AccountManagementService;
+use crate::services::alert_service::AlertService;
+use crate::services::analytics_service::AnalyticsService;
+use crate::services::corporate_action_service::CorporateActionService;
+use crate::services::export_service::ExportService;
+use crate::services::fee_service::FeeService;
+use crate::services::idempotency_service::IdempotencyService;
+use crate::services::interest_service::InterestService;
 use crate::services::loan_service::LoanService;
+use crate::services::market_data_provider::MarketDataProvider;
 use crate::services::market_maker_service;
 use crate::services::order_management_service::OrderManagementService;
 use crate::services::order_matchbook_service::{self, OrderMatchbookService};
 use crate::services::portfolio_management_service::PortfolioManagementService;
+use crate::services::rebalance_service::RebalanceService;
+use crate::services::reconciliation_service::ReconciliationService;
+use crate::services::risk_service::RiskService;
+use crate::services::simulation_service::SimulationService;
+use crate::services::snapshot_service::SnapshotService;
+use crate::services::tax_report_service::TaxReportService;
 use crate::services::ticker_service::TickerService;
 use crate::services::trade_service::TradeService;
 use crate::services::user_service::UserService;
+use crate::services::watchlist_service::WatchlistService;
 use sqlx::PgPool;
 use std::sync::Arc;
 use tracing::info;
@@ -24,20 +39,55 @@ pub struct AppState {
     pub order_matchbook_service: Arc<OrderMatchbookService>,
     pub portfolio_service: Arc<PortfolioManagementService>,
     pub account_management_service: Arc<AccountManagementService>,
+    pub analytics_service: Arc<AnalyticsService>,
+    pub export_service: Arc<ExportService>,
+    pub fee_service: Arc<FeeService>,
+    pub idempotency_service: Arc<IdempotencyService>,
+    pub interest_service: Arc<InterestService>,
     pub order_management_service: Arc<OrderManagementService>,
     pub loan_service: Arc<LoanService>,
     pub market_maker_service: Arc<market_maker_service::MarketMakerService>,
+    pub reconciliation_service: Arc<ReconciliationService>,
+    pub corporate_action_service: Arc<CorporateActionService>,
+    pub snapshot_service: Arc<SnapshotService>,
+    pub risk_service: Arc<RiskService>,
+    pub rebalance_service: Arc<RebalanceService>,
+    pub tax_report_service: Arc<TaxReportService>,
+    pub simulation_service: Arc<SimulationService>,
+    pub watchlist_service: Arc<WatchlistService>,
+    pub alert_service: Arc<AlertService>,
 }
 
 impl AppState {
-    pub fn new(db: PgPool, api_key: &str, system_user_id: Uuid) -> Self {
-        let ticker_service = Arc::new(TickerService::new(api_key, db.clone()));
+    pub fn new(
+        db: PgPool,
+        market_data: Arc<dyn MarketDataProvider>,
+        system_user_id: Uuid,
+    ) -> Self {
+        let ticker_service = Arc::new(TickerService::new(market_data, db.clone()));
         let account_management_service = Arc::new(AccountManagementService::new(db.clone()));
+        let export_service = Arc::new(ExportService::new(db.clone()));
+        let fee_service = Arc::new(FeeService::new(db.clone()));
+        let idempotency_service = Arc::new(IdempotencyService::new(db.clone()));
+        let tax_report_service = Arc::new(TaxReportService::new(db.clone()));
+        let watchlist_service = Arc::new(WatchlistService::new(db.clone()));
+        let alert_service = Arc::new(AlertService::new(db.clone()));
+        let interest_service = Arc::new(InterestService::new(db.clone(), system_user_id));
         let authentication_client = Arc::new(AuthorizationClient::new());
         let portfolio_service = Arc::new(PortfolioManagementService::new(
             db.clone(),
             ticker_service.clone(),
         ));
+        let snapshot_service = Arc::new(SnapshotService::new(
+            db.clone(),
+            portfolio_service.clone(),
+            system_user_id,
+        ));
+        let risk_service = Arc::new(RiskService::new(db.clone(), portfolio_service.clone()));
+        let analytics_service = Arc::new(AnalyticsService::new(
+            db.clone(),
+            snapshot_service.clone(),
+        ));
         let user_service = Arc::new(UserService::new(
             db.clone(),
             account_management_service.clone(),
@@ -49,12 +99,14 @@ impl AppState {
             ticker_service.clone(),
             account_management_service.clone(),
             portfolio_service.clone(),
+            fee_service.clone(),
         ));
         let order_matchbook_service =
             Arc::new(order_matchbook_service::OrderMatchbookService::new(
                 db.clone(),
                 trade_service.clone(),
                 ticker_service.clone(),
+                alert_service.clone(),
             ));
 
         let order_management_service = Arc::new(OrderManagementService::new(
@@ -64,19 +116,47 @@ impl AppState {
             account_management_service.clone(),
             portfolio_service.clone(),
             order_matchbook_service.clone(),
+            fee_service.clone(),
+        ));
+        let rebalance_service = Arc::new(RebalanceService::new(
+            db.clone(),
+            ticker_service.clone(),
+            order_management_service.clone(),
         ));
         let market_maker_service = Arc::new(market_maker_service::MarketMakerService::new(
             db.clone(),
             ticker_service.clone(),
             order_management_service.clone(),
+            alert_service.clone(),
             vec!["AAPL".to_string(), "GOOGL".to_string(), "MSFT".to_string()],
             system_user_id,
         ));
 
+        let reconciliation_service = Arc::new(ReconciliationService::new(
+            db.clone(),
+            trade_service.clone(),
+            order_matchbook_service.clone(),
+            system_user_id,
+        ));
+
+        let corporate_action_service = Arc::new(CorporateActionService::new(
+            db.clone(),
+            order_matchbook_service.clone(),
+            market_maker_service.clone(),
+        ));
+
         let loan_service = Arc::new(LoanService::new(
             db.clone(),
             account_management_service.clone(),
         ));
+        let simulation_service = Arc::new(SimulationService::new(
+            db.clone(),
+            portfolio_service.clone(),
+            account_management_service.clone(),
+            fee_service.clone(),
+            risk_service.clone(),
+            loan_service.clone(),
+        ));
         tracing::info!("App state created & all services are operational");
         Self {
             system_user_id,
@@ -86,14 +166,29 @@ impl AppState {
             trade_service,
             portfolio_service,
             account_management_service,
+            analytics_service,
+            export_service,
+            fee_service,
+            idempotency_service,
+            interest_service,
             order_management_service,
             loan_service,
             order_matchbook_service,
             market_maker_service,
+            reconciliation_service,
+            corporate_action_service,
+            snapshot_service,
+            risk_service,
+            rebalance_service,
+            tax_report_service,
+            simulation_service,
+            watchlist_service,
+            alert_service,
         }
     }
     pub async fn start_background_processes(
         &self,
+        reconciliation_auto_repair: bool,
     ) -> Vec<tokio::task::JoinHandle<Result<(), TradeError>>> {
         tracing::info!("Starting background processes");
         let mut handles: Vec<tokio::task::JoinHandle<Result<(), TradeError>>> = Vec::new();
@@ -101,17 +196,29 @@ impl AppState {
         info!("Ticker ids: {:?}", ticker_ids);
         if let Err(e) = self
             .user_service
-            .create_system_user(self.system_user_id, ticker_ids)
+            .create_system_user(self.system_user_id, ticker_ids.clone())
             .await
         {
             tracing::error!("Failed to create system user: {:?}", e);
         }
 
+        if let Err(e) = self.ticker_service.seed_price_history(&ticker_ids).await {
+            tracing::error!("Failed to seed price history: {:?}", e);
+        }
+
         let _ = self.order_matchbook_service.initialise_orderbooks().await;
         let _ = self.market_maker_service.initialise_market().await;
 
         handles.push(self.order_matchbook_service.create_worker_thread());
         handles.push(self.market_maker_service.spawn_price_engine().await);
+        handles.push(self.corporate_action_service.spawn_processing_job());
+        handles.push(self.interest_service.spawn_accrual_job());
+        handles.push(self.snapshot_service.spawn_snapshot_job());
+        handles.push(self.rebalance_service.spawn_auto_rebalance_job());
+        handles.push(
+            self.reconciliation_service
+                .spawn_reconciliation_job(reconciliation_auto_repair),
+        );
         return handles;
     }
 }
@@ -1,6 +1,7 @@
 use anyhow::Result;
 use backend::app_state::AppState;
 use backend::routes::router::create_router;
+use backend::services::market_data_provider::MarketDataConfig;
 use dotenv::dotenv;
 use sqlx::PgPool;
 use std::env;
@@ -25,8 +26,30 @@ async fn main() -> Result<()> {
         Uuid::parse_str(&env::var("marketmaker.user_id").expect("marketmaker.user_id NOT FOUND"))
             .expect("marketmaker.user_id NOT FOUND");
 
-    let app_state = AppState::new(_db, "mock", system_user_id);
-    let _task_handles = app_state.start_background_processes().await;
+    //scheduled reconciliation only reports breaks unless repairs are switched on
+    let reconciliation_auto_repair = env::var("reconciliation.auto_repair")
+        .map(|value| value == "true")
+        .unwrap_or(false);
+
+    //quotes come from synthetic prices unless market_data.provider says otherwise
+    let market_data = MarketDataConfig::from_env()
+        .map_err(anyhow::Error::msg)?
+        .provider();
+    info!("Market data from {}", market_data.name());
+
+    let app_state = AppState::new(_db, market_data, system_user_id);
+
+    //`backend backfill-snapshots [account_id]` fills in missing daily snapshots and exits
+    let mut args = env::args().skip(1);
+    if args.next().as_deref() == Some("backfill-snapshots") {
+        let account_id = args.next().map(|id| Uuid::parse_str(&id)).transpose()?;
+        app_state.snapshot_service.backfill(account_id).await?;
+        return Ok(());
+    }
+
+    let _task_handles = app_state
+        .start_background_processes(reconciliation_auto_repair)
+        .await;
     let app = create_router(app_state.clone()).with_state(app_state);
     let listener = tokio::net::TcpListener::bind("localhost:3000").await?;
     info!(
@@ -0,0 +1,32 @@
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::models::tax_lot::CostBasisMethod;
+
+/// The account a request acts on, resolved by the auth middleware from the `X-Account-Id`
+/// header. Falls back to the user's default account, whose id is the user id.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AccountId(pub Uuid);
+
+/// A sub-account holding its own cash, positions, orders and history.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Account {
+    pub account_id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    pub balance: BigDecimal,
+    pub available_balance: BigDecimal,
+    pub cost_basis_method: CostBasisMethod,
+    pub is_default: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CashTransfer {
+    pub transfer_id: Uuid,
+    pub from_account_id: Uuid,
+    pub to_account_id: Uuid,
+    pub amount: BigDecimal,
+}
@@ -0,0 +1,180 @@
+use bigdecimal::{BigDecimal, RoundingMode};
+use chrono::{DateTime, Utc};
+use num_traits::Zero;
+use serde::{Deserialize, Serialize};
+use strum::{Display, EnumString};
+use uuid::Uuid;
+
+const THRESHOLD_SCALE: i64 = 6;
+
+/// What an alert watches for. `threshold` is a price for `PriceAbove` and `PriceBelow`, a
+/// percentage either way from the previous close for `PercentMove`, and a multiple of the
+/// average daily volume for `VolumeSpike`. `OrderFilled` has no threshold and triggers when
+/// one of the user's orders in the ticker is filled in full.
+#[derive(
+    Debug, Clone, Copy, Display, EnumString, PartialEq, Serialize, Deserialize, sqlx::Type,
+)]
+#[strum(serialize_all = "SCREAMING_SNAKE_CASE")]
+#[sqlx(type_name = "alert_condition", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum AlertCondition {
+    PriceAbove,
+    PriceBelow,
+    PercentMove,
+    VolumeSpike,
+    OrderFilled,
+}
+
+/// One-shot alerts stop being `active` once they trigger. Recurring alerts stay active but
+/// aren't `armed` again until their condition has stopped holding.
+#[derive(Debug, Clone, Serialize)]
+pub struct Alert {
+    pub alert_id: Uuid,
+    pub ticker: String,
+    pub condition: AlertCondition,
+    pub threshold: Option<BigDecimal>,
+    pub recurring: bool,
+    pub active: bool,
+    pub armed: bool,
+    pub trigger_count: i32,
+    pub created_at: DateTime<Utc>,
+    pub last_triggered_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AlertRequest {
+    pub ticker: String,
+    pub condition: AlertCondition,
+    pub threshold: Option<BigDecimal>,
+    #[serde(default)]
+    pub recurring: bool,
+}
+
+impl AlertRequest {
+    /// Uppercases the ticker and rounds the threshold to six places, checking it suits the
+    /// condition.
+    pub fn normalise(self) -> Result<Self, String> {
+        let ticker = self.ticker.trim().to_uppercase();
+        if ticker.is_empty() || ticker.len() > 16 {
+            return Err(format!("{:?} is not a ticker", ticker));
+        }
+        let threshold = self
+            .threshold
+            .map(|threshold| threshold.with_scale_round(THRESHOLD_SCALE, RoundingMode::HalfUp));
+        match (self.condition, &threshold) {
+            (AlertCondition::OrderFilled, None) => {}
+            (AlertCondition::OrderFilled, Some(_)) => {
+                return Err("order filled alerts don't take a threshold".to_string())
+            }
+            (_, None) => return Err(format!("{} alerts need a threshold", self.condition)),
+            (AlertCondition::VolumeSpike, Some(multiple)) => {
+                let one = BigDecimal::from(1);
+                if *multiple <= one {
+                    return Err("a volume spike must be more than 1x the average".to_string());
+                }
+            }
+            (_, Some(threshold)) => {
+                if *threshold <= BigDecimal::zero() {
+                    return Err("threshold must be positive".to_string());
+                }
+            }
+        }
+        Ok(Self {
+            ticker,
+            threshold,
+            ..self
+        })
+    }
+}
+
+/// A new price for a ticker with what the day looks like so far. The day fields are None
+/// when there is no history to compare with.
+#[derive(Debug, Clone)]
+pub struct PriceUpdate {
+    pub ticker: String,
+    pub price: BigDecimal,
+    pub previous_close: Option<BigDecimal>,
+    pub day_volume: Option<i64>,
+    pub average_volume: Option<BigDecimal>,
+}
+
+impl PriceUpdate {
+    /// Days of volume the spike is measured against.
+    pub const AVERAGE_VOLUME_DAYS: i32 = 20;
+}
+
+impl Alert {
+    /// Whether the alert's condition holds at `update`, with the message to send if so. Order
+    /// filled alerts never hold on a price.
+    pub fn check(&self, update: &PriceUpdate) -> Option<String> {
+        let threshold = self.threshold.as_ref()?;
+        match self.condition {
+            AlertCondition::PriceAbove => (update.price >= *threshold).then(|| {
+                format!(
+                    "{} is at {}, at or above {}",
+                    update.ticker,
+                    update.price.normalized(),
+                    threshold.normalized()
+                )
+            }),
+            AlertCondition::PriceBelow => (update.price <= *threshold).then(|| {
+                format!(
+                    "{} is at {}, at or below {}",
+                    update.ticker,
+                    update.price.normalized(),
+                    threshold.normalized()
+                )
+            }),
+            AlertCondition::PercentMove => {
+                let previous = update.previous_close.as_ref().filter(|p| !p.is_zero())?;
+                let change = ((&update.price - previous) * BigDecimal::from(100) / previous)
+                    .with_scale_round(2, RoundingMode::HalfUp);
+                (change.abs() >= *threshold).then(|| {
+                    format!(
+                        "{} has moved {}% today to {}",
+                        update.ticker,
+                        change,
+                        update.price.normalized()
+                    )
+                })
+            }
+            AlertCondition::VolumeSpike => {
+                let average = update
+                    .average_volume
+                    .as_ref()
+                    .filter(|a| **a > BigDecimal::zero())?;
+                let multiple = (BigDecimal::from(update.day_volume?) / average)
+                    .with_scale_round(2, RoundingMode::HalfUp);
+                (multiple >= *threshold).then(|| {
+                    format!(
+                        "{} has traded {} shares today, {}x its daily average",
+                        update.ticker,
+                        update.day_volume.unwrap_or_default(),
+                        multiple
+                    )
+                })
+            }
+            AlertCondition::OrderFilled => None,
+        }
+    }
+}
+
+/// An alert that triggered, as delivered to the user's inbox.
+#[derive(Debug, Clone, Serialize)]
+pub struct Notification {
+    pub notification_id: Uuid,
+    pub alert_id: Option<Uuid>,
+    pub ticker: String,
+    pub condition: AlertCondition,
+    pub message: String,
+    pub price: Option<BigDecimal>,
+    pub order_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub read_at: Option<DateTime<Utc>>,
+}
+
+/// The latest notifications, newest first, and how many are unread in all.
+#[derive(Debug, Clone, Serialize)]
+pub struct NotificationInbox {
+    pub unread: i64,
+    pub notifications: Vec<Notification>,
+}
@@ -0,0 +1,126 @@
+use std::collections::BTreeMap;
+
+use bigdecimal::{BigDecimal, RoundingMode};
+use num_traits::Zero;
+use serde::Serialize;
+
+use crate::models::instrument::{AssetClass, Instrument};
+
+/// A position at its latest price, with what is known about the instrument.
+pub struct Holding {
+    pub ticker: String,
+    pub quantity: BigDecimal,
+    pub market_value: BigDecimal,
+    pub instrument: Option<Instrument>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PositionAllocation {
+    pub ticker: String,
+    pub quantity: BigDecimal,
+    pub market_value: BigDecimal,
+    /// Share of the account's total value, cash included.
+    pub weight: BigDecimal,
+    pub asset_class: Option<AssetClass>,
+    pub sector: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AllocationGroup {
+    pub name: String,
+    pub market_value: BigDecimal,
+    pub weight: BigDecimal,
+}
+
+/// How an account's value splits across cash and positions. Positions, sectors and asset classes
+/// are largest first and their weights are of the total value, so with `cash_weight` they add
+/// up to one. The Herfindahl index is the sum of squared position weights within the invested
+/// value: 1 for a single position, 1/n for n equal ones, 0 for none.
+#[derive(Debug, Clone, Serialize)]
+pub struct PortfolioAllocation {
+    pub total_value: BigDecimal,
+    pub cash: BigDecimal,
+    pub cash_weight: BigDecimal,
+    pub positions: Vec<PositionAllocation>,
+    pub largest_position: Option<PositionAllocation>,
+    pub herfindahl_index: BigDecimal,
+    pub by_sector: Vec<AllocationGroup>,
+    pub by_asset_class: Vec<AllocationGroup>,
+}
+
+impl PortfolioAllocation {
+    const WEIGHT_SCALE: i64 = 6;
+    /// Group for instruments without metadata, or without a sector.
+    pub const UNCLASSIFIED: &'static str = "Unclassified";
+
+    fn weight(part: &BigDecimal, whole: &BigDecimal) -> BigDecimal {
+        if whole.is_zero() {
+            return BigDecimal::zero();
+        }
+        (part / whole).with_scale_round(Self::WEIGHT_SCALE, RoundingMode::HalfUp)
+    }
+
+    pub fn compute(cash: BigDecimal, holdings: Vec<Holding>) -> Self {
+        let invested: BigDecimal = holdings.iter().map(|h| &h.market_value).sum();
+        let total_value = &cash + &invested;
+        let herfindahl_index = holdings
+            .iter()
+            .map(|h| {
+                let share = if invested.is_zero() {
+                    BigDecimal::zero()
+                } else {
+                    &h.market_value / &invested
+                };
+                &share * &share
+            })
+            .sum::<BigDecimal>()
+            .with_scale_round(Self::WEIGHT_SCALE, RoundingMode::HalfUp);
+
+        let mut positions: Vec<PositionAllocation> = holdings
+            .into_iter()
+            .map(|h| {
+                let instrument = h.instrument.as_ref();
+                PositionAllocation {
+                    weight: Self::weight(&h.market_value, &total_value),
+                    asset_class: instrument.map(|i| i.asset_class),
+                    sector: instrument.and_then(|i| i.sector.clone()),
+                    ticker: h.ticker,
+                    quantity: h.quantity,
+                    market_value: h.market_value,
+                }
+            })
+            .collect();
+        positions.sort_by(|a, b| b.market_value.cmp(&a.market_value));
+
+        let group = |key: fn(&PositionAllocation) -> Option<String>| {
+            let mut values: BTreeMap<String, BigDecimal> = BTreeMap::new();
+            for position in &positions {
+                let name = key(position).unwrap_or_else(|| Self::UNCLASSIFIED.to_string());
+                *values.entry(name).or_default() += &position.market_value;
+            }
+            let mut groups: Vec<AllocationGroup> = values
+                .into_iter()
+                .map(|(name, market_value)| AllocationGroup {
+                    name,
+                    weight: Self::weight(&market_value, &total_value),
+                    market_value,
+                })
+                .collect();
+            groups.sort_by(|a, b| b.market_value.cmp(&a.market_value));
+            groups
+        };
+        let by_sector = group(|p| p.sector.clone());
+        let by_asset_class = group(|p| p.asset_class.map(|class| format!("{:?}", class)));
+
+        Self {
+            cash_weight: Self::weight(&cash, &total_value),
+            largest_position: positions.first().cloned(),
+            total_value,
+            cash,
+            positions,
+            herfindahl_index,
+            by_sector,
+            by_asset_class,
+        }
+    }
+}
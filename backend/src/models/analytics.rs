@@ -0,0 +1,366 @@
+use std::collections::HashMap;
+
+use chrono::NaiveDate;
+use serde::Serialize;
+
+/// An account's value at the end of a day and the cash that came in (positive) or went out
+/// (negative) from outside during it. Flows are taken to arrive at the start of the day.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DailyValuation {
+    pub date: NaiveDate,
+    pub value: f64,
+    pub net_flow: f64,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct DailyReturn {
+    pub date: NaiveDate,
+    #[serde(rename = "return")]
+    pub daily_return: f64,
+}
+
+/// Largest peak-to-trough fall of the time-weighted growth of the account, as a fraction.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Drawdown {
+    pub depth: f64,
+    pub peak_date: NaiveDate,
+    pub trough_date: NaiveDate,
+}
+
+/// Performance over a period. Returns are for the whole period and ratios are annualised over
+/// calendar days, since the market trades every day. Statistics that need more data than the
+/// period has are None.
+#[derive(Debug, Clone, Serialize)]
+pub struct PerformanceAnalytics {
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub starting_value: f64,
+    pub ending_value: f64,
+    pub net_flows: f64,
+    pub time_weighted_return: Option<f64>,
+    pub money_weighted_return: Option<f64>,
+    pub annualised_volatility: Option<f64>,
+    pub sharpe_ratio: Option<f64>,
+    pub sortino_ratio: Option<f64>,
+    pub max_drawdown: Option<Drawdown>,
+    pub best_day: Option<DailyReturn>,
+    pub worst_day: Option<DailyReturn>,
+}
+
+impl PerformanceAnalytics {
+    const DAYS_PER_YEAR: f64 = 365.0;
+
+    /// `opening_value` is the value at the end of the day before `days` starts. Days the
+    /// account held nothing have no return and are skipped.
+    pub fn compute(
+        opening_value: f64,
+        days: &[DailyValuation],
+        risk_free_rate: f64,
+    ) -> Option<Self> {
+        let first = days.first()?;
+        let last = days.last()?;
+        let returns = Self::daily_returns(opening_value, days);
+
+        let time_weighted_return = if returns.is_empty() {
+            None
+        } else {
+            Some(
+                returns
+                    .iter()
+                    .map(|r| 1.0 + r.daily_return)
+                    .product::<f64>()
+                    - 1.0,
+            )
+        };
+        let daily_risk_free = (1.0 + risk_free_rate).powf(1.0 / Self::DAYS_PER_YEAR) - 1.0;
+        let excess: Vec<f64> = returns
+            .iter()
+            .map(|r| r.daily_return - daily_risk_free)
+            .collect();
+        let volatility = Self::std_dev(&returns.iter().map(|r| r.daily_return).collect::<Vec<_>>());
+        let downside = if excess.len() < 2 {
+            None
+        } else {
+            Some(
+                (excess.iter().map(|r| r.min(0.0).powi(2)).sum::<f64>() / excess.len() as f64)
+                    .sqrt(),
+            )
+        };
+        let mean_excess = Self::mean(&excess);
+        let annualise = Self::DAYS_PER_YEAR.sqrt();
+        let ratio = |mean: Option<f64>, deviation: Option<f64>| match (mean, deviation) {
+            (Some(mean), Some(deviation)) if deviation > 0.0 => Some(mean / deviation * annualise),
+            _ => None,
+        };
+
+        Some(Self {
+            start_date: first.date,
+            end_date: last.date,
+            starting_value: opening_value,
+            ending_value: last.value,
+            net_flows: days.iter().map(|day| day.net_flow).sum(),
+            time_weighted_return,
+            money_weighted_return: Self::money_weighted_return(opening_value, days),
+            annualised_volatility: volatility.map(|v| v * annualise),
+            sharpe_ratio: ratio(mean_excess, volatility),
+            sortino_ratio: ratio(mean_excess, downside),
+            max_drawdown: Self::max_drawdown(&returns),
+            best_day: returns
+                .iter()
+                .max_by(|a, b| a.daily_return.total_cmp(&b.daily_return))
+                .cloned(),
+            worst_day: returns
+                .iter()
+                .min_by(|a, b| a.daily_return.total_cmp(&b.daily_return))
+                .cloned(),
+        })
+    }
+
+    /// Each day's gain over the value it started with, flows included: (V₁ - V₀ - F) / (V₀ + F).
+    pub fn daily_returns(opening_value: f64, days: &[DailyValuation]) -> Vec<DailyReturn> {
+        let mut previous = opening_value;
+        let mut returns = Vec::new();
+        for day in days {
+            let invested = previous + day.net_flow;
+            if invested > 0.0 {
+                returns.push(DailyReturn {
+                    date: day.date,
+                    daily_return: (day.value - invested) / invested,
+                });
+            }
+            previous = day.value;
+        }
+        returns
+    }
+
+    /// The internal rate of return of the opening value and flows against the ending value,
+    /// found as a daily rate by bisection and restated for the period. None if it has no root.
+    pub fn money_weighted_return(opening_value: f64, days: &[DailyValuation]) -> Option<f64> {
+        let periods = days.len() as f64;
+        let ending_value = days.last()?.value;
+        //value the rate would have grown the money put in to by the end of the period
+        let surplus = |rate: f64| {
+            let mut grown = opening_value * (1.0 + rate).powf(periods);
+            for (i, day) in days.iter().enumerate() {
+                grown += day.net_flow * (1.0 + rate).powi((days.len() - i) as i32);
+            }
+            grown - ending_value
+        };
+        //the highest rate that can compound over the period without overflowing
+        let (mut low, mut high) = (-0.9999, ((700.0 / periods).exp() - 1.0).min(10.0));
+        let (mut f_low, f_high) = (surplus(low), surplus(high));
+        if !f_low.is_finite() || !f_high.is_finite() || f_low.signum() == f_high.signum() {
+            return None;
+        }
+        for _ in 0..200 {
+            let mid = (low + high) / 2.0;
+            let f_mid = surplus(mid);
+            if f_mid.signum() == f_low.signum() {
+                low = mid;
+                f_low = f_mid;
+            } else {
+                high = mid;
+            }
+        }
+        Some((1.0 + (low + high) / 2.0).powf(periods) - 1.0)
+    }
+
+    pub fn max_drawdown(returns: &[DailyReturn]) -> Option<Drawdown> {
+        let first = returns.first()?.date;
+        let mut growth = 1.0;
+        //the value the period opened with is the first peak
+        let mut peak = (1.0, first.pred_opt().unwrap_or(first));
+        let mut worst: Option<Drawdown> = None;
+        for r in returns {
+            growth *= 1.0 + r.daily_return;
+            if growth > peak.0 {
+                peak = (growth, r.date);
+            }
+            let depth = 1.0 - growth / peak.0;
+            if depth > worst.as_ref().map_or(0.0, |w| w.depth) {
+                worst = Some(Drawdown {
+                    depth,
+                    peak_date: peak.1,
+                    trough_date: r.date,
+                });
+            }
+        }
+        worst
+    }
+
+    fn mean(values: &[f64]) -> Option<f64> {
+        if values.is_empty() {
+            return None;
+        }
+        Some(values.iter().sum::<f64>() / values.len() as f64)
+    }
+
+    /// Sample standard deviation.
+    fn std_dev(values: &[f64]) -> Option<f64> {
+        Self::covariance(values, values).map(f64::sqrt)
+    }
+
+    /// Sample covariance of two series of the same length.
+    fn covariance(a: &[f64], b: &[f64]) -> Option<f64> {
+        if a.len() < 2 || a.len() != b.len() {
+            return None;
+        }
+        let (mean_a, mean_b) = (Self::mean(a)?, Self::mean(b)?);
+        let sum: f64 = a
+            .iter()
+            .zip(b)
+            .map(|(x, y)| (x - mean_a) * (y - mean_b))
+            .sum();
+        Some(sum / (a.len() - 1) as f64)
+    }
+}
+
+/// One ticker of a benchmark and its share of it.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct BenchmarkWeight {
+    pub ticker: String,
+    pub weight: f64,
+}
+
+/// A ticker or weighted basket of tickers to compare an account against. The basket is bought
+/// in its weights at the start of the period and held.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct Benchmark(Vec<BenchmarkWeight>);
+
+impl Benchmark {
+    /// Parses `AAPL` or a basket like `AAPL:3,MSFT:1`. Weights are scaled to sum to one.
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let mut weights: Vec<BenchmarkWeight> = Vec::new();
+        for part in spec.split(',').map(str::trim) {
+            let (ticker, weight) = match part.split_once(':') {
+                Some((ticker, weight)) => (
+                    ticker.trim(),
+                    weight
+                        .trim()
+                        .parse::<f64>()
+                        .map_err(|_| format!("weight of {} is not a number", ticker.trim()))?,
+                ),
+                None => (part, 1.0),
+            };
+            if ticker.is_empty() {
+                return Err("ticker is missing".to_string());
+            }
+            if !weight.is_finite() || weight <= 0.0 {
+                return Err(format!("weight of {} must be positive", ticker));
+            }
+            let ticker = ticker.to_uppercase();
+            if weights.iter().any(|w| w.ticker == ticker) {
+                return Err(format!("{} is listed twice", ticker));
+            }
+            weights.push(BenchmarkWeight { ticker, weight });
+        }
+        let total: f64 = weights.iter().map(|w| w.weight).sum();
+        for w in weights.iter_mut() {
+            w.weight /= total;
+        }
+        Ok(Self(weights))
+    }
+
+    pub fn weights(&self) -> &[BenchmarkWeight] {
+        &self.0
+    }
+
+    pub fn tickers(&self) -> Vec<String> {
+        self.0.iter().map(|w| w.ticker.clone()).collect()
+    }
+}
+
+/// Growth of one unit invested in the account and in the benchmark at the start of the period.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct BenchmarkPoint {
+    pub date: NaiveDate,
+    pub portfolio: f64,
+    pub benchmark: f64,
+}
+
+/// How the account did against a benchmark, on time-weighted daily returns. Alpha and tracking
+/// error are annualised and alpha is measured against a zero risk-free rate.
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchmarkComparison {
+    pub benchmark: Benchmark,
+    pub series: Vec<BenchmarkPoint>,
+    pub portfolio_return: Option<f64>,
+    pub benchmark_return: f64,
+    pub excess_return: Option<f64>,
+    pub alpha: Option<f64>,
+    pub beta: Option<f64>,
+    pub tracking_error: Option<f64>,
+}
+
+impl BenchmarkComparison {
+    /// `levels` is the benchmark's level at the end of the day before the period and then at
+    /// the end of every day of it; `returns` are the account's daily returns over those days.
+    pub fn compute(
+        benchmark: Benchmark,
+        returns: &[DailyReturn],
+        levels: &[(NaiveDate, f64)],
+    ) -> Option<Self> {
+        let (_, base) = *levels.first()?;
+        if base <= 0.0 {
+            return None;
+        }
+        let portfolio_returns: HashMap<NaiveDate, f64> =
+            returns.iter().map(|r| (r.date, r.daily_return)).collect();
+        let mut growth = 1.0;
+        let mut series = Vec::new();
+        //days both moved on, as (account, benchmark) returns
+        let mut paired = Vec::new();
+        for pair in levels.windows(2) {
+            let (_, previous) = pair[0];
+            let (date, level) = pair[1];
+            if let Some(r) = portfolio_returns.get(&date) {
+                growth *= 1.0 + r;
+                if previous > 0.0 {
+                    paired.push((*r, level / previous - 1.0));
+                }
+            }
+            series.push(BenchmarkPoint {
+                date,
+                portfolio: growth,
+                benchmark: level / base,
+            });
+        }
+
+        let benchmark_return = levels.last()?.1 / base - 1.0;
+        let portfolio_return = if returns.is_empty() {
+            None
+        } else {
+            Some(growth - 1.0)
+        };
+        let (account, market): (Vec<f64>, Vec<f64>) = paired.iter().copied().unzip();
+        let beta = match (
+            PerformanceAnalytics::covariance(&account, &market),
+            PerformanceAnalytics::covariance(&market, &market),
+        ) {
+            (Some(covariance), Some(variance)) if variance > 0.0 => Some(covariance / variance),
+            _ => None,
+        };
+        let alpha = match (
+            beta,
+            PerformanceAnalytics::mean(&account),
+            PerformanceAnalytics::mean(&market),
+        ) {
+            (Some(beta), Some(account), Some(market)) => {
+                Some((account - beta * market) * PerformanceAnalytics::DAYS_PER_YEAR)
+            }
+            _ => None,
+        };
+        let differences: Vec<f64> = paired.iter().map(|(a, m)| a - m).collect();
+        Some(Self {
+            benchmark,
+            series,
+            portfolio_return,
+            benchmark_return,
+            excess_return: portfolio_return.map(|r| r - benchmark_return),
+            alpha,
+            beta,
+            tracking_error: PerformanceAnalytics::std_dev(&differences)
+                .map(|d| d * PerformanceAnalytics::DAYS_PER_YEAR.sqrt()),
+        })
+    }
+}
@@ -0,0 +1,81 @@
+use bigdecimal::{BigDecimal, RoundingMode};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use strum::Display;
+use strum::EnumString;
+use uuid::Uuid;
+
+#[derive(
+    Debug, Clone, Copy, Display, EnumString, PartialEq, Serialize, Deserialize, sqlx::Type,
+)]
+#[strum(serialize_all = "SCREAMING_SNAKE_CASE")]
+#[sqlx(
+    type_name = "corporate_action_type",
+    rename_all = "SCREAMING_SNAKE_CASE"
+)]
+pub enum CorporateActionType {
+    Dividend,
+    Split,
+    ReverseSplit,
+}
+
+#[derive(
+    Debug, Clone, Copy, Display, EnumString, PartialEq, Serialize, Deserialize, sqlx::Type,
+)]
+#[strum(serialize_all = "SCREAMING_SNAKE_CASE")]
+#[sqlx(
+    type_name = "corporate_action_status",
+    rename_all = "SCREAMING_SNAKE_CASE"
+)]
+pub enum CorporateActionStatus {
+    Scheduled,
+    Processed,
+    Cancelled,
+}
+
+/// `old_shares` become `new_shares`, so a 2-for-1 split is `new_shares: 2, old_shares: 1`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SplitRatio {
+    pub new_shares: i32,
+    pub old_shares: i32,
+}
+
+impl SplitRatio {
+    //quantities and prices are stored with 4 decimal places and rounded down so a rescaled
+    //buy never needs more cash than was reserved for it
+    const SCALE: i64 = 4;
+
+    pub fn adjust_quantity(&self, quantity: &BigDecimal) -> BigDecimal {
+        (quantity * BigDecimal::from(self.new_shares) / BigDecimal::from(self.old_shares))
+            .with_scale_round(Self::SCALE, RoundingMode::Down)
+    }
+
+    pub fn adjust_price(&self, price: &BigDecimal) -> BigDecimal {
+        (price * BigDecimal::from(self.old_shares) / BigDecimal::from(self.new_shares))
+            .with_scale_round(Self::SCALE, RoundingMode::Down)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CorporateAction {
+    pub action_id: Uuid,
+    pub ticker: String,
+    pub action_type: CorporateActionType,
+    pub effective_date: NaiveDate,
+    pub dividend_per_share: Option<BigDecimal>,
+    pub split_ratio: Option<SplitRatio>,
+    pub status: CorporateActionStatus,
+    pub created_at: DateTime<Utc>,
+    pub processed_at: Option<DateTime<Utc>>,
+}
+
+/// Terms of a corporate action as an admin schedules it. Dividends need `dividend_per_share`,
+/// splits and reverse splits need `split_ratio`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewCorporateAction {
+    pub ticker: String,
+    pub action_type: CorporateActionType,
+    pub effective_date: NaiveDate,
+    pub dividend_per_share: Option<BigDecimal>,
+    pub split_ratio: Option<SplitRatio>,
+}
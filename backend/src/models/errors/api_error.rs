@@ -14,6 +14,10 @@ pub enum ApiError {
     NotFound(String),
     #[error("Internal server error: {0}")]
     InternalServerError(String),
+    #[error("Conflict: {0}")]
+    Conflict(String),
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
     #[error("Unauthorized: {0}")]
     Unauthorized(String),
     #[error("Cookie not found: {0}")]
@@ -25,6 +29,8 @@ impl IntoResponse for ApiError {
         let (status, message) = match self {
             ApiError::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg),
             ApiError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
+            ApiError::Conflict(msg) => (StatusCode::CONFLICT, msg),
+            ApiError::Forbidden(msg) => (StatusCode::FORBIDDEN, msg),
             ApiError::Unauthorized(msg) => (StatusCode::UNAUTHORIZED, msg),
             ApiError::InternalServerError(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
             ApiError::MissingCookie(msg) => (StatusCode::UNAUTHORIZED, msg),
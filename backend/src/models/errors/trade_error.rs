@@ -24,6 +24,52 @@ pub enum TradeError {
     OrderBookNotFound,
     #[error("No match found for order")]
     NoMatchForOrder,
+    #[error("Invalid cursor")]
+    InvalidCursor,
+    #[error("Invalid idempotency key")]
+    InvalidIdempotencyKey,
+    #[error("Idempotency key was already used for a different request")]
+    IdempotencyKeyReused,
+    #[error("A request with this idempotency key is still being processed")]
+    IdempotentRequestInProgress,
+    #[error("Invalid corporate action: {0}")]
+    InvalidCorporateAction(String),
+    #[error("Corporate action not found")]
+    CorporateActionNotFound,
+    #[error("Corporate action is no longer scheduled")]
+    CorporateActionNotScheduled,
+    #[error("Invalid fee schedule: {0}")]
+    InvalidFeeSchedule(String),
+    #[error("Fee schedule not found")]
+    FeeScheduleNotFound,
+    #[error("Invalid interest rates: {0}")]
+    InvalidInterestRates(String),
+    #[error("Invalid lot selection: {0}")]
+    InvalidLotSelection(String),
+    #[error("Invalid benchmark: {0}")]
+    InvalidBenchmark(String),
+    #[error("Invalid risk parameters: {0}")]
+    InvalidRiskParameters(String),
+    #[error("Invalid rebalance: {0}")]
+    InvalidRebalance(String),
+    #[error("Rebalance proposal not found")]
+    RebalanceProposalNotFound,
+    #[error("Rebalance proposal was already submitted or has expired")]
+    RebalanceProposalNotPending,
+    #[error("Invalid tax year: {0}")]
+    InvalidTaxYear(String),
+    #[error("Invalid simulation: {0}")]
+    InvalidSimulation(String),
+    #[error("Invalid watchlist: {0}")]
+    InvalidWatchlist(String),
+    #[error("Watchlist not found")]
+    WatchlistNotFound,
+    #[error("Invalid alert: {0}")]
+    InvalidAlert(String),
+    #[error("Alert not found")]
+    AlertNotFound,
+    #[error("Notification not found")]
+    NotificationNotFound,
 }
 
 impl From<TradeError> for ApiError {
@@ -42,6 +88,69 @@ impl From<TradeError> for ApiError {
             TradeError::NoMatchForOrder => {
                 ApiError::NotFound("No match found for order".to_string())
             }
+            TradeError::InvalidCursor => ApiError::BadRequest("Invalid cursor".to_string()),
+            TradeError::InvalidIdempotencyKey => {
+                ApiError::BadRequest("Invalid idempotency key".to_string())
+            }
+            TradeError::IdempotencyKeyReused => ApiError::Conflict(
+                "Idempotency key was already used for a different request".to_string(),
+            ),
+            TradeError::IdempotentRequestInProgress => ApiError::Conflict(
+                "A request with this idempotency key is still being processed".to_string(),
+            ),
+            TradeError::InvalidCorporateAction(reason) => {
+                ApiError::BadRequest(format!("Invalid corporate action: {}", reason))
+            }
+            TradeError::CorporateActionNotFound => {
+                ApiError::NotFound("Corporate action not found".to_string())
+            }
+            TradeError::CorporateActionNotScheduled => {
+                ApiError::Conflict("Corporate action is no longer scheduled".to_string())
+            }
+            TradeError::InvalidFeeSchedule(reason) => {
+                ApiError::BadRequest(format!("Invalid fee schedule: {}", reason))
+            }
+            TradeError::FeeScheduleNotFound => {
+                ApiError::NotFound("Fee schedule not found".to_string())
+            }
+            TradeError::InvalidInterestRates(reason) => {
+                ApiError::BadRequest(format!("Invalid interest rates: {}", reason))
+            }
+            TradeError::InvalidLotSelection(reason) => {
+                ApiError::BadRequest(format!("Invalid lot selection: {}", reason))
+            }
+            TradeError::InvalidBenchmark(reason) => {
+                ApiError::BadRequest(format!("Invalid benchmark: {}", reason))
+            }
+            TradeError::InvalidRiskParameters(reason) => {
+                ApiError::BadRequest(format!("Invalid risk parameters: {}", reason))
+            }
+            TradeError::InvalidRebalance(reason) => {
+                ApiError::BadRequest(format!("Invalid rebalance: {}", reason))
+            }
+            TradeError::RebalanceProposalNotFound => {
+                ApiError::NotFound("Rebalance proposal not found".to_string())
+            }
+            TradeError::RebalanceProposalNotPending => ApiError::Conflict(
+                "Rebalance proposal was already submitted or has expired".to_string(),
+            ),
+            TradeError::InvalidTaxYear(year) => {
+                ApiError::BadRequest(format!("Invalid tax year: {}", year))
+            }
+            TradeError::InvalidSimulation(reason) => {
+                ApiError::BadRequest(format!("Invalid simulation: {}", reason))
+            }
+            TradeError::InvalidWatchlist(reason) => {
+                ApiError::BadRequest(format!("Invalid watchlist: {}", reason))
+            }
+            TradeError::WatchlistNotFound => ApiError::NotFound("Watchlist not found".to_string()),
+            TradeError::InvalidAlert(reason) => {
+                ApiError::BadRequest(format!("Invalid alert: {}", reason))
+            }
+            TradeError::AlertNotFound => ApiError::NotFound("Alert not found".to_string()),
+            TradeError::NotificationNotFound => {
+                ApiError::NotFound("Notification not found".to_string())
+            }
         }
     }
 }
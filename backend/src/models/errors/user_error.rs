@@ -33,6 +33,18 @@ pub enum UserError {
     UserDoesNotHaveLoan,
     #[error("User already has a loan")]
     UserAlreadyHasLoan,
+    #[error("Journal entry does not balance")]
+    UnbalancedJournalEntry,
+    #[error("Account not found")]
+    AccountNotFound,
+    #[error("Account name already exists")]
+    AccountNameAlreadyExists,
+    #[error("Invalid account name")]
+    InvalidAccountName,
+    #[error("Cannot transfer to the same account")]
+    InvalidTransfer,
+    #[error("Admin access required")]
+    Forbidden,
 }
 use crate::models::errors::api_error::ApiError;
 
@@ -60,6 +72,20 @@ impl From<UserError> for ApiError {
             UserError::UserAlreadyHasLoan => {
                 ApiError::BadRequest("User already has a loan".to_string())
             }
+            UserError::UnbalancedJournalEntry => {
+                ApiError::InternalServerError("Journal entry does not balance".to_string())
+            }
+            UserError::AccountNotFound => ApiError::NotFound("Account not found".to_string()),
+            UserError::AccountNameAlreadyExists => {
+                ApiError::Conflict("Account name already exists".to_string())
+            }
+            UserError::InvalidAccountName => {
+                ApiError::BadRequest("Invalid account name".to_string())
+            }
+            UserError::InvalidTransfer => {
+                ApiError::BadRequest("Cannot transfer to the same account".to_string())
+            }
+            UserError::Forbidden => ApiError::Forbidden("Admin access required".to_string()),
         }
     }
 }
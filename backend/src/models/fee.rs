@@ -0,0 +1,103 @@
+use bigdecimal::{BigDecimal, RoundingMode};
+use chrono::{DateTime, Utc};
+use num_traits::Zero;
+use serde::{Deserialize, Serialize};
+use strum::Display;
+use strum::EnumString;
+
+/// Whether a fill added liquidity (the order was resting in the book) or took it.
+#[derive(
+    Debug, Clone, Copy, Display, EnumString, PartialEq, Serialize, Deserialize, sqlx::Type,
+)]
+#[strum(serialize_all = "SCREAMING_SNAKE_CASE")]
+#[sqlx(type_name = "liquidity", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum Liquidity {
+    Maker,
+    Taker,
+}
+
+/// One execution of an order, as far as fees are concerned.
+#[derive(Debug, Clone)]
+pub struct Fill {
+    pub liquidity: Liquidity,
+    pub quantity: BigDecimal,
+    pub notional: BigDecimal,
+}
+
+/// Commission terms. `flat_fee` and `minimum_fee` apply once per order, the other components
+/// per fill. Rates are fractions of notional and a negative fee is a rebate.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FeeTerms {
+    pub flat_fee: BigDecimal,
+    pub per_share_fee: BigDecimal,
+    pub taker_rate: BigDecimal,
+    pub maker_rate: BigDecimal,
+    pub minimum_fee: BigDecimal,
+}
+
+/// The fee terms assigned to a user tier.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeeSchedule {
+    pub tier: String,
+    #[serde(flatten)]
+    pub terms: FeeTerms,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl FeeTerms {
+    const SCALE: i64 = 4;
+
+    /// Charges can't be negative and only makers may be paid a rebate.
+    pub fn validate(&self) -> Result<(), String> {
+        let zero = BigDecimal::zero();
+        if self.flat_fee < zero
+            || self.per_share_fee < zero
+            || self.taker_rate < zero
+            || self.minimum_fee < zero
+        {
+            return Err("fees and the taker rate can't be negative".to_string());
+        }
+        if self.maker_rate > self.taker_rate {
+            return Err("maker_rate can't exceed taker_rate".to_string());
+        }
+        Ok(())
+    }
+
+    fn rate(&self, liquidity: Liquidity) -> &BigDecimal {
+        match liquidity {
+            Liquidity::Maker => &self.maker_rate,
+            Liquidity::Taker => &self.taker_rate,
+        }
+    }
+
+    fn finalise(&self, fee: BigDecimal) -> BigDecimal {
+        //the minimum only lifts charges, rebates are paid out in full
+        let fee = if fee > BigDecimal::zero() && fee < self.minimum_fee {
+            self.minimum_fee.clone()
+        } else {
+            fee
+        };
+        fee.with_scale_round(Self::SCALE, RoundingMode::HalfUp)
+    }
+
+    /// Total fee for an order filled by `fills`.
+    pub fn order_fee(&self, fills: &[Fill]) -> BigDecimal {
+        if fills.is_empty() {
+            return BigDecimal::zero();
+        }
+        let mut fee = self.flat_fee.clone();
+        for fill in fills {
+            fee +=
+                &self.per_share_fee * &fill.quantity + self.rate(fill.liquidity) * &fill.notional;
+        }
+        self.finalise(fee)
+    }
+
+    /// The most an order of `quantity` worth at most `notional` can be charged, which is what
+    /// a buy reserves on top of its price. Makers never pay more than takers.
+    pub fn max_order_fee(&self, quantity: &BigDecimal, notional: &BigDecimal) -> BigDecimal {
+        let fee = &self.flat_fee + &self.per_share_fee * quantity + &self.taker_rate * notional;
+        self.finalise(fee)
+    }
+}
@@ -0,0 +1,129 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{Postgres, QueryBuilder};
+use uuid::Uuid;
+
+use crate::models::errors::trade_error::TradeError;
+use crate::models::order::{OrderStatus, OrderType};
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SortDirection {
+    Asc,
+    #[default]
+    Desc,
+}
+
+/// Filters shared by the paginated history endpoints. Filters that do not apply to a
+/// history (e.g. `status` for transactions) are ignored.
+#[derive(Deserialize, Debug, Default, Clone)]
+pub struct HistoryQuery {
+    pub ticker: Option<String>,
+    pub side: Option<OrderType>,
+    pub status: Option<OrderStatus>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub sort: SortDirection,
+    pub cursor: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Serialize, Debug)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub next_cursor: Option<String>,
+}
+
+/// Keyset position of the last row on a page, encoded as `<unix micros>_<row id>`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryCursor {
+    pub timestamp: DateTime<Utc>,
+    pub id: Uuid,
+}
+
+impl HistoryCursor {
+    pub fn encode(&self) -> String {
+        format!("{}_{}", self.timestamp.timestamp_micros(), self.id)
+    }
+
+    pub fn decode(cursor: &str) -> Result<HistoryCursor, TradeError> {
+        let (micros, id) = cursor.split_once('_').ok_or(TradeError::InvalidCursor)?;
+        let micros: i64 = micros.parse().map_err(|_| TradeError::InvalidCursor)?;
+        Ok(HistoryCursor {
+            timestamp: DateTime::from_timestamp_micros(micros).ok_or(TradeError::InvalidCursor)?,
+            id: Uuid::parse_str(id).map_err(|_| TradeError::InvalidCursor)?,
+        })
+    }
+}
+
+impl HistoryQuery {
+    const DEFAULT_PAGE_SIZE: i64 = 50;
+    const MAX_PAGE_SIZE: i64 = 200;
+
+    pub fn page_size(&self) -> i64 {
+        self.limit
+            .unwrap_or(Self::DEFAULT_PAGE_SIZE)
+            .clamp(1, Self::MAX_PAGE_SIZE)
+    }
+
+    /// Appends the ticker, side, date range and cursor conditions followed by the ORDER BY and
+    /// LIMIT clauses. One extra row is fetched so callers can tell whether another page exists.
+    pub fn push_conditions(
+        &self,
+        builder: &mut QueryBuilder<'_, Postgres>,
+        time_column: &str,
+        id_column: &str,
+    ) -> Result<(), TradeError> {
+        if let Some(ticker) = &self.ticker {
+            builder.push(" AND ticker = ").push_bind(ticker.clone());
+        }
+        if let Some(side) = &self.side {
+            builder.push(" AND order_type = ").push_bind(side.clone());
+        }
+        if let Some(from) = self.from {
+            builder
+                .push(format!(" AND {} >= ", time_column))
+                .push_bind(from);
+        }
+        if let Some(to) = self.to {
+            builder.push(format!(" AND {} < ", time_column)).push_bind(to);
+        }
+        let (comparison, direction) = match self.sort {
+            SortDirection::Asc => (">", "ASC"),
+            SortDirection::Desc => ("<", "DESC"),
+        };
+        if let Some(cursor) = &self.cursor {
+            let cursor = HistoryCursor::decode(cursor)?;
+            builder
+                .push(format!(" AND ({}, {}) {} (", time_column, id_column, comparison))
+                .push_bind(cursor.timestamp)
+                .push(", ")
+                .push_bind(cursor.id)
+                .push(")");
+        }
+        builder
+            .push(format!(
+                " ORDER BY {} {}, {} {} LIMIT ",
+                time_column, direction, id_column, direction
+            ))
+            .push_bind(self.page_size() + 1);
+        Ok(())
+    }
+
+    /// Trims the extra row fetched by `push_conditions` and builds the cursor for the next page.
+    pub fn into_page<T>(
+        &self,
+        mut items: Vec<T>,
+        cursor_of: impl Fn(&T) -> HistoryCursor,
+    ) -> Page<T> {
+        let page_size = self.page_size() as usize;
+        let next_cursor = if items.len() > page_size {
+            items.truncate(page_size);
+            items.last().map(|item| cursor_of(item).encode())
+        } else {
+            None
+        };
+        Page { items, next_cursor }
+    }
+}
@@ -0,0 +1,16 @@
+/// A response recorded against an idempotency key, replayed verbatim on retries.
+#[derive(Debug, Clone)]
+pub struct StoredResponse {
+    pub status: u16,
+    pub content_type: Option<String>,
+    pub body: Vec<u8>,
+}
+
+/// What to do with a request carrying an `Idempotency-Key` header.
+#[derive(Debug, Clone)]
+pub enum IdempotencyOutcome {
+    /// First time the key is seen: run the handler and record its response.
+    Execute,
+    /// Same key and same request already completed: return the stored response.
+    Replay(StoredResponse),
+}
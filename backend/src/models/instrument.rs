@@ -0,0 +1,46 @@
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use strum::Display;
+use strum::EnumString;
+
+#[derive(
+    Debug, Clone, Copy, Display, EnumString, PartialEq, Serialize, Deserialize, sqlx::Type,
+)]
+#[strum(serialize_all = "SCREAMING_SNAKE_CASE")]
+#[sqlx(type_name = "asset_class", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum AssetClass {
+    Equity,
+    Etf,
+    Fund,
+    Bond,
+    Commodity,
+    Crypto,
+}
+
+/// What a ticker is. Sector is None for instruments that don't have one, e.g. bonds.
+/// Orders the system generates are sized in whole multiples of `lot_size`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Instrument {
+    pub ticker: String,
+    pub name: String,
+    pub asset_class: AssetClass,
+    pub sector: Option<String>,
+    pub lot_size: BigDecimal,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct InstrumentDetails {
+    pub name: String,
+    pub asset_class: AssetClass,
+    pub sector: Option<String>,
+    #[serde(default = "InstrumentDetails::default_lot_size")]
+    pub lot_size: BigDecimal,
+}
+
+impl InstrumentDetails {
+    fn default_lot_size() -> BigDecimal {
+        BigDecimal::from(1)
+    }
+}
@@ -0,0 +1,76 @@
+use bigdecimal::{BigDecimal, RoundingMode};
+use chrono::NaiveDate;
+use num_traits::Zero;
+use serde::{Deserialize, Serialize};
+
+/// `annual_rate` is paid on the part of a balance above `min_balance`, up to the next tier.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InterestRateTier {
+    pub min_balance: BigDecimal,
+    pub annual_rate: BigDecimal,
+}
+
+/// The tiers interest on idle cash is paid at, lowest first. No tiers means no interest.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct InterestRateTiers(Vec<InterestRateTier>);
+
+impl InterestRateTiers {
+    const DAYS_PER_YEAR: u32 = 365;
+    const SCALE: i64 = 4;
+
+    /// Sorts the tiers and rejects negative values or two tiers starting at the same balance.
+    pub fn new(mut tiers: Vec<InterestRateTier>) -> Result<Self, String> {
+        if tiers.iter().any(|tier| {
+            tier.min_balance < BigDecimal::zero() || tier.annual_rate < BigDecimal::zero()
+        }) {
+            return Err("min_balance and annual_rate can't be negative".to_string());
+        }
+        tiers.sort_by(|a, b| a.min_balance.cmp(&b.min_balance));
+        if tiers
+            .windows(2)
+            .any(|pair| pair[0].min_balance == pair[1].min_balance)
+        {
+            return Err("two tiers can't start at the same min_balance".to_string());
+        }
+        Ok(Self(tiers))
+    }
+
+    pub fn tiers(&self) -> &[InterestRateTier] {
+        &self.0
+    }
+
+    /// One day of interest on `balance`, rounded down to the 4 decimal places cash is kept in.
+    pub fn daily_interest(&self, balance: &BigDecimal) -> BigDecimal {
+        let mut yearly = BigDecimal::zero();
+        for (i, tier) in self.0.iter().enumerate() {
+            if *balance <= tier.min_balance {
+                break;
+            }
+            let top = match self.0.get(i + 1) {
+                Some(next) if next.min_balance < *balance => &next.min_balance,
+                _ => balance,
+            };
+            yearly += (top - &tier.min_balance) * &tier.annual_rate;
+        }
+        (yearly / BigDecimal::from(Self::DAYS_PER_YEAR))
+            .with_scale_round(Self::SCALE, RoundingMode::Down)
+    }
+}
+
+/// Interest a user earned across their accounts in one calendar month.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MonthlyInterest {
+    pub month: NaiveDate,
+    pub days_accrued: i64,
+    pub average_balance: BigDecimal,
+    pub interest: BigDecimal,
+}
+
+/// What one run of the accrual job paid out.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InterestAccrualRun {
+    pub accrual_date: NaiveDate,
+    pub accounts_credited: i64,
+    pub total_interest: BigDecimal,
+}
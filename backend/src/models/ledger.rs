@@ -0,0 +1,114 @@
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use strum::Display;
+use strum::EnumString;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Copy, Display, EnumString, PartialEq, Serialize, Deserialize, sqlx::Type)]
+#[strum(serialize_all = "SCREAMING_SNAKE_CASE")]
+#[sqlx(type_name = "journal_entry_type", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum JournalEntryType {
+    OpeningBalance,
+    Deposit,
+    Withdrawal,
+    Reservation,
+    TradeBuy,
+    TradeSell,
+    Fee,
+    LoanDisbursement,
+    LoanRepayment,
+    Reset,
+    Transfer,
+    Dividend,
+    Interest,
+}
+
+impl JournalEntryType {
+    /// Cash moved in or out of the account from outside, as opposed to what it earned or spent
+    /// trading. Performance figures take these out of returns.
+    pub fn is_external_flow(&self) -> bool {
+        matches!(
+            self,
+            JournalEntryType::OpeningBalance
+                | JournalEntryType::Deposit
+                | JournalEntryType::Withdrawal
+                | JournalEntryType::LoanDisbursement
+                | JournalEntryType::LoanRepayment
+                | JournalEntryType::Reset
+                | JournalEntryType::Transfer
+        )
+    }
+
+    //the account on the other side of a user's cash movement
+    pub fn counter_account(&self) -> LedgerAccount {
+        match self {
+            JournalEntryType::Deposit | JournalEntryType::Withdrawal => LedgerAccount::ExternalBank,
+            JournalEntryType::TradeBuy | JournalEntryType::TradeSell => LedgerAccount::Market,
+            JournalEntryType::Fee => LedgerAccount::Fees,
+            JournalEntryType::LoanDisbursement | JournalEntryType::LoanRepayment => {
+                LedgerAccount::Loans
+            }
+            JournalEntryType::Transfer => LedgerAccount::Transfers,
+            JournalEntryType::Dividend => LedgerAccount::Dividends,
+            JournalEntryType::Interest => LedgerAccount::Interest,
+            JournalEntryType::OpeningBalance
+            | JournalEntryType::Reservation
+            | JournalEntryType::Reset => LedgerAccount::Equity,
+        }
+    }
+}
+
+/// USER_CASH is the account's available balance and USER_RESERVED the cash held for open buys,
+/// so `accounts.balance` = USER_CASH + USER_RESERVED and `accounts.available_balance` = USER_CASH.
+#[derive(Debug, Clone, Copy, Display, EnumString, PartialEq, Serialize, Deserialize, sqlx::Type)]
+#[strum(serialize_all = "SCREAMING_SNAKE_CASE")]
+#[sqlx(type_name = "ledger_account", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum LedgerAccount {
+    UserCash,
+    UserReserved,
+    ExternalBank,
+    Market,
+    Fees,
+    Loans,
+    Equity,
+    Transfers,
+    Dividends,
+    Interest,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalLine {
+    pub account: LedgerAccount,
+    pub amount: BigDecimal,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub entry_id: Uuid,
+    pub account_id: Uuid,
+    pub entry_type: JournalEntryType,
+    pub reference_id: Option<Uuid>,
+    pub lines: Vec<JournalLine>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LedgerBalance {
+    pub account_id: Uuid,
+    pub cash: BigDecimal,
+    pub reserved: BigDecimal,
+}
+
+/// A journal entry seen from the user's side: how much their total balance changed and what it
+/// stood at afterwards. Reservations move cash between user accounts and are not included.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CashMovement {
+    pub entry_id: Uuid,
+    pub account_id: Uuid,
+    pub movement_type: JournalEntryType,
+    pub reference_id: Option<Uuid>,
+    pub amount: BigDecimal,
+    pub running_balance: BigDecimal,
+    pub created_at: DateTime<Utc>,
+}
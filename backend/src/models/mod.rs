@@ -1,9 +1,29 @@
+pub mod account;
+pub mod alert;
+pub mod allocation;
+pub mod analytics;
 pub mod authentication;
+pub mod corporate_action;
 pub mod errors;
+pub mod fee;
+pub mod history;
+pub mod idempotency;
+pub mod instrument;
+pub mod interest;
+pub mod ledger;
 pub mod loan;
 pub mod order;
 pub mod portfolio_ticker;
+pub mod price_bar;
+pub mod rebalance;
+pub mod reconciliation;
+pub mod risk;
+pub mod simulation;
+pub mod snapshot;
 pub mod stock_ticker;
 pub mod stock_trade;
+pub mod tax_lot;
+pub mod tax_report;
 pub mod transaction;
 pub mod user;
+pub mod watchlist;
@@ -1,4 +1,5 @@
 use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use strum::Display;
 use strum::EnumString;
@@ -8,12 +9,13 @@ use uuid::Uuid;
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Order {
     pub order_id: Uuid,
-    pub user_id: Uuid,
+    pub account_id: Uuid,
     pub ticker: String,
     pub quantity: BigDecimal,
     pub price_per_share: BigDecimal,
     pub order_type: OrderType, // Buy or Sell
     pub status: OrderStatus,
+    pub created_at: DateTime<Utc>,
 }
 #[allow(dead_code)]
 #[derive(Debug, Clone, Display, EnumString, Serialize, Deserialize, sqlx::Type)]
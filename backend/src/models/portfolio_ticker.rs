@@ -5,25 +5,30 @@ use uuid::Uuid;
 
 #[derive(Serialize)]
 pub struct PortfolioTicker {
-    pub user_id: Uuid,
+    pub account_id: Uuid,
     pub ticker: String,
     pub quantity: BigDecimal,
+    pub available_quantity: BigDecimal,
+    /// Cost basis of the shares still held.
     pub total_money_spent: BigDecimal,
+    pub average_cost: BigDecimal,
+    pub market_value: BigDecimal,
+    /// Market value less the cost basis of the shares held.
+    pub unrealized_pnl: BigDecimal,
+    /// Proceeds less cost basis of the shares sold since the position was opened.
+    pub realized_pnl: BigDecimal,
+    /// Commissions paid on the position, net of rebates. Not part of `total_money_spent`.
+    pub total_fees: BigDecimal,
+    /// Realized and unrealized P&L less fees.
     pub total_profit: BigDecimal,
     pub created_at: DateTime<Utc>,
 }
 
-#[derive(Serialize)]
-pub struct HistoricalStockValue {
-    pub date: DateTime<Utc>,
-    pub ticker: String,
-    pub price_per_share: BigDecimal,
-    pub quantity: BigDecimal,
-    pub total_value: BigDecimal,
-}
-
+/// The account's value at the end of a past day, or right now for the last point of a history.
 #[derive(Serialize)]
 pub struct PortfolioHistoryPoint {
     pub date: DateTime<Utc>,
+    pub cash: BigDecimal,
+    pub positions_value: BigDecimal,
     pub total_value: BigDecimal,
 }
@@ -0,0 +1,57 @@
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// How long each bar covers. Buckets are aligned to the Unix epoch, so daily bars start at
+/// midnight UTC like the rows in `stock_prices`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "snake_case")]
+#[sqlx(type_name = "bar_interval", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum BarInterval {
+    Minute,
+    FiveMinutes,
+    FifteenMinutes,
+    Hour,
+    Day,
+}
+
+impl BarInterval {
+    pub const ALL: [BarInterval; 5] = [
+        BarInterval::Minute,
+        BarInterval::FiveMinutes,
+        BarInterval::FifteenMinutes,
+        BarInterval::Hour,
+        BarInterval::Day,
+    ];
+
+    pub fn seconds(&self) -> i64 {
+        match self {
+            BarInterval::Minute => 60,
+            BarInterval::FiveMinutes => 5 * 60,
+            BarInterval::FifteenMinutes => 15 * 60,
+            BarInterval::Hour => 60 * 60,
+            BarInterval::Day => 24 * 60 * 60,
+        }
+    }
+
+    /// Start of the bar that a trade at `at` falls into.
+    pub fn bucket_start(&self, at: DateTime<Utc>) -> DateTime<Utc> {
+        let seconds = at.timestamp().div_euclid(self.seconds()) * self.seconds();
+        DateTime::from_timestamp(seconds, 0).unwrap_or(at)
+    }
+}
+
+/// Open, high, low, close and volume of the trades in one bucket.
+#[derive(Debug, Clone, Serialize)]
+pub struct PriceBar {
+    pub ticker: String,
+    pub interval: BarInterval,
+    pub bucket_start: DateTime<Utc>,
+    pub open: BigDecimal,
+    pub high: BigDecimal,
+    pub low: BigDecimal,
+    pub close: BigDecimal,
+    pub volume: BigDecimal,
+    pub trade_count: i32,
+    pub last_trade_at: DateTime<Utc>,
+}
@@ -0,0 +1,280 @@
+use std::collections::{BTreeMap, HashSet};
+
+use bigdecimal::{BigDecimal, RoundingMode};
+use chrono::{DateTime, Utc};
+use num_traits::Zero;
+use serde::{Deserialize, Serialize};
+use strum::{Display, EnumString};
+use uuid::Uuid;
+
+use crate::models::fee::FeeTerms;
+use crate::models::order::OrderType;
+
+const WEIGHT_SCALE: i64 = 6;
+
+fn weight(part: &BigDecimal, whole: &BigDecimal) -> BigDecimal {
+    if whole.is_zero() {
+        return BigDecimal::zero();
+    }
+    (part / whole).with_scale_round(WEIGHT_SCALE, RoundingMode::HalfUp)
+}
+
+/// `quantity` rounded down to a whole number of lots.
+fn whole_lots(quantity: &BigDecimal, lot_size: &BigDecimal) -> BigDecimal {
+    (quantity / lot_size).with_scale_round(0, RoundingMode::Down) * lot_size
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TargetWeight {
+    pub ticker: String,
+    pub weight: BigDecimal,
+}
+
+/// The weights an account wants to hold. Whatever the ticker weights leave over is the cash
+/// target, e.g. 40% AAPL and 30% MSFT keep 30% in cash. With `auto_rebalance` on, the account
+/// is rebalanced without confirmation once it drifts further than `drift_threshold`.
+#[derive(Debug, Clone, Serialize)]
+pub struct TargetAllocation {
+    pub targets: Vec<TargetWeight>,
+    pub cash_weight: BigDecimal,
+    pub drift_threshold: Option<BigDecimal>,
+    pub auto_rebalance: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct TargetAllocationRequest {
+    pub targets: Vec<TargetWeight>,
+    pub drift_threshold: Option<BigDecimal>,
+    #[serde(default)]
+    pub auto_rebalance: bool,
+}
+
+impl TargetAllocation {
+    /// Checks and normalises a request: tickers are uppercased and sorted, weights are rounded
+    /// to six places and must be positive and add up to at most one.
+    pub fn new(request: TargetAllocationRequest) -> Result<Self, String> {
+        let mut seen = HashSet::new();
+        let mut targets = Vec::with_capacity(request.targets.len());
+        for target in request.targets {
+            let ticker = target.ticker.trim().to_uppercase();
+            if ticker.is_empty() {
+                return Err("ticker must not be empty".to_string());
+            }
+            if !seen.insert(ticker.clone()) {
+                return Err(format!("{} is listed more than once", ticker));
+            }
+            let weight = target
+                .weight
+                .with_scale_round(WEIGHT_SCALE, RoundingMode::HalfUp);
+            if weight <= BigDecimal::zero() {
+                return Err(format!("weight of {} must be positive", ticker));
+            }
+            targets.push(TargetWeight { ticker, weight });
+        }
+        targets.sort_by(|a, b| a.ticker.cmp(&b.ticker));
+        let one = BigDecimal::from(1);
+        let invested: BigDecimal = targets.iter().map(|t| &t.weight).sum();
+        if invested > one {
+            return Err("weights add up to more than 1".to_string());
+        }
+        let drift_threshold = request
+            .drift_threshold
+            .map(|threshold| threshold.with_scale_round(WEIGHT_SCALE, RoundingMode::HalfUp));
+        if let Some(threshold) = &drift_threshold {
+            if *threshold <= BigDecimal::zero() || *threshold >= one {
+                return Err("drift threshold must be between 0 and 1".to_string());
+            }
+        }
+        if request.auto_rebalance && drift_threshold.is_none() {
+            return Err("auto-rebalancing needs a drift threshold".to_string());
+        }
+        Ok(Self {
+            cash_weight: one - invested,
+            targets,
+            drift_threshold,
+            auto_rebalance: request.auto_rebalance,
+        })
+    }
+}
+
+/// A ticker the account holds or targets, at its latest price. `quantity` is zero for targets
+/// not yet held.
+#[derive(Debug, Clone)]
+pub struct RebalanceHolding {
+    pub ticker: String,
+    pub quantity: BigDecimal,
+    /// Shares not already reserved by open sell orders.
+    pub available_quantity: BigDecimal,
+    pub price: BigDecimal,
+    pub lot_size: BigDecimal,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProposedOrder {
+    pub ticker: String,
+    pub order_type: OrderType,
+    pub quantity: BigDecimal,
+    pub price_per_share: BigDecimal,
+    pub current_weight: BigDecimal,
+    pub target_weight: BigDecimal,
+    /// Set once the proposal is submitted, to the placed order or why it could not be placed.
+    pub order_id: Option<Uuid>,
+    pub error: Option<String>,
+}
+
+/// The orders that bring an account back to its targets, sells first. `drift` is the largest
+/// gap between a current weight and its target, cash included.
+#[derive(Debug, Clone)]
+pub struct RebalancePlan {
+    pub total_value: BigDecimal,
+    pub drift: BigDecimal,
+    pub orders: Vec<ProposedOrder>,
+}
+
+impl RebalancePlan {
+    /// Sizes orders in whole lots at the latest prices. Tickers held without a target are sold
+    /// in full. Sells are limited to the shares not already reserved, and buys are scaled back
+    /// to what the available cash and the sells' proceeds pay for once the most `fees` can
+    /// charge on each order is taken off.
+    pub fn compute(
+        cash: &BigDecimal,
+        available_cash: &BigDecimal,
+        holdings: &[RebalanceHolding],
+        targets: &[TargetWeight],
+        fees: &FeeTerms,
+    ) -> Self {
+        let target_weights: BTreeMap<&str, &BigDecimal> = targets
+            .iter()
+            .map(|t| (t.ticker.as_str(), &t.weight))
+            .collect();
+        let invested: BigDecimal = holdings.iter().map(|h| &h.quantity * &h.price).sum();
+        let total_value = cash + &invested;
+        let cash_target =
+            BigDecimal::from(1) - targets.iter().map(|t| &t.weight).sum::<BigDecimal>();
+
+        let mut drift = (weight(cash, &total_value) - cash_target).abs();
+        let mut sells = Vec::new();
+        let mut buys = Vec::new();
+        for holding in holdings {
+            let target_weight = target_weights
+                .get(holding.ticker.as_str())
+                .map_or_else(BigDecimal::zero, |w| (*w).clone());
+            let value = &holding.quantity * &holding.price;
+            let current_weight = weight(&value, &total_value);
+            drift = drift.max((&current_weight - &target_weight).abs());
+            if holding.price <= BigDecimal::zero() {
+                continue;
+            }
+            let gap = &target_weight * &total_value - &value;
+            let (order_type, quantity) = if gap < BigDecimal::zero() {
+                let quantity = if target_weight.is_zero() {
+                    holding.available_quantity.clone()
+                } else {
+                    whole_lots(&(-&gap / &holding.price), &holding.lot_size)
+                        .min(holding.available_quantity.clone())
+                };
+                (OrderType::Sell, quantity)
+            } else {
+                let quantity = whole_lots(&(&gap / &holding.price), &holding.lot_size);
+                (OrderType::Buy, quantity)
+            };
+            if quantity <= BigDecimal::zero() {
+                continue;
+            }
+            let order = ProposedOrder {
+                ticker: holding.ticker.clone(),
+                order_type,
+                quantity,
+                price_per_share: holding.price.clone(),
+                current_weight,
+                target_weight,
+                order_id: None,
+                error: None,
+            };
+            match order.order_type {
+                OrderType::Sell => sells.push(order),
+                OrderType::Buy => buys.push((order, &holding.lot_size)),
+            }
+        }
+
+        let proceeds: BigDecimal = sells
+            .iter()
+            .map(|o| {
+                let notional = &o.quantity * &o.price_per_share;
+                &notional - fees.max_order_fee(&o.quantity, &notional)
+            })
+            .sum();
+        let budget = available_cash + proceeds;
+        //what placing the buy reserves, its price and the most it can be charged
+        let cost_of = |order: &ProposedOrder| {
+            if order.quantity <= BigDecimal::zero() {
+                return BigDecimal::zero();
+            }
+            let notional = &order.quantity * &order.price_per_share;
+            fees.max_order_fee(&order.quantity, &notional) + notional
+        };
+        let cost: BigDecimal = buys.iter().map(|(o, _)| cost_of(o)).sum();
+        if cost > budget {
+            let scale = if budget > BigDecimal::zero() {
+                &budget / &cost
+            } else {
+                BigDecimal::zero()
+            };
+            for (order, lot_size) in &mut buys {
+                order.quantity = whole_lots(&(&order.quantity * &scale), lot_size);
+            }
+            //a minimum fee doesn't shrink with the order, so the largest buy gives up a lot at
+            //a time until the fees fit as well
+            while buys.iter().map(|(o, _)| cost_of(o)).sum::<BigDecimal>() > budget {
+                let Some((order, lot_size)) = buys
+                    .iter_mut()
+                    .filter(|(o, _)| o.quantity > BigDecimal::zero())
+                    .max_by_key(|(o, _)| &o.quantity * &o.price_per_share)
+                else {
+                    break;
+                };
+                order.quantity = (&order.quantity - *lot_size).max(BigDecimal::zero());
+            }
+        }
+
+        let mut orders = sells;
+        orders.extend(
+            buys.into_iter()
+                .map(|(order, _)| order)
+                .filter(|order| order.quantity > BigDecimal::zero()),
+        );
+        Self {
+            total_value,
+            drift,
+            orders,
+        }
+    }
+}
+
+#[derive(
+    Debug, Clone, Copy, Display, EnumString, PartialEq, Serialize, Deserialize, sqlx::Type,
+)]
+#[strum(serialize_all = "SCREAMING_SNAKE_CASE")]
+#[sqlx(
+    type_name = "rebalance_proposal_status",
+    rename_all = "SCREAMING_SNAKE_CASE"
+)]
+pub enum RebalanceProposalStatus {
+    Proposed,
+    Submitted,
+}
+
+/// A rebalance plan waiting for confirmation, or submitted as a batch of orders. Proposals
+/// can't be submitted after `expires_at`, as the prices they were sized at go stale.
+#[derive(Debug, Clone, Serialize)]
+pub struct RebalanceProposal {
+    pub proposal_id: Uuid,
+    pub account_id: Uuid,
+    pub status: RebalanceProposalStatus,
+    pub total_value: BigDecimal,
+    pub drift: BigDecimal,
+    pub orders: Vec<ProposedOrder>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub submitted_at: Option<DateTime<Utc>>,
+}
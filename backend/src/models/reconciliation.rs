@@ -0,0 +1,65 @@
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use strum::Display;
+use strum::EnumString;
+use uuid::Uuid;
+
+#[derive(
+    Debug, Clone, Copy, Display, EnumString, PartialEq, Serialize, Deserialize, sqlx::Type,
+)]
+#[strum(serialize_all = "SCREAMING_SNAKE_CASE")]
+#[sqlx(
+    type_name = "reconciliation_break_type",
+    rename_all = "SCREAMING_SNAKE_CASE"
+)]
+pub enum BreakType {
+    /// `portfolio.quantity` differs from the net of the account's transactions and splits.
+    PositionQuantity,
+    /// Shares held back from `available_quantity` differ from the account's pending sells.
+    ReservedQuantity,
+    /// `accounts.balance` differs from non-trade ledger entries plus the net trade cash flow.
+    CashBalance,
+    /// `accounts.available_balance` differs from the ledger's USER_CASH total.
+    AvailableCash,
+    /// A PENDING order is not resting in the in-memory order book.
+    MissingFromOrderBook,
+    /// The in-memory order book holds an order that is no longer PENDING.
+    StaleInOrderBook,
+    /// The resting quantity in the order book differs from the PENDING row.
+    OrderBookQuantity,
+    /// The system user holds fewer shares than it has sold since it was seeded.
+    SystemInventory,
+}
+
+impl BreakType {
+    //only the in-memory order book is repaired automatically, stored data needs a human
+    pub fn is_repairable(&self) -> bool {
+        matches!(
+            self,
+            BreakType::MissingFromOrderBook
+                | BreakType::StaleInOrderBook
+                | BreakType::OrderBookQuantity
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReconciliationBreak {
+    pub break_type: BreakType,
+    pub account_id: Option<Uuid>,
+    pub ticker: Option<String>,
+    pub order_id: Option<Uuid>,
+    pub expected: BigDecimal,
+    pub actual: BigDecimal,
+    pub repaired: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReconciliationReport {
+    pub report_id: Uuid,
+    pub auto_repair: bool,
+    pub started_at: DateTime<Utc>,
+    pub completed_at: DateTime<Utc>,
+    pub breaks: Vec<ReconciliationBreak>,
+}
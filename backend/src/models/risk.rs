@@ -0,0 +1,267 @@
+use rand::Rng;
+use rand_distr::StandardNormal;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VarMethod {
+    /// Replays the actual daily returns of the lookback window against today's positions.
+    Historical,
+    /// Draws returns from a multivariate normal fitted to the window's log returns.
+    MonteCarlo,
+}
+
+/// How a risk report is computed. `seed` makes Monte Carlo runs repeatable.
+#[derive(Debug, Clone, Deserialize)]
+pub struct RiskParameters {
+    #[serde(default = "RiskParameters::default_method")]
+    pub method: VarMethod,
+    #[serde(default = "RiskParameters::default_confidence")]
+    pub confidence: f64,
+    #[serde(default = "RiskParameters::default_lookback_days")]
+    pub lookback_days: u32,
+    #[serde(default = "RiskParameters::default_simulations")]
+    pub simulations: u32,
+    pub seed: Option<u64>,
+}
+
+impl Default for RiskParameters {
+    fn default() -> Self {
+        Self {
+            method: Self::default_method(),
+            confidence: Self::default_confidence(),
+            lookback_days: Self::default_lookback_days(),
+            simulations: Self::default_simulations(),
+            seed: None,
+        }
+    }
+}
+
+impl RiskParameters {
+    /// Enough for stable tail estimates while keeping a run to a fraction of a second.
+    pub const MAX_SIMULATIONS: u32 = 100_000;
+
+    fn default_method() -> VarMethod {
+        VarMethod::Historical
+    }
+
+    fn default_confidence() -> f64 {
+        0.95
+    }
+
+    fn default_lookback_days() -> u32 {
+        250
+    }
+
+    fn default_simulations() -> u32 {
+        10_000
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.confidence > 0.5 && self.confidence < 1.0) {
+            return Err("confidence must be between 0.5 and 1".to_string());
+        }
+        if !(2..=3650).contains(&self.lookback_days) {
+            return Err("lookback must be between 2 and 3650 days".to_string());
+        }
+        if !(100..=Self::MAX_SIMULATIONS).contains(&self.simulations) {
+            return Err(format!(
+                "simulations must be between 100 and {}",
+                Self::MAX_SIMULATIONS
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Loss over the horizon that is only exceeded with probability 1 - confidence, and the average
+/// loss when it is. Losses are positive; a negative value at risk means even the tail gains.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct RiskMeasure {
+    pub value_at_risk: f64,
+    pub expected_shortfall: f64,
+}
+
+impl RiskMeasure {
+    pub fn from_pnl(mut pnl: Vec<f64>, confidence: f64) -> Option<Self> {
+        if pnl.is_empty() {
+            return None;
+        }
+        pnl.sort_by(f64::total_cmp);
+        //the worst (1 - confidence) of outcomes, allowing for 1 - 0.95 not being exactly 0.05
+        let tail = ((1.0 - confidence) * pnl.len() as f64 + 1e-9).floor() as usize;
+        let tail = tail.clamp(1, pnl.len());
+        Some(Self {
+            value_at_risk: -pnl[tail - 1],
+            expected_shortfall: -pnl[..tail].iter().sum::<f64>() / tail as f64,
+        })
+    }
+
+    fn zero() -> Self {
+        Self {
+            value_at_risk: 0.0,
+            expected_shortfall: 0.0,
+        }
+    }
+}
+
+/// One-day and ten-day risk of the account's current positions. `ten_day` is None when the
+/// window is too short to hold ten days of returns.
+#[derive(Debug, Clone, Serialize)]
+pub struct RiskReport {
+    pub method: VarMethod,
+    pub confidence: f64,
+    pub lookback_days: u32,
+    /// Days of returns the window held for every ticker.
+    pub observations: usize,
+    pub positions_value: f64,
+    pub one_day: RiskMeasure,
+    pub ten_day: Option<RiskMeasure>,
+}
+
+/// The value held in each ticker and their daily simple returns, one row per day with a column
+/// per ticker in the same order.
+pub struct ReturnHistory {
+    pub exposures: Vec<f64>,
+    pub returns: Vec<Vec<f64>>,
+}
+
+impl ReturnHistory {
+    pub const TEN_DAYS: usize = 10;
+
+    fn pnl(&self, returns: &[f64]) -> f64 {
+        self.exposures.iter().zip(returns).map(|(e, r)| e * r).sum()
+    }
+
+    pub fn report(&self, parameters: &RiskParameters, rng: &mut impl Rng) -> RiskReport {
+        let (one_day, ten_day) = match parameters.method {
+            VarMethod::Historical => self.historical(parameters.confidence),
+            VarMethod::MonteCarlo => {
+                self.monte_carlo(parameters.confidence, parameters.simulations as usize, rng)
+            }
+        };
+        RiskReport {
+            method: parameters.method,
+            confidence: parameters.confidence,
+            lookback_days: parameters.lookback_days,
+            observations: self.returns.len(),
+            positions_value: self.exposures.iter().sum(),
+            one_day: one_day.unwrap_or_else(RiskMeasure::zero),
+            ten_day,
+        }
+    }
+
+    /// Ten-day losses come from every overlapping ten-day window of the history.
+    pub fn historical(&self, confidence: f64) -> (Option<RiskMeasure>, Option<RiskMeasure>) {
+        let one_day = self.returns.iter().map(|day| self.pnl(day)).collect();
+        let ten_day: Vec<f64> = self
+            .returns
+            .windows(Self::TEN_DAYS)
+            .map(|window| {
+                let compounded: Vec<f64> = (0..self.exposures.len())
+                    .map(|i| window.iter().map(|day| 1.0 + day[i]).product::<f64>() - 1.0)
+                    .collect();
+                self.pnl(&compounded)
+            })
+            .collect();
+        (
+            RiskMeasure::from_pnl(one_day, confidence),
+            RiskMeasure::from_pnl(ten_day, confidence),
+        )
+    }
+
+    /// Log returns are drawn from a normal with the history's means and covariances. Ten days
+    /// of independent draws add up to ten times the mean and √10 times the deviation.
+    pub fn monte_carlo(
+        &self,
+        confidence: f64,
+        simulations: usize,
+        rng: &mut impl Rng,
+    ) -> (Option<RiskMeasure>, Option<RiskMeasure>) {
+        let n = self.exposures.len();
+        if n == 0 || self.returns.len() < 2 {
+            return (None, None);
+        }
+        let logs: Vec<Vec<f64>> = self
+            .returns
+            .iter()
+            .map(|day| day.iter().map(|r| r.ln_1p()).collect())
+            .collect();
+        let days = logs.len() as f64;
+        let means: Vec<f64> = (0..n)
+            .map(|i| logs.iter().map(|day| day[i]).sum::<f64>() / days)
+            .collect();
+        let covariance: Vec<Vec<f64>> = (0..n)
+            .map(|i| {
+                (0..n)
+                    .map(|j| {
+                        logs.iter()
+                            .map(|day| (day[i] - means[i]) * (day[j] - means[j]))
+                            .sum::<f64>()
+                            / (days - 1.0)
+                    })
+                    .collect()
+            })
+            .collect();
+        let Some(factor) = cholesky(&covariance) else {
+            return (None, None);
+        };
+
+        let horizon = Self::TEN_DAYS as f64;
+        let mut one_day = Vec::with_capacity(simulations);
+        let mut ten_day = Vec::with_capacity(simulations);
+        for _ in 0..simulations {
+            let z: Vec<f64> = (0..n).map(|_| rng.sample(StandardNormal)).collect();
+            let shock: Vec<f64> = (0..n)
+                .map(|i| (0..=i).map(|j| factor[i][j] * z[j]).sum())
+                .collect();
+            let simulate = |days: f64| -> Vec<f64> {
+                (0..n)
+                    .map(|i| (means[i] * days + shock[i] * days.sqrt()).exp_m1())
+                    .collect()
+            };
+            one_day.push(self.pnl(&simulate(1.0)));
+            ten_day.push(self.pnl(&simulate(horizon)));
+        }
+        (
+            RiskMeasure::from_pnl(one_day, confidence),
+            RiskMeasure::from_pnl(ten_day, confidence),
+        )
+    }
+}
+
+/// Lower-triangular L with L·Lᵀ = `matrix`. Tickers that moved in lockstep make the covariance
+/// only semi-definite, so a little is added to the diagonal until it factors.
+fn cholesky(matrix: &[Vec<f64>]) -> Option<Vec<Vec<f64>>> {
+    let n = matrix.len();
+    let scale = (0..n).map(|i| matrix[i][i]).sum::<f64>() / n as f64;
+    let mut jitter = 0.0;
+    for _ in 0..10 {
+        let mut factor = vec![vec![0.0; n]; n];
+        let mut factored = true;
+        'rows: for i in 0..n {
+            for j in 0..=i {
+                let sum: f64 = (0..j).map(|k| factor[i][k] * factor[j][k]).sum();
+                if i == j {
+                    let diagonal = matrix[i][i] + jitter - sum;
+                    if diagonal < 0.0 || !diagonal.is_finite() {
+                        factored = false;
+                        break 'rows;
+                    }
+                    factor[i][j] = diagonal.sqrt();
+                } else if factor[j][j] > 0.0 {
+                    factor[i][j] = (matrix[i][j] - sum) / factor[j][j];
+                }
+            }
+        }
+        if factored {
+            return Some(factor);
+        }
+        jitter = if jitter == 0.0 {
+            scale.max(f64::EPSILON) * 1e-10
+        } else {
+            jitter * 10.0
+        };
+    }
+    None
+}
@@ -0,0 +1,237 @@
+use std::collections::BTreeMap;
+
+use bigdecimal::{BigDecimal, RoundingMode};
+use chrono::Utc;
+use num_traits::Zero;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::models::order::OrderType;
+use crate::models::risk::{RiskParameters, RiskReport};
+use crate::models::tax_lot::{CostBasisMethod, Position, TaxLot};
+
+/// A hypothetical order. Without a price it fills at the latest close.
+#[derive(Debug, Clone, Deserialize)]
+pub struct SimulatedOrder {
+    pub ticker: String,
+    pub order_type: OrderType,
+    pub quantity: BigDecimal,
+    pub price_per_share: Option<BigDecimal>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SimulationRequest {
+    pub orders: Vec<SimulatedOrder>,
+    /// How value at risk is measured before and after the orders.
+    #[serde(default)]
+    pub risk: RiskParameters,
+}
+
+/// An order as the simulation fills it, in full at `price_per_share` and charged the most its
+/// fee can be.
+#[derive(Debug, Clone, Serialize)]
+pub struct SimulatedFill {
+    pub ticker: String,
+    pub order_type: OrderType,
+    pub quantity: BigDecimal,
+    pub price_per_share: BigDecimal,
+    pub estimated_fee: BigDecimal,
+}
+
+/// A position being simulated: its open lots, cost and the price it is valued at.
+#[derive(Debug, Clone)]
+pub struct ProjectedHolding {
+    pub quantity: BigDecimal,
+    /// Shares not reserved by open sell orders, so free to sell.
+    pub available_quantity: BigDecimal,
+    pub cost_basis: BigDecimal,
+    pub price: BigDecimal,
+    pub lots: Vec<TaxLot>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PositionState {
+    pub ticker: String,
+    pub quantity: BigDecimal,
+    pub average_cost: BigDecimal,
+    pub market_value: BigDecimal,
+    /// Share of the account's total value, cash included.
+    pub weight: BigDecimal,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PortfolioState {
+    pub cash: BigDecimal,
+    pub available_cash: BigDecimal,
+    pub positions_value: BigDecimal,
+    pub total_value: BigDecimal,
+    pub positions: Vec<PositionState>,
+}
+
+/// The account as it would be after its hypothetical fills. Sells close lots the way the
+/// account's cost basis method would, so average cost and realized P&L follow the real rules.
+#[derive(Debug, Clone)]
+pub struct PortfolioProjection {
+    pub account_id: Uuid,
+    pub cost_basis_method: CostBasisMethod,
+    pub cash: BigDecimal,
+    pub available_cash: BigDecimal,
+    pub holdings: BTreeMap<String, ProjectedHolding>,
+    pub realized_pnl: BigDecimal,
+}
+
+impl PortfolioProjection {
+    const SCALE: i64 = 4;
+    const WEIGHT_SCALE: i64 = 6;
+
+    /// Fills `fill` against the projection, or says why the account couldn't place it.
+    pub fn apply(&mut self, fill: &SimulatedFill) -> Result<(), String> {
+        if fill.quantity <= BigDecimal::zero() {
+            return Err(format!("quantity of {} must be positive", fill.ticker));
+        }
+        let value = (&fill.quantity * &fill.price_per_share)
+            .with_scale_round(Self::SCALE, RoundingMode::HalfUp);
+        match fill.order_type {
+            OrderType::Buy => {
+                let cost = &value + &fill.estimated_fee;
+                if cost > self.available_cash {
+                    return Err(format!(
+                        "not enough cash to buy {} {}",
+                        fill.quantity, fill.ticker
+                    ));
+                }
+                self.cash -= &cost;
+                self.available_cash -= &cost;
+                let holding =
+                    self.holdings
+                        .entry(fill.ticker.clone())
+                        .or_insert_with(|| ProjectedHolding {
+                            quantity: BigDecimal::zero(),
+                            available_quantity: BigDecimal::zero(),
+                            cost_basis: BigDecimal::zero(),
+                            price: fill.price_per_share.clone(),
+                            lots: Vec::new(),
+                        });
+                holding.quantity += &fill.quantity;
+                holding.available_quantity += &fill.quantity;
+                holding.cost_basis += &value;
+                holding.lots.push(TaxLot {
+                    lot_id: Uuid::new_v4(),
+                    account_id: self.account_id,
+                    ticker: fill.ticker.clone(),
+                    order_id: None,
+                    acquired_at: Utc::now(),
+                    quantity: fill.quantity.clone(),
+                    remaining_quantity: fill.quantity.clone(),
+                    cost_basis: value,
+                });
+            }
+            OrderType::Sell => {
+                let holding = self
+                    .holdings
+                    .get_mut(&fill.ticker)
+                    .filter(|holding| holding.available_quantity >= fill.quantity)
+                    .ok_or_else(|| {
+                        format!(
+                            "not enough {} shares to sell {}",
+                            fill.ticker, fill.quantity
+                        )
+                    })?;
+                let disposals = Position {
+                    lots: &holding.lots,
+                    quantity: &holding.quantity,
+                    cost_basis: &holding.cost_basis,
+                }
+                .dispose(self.cost_basis_method, &[], &fill.quantity);
+                let mut realized_cost = BigDecimal::zero();
+                for disposal in &disposals {
+                    realized_cost += &disposal.cost_basis;
+                    if let Some(lot) = holding
+                        .lots
+                        .iter_mut()
+                        .find(|lot| Some(lot.lot_id) == disposal.lot_id)
+                    {
+                        lot.remaining_quantity -= &disposal.quantity;
+                        lot.cost_basis -= &disposal.lot_cost;
+                    }
+                }
+                holding
+                    .lots
+                    .retain(|lot| lot.remaining_quantity > BigDecimal::zero());
+                holding.quantity -= &fill.quantity;
+                holding.available_quantity -= &fill.quantity;
+                holding.cost_basis -= &realized_cost;
+                self.realized_pnl += &value - &realized_cost;
+                let proceeds = &value - &fill.estimated_fee;
+                self.cash += &proceeds;
+                self.available_cash += &proceeds;
+                if holding.quantity.is_zero() {
+                    self.holdings.remove(&fill.ticker);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Value held in each ticker, for measuring risk.
+    pub fn exposures(&self) -> Vec<(String, BigDecimal)> {
+        self.holdings
+            .iter()
+            .map(|(ticker, holding)| (ticker.clone(), &holding.quantity * &holding.price))
+            .collect()
+    }
+
+    pub fn state(&self) -> PortfolioState {
+        let positions_value: BigDecimal = self
+            .holdings
+            .values()
+            .map(|holding| &holding.quantity * &holding.price)
+            .sum();
+        let total_value = &self.cash + &positions_value;
+        let positions = self
+            .holdings
+            .iter()
+            .map(|(ticker, holding)| {
+                let market_value = &holding.quantity * &holding.price;
+                PositionState {
+                    ticker: ticker.clone(),
+                    average_cost: if holding.quantity.is_zero() {
+                        BigDecimal::zero()
+                    } else {
+                        (&holding.cost_basis / &holding.quantity)
+                            .with_scale_round(Self::SCALE, RoundingMode::HalfUp)
+                    },
+                    weight: if total_value.is_zero() {
+                        BigDecimal::zero()
+                    } else {
+                        (&market_value / &total_value)
+                            .with_scale_round(Self::WEIGHT_SCALE, RoundingMode::HalfUp)
+                    },
+                    quantity: holding.quantity.clone(),
+                    market_value,
+                }
+            })
+            .collect();
+        PortfolioState {
+            cash: self.cash.clone(),
+            available_cash: self.available_cash.clone(),
+            positions_value,
+            total_value,
+            positions,
+        }
+    }
+}
+
+/// The account now and after the hypothetical orders. Credit scores are None when the user
+/// has no loan to score against.
+#[derive(Debug, Clone, Serialize)]
+pub struct SimulationResult {
+    pub orders: Vec<SimulatedFill>,
+    pub before: PortfolioState,
+    pub after: PortfolioState,
+    pub realized_pnl: BigDecimal,
+    pub risk_before: RiskReport,
+    pub risk_after: RiskReport,
+    pub credit_score_before: Option<BigDecimal>,
+    pub credit_score_after: Option<BigDecimal>,
+}
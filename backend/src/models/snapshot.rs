@@ -0,0 +1,30 @@
+use bigdecimal::BigDecimal;
+use chrono::NaiveDate;
+use serde::Serialize;
+use uuid::Uuid;
+
+/// An account's cash and positions at the end of a day, valued at that day's last prices.
+#[derive(Debug, Clone, Serialize)]
+pub struct PortfolioSnapshot {
+    pub account_id: Uuid,
+    pub snapshot_date: NaiveDate,
+    /// Available and reserved cash together.
+    pub cash: BigDecimal,
+    pub positions_value: BigDecimal,
+    pub total_value: BigDecimal,
+    pub positions: Vec<SnapshotPosition>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SnapshotPosition {
+    pub ticker: String,
+    pub quantity: BigDecimal,
+    pub price: BigDecimal,
+    pub market_value: BigDecimal,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SnapshotRun {
+    pub accounts_snapshotted: i64,
+    pub snapshots_written: i64,
+}
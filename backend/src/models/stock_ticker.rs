@@ -13,6 +13,16 @@ pub struct Ticker {
     pub low: Option<BigDecimal>,
 }
 
+/// Counters for the in-memory quote cache. Hits and misses count since startup.
+#[derive(Serialize, Debug)]
+pub struct QuoteCacheStats {
+    pub entries: usize,
+    pub fresh_entries: usize,
+    pub hits: u64,
+    pub misses: u64,
+    pub ttl_secs: u64,
+}
+
 #[derive(Serialize, Deserialize, Clone, Copy, Debug)]
 #[serde(rename_all = "snake_case")]
 pub enum TimeFrame {
@@ -23,3 +33,17 @@ pub enum TimeFrame {
     FiveYear,
     AllYears,
 }
+
+impl TimeFrame {
+    /// How many days the timeframe covers, today included; None for all of history.
+    pub fn lookback_days(&self) -> Option<i64> {
+        match self {
+            TimeFrame::Day => Some(1),
+            TimeFrame::Month => Some(30),
+            TimeFrame::HalfYear => Some(180),
+            TimeFrame::Year => Some(365),
+            TimeFrame::FiveYear => Some(5 * 365),
+            TimeFrame::AllYears => None,
+        }
+    }
+}
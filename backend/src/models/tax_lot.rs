@@ -0,0 +1,145 @@
+use std::cmp::Ordering;
+
+use bigdecimal::{BigDecimal, RoundingMode};
+use chrono::{DateTime, Utc};
+use num_traits::Zero;
+use serde::{Deserialize, Serialize};
+use strum::Display;
+use strum::EnumString;
+use uuid::Uuid;
+
+/// How a sell picks the lots it closes. `SpecificLot` accounts name the lots on each sell order;
+/// any quantity the named lots can't cover is taken first-in, first-out.
+#[derive(
+    Debug, Clone, Copy, Display, EnumString, PartialEq, Serialize, Deserialize, sqlx::Type,
+)]
+#[strum(serialize_all = "SCREAMING_SNAKE_CASE")]
+#[sqlx(type_name = "cost_basis_method", rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum CostBasisMethod {
+    Fifo,
+    Lifo,
+    AverageCost,
+    SpecificLot,
+}
+
+/// Shares bought in one fill, and what is left of them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaxLot {
+    pub lot_id: Uuid,
+    pub account_id: Uuid,
+    pub ticker: String,
+    pub order_id: Option<Uuid>,
+    pub acquired_at: DateTime<Utc>,
+    pub quantity: BigDecimal,
+    pub remaining_quantity: BigDecimal,
+    pub cost_basis: BigDecimal,
+}
+
+/// A sell fill, for recording what it realized.
+#[derive(Debug, Clone)]
+pub struct Sale {
+    pub order_id: Uuid,
+    pub price_per_share: BigDecimal,
+}
+
+/// Quantity closed out of one lot. `lot_cost` comes off the lot, `cost_basis` is what the sale
+/// realizes against, which differs from it under average cost. Quantity no lot covers (e.g.
+/// split rounding) has no `lot_id`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LotDisposal {
+    pub lot_id: Option<Uuid>,
+    pub acquired_at: Option<DateTime<Utc>>,
+    pub quantity: BigDecimal,
+    pub lot_cost: BigDecimal,
+    pub cost_basis: BigDecimal,
+}
+
+/// The open lots of one position along with its quantity and cost.
+pub struct Position<'a> {
+    pub lots: &'a [TaxLot],
+    pub quantity: &'a BigDecimal,
+    pub cost_basis: &'a BigDecimal,
+}
+
+impl Position<'_> {
+    const SCALE: i64 = 4;
+
+    fn share_of(total: &BigDecimal, part: &BigDecimal, whole: &BigDecimal) -> BigDecimal {
+        if whole.is_zero() {
+            return BigDecimal::zero();
+        }
+        (total * part / whole).with_scale_round(Self::SCALE, RoundingMode::HalfUp)
+    }
+
+    /// Closes `quantity` shares, taking the `selected` lots first in the order given and then
+    /// whatever `method` picks. Closing the whole position realizes its whole cost.
+    pub fn dispose(
+        &self,
+        method: CostBasisMethod,
+        selected: &[Uuid],
+        quantity: &BigDecimal,
+    ) -> Vec<LotDisposal> {
+        let mut ordered: Vec<&TaxLot> = self
+            .lots
+            .iter()
+            .filter(|lot| lot.remaining_quantity > BigDecimal::zero())
+            .collect();
+        ordered.sort_by(|a, b| {
+            let by_priority = |lot: &TaxLot| selected.iter().position(|id| *id == lot.lot_id);
+            match (by_priority(a), by_priority(b)) {
+                (Some(a), Some(b)) => a.cmp(&b),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => match method {
+                    CostBasisMethod::Lifo => b.acquired_at.cmp(&a.acquired_at),
+                    _ => a.acquired_at.cmp(&b.acquired_at),
+                },
+            }
+        });
+
+        let mut disposals = Vec::new();
+        let mut left = quantity.clone();
+        for lot in ordered {
+            if left <= BigDecimal::zero() {
+                break;
+            }
+            let take = left.clone().min(lot.remaining_quantity.clone());
+            let lot_cost = if take == lot.remaining_quantity {
+                lot.cost_basis.clone()
+            } else {
+                Self::share_of(&lot.cost_basis, &take, &lot.remaining_quantity)
+            };
+            left -= &take;
+            disposals.push(LotDisposal {
+                lot_id: Some(lot.lot_id),
+                acquired_at: Some(lot.acquired_at),
+                cost_basis: lot_cost.clone(),
+                quantity: take,
+                lot_cost,
+            });
+        }
+        if left > BigDecimal::zero() {
+            disposals.push(LotDisposal {
+                lot_id: None,
+                acquired_at: None,
+                quantity: left,
+                lot_cost: BigDecimal::zero(),
+                cost_basis: BigDecimal::zero(),
+            });
+        }
+
+        for disposal in disposals.iter_mut() {
+            if method == CostBasisMethod::AverageCost || disposal.lot_id.is_none() {
+                disposal.cost_basis =
+                    Self::share_of(self.cost_basis, &disposal.quantity, self.quantity);
+            }
+        }
+        if quantity >= self.quantity {
+            let realized: BigDecimal = disposals.iter().map(|d| &d.cost_basis).sum();
+            if let Some(last) = disposals.last_mut() {
+                last.cost_basis += self.cost_basis - realized;
+            }
+        }
+        disposals
+    }
+}
@@ -0,0 +1,209 @@
+use bigdecimal::{BigDecimal, RoundingMode};
+use chrono::{DateTime, Duration, Months, Utc};
+use num_traits::Zero;
+use serde::Serialize;
+use strum::Display;
+use uuid::Uuid;
+
+/// Shares held for more than a year are long-term. Shares sold without a known acquisition date
+/// (e.g. split rounding) are reported as short-term.
+#[derive(Debug, Clone, Copy, Display, PartialEq, Serialize)]
+#[strum(serialize_all = "SCREAMING_SNAKE_CASE")]
+pub enum HoldingPeriod {
+    ShortTerm,
+    LongTerm,
+}
+
+impl HoldingPeriod {
+    pub fn of(acquired_at: Option<DateTime<Utc>>, disposed_at: DateTime<Utc>) -> Self {
+        match acquired_at.and_then(|acquired| acquired.checked_add_months(Months::new(12))) {
+            Some(year_later) if disposed_at > year_later => HoldingPeriod::LongTerm,
+            _ => HoldingPeriod::ShortTerm,
+        }
+    }
+}
+
+/// Quantity of one lot closed by a sell fill. `lot_order_id` is the buy order that opened the
+/// lot, whose own fills don't count as replacement shares.
+#[derive(Debug, Clone)]
+pub struct Disposal {
+    pub ticker: String,
+    pub order_id: Uuid,
+    pub lot_id: Option<Uuid>,
+    pub lot_order_id: Option<Uuid>,
+    pub acquired_at: Option<DateTime<Utc>>,
+    pub disposed_at: DateTime<Utc>,
+    pub quantity: BigDecimal,
+    pub proceeds: BigDecimal,
+    pub cost_basis: BigDecimal,
+}
+
+/// A buy fill that could replace shares sold at a loss.
+#[derive(Debug, Clone)]
+pub struct Purchase {
+    pub ticker: String,
+    pub order_id: Option<Uuid>,
+    pub executed_at: DateTime<Utc>,
+    pub quantity: BigDecimal,
+}
+
+/// A closed lot as reported for tax. A loss is a wash sale when shares of the same ticker were
+/// bought within 30 days either side of the sale; the part of the loss those shares cover is
+/// `disallowed_loss`, and `adjusted_gain` is the gain with it added back.
+#[derive(Debug, Clone, Serialize)]
+pub struct ClosedLot {
+    pub ticker: String,
+    pub order_id: Uuid,
+    pub lot_id: Option<Uuid>,
+    pub acquired_at: Option<DateTime<Utc>>,
+    pub disposed_at: DateTime<Utc>,
+    pub quantity: BigDecimal,
+    pub proceeds: BigDecimal,
+    pub cost_basis: BigDecimal,
+    pub gain: BigDecimal,
+    pub holding_period: HoldingPeriod,
+    pub wash_sale: bool,
+    pub disallowed_loss: BigDecimal,
+    pub adjusted_gain: BigDecimal,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct GainTotals {
+    pub proceeds: BigDecimal,
+    pub cost_basis: BigDecimal,
+    pub gain: BigDecimal,
+    pub disallowed_loss: BigDecimal,
+    pub adjusted_gain: BigDecimal,
+}
+
+impl GainTotals {
+    fn add(&mut self, lot: &ClosedLot) {
+        self.proceeds += &lot.proceeds;
+        self.cost_basis += &lot.cost_basis;
+        self.gain += &lot.gain;
+        self.disallowed_loss += &lot.disallowed_loss;
+        self.adjusted_gain += &lot.adjusted_gain;
+    }
+}
+
+/// Every lot an account closed in a calendar year (UTC), oldest sale first, with short- and
+/// long-term totals.
+#[derive(Debug, Clone, Serialize)]
+pub struct TaxReport {
+    pub account_id: Uuid,
+    pub year: i32,
+    pub lots: Vec<ClosedLot>,
+    pub short_term: GainTotals,
+    pub long_term: GainTotals,
+    pub total: GainTotals,
+}
+
+impl TaxReport {
+    pub const WASH_SALE_DAYS: i64 = 30;
+    const SCALE: i64 = 4;
+    const CSV_HEADER: &'static str = "ticker,order_id,lot_id,acquired_at,disposed_at,quantity,\
+        proceeds,cost_basis,gain,holding_period,wash_sale,disallowed_loss,adjusted_gain\r\n";
+
+    /// `purchases` must cover 30 days either side of the year. Each purchased share replaces
+    /// at most one share sold at a loss, earliest sale first.
+    pub fn compute(
+        account_id: Uuid,
+        year: i32,
+        mut disposals: Vec<Disposal>,
+        purchases: &[Purchase],
+    ) -> Self {
+        disposals.sort_by_key(|disposal| disposal.disposed_at);
+        let mut unmatched: Vec<BigDecimal> = purchases.iter().map(|p| p.quantity.clone()).collect();
+        let window = Duration::days(Self::WASH_SALE_DAYS);
+
+        let mut report = Self {
+            account_id,
+            year,
+            lots: Vec::with_capacity(disposals.len()),
+            short_term: GainTotals::default(),
+            long_term: GainTotals::default(),
+            total: GainTotals::default(),
+        };
+        for disposal in disposals {
+            let gain = &disposal.proceeds - &disposal.cost_basis;
+            let mut replaced = BigDecimal::zero();
+            if gain < BigDecimal::zero() {
+                for (purchase, left) in purchases.iter().zip(unmatched.iter_mut()) {
+                    if replaced >= disposal.quantity {
+                        break;
+                    }
+                    let replaces = purchase.ticker == disposal.ticker
+                        && *left > BigDecimal::zero()
+                        && (purchase.order_id.is_none()
+                            || purchase.order_id != disposal.lot_order_id)
+                        && (purchase.executed_at - disposal.disposed_at).abs() <= window;
+                    if replaces {
+                        let take = (&disposal.quantity - &replaced).min(left.clone());
+                        *left -= &take;
+                        replaced += take;
+                    }
+                }
+            }
+            let disallowed_loss = if replaced.is_zero() || disposal.quantity.is_zero() {
+                BigDecimal::zero()
+            } else {
+                (-&gain * &replaced / &disposal.quantity)
+                    .with_scale_round(Self::SCALE, RoundingMode::HalfUp)
+            };
+            let lot = ClosedLot {
+                holding_period: HoldingPeriod::of(disposal.acquired_at, disposal.disposed_at),
+                wash_sale: !replaced.is_zero(),
+                adjusted_gain: &gain + &disallowed_loss,
+                disallowed_loss,
+                gain,
+                ticker: disposal.ticker,
+                order_id: disposal.order_id,
+                lot_id: disposal.lot_id,
+                acquired_at: disposal.acquired_at,
+                disposed_at: disposal.disposed_at,
+                quantity: disposal.quantity,
+                proceeds: disposal.proceeds,
+                cost_basis: disposal.cost_basis,
+            };
+            match lot.holding_period {
+                HoldingPeriod::ShortTerm => report.short_term.add(&lot),
+                HoldingPeriod::LongTerm => report.long_term.add(&lot),
+            }
+            report.total.add(&lot);
+            report.lots.push(lot);
+        }
+        report
+    }
+
+    /// One line per closed lot. Amounts are fixed-point so spreadsheets read them as numbers.
+    pub fn to_csv(&self) -> String {
+        let decimal = |value: &BigDecimal| {
+            value
+                .with_scale_round(Self::SCALE, RoundingMode::HalfEven)
+                .to_plain_string()
+        };
+        let mut csv = Self::CSV_HEADER.to_string();
+        for lot in &self.lots {
+            let fields = [
+                lot.ticker.clone(),
+                lot.order_id.to_string(),
+                lot.lot_id.map(|id| id.to_string()).unwrap_or_default(),
+                lot.acquired_at
+                    .map(|acquired| acquired.to_rfc3339())
+                    .unwrap_or_default(),
+                lot.disposed_at.to_rfc3339(),
+                decimal(&lot.quantity),
+                decimal(&lot.proceeds),
+                decimal(&lot.cost_basis),
+                decimal(&lot.gain),
+                lot.holding_period.to_string(),
+                lot.wash_sale.to_string(),
+                decimal(&lot.disallowed_loss),
+                decimal(&lot.adjusted_gain),
+            ];
+            csv.push_str(&fields.join(","));
+            csv.push_str("\r\n");
+        }
+        csv
+    }
+}
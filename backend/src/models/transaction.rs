@@ -1,3 +1,4 @@
+use crate::models::fee::Liquidity;
 use crate::models::order::OrderType;
 use bigdecimal::BigDecimal;
 use serde::{Deserialize, Serialize};
@@ -5,10 +6,16 @@ use uuid::Uuid;
 #[derive(Serialize, Deserialize)]
 pub struct Transaction {
     pub transaction_id: Uuid,
-    pub user_id: Uuid,
+    pub account_id: Uuid,
     pub ticker: String,
     pub quantity: BigDecimal,
     pub price_per_share: BigDecimal,
     pub order_type: OrderType,
+    pub order_id: Option<Uuid>,
+    pub liquidity: Option<Liquidity>,
+    /// Commission charged for this fill; negative for a maker rebate.
+    pub fee: BigDecimal,
+    /// Proceeds less cost basis of the lots a sell closed; None for buys.
+    pub realized_pnl: Option<BigDecimal>,
     pub executed_at: chrono::DateTime<chrono::Utc>,
 }
@@ -0,0 +1,122 @@
+use std::collections::HashSet;
+
+use bigdecimal::{BigDecimal, RoundingMode};
+use chrono::{DateTime, Utc};
+use num_traits::Zero;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// A named list of tickers, in the order the user put them in.
+#[derive(Debug, Clone, Serialize)]
+pub struct Watchlist {
+    pub watchlist_id: Uuid,
+    pub name: String,
+    pub position: i32,
+    pub tickers: Vec<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct WatchlistRequest {
+    pub name: String,
+    #[serde(default)]
+    pub tickers: Vec<String>,
+}
+
+impl WatchlistRequest {
+    pub const MAX_NAME_LENGTH: usize = 64;
+    pub const MAX_TICKERS: usize = 100;
+
+    /// Trims the name and uppercases the tickers, keeping their order.
+    pub fn normalise(self) -> Result<Self, String> {
+        let name = self.name.trim().to_string();
+        if name.is_empty() || name.chars().count() > Self::MAX_NAME_LENGTH {
+            return Err(format!(
+                "name must be between 1 and {} characters",
+                Self::MAX_NAME_LENGTH
+            ));
+        }
+        if self.tickers.len() > Self::MAX_TICKERS {
+            return Err(format!(
+                "a watchlist holds at most {} tickers",
+                Self::MAX_TICKERS
+            ));
+        }
+        let mut seen = HashSet::new();
+        let mut tickers = Vec::with_capacity(self.tickers.len());
+        for ticker in self.tickers {
+            let ticker = ticker.trim().to_uppercase();
+            if ticker.is_empty() || ticker.len() > 16 {
+                return Err(format!("{:?} is not a ticker", ticker));
+            }
+            if !seen.insert(ticker.clone()) {
+                return Err(format!("{} is listed more than once", ticker));
+            }
+            tickers.push(ticker);
+        }
+        Ok(Self { name, tickers })
+    }
+}
+
+/// The user's watchlists in the order they should be shown.
+#[derive(Debug, Clone, Deserialize)]
+pub struct WatchlistOrder {
+    pub watchlist_ids: Vec<Uuid>,
+}
+
+/// Latest price of a watched ticker and how it moved since the previous day's close. Price
+/// fields are None for tickers without prices. `volume` is summed over the latest day and
+/// `sparkline` holds a close per day, oldest first.
+#[derive(Debug, Clone, Serialize)]
+pub struct WatchlistQuote {
+    pub ticker: String,
+    pub price: Option<BigDecimal>,
+    pub quoted_at: Option<DateTime<Utc>>,
+    pub previous_close: Option<BigDecimal>,
+    pub day_change: Option<BigDecimal>,
+    pub day_change_percent: Option<BigDecimal>,
+    pub volume: Option<i64>,
+    pub sparkline: Vec<BigDecimal>,
+}
+
+impl WatchlistQuote {
+    pub const SPARKLINE_DAYS: i32 = 30;
+
+    pub fn new(
+        ticker: String,
+        price: Option<BigDecimal>,
+        quoted_at: Option<DateTime<Utc>>,
+        previous_close: Option<BigDecimal>,
+        volume: Option<i64>,
+        sparkline: Vec<BigDecimal>,
+    ) -> Self {
+        let day_change = match (&price, &previous_close) {
+            (Some(price), Some(previous)) => Some(price - previous),
+            _ => None,
+        };
+        let day_change_percent = match (&day_change, &previous_close) {
+            (Some(change), Some(previous)) if !previous.is_zero() => Some(
+                (change * BigDecimal::from(100) / previous)
+                    .with_scale_round(2, RoundingMode::HalfUp),
+            ),
+            _ => None,
+        };
+        Self {
+            ticker,
+            price,
+            quoted_at,
+            previous_close,
+            day_change,
+            day_change_percent,
+            volume,
+            sparkline,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WatchlistQuotes {
+    pub watchlist_id: Uuid,
+    pub name: String,
+    pub quotes: Vec<WatchlistQuote>,
+}
@@ -1,68 +1,250 @@
-use axum::{extract::State, Extension, Json};
+use axum::{
+    body::Body,
+    extract::{Query, State},
+    http::header,
+    response::IntoResponse,
+    Extension, Json,
+};
 use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::{
     app_state::AppState,
-    models::{errors::api_error::ApiError, transaction::Transaction},
+    models::{
+        account::{Account, AccountId, CashTransfer},
+        errors::api_error::ApiError,
+        fee::FeeSchedule,
+        history::{HistoryQuery, Page},
+        interest::MonthlyInterest,
+        ledger::{CashMovement, JournalEntryType},
+        tax_lot::CostBasisMethod,
+        transaction::Transaction,
+    },
+    services::export_service::ExportFormat,
 };
 
 #[derive(Serialize)]
 pub struct GetAccountBalanceResponse {
-    user_id: Uuid,
+    account_id: Uuid,
     available_balance: BigDecimal,
 }
+#[derive(Serialize)]
+pub struct AccountActivityResponse {
+    account_id: Uuid,
+    trades: Vec<Transaction>,
+    cash_movements: Vec<CashMovement>,
+}
+#[derive(Deserialize, Debug)]
+pub struct ExportActivityQuery {
+    format: ExportFormat,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+}
+#[derive(Deserialize, Debug)]
+pub struct InterestSummaryQuery {
+    year: Option<i32>,
+}
 #[derive(Serialize, Deserialize)]
 pub struct ChangeToUserBalanceRequest {
     amount: BigDecimal,
 }
+#[derive(Deserialize, Debug)]
+pub struct CreateAccountRequest {
+    name: String,
+}
+#[derive(Deserialize, Debug)]
+pub struct SetCostBasisMethodRequest {
+    method: CostBasisMethod,
+}
+#[derive(Deserialize, Debug)]
+pub struct TransferFundsRequest {
+    from_account_id: Uuid,
+    to_account_id: Uuid,
+    amount: BigDecimal,
+}
 pub async fn get_account_balance(
     State(app_state): State<AppState>,
-    Extension(user_id): Extension<Uuid>,
+    Extension(AccountId(account_id)): Extension<AccountId>,
 ) -> Result<Json<GetAccountBalanceResponse>, ApiError> {
     let available_balance = app_state
         .account_management_service
-        .get_user_balance(user_id)
+        .get_user_balance(account_id)
         .await?;
     let response = GetAccountBalanceResponse {
-        user_id,
+        account_id,
         available_balance,
     };
     Ok(Json(response))
 }
 
-pub async fn get_transaction_history(
+#[tracing::instrument(skip(app_state))]
+pub async fn get_fee_schedule(
+    State(app_state): State<AppState>,
+    Extension(AccountId(account_id)): Extension<AccountId>,
+) -> Result<Json<FeeSchedule>, ApiError> {
+    let schedule = app_state
+        .fee_service
+        .get_schedule_for_account(account_id)
+        .await?;
+    Ok(Json(schedule))
+}
+
+#[tracing::instrument(skip(app_state))]
+pub async fn get_interest_summary(
     State(app_state): State<AppState>,
     Extension(user_id): Extension<Uuid>,
-) -> Result<Json<Vec<Transaction>>, ApiError> {
-    let transactions = app_state
+    Query(query): Query<InterestSummaryQuery>,
+) -> Result<Json<Vec<MonthlyInterest>>, ApiError> {
+    let summary = app_state
+        .interest_service
+        .get_monthly_summary(user_id, query.year)
+        .await?;
+    Ok(Json(summary))
+}
+
+pub async fn get_transaction_history(
+    State(app_state): State<AppState>,
+    Extension(AccountId(account_id)): Extension<AccountId>,
+) -> Result<Json<AccountActivityResponse>, ApiError> {
+    let trades = app_state
+        .account_management_service
+        .get_transaction_history(account_id)
+        .await?;
+    let cash_movements = app_state
         .account_management_service
-        .get_transaction_history(user_id)
+        .get_cash_movements(account_id)
         .await?;
-    Ok(Json(transactions))
+    Ok(Json(AccountActivityResponse {
+        account_id,
+        trades,
+        cash_movements,
+    }))
+}
+
+#[tracing::instrument(skip(app_state))]
+pub async fn get_transaction_history_page(
+    State(app_state): State<AppState>,
+    Extension(AccountId(account_id)): Extension<AccountId>,
+    Query(query): Query<HistoryQuery>,
+) -> Result<Json<Page<Transaction>>, ApiError> {
+    let page = app_state
+        .account_management_service
+        .get_transaction_history_page(account_id, &query)
+        .await?;
+    Ok(Json(page))
+}
+
+#[tracing::instrument(skip(app_state))]
+pub async fn export_activity(
+    State(app_state): State<AppState>,
+    Extension(AccountId(account_id)): Extension<AccountId>,
+    Query(query): Query<ExportActivityQuery>,
+) -> impl IntoResponse {
+    let stream =
+        app_state
+            .export_service
+            .export_activity(account_id, query.format, query.from, query.to);
+    let content_disposition = format!(
+        "attachment; filename=\"activity.{}\"",
+        query.format.file_extension()
+    );
+    (
+        [
+            (header::CONTENT_TYPE, query.format.content_type().to_string()),
+            (header::CONTENT_DISPOSITION, content_disposition),
+        ],
+        Body::from_stream(stream),
+    )
 }
 
 pub async fn add_to_user_balance(
     State(app_state): State<AppState>,
-    Extension(user_id): Extension<Uuid>,
+    Extension(AccountId(account_id)): Extension<AccountId>,
     Json(request_body): Json<ChangeToUserBalanceRequest>,
 ) -> Result<(), ApiError> {
     app_state
         .account_management_service
-        .add_user_balance(user_id, &request_body.amount)
+        .add_user_balance(
+            account_id,
+            &request_body.amount,
+            JournalEntryType::Deposit,
+            None,
+        )
         .await?;
     Ok(())
 }
 
 pub async fn withdraw_funds(
     State(app_state): State<AppState>,
-    Extension(user_id): Extension<Uuid>,
+    Extension(AccountId(account_id)): Extension<AccountId>,
     Json(request_body): Json<ChangeToUserBalanceRequest>,
 ) -> Result<(), ApiError> {
     app_state
         .account_management_service
-        .deduct_user_balance(user_id, &request_body.amount)
+        .debit_user_balance(
+            account_id,
+            &request_body.amount,
+            JournalEntryType::Withdrawal,
+            None,
+        )
         .await?;
     Ok(())
 }
+
+#[tracing::instrument(skip(app_state))]
+pub async fn set_cost_basis_method(
+    State(app_state): State<AppState>,
+    Extension(AccountId(account_id)): Extension<AccountId>,
+    Json(request_body): Json<SetCostBasisMethodRequest>,
+) -> Result<(), ApiError> {
+    app_state
+        .account_management_service
+        .set_cost_basis_method(account_id, request_body.method)
+        .await?;
+    Ok(())
+}
+
+#[tracing::instrument(skip(app_state))]
+pub async fn list_accounts(
+    State(app_state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+) -> Result<Json<Vec<Account>>, ApiError> {
+    let accounts = app_state
+        .account_management_service
+        .list_accounts(user_id)
+        .await?;
+    Ok(Json(accounts))
+}
+
+#[tracing::instrument(skip(app_state))]
+pub async fn create_account(
+    State(app_state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Json(request_body): Json<CreateAccountRequest>,
+) -> Result<Json<Account>, ApiError> {
+    let account = app_state
+        .account_management_service
+        .create_account(user_id, &request_body.name)
+        .await?;
+    Ok(Json(account))
+}
+
+#[tracing::instrument(skip(app_state))]
+pub async fn transfer_funds(
+    State(app_state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Json(request_body): Json<TransferFundsRequest>,
+) -> Result<Json<CashTransfer>, ApiError> {
+    let transfer = app_state
+        .account_management_service
+        .transfer_funds(
+            user_id,
+            request_body.from_account_id,
+            request_body.to_account_id,
+            &request_body.amount,
+        )
+        .await?;
+    Ok(Json(transfer))
+}
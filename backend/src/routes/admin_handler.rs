@@ -0,0 +1,215 @@
+use axum::{
+    extract::{Path, Query, State},
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::{
+    app_state::AppState,
+    models::{
+        corporate_action::{CorporateAction, NewCorporateAction},
+        errors::api_error::ApiError,
+        fee::{FeeSchedule, FeeTerms},
+        instrument::{Instrument, InstrumentDetails},
+        interest::{InterestAccrualRun, InterestRateTier, InterestRateTiers},
+        reconciliation::ReconciliationReport,
+        stock_ticker::QuoteCacheStats,
+    },
+};
+
+#[derive(Deserialize, Debug)]
+pub struct RunReconciliationQuery {
+    #[serde(default)]
+    auto_repair: bool,
+}
+
+#[tracing::instrument(skip(app_state))]
+pub async fn run_reconciliation(
+    State(app_state): State<AppState>,
+    Query(query): Query<RunReconciliationQuery>,
+) -> Result<Json<ReconciliationReport>, ApiError> {
+    let report = app_state
+        .reconciliation_service
+        .run(query.auto_repair)
+        .await?;
+    Ok(Json(report))
+}
+
+#[tracing::instrument(skip(app_state))]
+pub async fn get_latest_reconciliation(
+    State(app_state): State<AppState>,
+) -> Result<Json<ReconciliationReport>, ApiError> {
+    let report = app_state
+        .reconciliation_service
+        .get_latest_report()
+        .await?
+        .ok_or(ApiError::NotFound(
+            "No reconciliation has run yet".to_string(),
+        ))?;
+    Ok(Json(report))
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ListCorporateActionsQuery {
+    ticker: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct SetFeeTierRequest {
+    tier: String,
+}
+
+#[tracing::instrument(skip(app_state))]
+pub async fn schedule_corporate_action(
+    State(app_state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Json(request_body): Json<NewCorporateAction>,
+) -> Result<Json<CorporateAction>, ApiError> {
+    let action = app_state
+        .corporate_action_service
+        .schedule_action(user_id, request_body)
+        .await?;
+    Ok(Json(action))
+}
+
+#[tracing::instrument(skip(app_state))]
+pub async fn list_corporate_actions(
+    State(app_state): State<AppState>,
+    Query(query): Query<ListCorporateActionsQuery>,
+) -> Result<Json<Vec<CorporateAction>>, ApiError> {
+    let actions = app_state
+        .corporate_action_service
+        .list_actions(query.ticker.as_deref())
+        .await?;
+    Ok(Json(actions))
+}
+
+#[tracing::instrument(skip(app_state))]
+pub async fn cancel_corporate_action(
+    State(app_state): State<AppState>,
+    Path(action_id): Path<Uuid>,
+) -> Result<Json<CorporateAction>, ApiError> {
+    let action = app_state
+        .corporate_action_service
+        .cancel_action(action_id)
+        .await?;
+    Ok(Json(action))
+}
+
+#[tracing::instrument(skip(app_state))]
+pub async fn process_corporate_actions(
+    State(app_state): State<AppState>,
+) -> Result<Json<Vec<CorporateAction>>, ApiError> {
+    let processed = app_state
+        .corporate_action_service
+        .process_due_actions()
+        .await?;
+    Ok(Json(processed))
+}
+
+#[tracing::instrument(skip(app_state))]
+pub async fn list_fee_schedules(
+    State(app_state): State<AppState>,
+) -> Result<Json<Vec<FeeSchedule>>, ApiError> {
+    let schedules = app_state.fee_service.list_schedules().await?;
+    Ok(Json(schedules))
+}
+
+#[tracing::instrument(skip(app_state))]
+pub async fn upsert_fee_schedule(
+    State(app_state): State<AppState>,
+    Path(tier): Path<String>,
+    Json(request_body): Json<FeeTerms>,
+) -> Result<Json<FeeSchedule>, ApiError> {
+    let schedule = app_state
+        .fee_service
+        .upsert_schedule(&tier, &request_body)
+        .await?;
+    Ok(Json(schedule))
+}
+
+#[tracing::instrument(skip(app_state))]
+pub async fn set_user_fee_tier(
+    State(app_state): State<AppState>,
+    Path(user_id): Path<Uuid>,
+    Json(request_body): Json<SetFeeTierRequest>,
+) -> Result<(), ApiError> {
+    app_state
+        .fee_service
+        .set_user_tier(user_id, &request_body.tier)
+        .await?;
+    Ok(())
+}
+
+#[tracing::instrument(skip(app_state))]
+pub async fn get_interest_rates(
+    State(app_state): State<AppState>,
+) -> Result<Json<InterestRateTiers>, ApiError> {
+    let tiers = app_state.interest_service.get_rate_tiers().await?;
+    Ok(Json(tiers))
+}
+
+#[tracing::instrument(skip(app_state))]
+pub async fn set_interest_rates(
+    State(app_state): State<AppState>,
+    Json(request_body): Json<Vec<InterestRateTier>>,
+) -> Result<Json<InterestRateTiers>, ApiError> {
+    let tiers = app_state
+        .interest_service
+        .set_rate_tiers(request_body)
+        .await?;
+    Ok(Json(tiers))
+}
+
+#[tracing::instrument(skip(app_state))]
+pub async fn accrue_interest(
+    State(app_state): State<AppState>,
+) -> Result<Json<Vec<InterestAccrualRun>>, ApiError> {
+    let runs = app_state.interest_service.accrue_outstanding_days().await?;
+    Ok(Json(runs))
+}
+
+#[tracing::instrument(skip(app_state))]
+pub async fn upsert_instrument(
+    State(app_state): State<AppState>,
+    Path(ticker): Path<String>,
+    Json(request_body): Json<InstrumentDetails>,
+) -> Result<Json<Instrument>, ApiError> {
+    let instrument = app_state
+        .ticker_service
+        .upsert_instrument(&ticker.to_uppercase(), &request_body)
+        .await?;
+    Ok(Json(instrument))
+}
+
+#[derive(Serialize, Debug)]
+pub struct PriceImport {
+    ticker: String,
+    provider: &'static str,
+    bars: usize,
+}
+
+#[tracing::instrument(skip(app_state))]
+pub async fn import_price_history(
+    State(app_state): State<AppState>,
+    Path(ticker): Path<String>,
+) -> Result<Json<PriceImport>, ApiError> {
+    let ticker = ticker.to_uppercase();
+    let bars = app_state
+        .ticker_service
+        .import_price_history(&ticker)
+        .await?;
+    Ok(Json(PriceImport {
+        ticker,
+        provider: app_state.ticker_service.market_data.name(),
+        bars,
+    }))
+}
+
+#[tracing::instrument(skip(app_state))]
+pub async fn get_quote_cache_stats(
+    State(app_state): State<AppState>,
+) -> Result<Json<QuoteCacheStats>, ApiError> {
+    Ok(Json(app_state.ticker_service.cache_stats().await))
+}
@@ -0,0 +1,91 @@
+use axum::{
+    extract::{Path, Query, State},
+    Extension, Json,
+};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::{
+    app_state::AppState,
+    models::{
+        alert::{Alert, AlertRequest, NotificationInbox},
+        errors::api_error::ApiError,
+    },
+};
+
+#[tracing::instrument(skip(app_state))]
+pub async fn list_alerts(
+    State(app_state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+) -> Result<Json<Vec<Alert>>, ApiError> {
+    let alerts = app_state.alert_service.list_alerts(user_id).await?;
+    Ok(Json(alerts))
+}
+
+#[tracing::instrument(skip(app_state))]
+pub async fn create_alert(
+    State(app_state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Json(request_body): Json<AlertRequest>,
+) -> Result<Json<Alert>, ApiError> {
+    let alert = app_state
+        .alert_service
+        .create_alert(user_id, request_body)
+        .await?;
+    Ok(Json(alert))
+}
+
+#[tracing::instrument(skip(app_state))]
+pub async fn delete_alert(
+    State(app_state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Path(alert_id): Path<Uuid>,
+) -> Result<(), ApiError> {
+    app_state
+        .alert_service
+        .delete_alert(user_id, alert_id)
+        .await?;
+    Ok(())
+}
+
+#[derive(Deserialize, Debug)]
+pub struct NotificationQuery {
+    #[serde(default)]
+    unread_only: bool,
+    limit: Option<i64>,
+}
+
+#[tracing::instrument(skip(app_state))]
+pub async fn get_notifications(
+    State(app_state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Query(query): Query<NotificationQuery>,
+) -> Result<Json<NotificationInbox>, ApiError> {
+    let inbox = app_state
+        .alert_service
+        .get_notifications(user_id, query.unread_only, query.limit.unwrap_or(50))
+        .await?;
+    Ok(Json(inbox))
+}
+
+#[tracing::instrument(skip(app_state))]
+pub async fn mark_notification_read(
+    State(app_state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Path(notification_id): Path<Uuid>,
+) -> Result<(), ApiError> {
+    app_state
+        .alert_service
+        .mark_read(user_id, notification_id)
+        .await?;
+    Ok(())
+}
+
+#[tracing::instrument(skip(app_state))]
+pub async fn mark_all_notifications_read(
+    State(app_state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+) -> Result<(), ApiError> {
+    app_state.alert_service.mark_all_read(user_id).await?;
+    Ok(())
+}
@@ -1,10 +1,21 @@
+use crate::models::account::AccountId;
+use crate::models::errors::trade_error::TradeError;
+use crate::models::errors::user_error::UserError;
+use crate::models::idempotency::{IdempotencyOutcome, StoredResponse};
+use crate::services::idempotency_service::IdempotencyService;
 use crate::{app_state::AppState, models::errors::api_error::ApiError};
-use axum::{body::Body, extract::State, http::Request, middleware::Next, response::Response};
+use axum::http::{header, HeaderValue, StatusCode};
+use axum::{
+    body::Body, extract::State, http::Request, middleware::Next, response::Response, Extension,
+};
 use axum_extra::extract::CookieJar;
 use chrono::Utc;
 use sqlx::Row;
+use std::sync::Arc;
 use uuid::Uuid;
 
+const ACCOUNT_ID_HEADER: &str = "x-account-id";
+
 pub async fn auth0_middleware(
     State(app_state): State<AppState>,
     cookies: CookieJar,
@@ -40,8 +51,190 @@ pub async fn auth0_middleware(
         .map_err(|_| {
             ApiError::InternalServerError("Database error: can't update session".to_string())
         })?;
-    //attach the user_id to the request
+    //resolve the account the request acts on, defaulting to the user's default account
+    let account_id = match req.headers().get(ACCOUNT_ID_HEADER) {
+        Some(header) => {
+            let account_id = header
+                .to_str()
+                .ok()
+                .and_then(|value| Uuid::parse_str(value).ok())
+                .ok_or(ApiError::BadRequest("Invalid account ID".to_string()))?;
+            app_state
+                .account_management_service
+                .get_account(user_id, account_id)
+                .await?
+                .account_id
+        }
+        None => user_id,
+    };
+    //attach the user_id and account_id to the request
     req.extensions_mut().insert(user_id);
+    req.extensions_mut().insert(AccountId(account_id));
     let response = next.run(req).await;
     Ok(response)
 }
+
+/// Restricts a route to admins. Runs after `auth0_middleware`, which supplies the user id.
+pub async fn admin_middleware(
+    State(app_state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    req: Request<Body>,
+    next: Next,
+) -> Result<Response, ApiError> {
+    if !app_state.user_service.is_admin(user_id).await? {
+        return Err(UserError::Forbidden.into());
+    }
+    Ok(next.run(req).await)
+}
+
+const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+const IDEMPOTENT_REPLAYED_HEADER: &str = "idempotent-replayed";
+const MAX_IDEMPOTENT_BODY_BYTES: usize = 1024 * 1024;
+
+/// Makes state-changing routes safe to retry. Requests carrying an `Idempotency-Key` header are
+/// recorded with their response; a retry with the same key and payload gets the recorded
+/// response back instead of running the handler again. Requests without the header pass through.
+pub async fn idempotency_middleware(
+    State(app_state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Extension(AccountId(account_id)): Extension<AccountId>,
+    req: Request<Body>,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let Some(key) = req.headers().get(IDEMPOTENCY_KEY_HEADER) else {
+        return Ok(next.run(req).await);
+    };
+    let key = key
+        .to_str()
+        .map_err(|_| TradeError::InvalidIdempotencyKey)?
+        .to_string();
+    //buffer the body so it can be hashed and still handed to the handler
+    let (parts, body) = req.into_parts();
+    let body = axum::body::to_bytes(body, MAX_IDEMPOTENT_BODY_BYTES)
+        .await
+        .map_err(|_| ApiError::BadRequest("Request body too large".to_string()))?;
+    let path = parts
+        .uri
+        .path_and_query()
+        .map(|path| path.as_str())
+        .unwrap_or_else(|| parts.uri.path());
+    let request_hash =
+        IdempotencyService::hash_request(account_id, parts.method.as_str(), path, &body);
+    let idempotency_service = &app_state.idempotency_service;
+    match idempotency_service
+        .begin(user_id, &key, &request_hash)
+        .await?
+    {
+        IdempotencyOutcome::Replay(stored) => {
+            tracing::info!("Replaying response for idempotency key {}", key);
+            replay_response(stored)
+        }
+        IdempotencyOutcome::Execute => {
+            let claim = ClaimGuard::new(idempotency_service.clone(), user_id, key.clone());
+            //the lease is renewed for as long as the handler runs; if the request is dropped the
+            //renewals stop with it and the guard releases the key
+            let run = next.run(Request::from_parts(parts, body.into()));
+            tokio::pin!(run);
+            let mut renewal = tokio::time::interval(IdempotencyService::LEASE_RENEWAL_INTERVAL);
+            renewal.tick().await;
+            let response = loop {
+                tokio::select! {
+                    response = &mut run => break response,
+                    _ = renewal.tick() => {
+                        if let Err(e) = idempotency_service.renew(user_id, &key).await {
+                            tracing::error!("Failed to renew idempotency key {}: {:?}", key, e);
+                        }
+                    }
+                }
+            };
+            //server errors are not recorded so the client can safely retry
+            if response.status().is_server_error() {
+                claim.disarm();
+                idempotency_service.abandon(user_id, &key).await?;
+                return Ok(response);
+            }
+            let (parts, body) = response.into_parts();
+            let body = match axum::body::to_bytes(body, usize::MAX).await {
+                Ok(body) => body,
+                Err(e) => {
+                    claim.disarm();
+                    idempotency_service.abandon(user_id, &key).await?;
+                    return Err(ApiError::InternalServerError(e.to_string()));
+                }
+            };
+            claim.disarm();
+            let stored = StoredResponse {
+                status: parts.status.as_u16(),
+                content_type: parts
+                    .headers
+                    .get(header::CONTENT_TYPE)
+                    .and_then(|value| value.to_str().ok())
+                    .map(str::to_string),
+                body: body.to_vec(),
+            };
+            //the handler already ran, so its response is returned even if recording it fails
+            if let Err(e) = idempotency_service.complete(user_id, &key, &stored).await {
+                tracing::error!(
+                    "Failed to record response for idempotency key {}: {:?}",
+                    key,
+                    e
+                );
+            }
+            Ok(Response::from_parts(parts, body.into()))
+        }
+    }
+}
+
+/// Releases a claimed idempotency key if the request is dropped before it finishes, e.g. when
+/// the client disconnects, so retries aren't refused as in progress until the lease runs out.
+struct ClaimGuard {
+    idempotency_service: Arc<IdempotencyService>,
+    user_id: Uuid,
+    key: String,
+    armed: bool,
+}
+
+impl ClaimGuard {
+    fn new(idempotency_service: Arc<IdempotencyService>, user_id: Uuid, key: String) -> Self {
+        Self {
+            idempotency_service,
+            user_id,
+            key,
+            armed: true,
+        }
+    }
+
+    /// The request reached an outcome and settles the key itself.
+    fn disarm(mut self) {
+        self.armed = false;
+    }
+}
+
+impl Drop for ClaimGuard {
+    fn drop(&mut self) {
+        if !self.armed {
+            return;
+        }
+        let idempotency_service = self.idempotency_service.clone();
+        let (user_id, key) = (self.user_id, std::mem::take(&mut self.key));
+        tokio::spawn(async move {
+            if let Err(e) = idempotency_service.abandon(user_id, &key).await {
+                tracing::error!("Failed to release idempotency key {}: {:?}", key, e);
+            }
+        });
+    }
+}
+
+fn replay_response(stored: StoredResponse) -> Result<Response, ApiError> {
+    let status = StatusCode::from_u16(stored.status)
+        .map_err(|e| ApiError::InternalServerError(e.to_string()))?;
+    let mut builder = Response::builder()
+        .status(status)
+        .header(IDEMPOTENT_REPLAYED_HEADER, HeaderValue::from_static("true"));
+    if let Some(content_type) = stored.content_type {
+        builder = builder.header(header::CONTENT_TYPE, content_type);
+    }
+    builder
+        .body(Body::from(stored.body))
+        .map_err(|e| ApiError::InternalServerError(e.to_string()))
+}
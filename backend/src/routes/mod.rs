@@ -1,4 +1,6 @@
 pub mod account_handler;
+pub mod alert_handler;
+pub mod admin_handler;
 pub mod health;
 pub mod loan_handler;
 pub mod middleware;
@@ -7,3 +9,4 @@ pub mod portfolio_handler;
 pub mod router;
 pub mod ticker_handler;
 pub mod user_handler;
+pub mod watchlist_handler;
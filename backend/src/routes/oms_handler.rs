@@ -1,12 +1,14 @@
 use std::fmt::Debug;
 
 use crate::app_state::AppState;
+use crate::models::account::AccountId;
 use crate::models::errors::api_error::ApiError;
+use crate::models::history::{HistoryQuery, Page};
 use crate::models::order::OrderStatus;
 use crate::models::order::{Order, OrderType};
 use axum::Extension;
 use axum::{
-    extract::{Path, State},
+    extract::{Path, Query, State},
     Json,
 };
 use bigdecimal::BigDecimal;
@@ -20,27 +22,42 @@ pub struct PlaceOrderRequest {
     pub quantity: BigDecimal,
     pub order_type: OrderType, // Assuming OrderType also implements Deserialize
     pub price_buffer: BigDecimal,
+    /// Tax lots a sell closes first, in order.
+    #[serde(default)]
+    pub lot_ids: Vec<Uuid>,
 }
 //getters
 pub async fn get_order_status(
     State(app_state): State<AppState>,
     Path(order_id): Path<Uuid>,
-    Extension(user_id): Extension<Uuid>,
+    Extension(AccountId(account_id)): Extension<AccountId>,
 ) -> Result<Json<OrderStatus>, ApiError> {
     let order_status = app_state
         .order_management_service
-        .get_order_status(order_id, user_id)
+        .get_order_status(order_id, account_id)
         .await?;
     Ok(Json(order_status))
 }
 #[tracing::instrument(skip(app_state))]
 pub async fn get_pending_orders(
     State(app_state): State<AppState>,
-    Extension(user_id): Extension<Uuid>,
+    Extension(AccountId(account_id)): Extension<AccountId>,
 ) -> Result<Json<Vec<Order>>, ApiError> {
     let orders = app_state
         .order_management_service
-        .get_pending_orders(user_id)
+        .get_pending_orders(account_id)
+        .await?;
+    Ok(Json(orders))
+}
+#[tracing::instrument(skip(app_state))]
+pub async fn get_order_history(
+    State(app_state): State<AppState>,
+    Extension(AccountId(account_id)): Extension<AccountId>,
+    Query(query): Query<HistoryQuery>,
+) -> Result<Json<Page<Order>>, ApiError> {
+    let orders = app_state
+        .order_management_service
+        .get_order_history(account_id, &query)
         .await?;
     Ok(Json(orders))
 }
@@ -48,11 +65,11 @@ pub async fn get_pending_orders(
 pub async fn get_order(
     State(app_state): State<AppState>,
     Path(order_id): Path<Uuid>,
-    Extension(user_id): Extension<Uuid>,
+    Extension(AccountId(account_id)): Extension<AccountId>,
 ) -> Result<Json<Order>, ApiError> {
     let order = app_state
         .order_management_service
-        .get_order(order_id, user_id)
+        .get_order(order_id, account_id)
         .await?;
     Ok(Json(order))
 }
@@ -60,18 +77,18 @@ pub async fn get_order(
 #[tracing::instrument(skip(app_state))]
 pub async fn place_order(
     State(app_state): State<AppState>,
-    Extension(user_id): Extension<Uuid>,
+    Extension(AccountId(account_id)): Extension<AccountId>,
     Json(request_body): Json<PlaceOrderRequest>,
 ) -> Result<(), ApiError> {
     app_state
         .order_management_service
-        .place_order(
-            user_id,
+        .place_order_with_lots(
+            account_id,
             &request_body.ticker,
             request_body.quantity,
             request_body.order_type,
-            request_body.price_buffer,
             None,
+            &request_body.lot_ids,
         )
         .await?;
     tracing::info!("Order placed successfully");
@@ -81,12 +98,12 @@ pub async fn place_order(
 #[tracing::instrument(skip(app_state))]
 pub async fn cancel_order(
     State(app_state): State<AppState>,
-    Extension(user_id): Extension<Uuid>,
+    Extension(AccountId(account_id)): Extension<AccountId>,
     Path(order_id): Path<Uuid>,
 ) -> Result<(), ApiError> {
     app_state
         .order_management_service
-        .cancel_order(order_id, user_id)
+        .cancel_order(order_id, account_id)
         .await?;
     Ok(())
 }
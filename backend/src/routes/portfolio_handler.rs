@@ -1,13 +1,23 @@
 use crate::{
     app_state::AppState,
     models::{
-        errors::api_error::ApiError,
+        account::AccountId,
+        allocation::PortfolioAllocation,
+        analytics::{Benchmark, BenchmarkComparison, PerformanceAnalytics},
+        errors::{api_error::ApiError, trade_error::TradeError},
         portfolio_ticker::{PortfolioHistoryPoint, PortfolioTicker},
+        rebalance::{RebalanceProposal, TargetAllocation, TargetAllocationRequest},
+        risk::{RiskParameters, RiskReport},
+        simulation::{SimulationRequest, SimulationResult},
         stock_ticker::TimeFrame,
+        tax_lot::TaxLot,
+        tax_report::TaxReport,
     },
 };
 use axum::{
-    extract::{Query, State},
+    extract::{Path, Query, State},
+    http::header,
+    response::IntoResponse,
     Extension, Json,
 };
 use serde::{Deserialize, Serialize};
@@ -15,33 +25,232 @@ use uuid::Uuid;
 
 #[derive(Serialize)]
 pub struct PortfolioResponse {
-    user_id: Uuid,
+    account_id: Uuid,
     portfolio: Vec<PortfolioTicker>,
 }
 
 #[tracing::instrument(skip(app_state))]
 pub async fn get_portfolio(
     State(app_state): State<AppState>,
-    Extension(user_id): Extension<Uuid>,
+    Extension(AccountId(account_id)): Extension<AccountId>,
 ) -> Result<Json<PortfolioResponse>, ApiError> {
-    let portfolio = app_state.portfolio_service.get_portfolio(user_id).await?;
-    Ok(Json(PortfolioResponse { user_id, portfolio }))
+    //closed positions are listed too, for the P&L they realized
+    let portfolio = app_state
+        .portfolio_service
+        .get_positions(account_id, true)
+        .await?;
+    Ok(Json(PortfolioResponse { account_id, portfolio }))
 }
 
 #[derive(Deserialize, Debug)]
 pub struct PortfolioHistoryQuery {
     timeframe: TimeFrame,
+    /// A ticker, e.g. `SPY`, or a weighted basket, e.g. `AAPL:0.6,MSFT:0.4`.
+    benchmark: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct PortfolioHistoryResponse {
+    points: Vec<PortfolioHistoryPoint>,
+    benchmark: Option<BenchmarkComparison>,
 }
 
 #[tracing::instrument(skip(app_state))]
 pub async fn get_portfolio_history(
     State(app_state): State<AppState>,
-    Extension(user_id): Extension<Uuid>,
+    Extension(AccountId(account_id)): Extension<AccountId>,
     Query(query): Query<PortfolioHistoryQuery>,
-) -> Result<Json<Vec<PortfolioHistoryPoint>>, ApiError> {
-    let history = app_state
+) -> Result<Json<PortfolioHistoryResponse>, ApiError> {
+    let benchmark = query
+        .benchmark
+        .as_deref()
+        .map(Benchmark::parse)
+        .transpose()
+        .map_err(TradeError::InvalidBenchmark)?;
+    let points = app_state
+        .snapshot_service
+        .get_portfolio_history(account_id, query.timeframe)
+        .await?;
+    let benchmark = match benchmark {
+        Some(benchmark) => Some(
+            app_state
+                .analytics_service
+                .compare_to_benchmark(account_id, query.timeframe, benchmark)
+                .await?,
+        ),
+        None => None,
+    };
+    Ok(Json(PortfolioHistoryResponse { points, benchmark }))
+}
+
+#[derive(Deserialize, Debug)]
+pub struct TaxLotsQuery {
+    ticker: Option<String>,
+}
+
+#[tracing::instrument(skip(app_state))]
+pub async fn get_tax_lots(
+    State(app_state): State<AppState>,
+    Extension(AccountId(account_id)): Extension<AccountId>,
+    Query(query): Query<TaxLotsQuery>,
+) -> Result<Json<Vec<TaxLot>>, ApiError> {
+    let lots = app_state
         .portfolio_service
-        .get_portfolio_history(user_id, query.timeframe)
+        .get_tax_lots(account_id, query.ticker.as_deref())
+        .await?;
+    Ok(Json(lots))
+}
+
+#[derive(Deserialize, Debug)]
+pub struct PortfolioAnalyticsQuery {
+    timeframe: TimeFrame,
+    /// Annual rate the Sharpe and Sortino ratios measure excess return against, e.g. 0.04.
+    #[serde(default)]
+    risk_free_rate: f64,
+}
+
+#[tracing::instrument(skip(app_state))]
+pub async fn get_portfolio_analytics(
+    State(app_state): State<AppState>,
+    Extension(AccountId(account_id)): Extension<AccountId>,
+    Query(query): Query<PortfolioAnalyticsQuery>,
+) -> Result<Json<PerformanceAnalytics>, ApiError> {
+    let analytics = app_state
+        .analytics_service
+        .get_performance(account_id, query.timeframe, query.risk_free_rate)
+        .await?;
+    Ok(Json(analytics))
+}
+
+#[tracing::instrument(skip(app_state))]
+pub async fn get_portfolio_allocation(
+    State(app_state): State<AppState>,
+    Extension(AccountId(account_id)): Extension<AccountId>,
+) -> Result<Json<PortfolioAllocation>, ApiError> {
+    let allocation = app_state.portfolio_service.get_allocation(account_id).await?;
+    Ok(Json(allocation))
+}
+
+#[tracing::instrument(skip(app_state))]
+pub async fn get_portfolio_risk(
+    State(app_state): State<AppState>,
+    Extension(AccountId(account_id)): Extension<AccountId>,
+    Query(parameters): Query<RiskParameters>,
+) -> Result<Json<RiskReport>, ApiError> {
+    let report = app_state
+        .risk_service
+        .get_risk(account_id, &parameters)
+        .await?;
+    Ok(Json(report))
+}
+
+#[tracing::instrument(skip(app_state))]
+pub async fn get_target_allocation(
+    State(app_state): State<AppState>,
+    Extension(AccountId(account_id)): Extension<AccountId>,
+) -> Result<Json<TargetAllocation>, ApiError> {
+    let targets = app_state.rebalance_service.get_targets(account_id).await?;
+    Ok(Json(targets))
+}
+
+#[tracing::instrument(skip(app_state))]
+pub async fn set_target_allocation(
+    State(app_state): State<AppState>,
+    Extension(AccountId(account_id)): Extension<AccountId>,
+    Json(request): Json<TargetAllocationRequest>,
+) -> Result<Json<TargetAllocation>, ApiError> {
+    let targets = app_state
+        .rebalance_service
+        .set_targets(account_id, request)
+        .await?;
+    Ok(Json(targets))
+}
+
+#[tracing::instrument(skip(app_state))]
+pub async fn propose_rebalance(
+    State(app_state): State<AppState>,
+    Extension(AccountId(account_id)): Extension<AccountId>,
+) -> Result<Json<RebalanceProposal>, ApiError> {
+    let proposal = app_state.rebalance_service.propose(account_id).await?;
+    Ok(Json(proposal))
+}
+
+#[tracing::instrument(skip(app_state))]
+pub async fn get_rebalance_proposal(
+    State(app_state): State<AppState>,
+    Extension(AccountId(account_id)): Extension<AccountId>,
+    Path(proposal_id): Path<Uuid>,
+) -> Result<Json<RebalanceProposal>, ApiError> {
+    let proposal = app_state
+        .rebalance_service
+        .get_proposal(account_id, proposal_id)
+        .await?;
+    Ok(Json(proposal))
+}
+
+#[tracing::instrument(skip(app_state))]
+pub async fn confirm_rebalance_proposal(
+    State(app_state): State<AppState>,
+    Extension(AccountId(account_id)): Extension<AccountId>,
+    Path(proposal_id): Path<Uuid>,
+) -> Result<Json<RebalanceProposal>, ApiError> {
+    let proposal = app_state
+        .rebalance_service
+        .confirm_proposal(account_id, proposal_id)
+        .await?;
+    Ok(Json(proposal))
+}
+
+#[derive(Deserialize, Debug)]
+pub struct TaxReportQuery {
+    year: i32,
+}
+
+#[tracing::instrument(skip(app_state))]
+pub async fn get_tax_report(
+    State(app_state): State<AppState>,
+    Extension(AccountId(account_id)): Extension<AccountId>,
+    Query(query): Query<TaxReportQuery>,
+) -> Result<Json<TaxReport>, ApiError> {
+    let report = app_state
+        .tax_report_service
+        .get_report(account_id, query.year)
+        .await?;
+    Ok(Json(report))
+}
+
+#[tracing::instrument(skip(app_state))]
+pub async fn export_tax_report(
+    State(app_state): State<AppState>,
+    Extension(AccountId(account_id)): Extension<AccountId>,
+    Query(query): Query<TaxReportQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let report = app_state
+        .tax_report_service
+        .get_report(account_id, query.year)
+        .await?;
+    let content_disposition = format!(
+        "attachment; filename=\"realized-gains-{}.csv\"",
+        report.year
+    );
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (header::CONTENT_DISPOSITION, content_disposition),
+        ],
+        report.to_csv(),
+    ))
+}
+
+#[tracing::instrument(skip(app_state))]
+pub async fn simulate_orders(
+    State(app_state): State<AppState>,
+    Extension(AccountId(account_id)): Extension<AccountId>,
+    Json(request): Json<SimulationRequest>,
+) -> Result<Json<SimulationResult>, ApiError> {
+    let result = app_state
+        .simulation_service
+        .simulate(account_id, request)
         .await?;
-    Ok(Json(history))
+    Ok(Json(result))
 }
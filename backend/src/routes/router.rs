@@ -1,38 +1,144 @@
 use crate::routes::account_handler::{
-    add_to_user_balance, get_account_balance, get_transaction_history, withdraw_funds,
+    add_to_user_balance, create_account, export_activity, get_account_balance, get_fee_schedule,
+    get_interest_summary, get_transaction_history, get_transaction_history_page, list_accounts,
+    set_cost_basis_method, transfer_funds, withdraw_funds,
+};
+use crate::routes::admin_handler::{
+    accrue_interest, cancel_corporate_action, get_interest_rates, get_latest_reconciliation,
+    get_quote_cache_stats, import_price_history, list_corporate_actions, list_fee_schedules,
+    process_corporate_actions, run_reconciliation, schedule_corporate_action, set_interest_rates,
+    set_user_fee_tier, upsert_fee_schedule, upsert_instrument,
+};
+use crate::routes::alert_handler::{
+    create_alert, delete_alert, get_notifications, list_alerts, mark_all_notifications_read,
+    mark_notification_read,
 };
 use crate::routes::health::health;
 use crate::routes::loan_handler::{get_loan, repay_loan, request_loan};
-use crate::routes::middleware::auth0_middleware;
-use crate::routes::oms_handler::{cancel_order, get_order, get_pending_orders, place_order};
-use crate::routes::portfolio_handler::{get_portfolio, get_portfolio_history};
+use crate::routes::middleware::{admin_middleware, auth0_middleware, idempotency_middleware};
+use crate::routes::oms_handler::{
+    cancel_order, get_order, get_order_history, get_pending_orders, place_order,
+};
+use crate::routes::portfolio_handler::{
+    confirm_rebalance_proposal, export_tax_report, get_portfolio, get_portfolio_allocation,
+    get_portfolio_analytics, get_portfolio_history, get_portfolio_risk, get_rebalance_proposal,
+    get_target_allocation, get_tax_lots, get_tax_report, propose_rebalance, set_target_allocation,
+    simulate_orders,
+};
 use crate::routes::user_handler::{auth0_callback, login_user};
-use crate::{app_state::AppState, routes::ticker_handler::{get_ticker, get_ticker_history}};
+use crate::routes::watchlist_handler::{
+    create_watchlist, delete_watchlist, get_watchlist_quotes, list_watchlists,
+    reorder_watchlists, update_watchlist,
+};
+use crate::{
+    app_state::AppState,
+    routes::ticker_handler::{
+        get_instrument, get_price_bars, get_ticker, get_ticker_history, get_tickers,
+    },
+};
 use axum::middleware::from_fn_with_state;
-use axum::routing::{delete, post};
+use axum::routing::{delete, post, put};
 use axum::{routing::get, Router};
 
 pub fn create_router(app_state: AppState) -> Router<AppState> {
     let public_routes = Router::new()
+        .route("/tickers", get(get_tickers))
         .route("/tickers/:ticker", get(get_ticker))
         .route("/tickers/:ticker/history", get(get_ticker_history))
+        .route("/tickers/:ticker/bars", get(get_price_bars))
+        .route("/tickers/:ticker/instrument", get(get_instrument))
         .route("/auth/login", post(login_user))
         .route("/health", get(health))
         .route("/auth/callback", get(auth0_callback));
+    //state-changing routes accept an Idempotency-Key header so clients can retry them safely
+    let idempotent_routes = Router::new()
+        .route("/account/withdrawals", post(withdraw_funds))
+        .route("/account/deposits", post(add_to_user_balance))
+        .route("/accounts", post(create_account))
+        .route("/accounts/transfers", post(transfer_funds))
+        .route("/account/cost-basis-method", put(set_cost_basis_method))
+        .route("/orders", post(place_order))
+        .route("/orders/:order_id", delete(cancel_order))
+        .route("/loans/:loan_type", post(request_loan))
+        .route("/loans/repay", post(repay_loan))
+        .route("/portfolio/targets", put(set_target_allocation))
+        .route("/portfolio/rebalance", post(propose_rebalance))
+        .route(
+            "/portfolio/rebalance/:proposal_id/confirm",
+            post(confirm_rebalance_proposal),
+        )
+        .route("/watchlists", post(create_watchlist))
+        .route("/watchlists/order", put(reorder_watchlists))
+        .route(
+            "/watchlists/:watchlist_id",
+            put(update_watchlist).delete(delete_watchlist),
+        )
+        .route("/alerts", post(create_alert))
+        .route("/alerts/:alert_id", delete(delete_alert))
+        .route("/notifications/read", post(mark_all_notifications_read))
+        .route(
+            "/notifications/:notification_id/read",
+            post(mark_notification_read),
+        )
+        .route_layer(from_fn_with_state(app_state.clone(), idempotency_middleware));
+    let idempotent_admin_routes = Router::new()
+        .route("/admin/reconciliation", post(run_reconciliation))
+        .route("/admin/corporate-actions", post(schedule_corporate_action))
+        .route(
+            "/admin/corporate-actions/:action_id",
+            delete(cancel_corporate_action),
+        )
+        .route(
+            "/admin/corporate-actions/process",
+            post(process_corporate_actions),
+        )
+        .route("/admin/fee-schedules/:tier", put(upsert_fee_schedule))
+        .route("/admin/users/:user_id/fee-tier", put(set_user_fee_tier))
+        .route("/admin/interest-rates", put(set_interest_rates))
+        .route("/admin/interest/accrue", post(accrue_interest))
+        .route("/admin/instruments/:ticker", put(upsert_instrument))
+        .route("/admin/market-data/:ticker/import", post(import_price_history))
+        .route_layer(from_fn_with_state(app_state.clone(), idempotency_middleware));
+    let admin_routes = Router::new()
+        .route("/admin/reconciliation/latest", get(get_latest_reconciliation))
+        .route("/admin/corporate-actions", get(list_corporate_actions))
+        .route("/admin/fee-schedules", get(list_fee_schedules))
+        .route("/admin/interest-rates", get(get_interest_rates))
+        .route("/admin/market-data/cache", get(get_quote_cache_stats))
+        .merge(idempotent_admin_routes)
+        .route_layer(from_fn_with_state(app_state.clone(), admin_middleware));
     let private_routes = Router::new()
         .route("/portfolio", get(get_portfolio))
         .route("/portfolio/history", get(get_portfolio_history))
+        .route("/portfolio/analytics", get(get_portfolio_analytics))
+        .route("/portfolio/allocation", get(get_portfolio_allocation))
+        .route("/portfolio/risk", get(get_portfolio_risk))
+        .route("/portfolio/simulate", post(simulate_orders))
+        .route("/portfolio/lots", get(get_tax_lots))
+        .route("/portfolio/tax-report", get(get_tax_report))
+        .route("/portfolio/tax-report/export", get(export_tax_report))
+        .route("/portfolio/targets", get(get_target_allocation))
+        .route("/portfolio/rebalance/:proposal_id", get(get_rebalance_proposal))
         .route("/account", get(get_account_balance))
-        .route("/account/withdrawals", post(withdraw_funds))
-        .route("/account/deposits", post(add_to_user_balance))
+        .route("/account/fees", get(get_fee_schedule))
+        .route("/account/interest", get(get_interest_summary))
+        .route("/accounts", get(list_accounts))
         .route("/account/transactions", get(get_transaction_history))
+        .route(
+            "/account/transactions/history",
+            get(get_transaction_history_page),
+        )
+        .route("/account/transactions/export", get(export_activity))
         .route("/orders", get(get_pending_orders))
+        .route("/orders/history", get(get_order_history))
         .route("/orders/:order_id", get(get_order))
-        .route("/orders", post(place_order))
-        .route("/orders/:order_id", delete(cancel_order))
         .route("/loans", get(get_loan))
-        .route("/loans/:loan_type", post(request_loan))
-        .route("/loans/repay", post(repay_loan))
+        .route("/watchlists", get(list_watchlists))
+        .route("/watchlists/:watchlist_id/quotes", get(get_watchlist_quotes))
+        .route("/alerts", get(list_alerts))
+        .route("/notifications", get(get_notifications))
+        .merge(idempotent_routes)
+        .merge(admin_routes)
         .layer(from_fn_with_state(app_state, auth0_middleware));
     Router::new().merge(public_routes).merge(private_routes)
 }
@@ -1,3 +1,5 @@
+use crate::models::instrument::Instrument;
+use crate::models::price_bar::{BarInterval, PriceBar};
 use crate::models::stock_ticker::{Ticker, TimeFrame};
 use crate::{app_state::AppState, models::errors::api_error::ApiError};
 use axum::{
@@ -6,6 +8,8 @@ use axum::{
 };
 use serde::Deserialize;
 
+const MAX_BULK_QUOTES: usize = 100;
+
 #[tracing::instrument(skip(app_state))]
 pub async fn get_ticker(
     State(app_state): State<AppState>,
@@ -14,11 +18,44 @@ pub async fn get_ticker(
     Ok(Json(vec![
         app_state
             .ticker_service
-            .fetch_latest_price_ticker_from_db(&ticker)
+            .get_quote(&ticker)
             .await?,
     ]))
 }
 
+#[derive(Deserialize, Debug)]
+pub struct TickersQuery {
+    symbols: String,
+}
+
+/// Latest quotes for a comma separated list of tickers, in the order asked for. Tickers without
+/// prices are left out.
+#[tracing::instrument(skip(app_state))]
+pub async fn get_tickers(
+    State(app_state): State<AppState>,
+    Query(query): Query<TickersQuery>,
+) -> Result<Json<Vec<Ticker>>, ApiError> {
+    let tickers: Vec<String> = query
+        .symbols
+        .split(',')
+        .map(|ticker| ticker.trim().to_uppercase())
+        .filter(|ticker| !ticker.is_empty())
+        .collect();
+    if tickers.len() > MAX_BULK_QUOTES {
+        return Err(ApiError::BadRequest(format!(
+            "at most {} tickers can be quoted at once",
+            MAX_BULK_QUOTES
+        )));
+    }
+    let mut quotes = app_state.ticker_service.get_quotes(&tickers).await?;
+    Ok(Json(
+        tickers
+            .iter()
+            .filter_map(|ticker| quotes.remove(ticker))
+            .collect(),
+    ))
+}
+
 #[derive(Deserialize, Debug)]
 pub struct TickerHistoryQuery {
     timeframe: TimeFrame,
@@ -37,3 +74,31 @@ pub async fn get_ticker_history(
             .await?,
     ))
 }
+
+#[derive(Deserialize, Debug)]
+pub struct PriceBarQuery {
+    interval: BarInterval,
+    limit: Option<i64>,
+}
+
+#[tracing::instrument(skip(app_state))]
+pub async fn get_price_bars(
+    State(app_state): State<AppState>,
+    Path(ticker): Path<String>,
+    Query(query): Query<PriceBarQuery>,
+) -> Result<Json<Vec<PriceBar>>, ApiError> {
+    Ok(Json(
+        app_state
+            .ticker_service
+            .get_price_bars(&ticker, query.interval, query.limit.unwrap_or(100))
+            .await?,
+    ))
+}
+
+#[tracing::instrument(skip(app_state))]
+pub async fn get_instrument(
+    State(app_state): State<AppState>,
+    Path(ticker): Path<String>,
+) -> Result<Json<Instrument>, ApiError> {
+    Ok(Json(app_state.ticker_service.get_instrument(&ticker).await?))
+}
@@ -0,0 +1,88 @@
+use axum::{
+    extract::{Path, State},
+    Extension, Json,
+};
+use uuid::Uuid;
+
+use crate::{
+    app_state::AppState,
+    models::{
+        errors::api_error::ApiError,
+        watchlist::{Watchlist, WatchlistOrder, WatchlistQuotes, WatchlistRequest},
+    },
+};
+
+#[tracing::instrument(skip(app_state))]
+pub async fn list_watchlists(
+    State(app_state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+) -> Result<Json<Vec<Watchlist>>, ApiError> {
+    let watchlists = app_state.watchlist_service.list_watchlists(user_id).await?;
+    Ok(Json(watchlists))
+}
+
+#[tracing::instrument(skip(app_state))]
+pub async fn create_watchlist(
+    State(app_state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Json(request_body): Json<WatchlistRequest>,
+) -> Result<Json<Watchlist>, ApiError> {
+    let watchlist = app_state
+        .watchlist_service
+        .create_watchlist(user_id, request_body)
+        .await?;
+    Ok(Json(watchlist))
+}
+
+#[tracing::instrument(skip(app_state))]
+pub async fn update_watchlist(
+    State(app_state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Path(watchlist_id): Path<Uuid>,
+    Json(request_body): Json<WatchlistRequest>,
+) -> Result<Json<Watchlist>, ApiError> {
+    let watchlist = app_state
+        .watchlist_service
+        .update_watchlist(user_id, watchlist_id, request_body)
+        .await?;
+    Ok(Json(watchlist))
+}
+
+#[tracing::instrument(skip(app_state))]
+pub async fn delete_watchlist(
+    State(app_state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Path(watchlist_id): Path<Uuid>,
+) -> Result<(), ApiError> {
+    app_state
+        .watchlist_service
+        .delete_watchlist(user_id, watchlist_id)
+        .await?;
+    Ok(())
+}
+
+#[tracing::instrument(skip(app_state))]
+pub async fn reorder_watchlists(
+    State(app_state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Json(request_body): Json<WatchlistOrder>,
+) -> Result<Json<Vec<Watchlist>>, ApiError> {
+    let watchlists = app_state
+        .watchlist_service
+        .reorder_watchlists(user_id, request_body)
+        .await?;
+    Ok(Json(watchlists))
+}
+
+#[tracing::instrument(skip(app_state))]
+pub async fn get_watchlist_quotes(
+    State(app_state): State<AppState>,
+    Extension(user_id): Extension<Uuid>,
+    Path(watchlist_id): Path<Uuid>,
+) -> Result<Json<WatchlistQuotes>, ApiError> {
+    let quotes = app_state
+        .watchlist_service
+        .get_quotes(user_id, watchlist_id)
+        .await?;
+    Ok(Json(quotes))
+}
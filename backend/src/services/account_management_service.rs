@@ -1,10 +1,16 @@
+use crate::models::account::{Account, CashTransfer};
 use crate::models::errors::trade_error::TradeError;
 use crate::models::errors::user_error::UserError;
+use crate::models::history::{HistoryCursor, HistoryQuery, Page};
+use crate::models::ledger::{CashMovement, JournalEntryType, LedgerAccount, LedgerBalance};
+use crate::models::tax_lot::CostBasisMethod;
 use crate::models::transaction::Transaction;
 use bigdecimal::BigDecimal;
 use num_traits::Zero;
+use sqlx::postgres::PgRow;
 use sqlx::PgPool;
 use sqlx::Row;
+use sqlx::{PgTransaction, Postgres, QueryBuilder};
 use uuid::Uuid;
 
 #[derive(Clone)]
@@ -12,13 +18,17 @@ pub struct AccountManagementService {
     pub db: PgPool,
 }
 impl AccountManagementService {
+    const STARTING_BALANCE: i64 = 1_000_000;
+    const RESET_BALANCE: i64 = 100_000;
+    const DEFAULT_ACCOUNT_NAME: &'static str = "default";
+    const MAX_ACCOUNT_NAME_LENGTH: usize = 64;
     pub fn new(db: PgPool) -> Self {
         Self { db }
     }
     #[tracing::instrument(skip(self))]
-    pub async fn get_user_balance(&self, user_id: Uuid) -> Result<BigDecimal, UserError> {
-        let rec = sqlx::query("SELECT available_balance FROM users WHERE user_id = $1")
-            .bind(user_id)
+    pub async fn get_user_balance(&self, account_id: Uuid) -> Result<BigDecimal, UserError> {
+        let rec = sqlx::query("SELECT available_balance FROM accounts WHERE account_id = $1")
+            .bind(account_id)
             .fetch_one(&self.db)
             .await?;
         Ok(rec.get("available_balance"))
@@ -27,10 +37,10 @@ impl AccountManagementService {
     #[tracing::instrument(skip(self))]
     pub async fn get_transaction_history(
         &self,
-        user_id: Uuid,
+        account_id: Uuid,
     ) -> Result<Vec<Transaction>, UserError> {
-        let records = sqlx::query("SELECT * FROM transactions WHERE user_id = $1")
-            .bind(user_id)
+        let records = sqlx::query("SELECT * FROM transactions WHERE account_id = $1")
+            .bind(account_id)
             .fetch_all(&self.db)
             .await
             .map_err(|e| UserError::DatabaseError(e))?;
@@ -38,101 +48,507 @@ impl AccountManagementService {
         for rec in records {
             transactions.push(Transaction {
                 transaction_id: rec.get("transaction_id"),
-                user_id: rec.get("user_id"),
+                account_id: rec.get("account_id"),
                 ticker: rec.get("ticker"),
                 quantity: rec.get("quantity"),
                 price_per_share: rec.get("price_per_share"),
                 order_type: rec.get("order_type"),
+                order_id: rec.get("order_id"),
+                liquidity: rec.get("liquidity"),
+                fee: rec.get("fee"),
+                realized_pnl: rec.get("realized_pnl"),
                 executed_at: rec.get("executed_at"),
             });
         }
         Ok(transactions)
     }
 
+    /// Executed trades, newest first unless `sort=asc` is requested.
     #[tracing::instrument(skip(self))]
-    pub async fn reserve_funds(
+    pub async fn get_transaction_history_page(
+        &self,
+        account_id: Uuid,
+        query: &HistoryQuery,
+    ) -> Result<Page<Transaction>, TradeError> {
+        let mut builder =
+            QueryBuilder::<Postgres>::new("SELECT * FROM transactions WHERE account_id = ");
+        builder.push_bind(account_id);
+        query.push_conditions(&mut builder, "executed_at", "transaction_id")?;
+        let records = builder.build().fetch_all(&self.db).await?;
+        let mut transactions = vec![];
+        for rec in records {
+            transactions.push(Transaction {
+                transaction_id: rec.try_get("transaction_id")?,
+                account_id: rec.try_get("account_id")?,
+                ticker: rec.try_get("ticker")?,
+                quantity: rec.try_get("quantity")?,
+                price_per_share: rec.try_get("price_per_share")?,
+                order_type: rec.try_get("order_type")?,
+                order_id: rec.try_get("order_id")?,
+                liquidity: rec.try_get("liquidity")?,
+                fee: rec.try_get("fee")?,
+                realized_pnl: rec.try_get("realized_pnl")?,
+                executed_at: rec.try_get("executed_at")?,
+            });
+        }
+        Ok(query.into_page(transactions, |transaction| HistoryCursor {
+            timestamp: transaction.executed_at,
+            id: transaction.transaction_id,
+        }))
+    }
+
+    #[tracing::instrument(skip(self))]
+    pub async fn get_cash_movements(&self, account_id: Uuid) -> Result<Vec<CashMovement>, UserError> {
+        let records = sqlx::query(
+            "SELECT e.entry_id, e.account_id, e.entry_type, e.reference_id, e.created_at,
+                SUM(l.amount) AS amount,
+                SUM(SUM(l.amount)) OVER (ORDER BY e.created_at, e.entry_id) AS running_balance
+            FROM journal_entries e JOIN journal_lines l ON l.entry_id = e.entry_id
+            WHERE e.account_id = $1 AND l.account IN ($2, $3)
+            GROUP BY e.entry_id
+            HAVING SUM(l.amount) <> 0
+            ORDER BY e.created_at, e.entry_id",
+        )
+        .bind(account_id)
+        .bind(LedgerAccount::UserCash)
+        .bind(LedgerAccount::UserReserved)
+        .fetch_all(&self.db)
+        .await?;
+        let mut movements = vec![];
+        for rec in records {
+            movements.push(CashMovement {
+                entry_id: rec.try_get("entry_id")?,
+                account_id: rec.try_get("account_id")?,
+                movement_type: rec.try_get("entry_type")?,
+                reference_id: rec.try_get("reference_id")?,
+                amount: rec.try_get("amount")?,
+                running_balance: rec.try_get("running_balance")?,
+                created_at: rec.try_get("created_at")?,
+            });
+        }
+        Ok(movements)
+    }
+
+    #[tracing::instrument(skip(self))]
+    pub async fn get_ledger_balance(&self, account_id: Uuid) -> Result<LedgerBalance, UserError> {
+        let rec = sqlx::query(
+            "SELECT
+                COALESCE(SUM(l.amount) FILTER (WHERE l.account = $2), 0) AS cash,
+                COALESCE(SUM(l.amount) FILTER (WHERE l.account = $3), 0) AS reserved
+            FROM journal_entries e JOIN journal_lines l ON l.entry_id = e.entry_id
+            WHERE e.account_id = $1",
+        )
+        .bind(account_id)
+        .bind(LedgerAccount::UserCash)
+        .bind(LedgerAccount::UserReserved)
+        .fetch_one(&self.db)
+        .await?;
+        Ok(LedgerBalance {
+            account_id,
+            cash: rec.try_get("cash")?,
+            reserved: rec.try_get("reserved")?,
+        })
+    }
+
+    /// Creates a new user's default account, which shares the user's id, and funds it with the
+    /// starting balance. Opening an account that already exists does nothing.
+    #[tracing::instrument(skip(self))]
+    pub async fn open_account(&self, user_id: Uuid) -> Result<(), UserError> {
+        let mut tx = self.db.begin().await?;
+        let opened = sqlx::query(
+            "INSERT INTO accounts (account_id, user_id, name) VALUES ($1, $1, $2)
+            ON CONFLICT (account_id) DO NOTHING
+            RETURNING account_id",
+        )
+        .bind(user_id)
+        .bind(Self::DEFAULT_ACCOUNT_NAME)
+        .fetch_optional(&mut *tx)
+        .await?
+        .is_some();
+        if !opened {
+            return Ok(());
+        }
+        let amount = BigDecimal::from(Self::STARTING_BALANCE);
+        Self::post_journal_entry_in(
+            &mut tx,
+            user_id,
+            JournalEntryType::OpeningBalance,
+            None,
+            &[
+                (LedgerAccount::UserCash, amount.clone()),
+                (JournalEntryType::OpeningBalance.counter_account(), -amount),
+            ],
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Creates an empty sub-account; it is funded by a deposit or a transfer.
+    #[tracing::instrument(skip(self))]
+    pub async fn create_account(&self, user_id: Uuid, name: &str) -> Result<Account, UserError> {
+        let name = name.trim();
+        if name.is_empty() || name.chars().count() > Self::MAX_ACCOUNT_NAME_LENGTH {
+            return Err(UserError::InvalidAccountName);
+        }
+        let rec = sqlx::query(
+            "INSERT INTO accounts (account_id, user_id, name) VALUES ($1, $2, $3)
+            ON CONFLICT (user_id, name) DO NOTHING
+            RETURNING *, account_id = user_id AS is_default",
+        )
+        .bind(Uuid::new_v4())
+        .bind(user_id)
+        .bind(name)
+        .fetch_optional(&self.db)
+        .await?
+        .ok_or(UserError::AccountNameAlreadyExists)?;
+        Self::account_from_row(&rec)
+    }
+
+    #[tracing::instrument(skip(self))]
+    pub async fn list_accounts(&self, user_id: Uuid) -> Result<Vec<Account>, UserError> {
+        let records = sqlx::query(
+            "SELECT *, account_id = user_id AS is_default FROM accounts
+            WHERE user_id = $1 ORDER BY is_default DESC, created_at, name",
+        )
+        .bind(user_id)
+        .fetch_all(&self.db)
+        .await?;
+        records.iter().map(Self::account_from_row).collect()
+    }
+
+    /// Looks up one of the user's accounts; accounts owned by anyone else are reported as missing.
+    #[tracing::instrument(skip(self))]
+    pub async fn get_account(&self, user_id: Uuid, account_id: Uuid) -> Result<Account, UserError> {
+        let rec = sqlx::query(
+            "SELECT *, account_id = user_id AS is_default FROM accounts
+            WHERE account_id = $1 AND user_id = $2",
+        )
+        .bind(account_id)
+        .bind(user_id)
+        .fetch_optional(&self.db)
+        .await?
+        .ok_or(UserError::AccountNotFound)?;
+        Self::account_from_row(&rec)
+    }
+
+    /// Sets how future sells from the account pick their tax lots. Past sells keep their basis.
+    #[tracing::instrument(skip(self))]
+    pub async fn set_cost_basis_method(
+        &self,
+        account_id: Uuid,
+        method: CostBasisMethod,
+    ) -> Result<(), UserError> {
+        let rows_affected =
+            sqlx::query("UPDATE accounts SET cost_basis_method = $2 WHERE account_id = $1")
+                .bind(account_id)
+                .bind(method)
+                .execute(&self.db)
+                .await?
+                .rows_affected();
+        if rows_affected == 0 {
+            return Err(UserError::AccountNotFound);
+        }
+        Ok(())
+    }
+
+    /// Moves available cash between two of the user's accounts. Both legs are posted in one
+    /// transaction and share the transfer id as their reference.
+    #[tracing::instrument(skip(self))]
+    pub async fn transfer_funds(
         &self,
         user_id: Uuid,
+        from_account_id: Uuid,
+        to_account_id: Uuid,
+        amount: &BigDecimal,
+    ) -> Result<CashTransfer, TradeError> {
+        if amount <= &BigDecimal::zero() {
+            return Err(TradeError::InvalidAmount);
+        }
+        if from_account_id == to_account_id {
+            return Err(UserError::InvalidTransfer.into());
+        }
+        self.get_account(user_id, from_account_id).await?;
+        self.get_account(user_id, to_account_id).await?;
+        let transfer_id = Uuid::new_v4();
+        let counter_account = JournalEntryType::Transfer.counter_account();
+        let mut tx = self.db.begin().await?;
+        Self::post_journal_entry_in(
+            &mut tx,
+            from_account_id,
+            JournalEntryType::Transfer,
+            Some(transfer_id),
+            &[
+                (LedgerAccount::UserCash, -amount.clone()),
+                (counter_account, amount.clone()),
+            ],
+        )
+        .await?;
+        Self::post_journal_entry_in(
+            &mut tx,
+            to_account_id,
+            JournalEntryType::Transfer,
+            Some(transfer_id),
+            &[
+                (LedgerAccount::UserCash, amount.clone()),
+                (counter_account, -amount.clone()),
+            ],
+        )
+        .await?;
+        tx.commit().await?;
+        Ok(CashTransfer {
+            transfer_id,
+            from_account_id,
+            to_account_id,
+            amount: amount.clone(),
+        })
+    }
+
+    fn account_from_row(rec: &PgRow) -> Result<Account, UserError> {
+        Ok(Account {
+            account_id: rec.try_get("account_id")?,
+            user_id: rec.try_get("user_id")?,
+            name: rec.try_get("name")?,
+            balance: rec.try_get("balance")?,
+            available_balance: rec.try_get("available_balance")?,
+            cost_basis_method: rec.try_get("cost_basis_method")?,
+            is_default: rec.try_get("is_default")?,
+            created_at: rec.try_get("created_at")?,
+        })
+    }
+
+    /// Holds cash back for an open buy. Reservations made for an order carry its id so whatever
+    /// the order does not spend can be given back with `release_reservation`.
+    #[tracing::instrument(skip(self))]
+    pub async fn reserve_funds(
+        &self,
+        account_id: Uuid,
         reserve_amount: &BigDecimal,
+        order_id: Option<Uuid>,
     ) -> Result<(), TradeError> {
         if reserve_amount <= &BigDecimal::zero() {
             return Err(TradeError::InvalidAmount);
         }
-        tracing::debug!("Reserving funds {} for user {}", reserve_amount, user_id);
+        tracing::debug!("Reserving funds {} for account {}", reserve_amount, account_id);
         //only available funds are deducted, balance is deducted from when the order is executed
-        let rows_affected = sqlx::query(
-        "UPDATE users SET available_balance = available_balance - $1 WHERE user_id = $2 AND available_balance >= $1",
+        self.post_journal_entry(
+            account_id,
+            JournalEntryType::Reservation,
+            order_id,
+            &[
+                (LedgerAccount::UserCash, -reserve_amount.clone()),
+                (LedgerAccount::UserReserved, reserve_amount.clone()),
+            ],
         )
-        .bind(reserve_amount)
-        .bind(user_id)
-        .execute(&self.db)
-        .await
-        .map_err(|e| TradeError::UserError(UserError::DatabaseError(e)))?
-        .rows_affected();
-        if rows_affected > 0 {
-            Ok(())
-        } else {
-            Err(TradeError::UserError(UserError::InsufficientFunds))
-        }
+        .await?;
+        Ok(())
     }
 
+    /// Returns the cash still reserved for an order that was filled or cancelled.
+    #[tracing::instrument(skip(self))]
+    pub async fn release_reservation(
+        &self,
+        account_id: Uuid,
+        order_id: Uuid,
+    ) -> Result<(), TradeError> {
+        let remaining: Option<BigDecimal> = sqlx::query(
+            "SELECT SUM(l.amount) FROM journal_entries e
+                JOIN journal_lines l ON l.entry_id = e.entry_id
+            WHERE e.account_id = $1 AND e.reference_id = $2 AND l.account = $3",
+        )
+        .bind(account_id)
+        .bind(order_id)
+        .bind(LedgerAccount::UserReserved)
+        .fetch_one(&self.db)
+        .await?
+        .try_get(0)?;
+        let Some(remaining) = remaining.filter(|amount| amount > &BigDecimal::zero()) else {
+            return Ok(());
+        };
+        self.post_journal_entry(
+            account_id,
+            JournalEntryType::Reservation,
+            Some(order_id),
+            &[
+                (LedgerAccount::UserReserved, -remaining.clone()),
+                (LedgerAccount::UserCash, remaining),
+            ],
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Credits the account's available cash, e.g. for deposits, sale proceeds and loan disbursements.
     #[tracing::instrument(skip(self))]
     pub async fn add_user_balance(
         &self,
-        user_id: Uuid,
+        account_id: Uuid,
         amount: &BigDecimal,
+        entry_type: JournalEntryType,
+        reference_id: Option<Uuid>,
     ) -> Result<(), TradeError> {
         if amount <= &BigDecimal::zero() {
             return Err(TradeError::InvalidAmount);
         }
-        let rows_affected = sqlx::query(
-            "UPDATE users SET balance = balance + $1, available_balance = available_balance + $1 WHERE user_id = $2",
+        self.post_journal_entry(
+            account_id,
+            entry_type,
+            reference_id,
+            &[
+                (LedgerAccount::UserCash, amount.clone()),
+                (entry_type.counter_account(), -amount.clone()),
+            ],
         )
-        .bind(amount)
-        .bind(user_id)
-        .execute(&self.db)
-        .await
-        .map_err(|e| TradeError::UserError(UserError::DatabaseError(e)))?
-        .rows_affected();
-        if rows_affected > 0 {
-            Ok(())
-        } else {
-            Err(TradeError::UserError(UserError::InsufficientFunds))
+        .await?;
+        Ok(())
+    }
+
+    /// Debits the account's available cash directly, e.g. for withdrawals and loan repayments.
+    #[tracing::instrument(skip(self))]
+    pub async fn debit_user_balance(
+        &self,
+        account_id: Uuid,
+        amount: &BigDecimal,
+        entry_type: JournalEntryType,
+        reference_id: Option<Uuid>,
+    ) -> Result<(), TradeError> {
+        if amount <= &BigDecimal::zero() {
+            return Err(TradeError::InvalidAmount);
         }
+        self.post_journal_entry(
+            account_id,
+            entry_type,
+            reference_id,
+            &[
+                (LedgerAccount::UserCash, -amount.clone()),
+                (entry_type.counter_account(), amount.clone()),
+            ],
+        )
+        .await?;
+        Ok(())
     }
 
+    /// Settles against cash that was previously reserved with `reserve_funds`.
     #[tracing::instrument(skip(self))]
     pub async fn deduct_user_balance(
         &self,
-        user_id: Uuid,
+        account_id: Uuid,
         amount: &BigDecimal,
+        entry_type: JournalEntryType,
+        reference_id: Option<Uuid>,
     ) -> Result<(), TradeError> {
         if amount <= &BigDecimal::zero() {
             return Err(TradeError::InvalidAmount);
         }
         //only need to deduct balance as reserve funds already deducts from available balance
-        let rows_affected = sqlx::query(
-            "UPDATE users SET balance = balance - $1 WHERE user_id = $2 AND balance >= $1",
+        self.post_journal_entry(
+            account_id,
+            entry_type,
+            reference_id,
+            &[
+                (LedgerAccount::UserReserved, -amount.clone()),
+                (entry_type.counter_account(), amount.clone()),
+            ],
         )
-        .bind(amount)
-        .bind(user_id)
-        .execute(&self.db)
-        .await
-        .map_err(|e| TradeError::UserError(UserError::DatabaseError(e)))?;
-        if rows_affected.rows_affected() > 0 {
-            Ok(())
-        } else {
-            Err(TradeError::UserError(UserError::InsufficientFunds))
+        .await?;
+        Ok(())
+    }
+
+    pub async fn reset_user_balance(&self, account_id: Uuid) -> Result<(), TradeError> {
+        let current = self.get_ledger_balance(account_id).await?;
+        let target = BigDecimal::from(Self::RESET_BALANCE);
+        let cash_adjustment = &target - &current.cash;
+        let reserved_adjustment = -current.reserved.clone();
+        let equity_adjustment = -(&cash_adjustment + &reserved_adjustment);
+        let lines: Vec<(LedgerAccount, BigDecimal)> = [
+            (LedgerAccount::UserCash, cash_adjustment),
+            (LedgerAccount::UserReserved, reserved_adjustment),
+            (JournalEntryType::Reset.counter_account(), equity_adjustment),
+        ]
+        .into_iter()
+        .filter(|(_, amount)| !amount.is_zero())
+        .collect();
+        if lines.is_empty() {
+            return Ok(());
         }
+        self.post_journal_entry(account_id, JournalEntryType::Reset, None, &lines)
+            .await?;
+        Ok(())
     }
-    pub async fn reset_user_balance(&self, user_id: Uuid) -> Result<(), TradeError> {
+
+    /// Appends a balanced journal entry and refreshes the cached balances on `accounts` in the
+    /// same transaction. Fails with `InsufficientFunds` if either user account would go negative.
+    async fn post_journal_entry(
+        &self,
+        account_id: Uuid,
+        entry_type: JournalEntryType,
+        reference_id: Option<Uuid>,
+        lines: &[(LedgerAccount, BigDecimal)],
+    ) -> Result<Uuid, UserError> {
+        let mut tx = self.db.begin().await?;
+        let entry_id =
+            Self::post_journal_entry_in(&mut tx, account_id, entry_type, reference_id, lines)
+                .await?;
+        tx.commit().await?;
+        Ok(entry_id)
+    }
+
+    pub(crate) async fn post_journal_entry_in(
+        tx: &mut PgTransaction<'_>,
+        account_id: Uuid,
+        entry_type: JournalEntryType,
+        reference_id: Option<Uuid>,
+        lines: &[(LedgerAccount, BigDecimal)],
+    ) -> Result<Uuid, UserError> {
+        let total: BigDecimal = lines.iter().map(|(_, amount)| amount).sum();
+        if !total.is_zero() {
+            return Err(UserError::UnbalancedJournalEntry);
+        }
+        let cash_change: BigDecimal = lines
+            .iter()
+            .filter(|(account, _)| *account == LedgerAccount::UserCash)
+            .map(|(_, amount)| amount)
+            .sum();
+        let reserved_change: BigDecimal = lines
+            .iter()
+            .filter(|(account, _)| *account == LedgerAccount::UserReserved)
+            .map(|(_, amount)| amount)
+            .sum();
+
+        let rows_affected = sqlx::query(
+            "UPDATE accounts SET balance = balance + $2 + $3, available_balance = available_balance + $2
+            WHERE account_id = $1 AND available_balance + $2 >= 0 AND balance - available_balance + $3 >= 0",
+        )
+        .bind(account_id)
+        .bind(&cash_change)
+        .bind(&reserved_change)
+        .execute(&mut **tx)
+        .await?
+        .rows_affected();
+        if rows_affected == 0 {
+            return Err(UserError::InsufficientFunds);
+        }
+        let entry_id = Uuid::new_v4();
         sqlx::query(
-            "UPDATE users SET balance = 100000, available_balance = 100000 WHERE user_id = $1",
+            "INSERT INTO journal_entries (entry_id, account_id, entry_type, reference_id) VALUES ($1, $2, $3, $4)",
         )
-        .bind(user_id)
-        .execute(&self.db)
-        .await
-        .map_err(|e| TradeError::UserError(UserError::DatabaseError(e)))?;
-        Ok(())
+        .bind(entry_id)
+        .bind(account_id)
+        .bind(entry_type)
+        .bind(reference_id)
+        .execute(&mut **tx)
+        .await?;
+        for (account, amount) in lines {
+            sqlx::query(
+                "INSERT INTO journal_lines (line_id, entry_id, account, amount) VALUES ($1, $2, $3, $4)",
+            )
+            .bind(Uuid::new_v4())
+            .bind(entry_id)
+            .bind(account)
+            .bind(amount)
+            .execute(&mut **tx)
+            .await?;
+        }
+        Ok(entry_id)
     }
 }
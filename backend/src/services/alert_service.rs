@@ -0,0 +1,388 @@
+use bigdecimal::{BigDecimal, RoundingMode};
+use sqlx::postgres::PgRow;
+use sqlx::{PgPool, Row};
+use uuid::Uuid;
+
+use crate::models::alert::{
+    Alert, AlertCondition, AlertRequest, Notification, NotificationInbox, PriceUpdate,
+};
+use crate::models::errors::trade_error::TradeError;
+use crate::models::order::{OrderStatus, OrderType};
+
+/// Users' alerts on tickers, checked against every price the market maker steers to and every
+/// price the matcher fills at, and the inbox they notify through.
+pub struct AlertService {
+    db: PgPool,
+}
+
+impl AlertService {
+    pub const MAX_ALERTS: i64 = 100;
+    pub const MAX_NOTIFICATIONS: i64 = 200;
+    const PRICE_SCALE: i64 = 4;
+
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+
+    fn alert_from_row(rec: &PgRow) -> Result<Alert, TradeError> {
+        Ok(Alert {
+            alert_id: rec.try_get("alert_id")?,
+            ticker: rec.try_get("ticker")?,
+            condition: rec.try_get("condition")?,
+            threshold: rec.try_get("threshold")?,
+            recurring: rec.try_get("recurring")?,
+            active: rec.try_get("active")?,
+            armed: rec.try_get("armed")?,
+            trigger_count: rec.try_get("trigger_count")?,
+            created_at: rec.try_get("created_at")?,
+            last_triggered_at: rec.try_get("last_triggered_at")?,
+        })
+    }
+
+    #[tracing::instrument(skip(self))]
+    pub async fn list_alerts(&self, user_id: Uuid) -> Result<Vec<Alert>, TradeError> {
+        sqlx::query("SELECT * FROM alerts WHERE user_id = $1 ORDER BY created_at")
+            .bind(user_id)
+            .fetch_all(&self.db)
+            .await?
+            .iter()
+            .map(Self::alert_from_row)
+            .collect()
+    }
+
+    #[tracing::instrument(skip(self))]
+    pub async fn create_alert(
+        &self,
+        user_id: Uuid,
+        request: AlertRequest,
+    ) -> Result<Alert, TradeError> {
+        let request = request.normalise().map_err(TradeError::InvalidAlert)?;
+        let mut tx = self.db.begin().await?;
+        //serialises alert creation per user so the limit holds
+        sqlx::query("SELECT user_id FROM users WHERE user_id = $1 FOR UPDATE")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        let alerts: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM alerts WHERE user_id = $1")
+            .bind(user_id)
+            .fetch_one(&mut *tx)
+            .await?;
+        if alerts >= Self::MAX_ALERTS {
+            return Err(TradeError::InvalidAlert(format!(
+                "a user can have at most {} alerts",
+                Self::MAX_ALERTS
+            )));
+        }
+        let rec = sqlx::query(
+            "INSERT INTO alerts (alert_id, user_id, ticker, condition, threshold, recurring)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING *",
+        )
+        .bind(Uuid::new_v4())
+        .bind(user_id)
+        .bind(&request.ticker)
+        .bind(request.condition)
+        .bind(&request.threshold)
+        .bind(request.recurring)
+        .fetch_one(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Self::alert_from_row(&rec)
+    }
+
+    #[tracing::instrument(skip(self))]
+    pub async fn delete_alert(&self, user_id: Uuid, alert_id: Uuid) -> Result<(), TradeError> {
+        let deleted = sqlx::query("DELETE FROM alerts WHERE alert_id = $1 AND user_id = $2")
+            .bind(alert_id)
+            .bind(user_id)
+            .execute(&self.db)
+            .await?;
+        if deleted.rows_affected() == 0 {
+            return Err(TradeError::AlertNotFound);
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(skip(self))]
+    pub async fn get_notifications(
+        &self,
+        user_id: Uuid,
+        unread_only: bool,
+        limit: i64,
+    ) -> Result<NotificationInbox, TradeError> {
+        let notifications = sqlx::query(
+            "SELECT * FROM notifications
+            WHERE user_id = $1 AND ($2 = FALSE OR read_at IS NULL)
+            ORDER BY created_at DESC
+            LIMIT $3",
+        )
+        .bind(user_id)
+        .bind(unread_only)
+        .bind(limit.clamp(1, Self::MAX_NOTIFICATIONS))
+        .fetch_all(&self.db)
+        .await?
+        .iter()
+        .map(|rec| {
+            Ok(Notification {
+                notification_id: rec.try_get("notification_id")?,
+                alert_id: rec.try_get("alert_id")?,
+                ticker: rec.try_get("ticker")?,
+                condition: rec.try_get("condition")?,
+                message: rec.try_get("message")?,
+                price: rec.try_get("price")?,
+                order_id: rec.try_get("order_id")?,
+                created_at: rec.try_get("created_at")?,
+                read_at: rec.try_get("read_at")?,
+            })
+        })
+        .collect::<Result<Vec<_>, TradeError>>()?;
+        let unread = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM notifications WHERE user_id = $1 AND read_at IS NULL",
+        )
+        .bind(user_id)
+        .fetch_one(&self.db)
+        .await?;
+        Ok(NotificationInbox {
+            unread,
+            notifications,
+        })
+    }
+
+    #[tracing::instrument(skip(self))]
+    pub async fn mark_read(&self, user_id: Uuid, notification_id: Uuid) -> Result<(), TradeError> {
+        let updated = sqlx::query(
+            "UPDATE notifications SET read_at = COALESCE(read_at, NOW())
+            WHERE notification_id = $1 AND user_id = $2",
+        )
+        .bind(notification_id)
+        .bind(user_id)
+        .execute(&self.db)
+        .await?;
+        if updated.rows_affected() == 0 {
+            return Err(TradeError::NotificationNotFound);
+        }
+        Ok(())
+    }
+
+    #[tracing::instrument(skip(self))]
+    pub async fn mark_all_read(&self, user_id: Uuid) -> Result<(), TradeError> {
+        sqlx::query(
+            "UPDATE notifications SET read_at = NOW() WHERE user_id = $1 AND read_at IS NULL",
+        )
+        .bind(user_id)
+        .execute(&self.db)
+        .await?;
+        Ok(())
+    }
+
+    /// The ticker's previous close, its volume so far today and its average daily volume
+    /// before today, all by UTC day.
+    async fn price_update(
+        &self,
+        ticker: &str,
+        price: &BigDecimal,
+    ) -> Result<PriceUpdate, TradeError> {
+        let rec = sqlx::query(
+            "SELECT
+                (SELECT close FROM stock_prices
+                    WHERE ticker = $1 AND close IS NOT NULL AND date < date_trunc('day', NOW(), 'UTC')
+                    ORDER BY date DESC LIMIT 1) AS previous_close,
+                (SELECT SUM(volume)::bigint FROM stock_prices
+                    WHERE ticker = $1 AND date >= date_trunc('day', NOW(), 'UTC')
+                        AND date <= NOW()) AS day_volume,
+                (SELECT AVG(volume) FROM (
+                    SELECT SUM(volume) AS volume FROM stock_prices
+                    WHERE ticker = $1 AND volume IS NOT NULL
+                        AND date < date_trunc('day', NOW(), 'UTC')
+                    GROUP BY (date AT TIME ZONE 'UTC')::date
+                    ORDER BY (date AT TIME ZONE 'UTC')::date DESC
+                    LIMIT $2
+                ) days) AS average_volume",
+        )
+        .bind(ticker)
+        .bind(PriceUpdate::AVERAGE_VOLUME_DAYS)
+        .fetch_one(&self.db)
+        .await?;
+        Ok(PriceUpdate {
+            ticker: ticker.to_string(),
+            price: price.clone(),
+            previous_close: rec.try_get("previous_close")?,
+            day_volume: rec.try_get("day_volume")?,
+            average_volume: rec.try_get("average_volume")?,
+        })
+    }
+
+    /// Checks the ticker's price alerts against a new price. Alerts whose condition holds
+    /// trigger if armed, and recurring alerts whose condition no longer holds are armed again.
+    /// Returns how many alerts triggered.
+    #[tracing::instrument(skip(self))]
+    pub async fn on_price(&self, ticker: &str, price: &BigDecimal) -> Result<usize, TradeError> {
+        //prices are kept to four places, the market maker's targets are not
+        let price = &price.with_scale_round(Self::PRICE_SCALE, RoundingMode::HalfUp);
+        let alerts = sqlx::query(
+            "SELECT * FROM alerts WHERE ticker = $1 AND active AND condition <> 'ORDER_FILLED'",
+        )
+        .bind(ticker)
+        .fetch_all(&self.db)
+        .await?
+        .iter()
+        .map(Self::alert_from_row)
+        .collect::<Result<Vec<_>, TradeError>>()?;
+        if alerts.is_empty() {
+            return Ok(0);
+        }
+        let needs_history = alerts.iter().any(|alert| {
+            matches!(
+                alert.condition,
+                AlertCondition::PercentMove | AlertCondition::VolumeSpike
+            )
+        });
+        let update = if needs_history {
+            self.price_update(ticker, price).await?
+        } else {
+            PriceUpdate {
+                ticker: ticker.to_string(),
+                price: price.clone(),
+                previous_close: None,
+                day_volume: None,
+                average_volume: None,
+            }
+        };
+
+        let mut rearm = Vec::new();
+        let mut triggered = 0;
+        for alert in &alerts {
+            match (alert.check(&update), alert.armed) {
+                (Some(message), true) => {
+                    triggered += usize::from(
+                        self.trigger(alert.alert_id, &message, Some(price), None)
+                            .await?,
+                    );
+                }
+                (None, false) if alert.recurring => rearm.push(alert.alert_id),
+                _ => {}
+            }
+        }
+        if !rearm.is_empty() {
+            sqlx::query("UPDATE alerts SET armed = TRUE WHERE alert_id = ANY($1) AND active")
+                .bind(&rearm)
+                .execute(&self.db)
+                .await?;
+        }
+        Ok(triggered)
+    }
+
+    /// Triggers the order filled alerts of the order's owner once the order is filled in full.
+    /// Returns how many alerts triggered; an order only ever triggers an alert once.
+    #[tracing::instrument(skip(self))]
+    pub async fn on_order_filled(&self, order_id: Uuid) -> Result<usize, TradeError> {
+        let Some(order) = sqlx::query(
+            "SELECT o.ticker, o.order_type, o.status, a.user_id,
+                SUM(t.quantity) AS filled,
+                SUM(t.quantity * t.price_per_share) / NULLIF(SUM(t.quantity), 0) AS average_price
+            FROM orders o
+            JOIN accounts a ON a.account_id = o.account_id
+            LEFT JOIN transactions t ON t.order_id = o.order_id
+            WHERE o.order_id = $1
+            GROUP BY o.order_id, a.user_id",
+        )
+        .bind(order_id)
+        .fetch_optional(&self.db)
+        .await?
+        else {
+            return Ok(0);
+        };
+        let status: OrderStatus = order.try_get("status")?;
+        if status != OrderStatus::Executed {
+            return Ok(0);
+        }
+        let ticker: String = order.try_get("ticker")?;
+        let order_type: OrderType = order.try_get("order_type")?;
+        let filled: Option<BigDecimal> = order.try_get("filled")?;
+        let average_price: Option<BigDecimal> = order.try_get("average_price")?;
+        let message = match (&filled, &average_price) {
+            (Some(filled), Some(price)) => format!(
+                "Your {} order for {} {} was filled at an average of {}",
+                order_type.to_string().to_lowercase(),
+                filled.normalized(),
+                ticker,
+                price
+                    .with_scale_round(Self::PRICE_SCALE, RoundingMode::HalfUp)
+                    .normalized()
+            ),
+            _ => format!(
+                "Your {} order for {} was filled",
+                order_type.to_string().to_lowercase(),
+                ticker
+            ),
+        };
+
+        let alert_ids: Vec<Uuid> = sqlx::query_scalar(
+            "SELECT alert_id FROM alerts
+            WHERE user_id = $1 AND ticker = $2 AND active AND condition = 'ORDER_FILLED'",
+        )
+        .bind(order.try_get::<Uuid, _>("user_id")?)
+        .bind(&ticker)
+        .fetch_all(&self.db)
+        .await?;
+        let mut triggered = 0;
+        for alert_id in alert_ids {
+            if self
+                .trigger(alert_id, &message, average_price.as_ref(), Some(order_id))
+                .await?
+            {
+                triggered += 1;
+            }
+        }
+        Ok(triggered)
+    }
+
+    /// Claims the alert and delivers its notification in one transaction, so an alert checked
+    /// by the market maker and the matcher at once only notifies once. Returns false if the
+    /// alert had already triggered or the order was already notified.
+    async fn trigger(
+        &self,
+        alert_id: Uuid,
+        message: &str,
+        price: Option<&BigDecimal>,
+        order_id: Option<Uuid>,
+    ) -> Result<bool, TradeError> {
+        let mut tx = self.db.begin().await?;
+        let Some(alert) = sqlx::query(
+            "UPDATE alerts SET
+                active = recurring,
+                armed = (condition = 'ORDER_FILLED'),
+                trigger_count = trigger_count + 1,
+                last_triggered_at = NOW()
+            WHERE alert_id = $1 AND active AND (armed OR condition = 'ORDER_FILLED')
+            RETURNING user_id, ticker, condition",
+        )
+        .bind(alert_id)
+        .fetch_optional(&mut *tx)
+        .await?
+        else {
+            return Ok(false);
+        };
+        let delivered = sqlx::query(
+            "INSERT INTO notifications
+                (notification_id, user_id, alert_id, ticker, condition, message, price, order_id)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT DO NOTHING",
+        )
+        .bind(Uuid::new_v4())
+        .bind(alert.try_get::<Uuid, _>("user_id")?)
+        .bind(alert_id)
+        .bind(alert.try_get::<String, _>("ticker")?)
+        .bind(alert.try_get::<AlertCondition, _>("condition")?)
+        .bind(message)
+        .bind(price)
+        .bind(order_id)
+        .execute(&mut *tx)
+        .await?;
+        if delivered.rows_affected() == 0 {
+            return Ok(false);
+        }
+        tx.commit().await?;
+        Ok(true)
+    }
+}
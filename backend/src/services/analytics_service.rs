@@ -0,0 +1,188 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use bigdecimal::BigDecimal;
+use chrono::{Days, NaiveDate, NaiveTime, Utc};
+use num_traits::ToPrimitive;
+use sqlx::{PgPool, Row};
+use uuid::Uuid;
+
+use crate::models::analytics::{
+    Benchmark, BenchmarkComparison, DailyValuation, PerformanceAnalytics,
+};
+use crate::models::errors::trade_error::TradeError;
+use crate::models::ledger::{JournalEntryType, LedgerAccount};
+use crate::models::stock_ticker::TimeFrame;
+use crate::services::snapshot_service::SnapshotService;
+
+/// Measures how accounts performed from their daily snapshots, with deposits, withdrawals and
+/// transfers taken out.
+pub struct AnalyticsService {
+    db: PgPool,
+    snapshot_service: Arc<SnapshotService>,
+}
+
+impl AnalyticsService {
+    pub fn new(db: PgPool, snapshot_service: Arc<SnapshotService>) -> Self {
+        Self {
+            db,
+            snapshot_service,
+        }
+    }
+
+    #[tracing::instrument(skip(self))]
+    pub async fn get_performance(
+        &self,
+        account_id: Uuid,
+        timeframe: TimeFrame,
+        risk_free_rate: f64,
+    ) -> Result<PerformanceAnalytics, TradeError> {
+        let start = self
+            .snapshot_service
+            .timeframe_start(account_id, timeframe)
+            .await?;
+        let mut valuations = self
+            .daily_valuations(account_id, start - Days::new(1), Utc::now().date_naive())
+            .await?;
+        let opening = valuations.remove(0);
+        PerformanceAnalytics::compute(opening.value, &valuations, risk_free_rate)
+            .ok_or(TradeError::InvalidAmount)
+    }
+
+    /// Compares the account's time-weighted growth over `timeframe` with the benchmark's,
+    /// day by day.
+    #[tracing::instrument(skip(self))]
+    pub async fn compare_to_benchmark(
+        &self,
+        account_id: Uuid,
+        timeframe: TimeFrame,
+        benchmark: Benchmark,
+    ) -> Result<BenchmarkComparison, TradeError> {
+        let start = self
+            .snapshot_service
+            .timeframe_start(account_id, timeframe)
+            .await?;
+        let base_day = start - Days::new(1);
+        let today = Utc::now().date_naive();
+        let levels = self.benchmark_levels(&benchmark, base_day, today).await?;
+        let mut valuations = self.daily_valuations(account_id, base_day, today).await?;
+        let opening = valuations.remove(0);
+        let returns = PerformanceAnalytics::daily_returns(opening.value, &valuations);
+        BenchmarkComparison::compute(benchmark, &returns, &levels).ok_or_else(|| {
+            TradeError::InvalidBenchmark("it has no prices for the period".to_string())
+        })
+    }
+
+    /// The benchmark's level at the end of each day from `from` to `to`, starting at one. Each
+    /// ticker counts from its close on `from`, or its first close after that if it had none.
+    async fn benchmark_levels(
+        &self,
+        benchmark: &Benchmark,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<(NaiveDate, f64)>, TradeError> {
+        let mut closes: HashMap<String, BTreeMap<NaiveDate, f64>> = HashMap::new();
+        let price_records = sqlx::query(
+            "SELECT DISTINCT ON (ticker, (date AT TIME ZONE 'UTC')::date)
+                ticker, (date AT TIME ZONE 'UTC')::date AS day, close
+            FROM stock_prices
+            WHERE ticker = ANY($1) AND date <= NOW() AND close > 0
+                AND (date AT TIME ZONE 'UTC')::date <= $2
+            ORDER BY ticker, (date AT TIME ZONE 'UTC')::date, date DESC",
+        )
+        .bind(benchmark.tickers())
+        .bind(to)
+        .fetch_all(&self.db)
+        .await?;
+        for rec in price_records {
+            closes
+                .entry(rec.try_get("ticker")?)
+                .or_default()
+                .insert(rec.try_get("day")?, Self::to_f64(&rec.try_get("close")?));
+        }
+
+        let mut bases = Vec::new();
+        for weight in benchmark.weights() {
+            let ticker_closes = closes.get(&weight.ticker).ok_or_else(|| {
+                TradeError::InvalidBenchmark(format!("{} has no prices", weight.ticker))
+            })?;
+            let base = ticker_closes
+                .range(..=from)
+                .next_back()
+                .or_else(|| ticker_closes.range(from..).next())
+                .map(|(_, close)| *close)
+                .ok_or_else(|| {
+                    TradeError::InvalidBenchmark(format!("{} has no prices", weight.ticker))
+                })?;
+            bases.push((weight, ticker_closes, base));
+        }
+        Ok(from
+            .iter_days()
+            .take_while(|day| *day <= to)
+            .map(|day| {
+                let level = bases
+                    .iter()
+                    .map(|(weight, ticker_closes, base)| {
+                        let close = ticker_closes
+                            .range(..=day)
+                            .next_back()
+                            .map_or(*base, |(_, close)| *close);
+                        weight.weight * close / base
+                    })
+                    .sum();
+                (day, level)
+            })
+            .collect())
+    }
+
+    /// End-of-day value and external flows of the account for every day from `from` to `to`.
+    pub async fn daily_valuations(
+        &self,
+        account_id: Uuid,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<DailyValuation>, TradeError> {
+        let mut flows: HashMap<NaiveDate, f64> = HashMap::new();
+        let flow_records = sqlx::query(
+            "SELECT (e.created_at AT TIME ZONE 'UTC')::date AS day, e.entry_type,
+                SUM(l.amount) AS amount
+            FROM journal_entries e JOIN journal_lines l ON l.entry_id = e.entry_id
+            WHERE e.account_id = $1 AND l.account IN ($2, $3)
+                AND e.created_at >= $4 AND e.created_at < $5
+            GROUP BY 1, 2",
+        )
+        .bind(account_id)
+        .bind(LedgerAccount::UserCash)
+        .bind(LedgerAccount::UserReserved)
+        .bind(from.and_time(NaiveTime::MIN).and_utc())
+        .bind((to + Days::new(1)).and_time(NaiveTime::MIN).and_utc())
+        .fetch_all(&self.db)
+        .await?;
+        for rec in flow_records {
+            let entry_type: JournalEntryType = rec.try_get("entry_type")?;
+            if entry_type.is_external_flow() {
+                *flows.entry(rec.try_get("day")?).or_default() +=
+                    Self::to_f64(&rec.try_get("amount")?);
+            }
+        }
+
+        Ok(self
+            .snapshot_service
+            .daily_snapshots(account_id, from, to)
+            .await?
+            .into_iter()
+            .map(|snapshot| DailyValuation {
+                date: snapshot.snapshot_date,
+                value: Self::to_f64(&snapshot.total_value),
+                net_flow: flows
+                    .get(&snapshot.snapshot_date)
+                    .copied()
+                    .unwrap_or_default(),
+            })
+            .collect())
+    }
+
+    fn to_f64(value: &BigDecimal) -> f64 {
+        value.to_f64().unwrap_or_default()
+    }
+}
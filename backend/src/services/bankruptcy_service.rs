@@ -36,23 +36,26 @@ impl BankruptcyService {
             order_management_service,
         }
     }
+    /// Net worth is taken across all of the user's accounts.
     pub async fn check_for_bankruptcy(&self, user_id: Uuid) -> Result<bool, TradeError> {
         let loan = self.loan_service.get_loan(user_id).await;
         match loan {
             Ok(loan) => {
-                let current_cash_balance = self
+                let mut asset_value = BigDecimal::from(0);
+                for account in self
                     .account_management_service
-                    .get_user_balance(user_id)
-                    .await?;
-                let portfolio_value = self
-                    .portfolio_management_service
-                    .get_total_portfolio_value(user_id)
-                    .await?;
+                    .list_accounts(user_id)
+                    .await?
+                {
+                    asset_value += account.available_balance;
+                    asset_value += self
+                        .portfolio_management_service
+                        .get_total_portfolio_value(account.account_id)
+                        .await?;
+                }
                 let liabilities = loan.get_current_balance();
-                let credit_score = Self::calculate_credit_score(
-                    current_cash_balance + portfolio_value,
-                    liabilities.0 + liabilities.1,
-                );
+                let credit_score =
+                    Self::calculate_credit_score(asset_value, liabilities.0 + liabilities.1);
                 if credit_score < BigDecimal::from(Self::CREDIT_SCORE_THRESHOLD) {
                     return Ok(true);
                 }
@@ -72,27 +75,51 @@ impl BankruptcyService {
                     self.loan_service
                         .set_loan_status(user_id, LoanStatus::DEFAULTED)
                         .await?;
-                    //reset user
-                    self.account_management_service
-                        .reset_user_balance(user_id)
-                        .await?;
-                    //liquidate portfolio
-                    let user_portfolio = self
-                        .portfolio_management_service
-                        .get_portfolio(user_id)
+                    let accounts = self
+                        .account_management_service
+                        .list_accounts(user_id)
                         .await?;
-                    for portfolio_item in user_portfolio {
-                        self.portfolio_management_service
-                            .remove_from_portfolio(
-                                portfolio_item.user_id,
-                                &portfolio_item.ticker,
-                                &portfolio_item.quantity,
-                            )
+                    for account in &accounts {
+                        //cancel all orders from the account
+                        self.order_management_service
+                            .cancel_all_orders(account.account_id)
+                            .await?;
+                        //liquidate portfolio
+                        let account_portfolio = self
+                            .portfolio_management_service
+                            .get_portfolio(account.account_id)
+                            .await?;
+                        for portfolio_item in account_portfolio {
+                            self.portfolio_management_service
+                                .remove_from_portfolio(
+                                    portfolio_item.account_id,
+                                    &portfolio_item.ticker,
+                                    &portfolio_item.quantity,
+                                    None,
+                                )
+                                .await?;
+                        }
+                    }
+                    //sweep sub-account cash into the default account before resetting it
+                    for account in accounts.iter().filter(|account| !account.is_default) {
+                        let available_balance = self
+                            .account_management_service
+                            .get_user_balance(account.account_id)
                             .await?;
+                        if available_balance > 0 {
+                            self.account_management_service
+                                .transfer_funds(
+                                    user_id,
+                                    account.account_id,
+                                    user_id,
+                                    &available_balance,
+                                )
+                                .await?;
+                        }
                     }
-                    //cancel all orders from the user
-                    self.order_management_service
-                        .cancel_all_orders(user_id)
+                    //reset user
+                    self.account_management_service
+                        .reset_user_balance(user_id)
                         .await?;
                 }
             }
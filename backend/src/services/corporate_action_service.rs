@@ -0,0 +1,490 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use bigdecimal::{BigDecimal, RoundingMode};
+use chrono::Utc;
+use num_traits::Zero;
+use sqlx::postgres::PgRow;
+use sqlx::{PgPool, PgTransaction, Row};
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::models::corporate_action::{
+    CorporateAction, CorporateActionStatus, CorporateActionType, NewCorporateAction, SplitRatio,
+};
+use crate::models::errors::ticker_error::TickerError;
+use crate::models::errors::trade_error::TradeError;
+use crate::models::ledger::{JournalEntryType, LedgerAccount};
+use crate::models::order::{Order, OrderStatus, OrderType};
+use crate::services::account_management_service::AccountManagementService;
+use crate::services::market_maker_service::MarketMakerService;
+use crate::services::order_matchbook_service::OrderMatchbookService;
+
+/// Applies admin-scheduled dividends, splits and reverse splits once their effective date is
+/// reached, keeping positions, resting orders and price history consistent with each other.
+#[derive(Clone)]
+pub struct CorporateActionService {
+    db: PgPool,
+    order_matchbook_service: Arc<OrderMatchbookService>,
+    market_maker_service: Arc<MarketMakerService>,
+}
+
+impl CorporateActionService {
+    const PROCESSING_INTERVAL_SECS: u64 = 60 * 60;
+
+    pub fn new(
+        db: PgPool,
+        order_matchbook_service: Arc<OrderMatchbookService>,
+        market_maker_service: Arc<MarketMakerService>,
+    ) -> Self {
+        Self {
+            db,
+            order_matchbook_service,
+            market_maker_service,
+        }
+    }
+
+    /// Checks that the terms match the action type: a positive dividend, a split that raises
+    /// the share count or a reverse split that lowers it.
+    pub fn validate(action: &NewCorporateAction) -> Result<(), TradeError> {
+        let invalid = |reason: &str| Err(TradeError::InvalidCorporateAction(reason.to_string()));
+        match (
+            action.action_type,
+            &action.dividend_per_share,
+            &action.split_ratio,
+        ) {
+            (CorporateActionType::Dividend, Some(dividend), None) => {
+                if *dividend <= BigDecimal::zero() {
+                    return invalid("dividend_per_share must be positive");
+                }
+                Ok(())
+            }
+            (CorporateActionType::Split, None, Some(ratio)) => {
+                if ratio.old_shares <= 0 || ratio.new_shares <= ratio.old_shares {
+                    return invalid("a split must turn old_shares into more new_shares");
+                }
+                Ok(())
+            }
+            (CorporateActionType::ReverseSplit, None, Some(ratio)) => {
+                if ratio.new_shares <= 0 || ratio.new_shares >= ratio.old_shares {
+                    return invalid("a reverse split must turn old_shares into fewer new_shares");
+                }
+                Ok(())
+            }
+            (CorporateActionType::Dividend, _, _) => {
+                invalid("a dividend needs dividend_per_share and no split_ratio")
+            }
+            _ => invalid("a split needs split_ratio and no dividend_per_share"),
+        }
+    }
+
+    #[tracing::instrument(skip(self))]
+    pub async fn schedule_action(
+        &self,
+        created_by: Uuid,
+        action: NewCorporateAction,
+    ) -> Result<CorporateAction, TradeError> {
+        Self::validate(&action)?;
+        if action.effective_date < Utc::now().date_naive() {
+            return Err(TradeError::InvalidCorporateAction(
+                "effective_date is in the past".to_string(),
+            ));
+        }
+        let listed: bool =
+            sqlx::query("SELECT EXISTS(SELECT 1 FROM stock_prices WHERE ticker = $1)")
+                .bind(&action.ticker)
+                .fetch_one(&self.db)
+                .await?
+                .try_get(0)?;
+        if !listed {
+            return Err(TickerError::InvalidSymbol(action.ticker).into());
+        }
+        let rec = sqlx::query(
+            "INSERT INTO corporate_actions (action_id, ticker, action_type, effective_date,
+                dividend_per_share, new_shares, old_shares, created_by)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING *",
+        )
+        .bind(Uuid::new_v4())
+        .bind(&action.ticker)
+        .bind(action.action_type)
+        .bind(action.effective_date)
+        .bind(&action.dividend_per_share)
+        .bind(action.split_ratio.map(|ratio| ratio.new_shares))
+        .bind(action.split_ratio.map(|ratio| ratio.old_shares))
+        .bind(created_by)
+        .fetch_one(&self.db)
+        .await?;
+        Self::action_from_row(&rec)
+    }
+
+    #[tracing::instrument(skip(self))]
+    pub async fn list_actions(
+        &self,
+        ticker: Option<&str>,
+    ) -> Result<Vec<CorporateAction>, TradeError> {
+        let records = sqlx::query(
+            "SELECT * FROM corporate_actions WHERE ($1::text IS NULL OR ticker = $1)
+            ORDER BY effective_date DESC, created_at DESC",
+        )
+        .bind(ticker)
+        .fetch_all(&self.db)
+        .await?;
+        records.iter().map(Self::action_from_row).collect()
+    }
+
+    /// Cancels an action that has not been applied yet.
+    #[tracing::instrument(skip(self))]
+    pub async fn cancel_action(&self, action_id: Uuid) -> Result<CorporateAction, TradeError> {
+        let rec = sqlx::query(
+            "UPDATE corporate_actions SET status = $2
+            WHERE action_id = $1 AND status = $3
+            RETURNING *",
+        )
+        .bind(action_id)
+        .bind(CorporateActionStatus::Cancelled)
+        .bind(CorporateActionStatus::Scheduled)
+        .fetch_optional(&self.db)
+        .await?;
+        match rec {
+            Some(rec) => Self::action_from_row(&rec),
+            None => {
+                let exists: bool = sqlx::query(
+                    "SELECT EXISTS(SELECT 1 FROM corporate_actions WHERE action_id = $1)",
+                )
+                .bind(action_id)
+                .fetch_one(&self.db)
+                .await?
+                .try_get(0)?;
+                if exists {
+                    Err(TradeError::CorporateActionNotScheduled)
+                } else {
+                    Err(TradeError::CorporateActionNotFound)
+                }
+            }
+        }
+    }
+
+    /// Applies every scheduled action whose effective date has been reached, oldest first.
+    #[tracing::instrument(skip(self))]
+    pub async fn process_due_actions(&self) -> Result<Vec<CorporateAction>, TradeError> {
+        let due: Vec<Uuid> = sqlx::query(
+            "SELECT action_id FROM corporate_actions
+            WHERE status = $1 AND effective_date <= CURRENT_DATE
+            ORDER BY effective_date, created_at",
+        )
+        .bind(CorporateActionStatus::Scheduled)
+        .fetch_all(&self.db)
+        .await?
+        .iter()
+        .map(|rec| rec.try_get("action_id"))
+        .collect::<Result<_, _>>()?;
+        let mut processed = Vec::new();
+        for action_id in due {
+            if let Some(action) = self.process_action(action_id).await? {
+                processed.push(action);
+            }
+        }
+        Ok(processed)
+    }
+
+    pub fn spawn_processing_job(&self) -> JoinHandle<Result<(), TradeError>> {
+        info!("Starting corporate action thread");
+        let service = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(
+                Self::PROCESSING_INTERVAL_SECS,
+            ));
+            loop {
+                interval.tick().await;
+                if let Err(e) = service.process_due_actions().await {
+                    warn!(error = ?e, "Corporate action processing failed");
+                }
+            }
+        })
+    }
+
+    /// Applies one action in a single transaction. Returns None if another run got to it first.
+    async fn process_action(&self, action_id: Uuid) -> Result<Option<CorporateAction>, TradeError> {
+        let mut tx = self.db.begin().await?;
+        let Some(rec) = sqlx::query(
+            "SELECT * FROM corporate_actions WHERE action_id = $1 AND status = $2 FOR UPDATE",
+        )
+        .bind(action_id)
+        .bind(CorporateActionStatus::Scheduled)
+        .fetch_optional(&mut *tx)
+        .await?
+        else {
+            return Ok(None);
+        };
+        let action = Self::action_from_row(&rec)?;
+        let mut rescaled_orders = Vec::new();
+        match (&action.dividend_per_share, &action.split_ratio) {
+            (Some(dividend), _) => Self::pay_dividend(&mut tx, &action, dividend).await?,
+            (None, Some(ratio)) => {
+                rescaled_orders = Self::apply_split(&mut tx, &action, ratio).await?;
+            }
+            (None, None) => {
+                return Err(TradeError::InvalidCorporateAction(
+                    "stored action has no terms".to_string(),
+                ))
+            }
+        }
+        let rec = sqlx::query(
+            "UPDATE corporate_actions SET status = $2, processed_at = NOW()
+            WHERE action_id = $1 RETURNING *",
+        )
+        .bind(action_id)
+        .bind(CorporateActionStatus::Processed)
+        .fetch_one(&mut *tx)
+        .await?;
+        tx.commit().await?;
+
+        if let Some(ratio) = &action.split_ratio {
+            self.order_matchbook_service
+                .rescale_orders(&action.ticker, &rescaled_orders)
+                .await;
+            self.market_maker_service
+                .rescale_price_path(&action.ticker, ratio)
+                .await;
+        }
+        info!(
+            "Processed {} for ticker {}",
+            action.action_type, action.ticker
+        );
+        Ok(Some(Self::action_from_row(&rec)?))
+    }
+
+    /// Credits every holder's available cash with quantity × dividend, rounded down to the cent
+    /// fraction the ledger stores. Cost basis is left unchanged.
+    async fn pay_dividend(
+        tx: &mut PgTransaction<'_>,
+        action: &CorporateAction,
+        dividend_per_share: &BigDecimal,
+    ) -> Result<(), TradeError> {
+        let holdings =
+            sqlx::query(
+                "SELECT account_id, quantity FROM portfolio WHERE ticker = $1 AND quantity > 0 FOR UPDATE",
+            )
+                .bind(&action.ticker)
+                .fetch_all(&mut **tx)
+                .await?;
+        for rec in holdings {
+            let account_id: Uuid = rec.try_get("account_id")?;
+            let quantity: BigDecimal = rec.try_get("quantity")?;
+            let amount = (&quantity * dividend_per_share).with_scale_round(4, RoundingMode::Down);
+            if amount > BigDecimal::zero() {
+                AccountManagementService::post_journal_entry_in(
+                    tx,
+                    account_id,
+                    JournalEntryType::Dividend,
+                    Some(action.action_id),
+                    &[
+                        (LedgerAccount::UserCash, amount.clone()),
+                        (
+                            JournalEntryType::Dividend.counter_account(),
+                            -amount.clone(),
+                        ),
+                    ],
+                )
+                .await?;
+            }
+            Self::record_adjustment(tx, action, account_id, &quantity, &quantity, &amount).await?;
+        }
+        Ok(())
+    }
+
+    /// Rescales pending orders, positions and price history by the split ratio and returns the
+    /// rescaled orders. `total_money_spent` is kept, so the cost per share moves with the split.
+    async fn apply_split(
+        tx: &mut PgTransaction<'_>,
+        action: &CorporateAction,
+        ratio: &SplitRatio,
+    ) -> Result<Vec<Order>, TradeError> {
+        let pending = sqlx::query(
+            "SELECT * FROM orders WHERE ticker = $1 AND status = $2
+            ORDER BY created_at FOR UPDATE",
+        )
+        .bind(&action.ticker)
+        .bind(OrderStatus::Pending)
+        .fetch_all(&mut **tx)
+        .await?;
+        let mut rescaled_orders = Vec::new();
+        let mut reserved_sells: HashMap<Uuid, BigDecimal> = HashMap::new();
+        for rec in pending {
+            let mut order = Order {
+                order_id: rec.try_get("order_id")?,
+                account_id: rec.try_get("account_id")?,
+                ticker: rec.try_get("ticker")?,
+                quantity: rec.try_get("quantity")?,
+                price_per_share: rec.try_get("price_per_share")?,
+                order_type: rec.try_get("order_type")?,
+                status: rec.try_get("status")?,
+                created_at: rec.try_get("created_at")?,
+            };
+            order.quantity = ratio.adjust_quantity(&order.quantity);
+            order.price_per_share = ratio.adjust_price(&order.price_per_share);
+            sqlx::query(
+                "UPDATE orders SET quantity = $2, price_per_share = $3, updated_at = NOW()
+                WHERE order_id = $1",
+            )
+            .bind(order.order_id)
+            .bind(&order.quantity)
+            .bind(&order.price_per_share)
+            .execute(&mut **tx)
+            .await?;
+            if let OrderType::Sell = order.order_type {
+                *reserved_sells.entry(order.account_id).or_default() += &order.quantity;
+            }
+            rescaled_orders.push(order);
+        }
+
+        let holdings =
+            sqlx::query(
+                "SELECT account_id, quantity FROM portfolio WHERE ticker = $1 AND quantity > 0 FOR UPDATE",
+            )
+                .bind(&action.ticker)
+                .fetch_all(&mut **tx)
+                .await?;
+        for rec in holdings {
+            let account_id: Uuid = rec.try_get("account_id")?;
+            let quantity: BigDecimal = rec.try_get("quantity")?;
+            let new_quantity = ratio.adjust_quantity(&quantity);
+            if new_quantity.is_zero() {
+                //closed like a sold out position, keeping what it realized
+                sqlx::query(
+                    "UPDATE portfolio SET quantity = 0, available_quantity = 0, total_money_spent = 0,
+                        updated_at = NOW()
+                    WHERE account_id = $1 AND ticker = $2",
+                )
+                .bind(account_id)
+                .bind(&action.ticker)
+                .execute(&mut **tx)
+                .await?;
+            } else {
+                //shares held for pending sells are taken from the rescaled orders so the two agree
+                let reserved = reserved_sells.remove(&account_id).unwrap_or_default();
+                sqlx::query(
+                    "UPDATE portfolio SET quantity = $3, available_quantity = GREATEST($3 - $4, 0),
+                        updated_at = NOW()
+                    WHERE account_id = $1 AND ticker = $2",
+                )
+                .bind(account_id)
+                .bind(&action.ticker)
+                .bind(&new_quantity)
+                .bind(&reserved)
+                .execute(&mut **tx)
+                .await?;
+            }
+            Self::record_adjustment(
+                tx,
+                action,
+                account_id,
+                &quantity,
+                &new_quantity,
+                &BigDecimal::zero(),
+            )
+            .await?;
+        }
+
+        //open lots keep their cost, so the cost per share moves with the split like the position's
+        sqlx::query(
+            "UPDATE tax_lots SET
+                quantity = TRUNC(quantity * $3 / $2, 4),
+                remaining_quantity = TRUNC(remaining_quantity * $3 / $2, 4)
+            WHERE ticker = $1 AND remaining_quantity > 0",
+        )
+        .bind(&action.ticker)
+        .bind(ratio.old_shares)
+        .bind(ratio.new_shares)
+        .execute(&mut **tx)
+        .await?;
+        //positions a reverse split rounded away take their lots with them
+        sqlx::query(
+            "UPDATE tax_lots l SET remaining_quantity = 0, cost_basis = 0
+            WHERE l.ticker = $1 AND l.remaining_quantity > 0 AND NOT EXISTS (
+                SELECT 1 FROM portfolio p
+                WHERE p.account_id = l.account_id AND p.ticker = l.ticker AND p.quantity > 0)",
+        )
+        .bind(&action.ticker)
+        .execute(&mut **tx)
+        .await?;
+
+        //everything recorded so far traded before the split, so it is restated in new shares
+        sqlx::query(
+            "UPDATE stock_prices SET
+                close = ROUND(close * $2 / $3, 4),
+                open = ROUND(open * $2 / $3, 4),
+                high = ROUND(high * $2 / $3, 4),
+                low = ROUND(low * $2 / $3, 4),
+                volume = volume * $3 / $2
+            WHERE ticker = $1 AND date <= NOW()",
+        )
+        .bind(&action.ticker)
+        .bind(ratio.old_shares)
+        .bind(ratio.new_shares)
+        .execute(&mut **tx)
+        .await?;
+        sqlx::query(
+            "UPDATE price_bars SET
+                open = ROUND(open * $2 / $3, 4),
+                high = ROUND(high * $2 / $3, 4),
+                low = ROUND(low * $2 / $3, 4),
+                close = ROUND(close * $2 / $3, 4),
+                volume = ROUND(volume * $3 / $2, 4)
+            WHERE ticker = $1 AND bucket_start <= NOW()",
+        )
+        .bind(&action.ticker)
+        .bind(ratio.old_shares)
+        .bind(ratio.new_shares)
+        .execute(&mut **tx)
+        .await?;
+        Ok(rescaled_orders)
+    }
+
+    async fn record_adjustment(
+        tx: &mut PgTransaction<'_>,
+        action: &CorporateAction,
+        account_id: Uuid,
+        quantity_before: &BigDecimal,
+        quantity_after: &BigDecimal,
+        cash_amount: &BigDecimal,
+    ) -> Result<(), TradeError> {
+        sqlx::query(
+            "INSERT INTO corporate_action_adjustments
+                (action_id, account_id, ticker, quantity_before, quantity_after, cash_amount)
+            VALUES ($1, $2, $3, $4, $5, $6)",
+        )
+        .bind(action.action_id)
+        .bind(account_id)
+        .bind(&action.ticker)
+        .bind(quantity_before)
+        .bind(quantity_after)
+        .bind(cash_amount)
+        .execute(&mut **tx)
+        .await?;
+        Ok(())
+    }
+
+    fn action_from_row(rec: &PgRow) -> Result<CorporateAction, TradeError> {
+        let new_shares: Option<i32> = rec.try_get("new_shares")?;
+        let old_shares: Option<i32> = rec.try_get("old_shares")?;
+        Ok(CorporateAction {
+            action_id: rec.try_get("action_id")?,
+            ticker: rec.try_get("ticker")?,
+            action_type: rec.try_get("action_type")?,
+            effective_date: rec.try_get("effective_date")?,
+            dividend_per_share: rec.try_get("dividend_per_share")?,
+            split_ratio: new_shares
+                .zip(old_shares)
+                .map(|(new_shares, old_shares)| SplitRatio {
+                    new_shares,
+                    old_shares,
+                }),
+            status: rec.try_get("status")?,
+            created_at: rec.try_get("created_at")?,
+            processed_at: rec.try_get("processed_at")?,
+        })
+    }
+}
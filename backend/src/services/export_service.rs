@@ -0,0 +1,292 @@
+use bigdecimal::{BigDecimal, RoundingMode};
+use chrono::{DateTime, Utc};
+use futures::{Stream, StreamExt};
+use serde::Deserialize;
+use sqlx::PgPool;
+use sqlx::Row;
+use tokio::sync::mpsc;
+use uuid::Uuid;
+
+use crate::models::errors::trade_error::TradeError;
+use crate::models::ledger::{JournalEntryType, LedgerAccount};
+use crate::models::order::OrderType;
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    Csv,
+    Ofx,
+}
+
+impl ExportFormat {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Ofx => "application/x-ofx",
+        }
+    }
+
+    pub fn file_extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Ofx => "ofx",
+        }
+    }
+}
+
+/// One line of an activity export: either a share trade or a cash movement.
+/// Trade-only fields are `None` for cash movements.
+#[derive(Debug, Clone)]
+pub struct ActivityExportRow {
+    pub occurred_at: DateTime<Utc>,
+    pub activity_type: String,
+    pub reference_id: Uuid,
+    pub ticker: Option<String>,
+    pub side: Option<OrderType>,
+    pub quantity: Option<BigDecimal>,
+    pub price_per_share: Option<BigDecimal>,
+    /// Fee charged on the fill, negative for a maker rebate.
+    pub fee: Option<BigDecimal>,
+    /// Signed change to the account's cash: negative for buys, withdrawals and fees. For trades
+    /// this is net of the fee.
+    pub amount: BigDecimal,
+}
+
+pub struct ExportService {
+    db: PgPool,
+}
+
+impl ExportService {
+    const CHANNEL_CAPACITY: usize = 64;
+    const QUANTITY_SCALE: i64 = 4;
+    const PRICE_SCALE: i64 = 4;
+    const AMOUNT_SCALE: i64 = 2;
+    const CSV_HEADER: &'static str =
+        "date,type,reference_id,ticker,side,quantity,price_per_share,fee,amount\r\n";
+
+    pub fn new(db: PgPool) -> ExportService {
+        ExportService { db }
+    }
+
+    /// Streams the account's trades and cash movements in chronological order, already rendered
+    /// in `format`. Rows are read from the database as the client consumes the stream, so the
+    /// export is never held in memory in full.
+    #[tracing::instrument(skip(self))]
+    pub fn export_activity(
+        &self,
+        account_id: Uuid,
+        format: ExportFormat,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> impl Stream<Item = Result<String, TradeError>> {
+        let (sender, receiver) = mpsc::channel(Self::CHANNEL_CAPACITY);
+        let db = self.db.clone();
+        tokio::spawn(async move {
+            let header = match format {
+                ExportFormat::Csv => Self::CSV_HEADER.to_string(),
+                ExportFormat::Ofx => Self::ofx_header(account_id, from, to),
+            };
+            if sender.send(Ok(header)).await.is_err() {
+                return;
+            }
+            let mut rows = sqlx::query(
+                "SELECT * FROM (
+                    SELECT t.executed_at AS occurred_at, 'TRADE' AS activity_type,
+                        t.transaction_id AS reference_id, t.ticker, t.order_type,
+                        t.quantity, t.price_per_share, t.fee,
+                        CASE WHEN t.order_type = 'BUY' THEN -(t.quantity * t.price_per_share)
+                            ELSE t.quantity * t.price_per_share END - t.fee AS amount
+                    FROM transactions t
+                    WHERE t.account_id = $1
+                        AND ($2::timestamptz IS NULL OR t.executed_at >= $2)
+                        AND ($3::timestamptz IS NULL OR t.executed_at < $3)
+                    UNION ALL
+                    SELECT e.created_at, e.entry_type::text, e.entry_id, NULL, NULL, NULL, NULL,
+                        NULL, SUM(l.amount)
+                    FROM journal_entries e JOIN journal_lines l ON l.entry_id = e.entry_id
+                    WHERE e.account_id = $1
+                        AND ($2::timestamptz IS NULL OR e.created_at >= $2)
+                        AND ($3::timestamptz IS NULL OR e.created_at < $3)
+                        AND l.account IN ($4, $5)
+                        AND e.entry_type NOT IN ($6, $7)
+                        AND NOT (e.entry_type = $8 AND EXISTS (
+                            SELECT 1 FROM transactions f
+                            WHERE f.account_id = $1 AND f.order_id = e.reference_id))
+                    GROUP BY e.entry_id
+                    HAVING SUM(l.amount) <> 0
+                ) activity
+                ORDER BY occurred_at, reference_id",
+            )
+            .bind(account_id)
+            .bind(from)
+            .bind(to)
+            .bind(LedgerAccount::UserCash)
+            .bind(LedgerAccount::UserReserved)
+            //trades are exported from the transactions table instead
+            .bind(JournalEntryType::TradeBuy)
+            .bind(JournalEntryType::TradeSell)
+            //and so are their fees, which are netted into each trade row
+            .bind(JournalEntryType::Fee)
+            .fetch(&db);
+            while let Some(rec) = rows.next().await {
+                let line = rec
+                    .and_then(|rec| {
+                        Ok(ActivityExportRow {
+                            occurred_at: rec.try_get("occurred_at")?,
+                            activity_type: rec.try_get("activity_type")?,
+                            reference_id: rec.try_get("reference_id")?,
+                            ticker: rec.try_get("ticker")?,
+                            side: rec.try_get("order_type")?,
+                            quantity: rec.try_get("quantity")?,
+                            price_per_share: rec.try_get("price_per_share")?,
+                            fee: rec.try_get("fee")?,
+                            amount: rec.try_get("amount")?,
+                        })
+                    })
+                    .map(|row| match format {
+                        ExportFormat::Csv => Self::to_csv_line(&row),
+                        ExportFormat::Ofx => Self::to_ofx_transaction(&row),
+                    })
+                    .map_err(TradeError::DatabaseError);
+                let failed = line.is_err();
+                if sender.send(line).await.is_err() || failed {
+                    return;
+                }
+            }
+            if format == ExportFormat::Ofx {
+                let _ = sender.send(Ok(Self::ofx_footer())).await;
+            }
+        });
+        futures::stream::unfold(receiver, |mut receiver| async move {
+            receiver.recv().await.map(|line| (line, receiver))
+        })
+    }
+
+    /// Fixed-point rendering so exports never contain exponent notation.
+    pub fn format_decimal(value: &BigDecimal, scale: i64) -> String {
+        value
+            .with_scale_round(scale, RoundingMode::HalfEven)
+            .to_plain_string()
+    }
+
+    pub fn to_csv_line(row: &ActivityExportRow) -> String {
+        let fields = [
+            row.occurred_at.to_rfc3339(),
+            row.activity_type.clone(),
+            row.reference_id.to_string(),
+            row.ticker.clone().unwrap_or_default(),
+            row.side
+                .as_ref()
+                .map(|side| side.to_string())
+                .unwrap_or_default(),
+            row.quantity
+                .as_ref()
+                .map(|quantity| Self::format_decimal(quantity, Self::QUANTITY_SCALE))
+                .unwrap_or_default(),
+            row.price_per_share
+                .as_ref()
+                .map(|price| Self::format_decimal(price, Self::PRICE_SCALE))
+                .unwrap_or_default(),
+            row.fee
+                .as_ref()
+                .map(|fee| Self::format_decimal(fee, Self::PRICE_SCALE))
+                .unwrap_or_default(),
+            Self::format_decimal(&row.amount, Self::AMOUNT_SCALE),
+        ];
+        let escaped: Vec<String> = fields.iter().map(|field| Self::escape_csv(field)).collect();
+        format!("{}\r\n", escaped.join(","))
+    }
+
+    fn escape_csv(field: &str) -> String {
+        if field.contains([',', '"', '\r', '\n']) {
+            format!("\"{}\"", field.replace('"', "\"\""))
+        } else {
+            field.to_string()
+        }
+    }
+
+    fn ofx_date(date: &DateTime<Utc>) -> String {
+        date.format("%Y%m%d%H%M%S").to_string()
+    }
+
+    fn ofx_header(
+        account_id: Uuid,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> String {
+        let now = Utc::now();
+        let start = from.unwrap_or(DateTime::UNIX_EPOCH);
+        let end = to.unwrap_or(now);
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"no\"?>\n\
+            <?OFX OFXHEADER=\"200\" VERSION=\"220\" SECURITY=\"NONE\" OLDFILEUID=\"NONE\" NEWFILEUID=\"NONE\"?>\n\
+            <OFX>\n\
+            <SIGNONMSGSRSV1><SONRS><STATUS><CODE>0</CODE><SEVERITY>INFO</SEVERITY></STATUS>\
+            <DTSERVER>{now}</DTSERVER><LANGUAGE>ENG</LANGUAGE></SONRS></SIGNONMSGSRSV1>\n\
+            <INVSTMTMSGSRSV1><INVSTMTTRNRS><TRNUID>{trnuid}</TRNUID>\
+            <STATUS><CODE>0</CODE><SEVERITY>INFO</SEVERITY></STATUS>\n\
+            <INVSTMTRS><DTASOF>{now}</DTASOF><CURDEF>USD</CURDEF>\
+            <INVACCTFROM><BROKERID>projecttrade</BROKERID><ACCTID>{account_id}</ACCTID></INVACCTFROM>\n\
+            <INVTRANLIST><DTSTART>{start}</DTSTART><DTEND>{end}</DTEND>\n",
+            now = Self::ofx_date(&now),
+            trnuid = Uuid::new_v4(),
+            account_id = account_id,
+            start = Self::ofx_date(&start),
+            end = Self::ofx_date(&end),
+        )
+    }
+
+    fn ofx_footer() -> String {
+        "</INVTRANLIST></INVSTMTRS></INVSTMTTRNRS></INVSTMTMSGSRSV1>\n</OFX>\n".to_string()
+    }
+
+    /// Trades become BUYSTOCK/SELLSTOCK records and cash movements INVBANKTRAN records.
+    pub fn to_ofx_transaction(row: &ActivityExportRow) -> String {
+        let date = Self::ofx_date(&row.occurred_at);
+        let total = Self::format_decimal(&row.amount, Self::AMOUNT_SCALE);
+        match (&row.side, &row.ticker) {
+            (Some(side), Some(ticker)) => {
+                let (aggregate, body, kind) = match side {
+                    OrderType::Buy => ("BUYSTOCK", "INVBUY", "<BUYTYPE>BUY</BUYTYPE>"),
+                    OrderType::Sell => ("SELLSTOCK", "INVSELL", "<SELLTYPE>SELL</SELLTYPE>"),
+                };
+                let units = row
+                    .quantity
+                    .as_ref()
+                    .map(|quantity| Self::format_decimal(quantity, Self::QUANTITY_SCALE))
+                    .unwrap_or_default();
+                let unit_price = row
+                    .price_per_share
+                    .as_ref()
+                    .map(|price| Self::format_decimal(price, Self::PRICE_SCALE))
+                    .unwrap_or_default();
+                let fees = Self::format_decimal(
+                    &row.fee.clone().unwrap_or_default(),
+                    Self::PRICE_SCALE,
+                );
+                format!(
+                    "<{aggregate}><{body}><INVTRAN><FITID>{fitid}</FITID><DTTRADE>{date}</DTTRADE></INVTRAN>\
+                    <SECID><UNIQUEID>{ticker}</UNIQUEID><UNIQUEIDTYPE>TICKER</UNIQUEIDTYPE></SECID>\
+                    <UNITS>{units}</UNITS><UNITPRICE>{unit_price}</UNITPRICE><FEES>{fees}</FEES><TOTAL>{total}</TOTAL>\
+                    <SUBACCTSEC>CASH</SUBACCTSEC><SUBACCTFUND>CASH</SUBACCTFUND></{body}>{kind}</{aggregate}>\n",
+                    fitid = row.reference_id,
+                )
+            }
+            _ => {
+                let transaction_type = match row.activity_type.as_str() {
+                    "FEE" => "FEE",
+                    "DEPOSIT" => "DEP",
+                    _ if row.amount < 0 => "DEBIT",
+                    _ => "CREDIT",
+                };
+                format!(
+                    "<INVBANKTRAN><STMTTRN><TRNTYPE>{transaction_type}</TRNTYPE><DTPOSTED>{date}</DTPOSTED>\
+                    <TRNAMT>{total}</TRNAMT><FITID>{fitid}</FITID><NAME>{name}</NAME></STMTTRN>\
+                    <SUBACCTFUND>CASH</SUBACCTFUND></INVBANKTRAN>\n",
+                    fitid = row.reference_id,
+                    name = row.activity_type,
+                )
+            }
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use bigdecimal::BigDecimal;
+use sqlx::postgres::PgRow;
+use sqlx::PgPool;
+use sqlx::Row;
+use uuid::Uuid;
+
+use crate::models::errors::trade_error::TradeError;
+use crate::models::errors::user_error::UserError;
+use crate::models::fee::{FeeSchedule, FeeTerms, Fill};
+
+/// Looks up the fee schedule of an account's owner and prices fills against it.
+pub struct FeeService {
+    db: PgPool,
+}
+
+impl FeeService {
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+
+    #[tracing::instrument(skip(self))]
+    pub async fn get_schedule_for_account(
+        &self,
+        account_id: Uuid,
+    ) -> Result<FeeSchedule, TradeError> {
+        let rec = sqlx::query(
+            "SELECT f.* FROM accounts a
+                JOIN users u ON u.user_id = a.user_id
+                JOIN fee_schedules f ON f.tier = u.fee_tier
+            WHERE a.account_id = $1",
+        )
+        .bind(account_id)
+        .fetch_optional(&self.db)
+        .await?
+        .ok_or(UserError::AccountNotFound)?;
+        Self::schedule_from_row(&rec)
+    }
+
+    #[tracing::instrument(skip(self))]
+    pub async fn list_schedules(&self) -> Result<Vec<FeeSchedule>, TradeError> {
+        let records = sqlx::query("SELECT * FROM fee_schedules ORDER BY tier")
+            .fetch_all(&self.db)
+            .await?;
+        records.iter().map(Self::schedule_from_row).collect()
+    }
+
+    /// Creates the tier or replaces its terms. Changes apply to fills from then on.
+    #[tracing::instrument(skip(self))]
+    pub async fn upsert_schedule(
+        &self,
+        tier: &str,
+        terms: &FeeTerms,
+    ) -> Result<FeeSchedule, TradeError> {
+        terms.validate().map_err(TradeError::InvalidFeeSchedule)?;
+        if tier.is_empty() || tier.len() > 32 {
+            return Err(TradeError::InvalidFeeSchedule(
+                "tier must be 1 to 32 characters".to_string(),
+            ));
+        }
+        let rec = sqlx::query(
+            "INSERT INTO fee_schedules
+                (tier, flat_fee, per_share_fee, taker_rate, maker_rate, minimum_fee)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (tier) DO UPDATE SET flat_fee = $2, per_share_fee = $3,
+                taker_rate = $4, maker_rate = $5, minimum_fee = $6, updated_at = NOW()
+            RETURNING *",
+        )
+        .bind(tier)
+        .bind(&terms.flat_fee)
+        .bind(&terms.per_share_fee)
+        .bind(&terms.taker_rate)
+        .bind(&terms.maker_rate)
+        .bind(&terms.minimum_fee)
+        .fetch_one(&self.db)
+        .await?;
+        Self::schedule_from_row(&rec)
+    }
+
+    #[tracing::instrument(skip(self))]
+    pub async fn set_user_tier(&self, user_id: Uuid, tier: &str) -> Result<(), TradeError> {
+        let exists: bool =
+            sqlx::query("SELECT EXISTS(SELECT 1 FROM fee_schedules WHERE tier = $1)")
+                .bind(tier)
+                .fetch_one(&self.db)
+                .await?
+                .try_get(0)?;
+        if !exists {
+            return Err(TradeError::FeeScheduleNotFound);
+        }
+        let rows_affected = sqlx::query("UPDATE users SET fee_tier = $2 WHERE user_id = $1")
+            .bind(user_id)
+            .bind(tier)
+            .execute(&self.db)
+            .await?
+            .rows_affected();
+        if rows_affected == 0 {
+            return Err(UserError::NotFound.into());
+        }
+        Ok(())
+    }
+
+    /// The most a buy of `quantity` at a limit of `price_per_share` can be charged.
+    pub async fn max_order_fee(
+        &self,
+        account_id: Uuid,
+        quantity: &BigDecimal,
+        price_per_share: &BigDecimal,
+    ) -> Result<BigDecimal, TradeError> {
+        let schedule = self.get_schedule_for_account(account_id).await?;
+        Ok(schedule
+            .terms
+            .max_order_fee(quantity, &(price_per_share * quantity)))
+    }
+
+    /// Fee for the next fill of an order: what the order costs with this fill included, less
+    /// what its earlier fills were already charged.
+    #[tracing::instrument(skip(self))]
+    pub async fn fee_for_fill(
+        &self,
+        order_id: Uuid,
+        account_id: Uuid,
+        fill: Fill,
+    ) -> Result<BigDecimal, TradeError> {
+        let schedule = self.get_schedule_for_account(account_id).await?;
+        let records = sqlx::query(
+            "SELECT liquidity, quantity, price_per_share, fee FROM transactions
+            WHERE order_id = $1 AND liquidity IS NOT NULL",
+        )
+        .bind(order_id)
+        .fetch_all(&self.db)
+        .await?;
+        let mut fills = Vec::with_capacity(records.len() + 1);
+        let mut charged = BigDecimal::from(0);
+        for rec in records {
+            let quantity: BigDecimal = rec.try_get("quantity")?;
+            let price_per_share: BigDecimal = rec.try_get("price_per_share")?;
+            charged += rec.try_get::<BigDecimal, _>("fee")?;
+            fills.push(Fill {
+                liquidity: rec.try_get("liquidity")?,
+                notional: &quantity * &price_per_share,
+                quantity,
+            });
+        }
+        fills.push(fill);
+        Ok(schedule.terms.order_fee(&fills) - charged)
+    }
+
+    fn schedule_from_row(rec: &PgRow) -> Result<FeeSchedule, TradeError> {
+        Ok(FeeSchedule {
+            tier: rec.try_get("tier")?,
+            terms: FeeTerms {
+                flat_fee: rec.try_get("flat_fee")?,
+                per_share_fee: rec.try_get("per_share_fee")?,
+                taker_rate: rec.try_get("taker_rate")?,
+                maker_rate: rec.try_get("maker_rate")?,
+                minimum_fee: rec.try_get("minimum_fee")?,
+            },
+            updated_at: rec.try_get("updated_at")?,
+        })
+    }
+}
@@ -0,0 +1,157 @@
+use crate::models::errors::trade_error::TradeError;
+use crate::models::idempotency::{IdempotencyOutcome, StoredResponse};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use sqlx::Row;
+use std::time::Duration;
+use uuid::Uuid;
+
+pub struct IdempotencyService {
+    db: PgPool,
+}
+
+impl IdempotencyService {
+    pub const MAX_KEY_LENGTH: usize = 255;
+    const KEY_TTL_HOURS: i32 = 24;
+    /// How long a claimed key may go without its lease being renewed before another request can
+    /// take it over, in case the server died while the first request was running.
+    const IN_PROGRESS_LEASE_SECS: f64 = 60.0;
+    /// How often a running request renews its lease; well inside the lease so a slow database
+    /// round trip doesn't let it lapse.
+    pub const LEASE_RENEWAL_INTERVAL: Duration = Duration::from_secs(15);
+
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+
+    /// Fingerprint of a request, so a key reused with a different payload or against a
+    /// different account can be rejected.
+    pub fn hash_request(account_id: Uuid, method: &str, path: &str, body: &[u8]) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(account_id.as_bytes());
+        hasher.update(method.as_bytes());
+        hasher.update(b"\n");
+        hasher.update(path.as_bytes());
+        hasher.update(b"\n");
+        hasher.update(body);
+        hex::encode(hasher.finalize())
+    }
+
+    /// Claims `key` for this request, or returns the response recorded by an earlier identical
+    /// request. Keys expire after a day, and a claim that never got a response is given up once
+    /// its lease has gone unrenewed for `IN_PROGRESS_LEASE_SECS`.
+    #[tracing::instrument(skip(self))]
+    pub async fn begin(
+        &self,
+        user_id: Uuid,
+        key: &str,
+        request_hash: &str,
+    ) -> Result<IdempotencyOutcome, TradeError> {
+        if key.is_empty() || key.len() > Self::MAX_KEY_LENGTH {
+            return Err(TradeError::InvalidIdempotencyKey);
+        }
+        sqlx::query(
+            "DELETE FROM idempotency_keys
+            WHERE user_id = $1 AND idempotency_key = $2
+                AND (created_at < NOW() - make_interval(hours => $3)
+                    OR (response_status IS NULL
+                        AND renewed_at < NOW() - make_interval(secs => $4)))",
+        )
+        .bind(user_id)
+        .bind(key)
+        .bind(Self::KEY_TTL_HOURS)
+        .bind(Self::IN_PROGRESS_LEASE_SECS)
+        .execute(&self.db)
+        .await?;
+        let claimed = sqlx::query(
+            "INSERT INTO idempotency_keys (user_id, idempotency_key, request_hash)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (user_id, idempotency_key) DO NOTHING",
+        )
+        .bind(user_id)
+        .bind(key)
+        .bind(request_hash)
+        .execute(&self.db)
+        .await?
+        .rows_affected()
+            == 1;
+        if claimed {
+            return Ok(IdempotencyOutcome::Execute);
+        }
+        let rec = sqlx::query(
+            "SELECT request_hash, response_status, response_content_type, response_body
+            FROM idempotency_keys WHERE user_id = $1 AND idempotency_key = $2",
+        )
+        .bind(user_id)
+        .bind(key)
+        .fetch_one(&self.db)
+        .await?;
+        let stored_hash: String = rec.try_get("request_hash")?;
+        if stored_hash != request_hash {
+            return Err(TradeError::IdempotencyKeyReused);
+        }
+        let status: Option<i16> = rec.try_get("response_status")?;
+        match status {
+            Some(status) => Ok(IdempotencyOutcome::Replay(StoredResponse {
+                status: status as u16,
+                content_type: rec.try_get("response_content_type")?,
+                body: rec
+                    .try_get::<Option<Vec<u8>>, _>("response_body")?
+                    .unwrap_or_default(),
+            })),
+            None => Err(TradeError::IdempotentRequestInProgress),
+        }
+    }
+
+    /// Extends the lease of a key whose request is still running, so retries keep being refused
+    /// as in progress rather than taking it over.
+    #[tracing::instrument(skip(self))]
+    pub async fn renew(&self, user_id: Uuid, key: &str) -> Result<(), TradeError> {
+        sqlx::query(
+            "UPDATE idempotency_keys SET renewed_at = NOW()
+            WHERE user_id = $1 AND idempotency_key = $2 AND response_status IS NULL",
+        )
+        .bind(user_id)
+        .bind(key)
+        .execute(&self.db)
+        .await?;
+        Ok(())
+    }
+
+    /// Records the response for a claimed key so retries replay it.
+    #[tracing::instrument(skip(self, response))]
+    pub async fn complete(
+        &self,
+        user_id: Uuid,
+        key: &str,
+        response: &StoredResponse,
+    ) -> Result<(), TradeError> {
+        sqlx::query(
+            "UPDATE idempotency_keys
+            SET response_status = $3, response_content_type = $4, response_body = $5
+            WHERE user_id = $1 AND idempotency_key = $2",
+        )
+        .bind(user_id)
+        .bind(key)
+        .bind(response.status as i16)
+        .bind(&response.content_type)
+        .bind(&response.body)
+        .execute(&self.db)
+        .await?;
+        Ok(())
+    }
+
+    /// Releases a claimed key without a response, so the client can retry after a server error.
+    #[tracing::instrument(skip(self))]
+    pub async fn abandon(&self, user_id: Uuid, key: &str) -> Result<(), TradeError> {
+        sqlx::query(
+            "DELETE FROM idempotency_keys
+            WHERE user_id = $1 AND idempotency_key = $2 AND response_status IS NULL",
+        )
+        .bind(user_id)
+        .bind(key)
+        .execute(&self.db)
+        .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,228 @@
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Days, NaiveDate, NaiveTime, Utc};
+use num_traits::Zero;
+use sqlx::{PgPool, Row};
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::models::errors::trade_error::TradeError;
+use crate::models::interest::{
+    InterestAccrualRun, InterestRateTier, InterestRateTiers, MonthlyInterest,
+};
+use crate::models::ledger::{JournalEntryType, LedgerAccount};
+use crate::services::account_management_service::AccountManagementService;
+
+/// Pays daily interest on the cash accounts leave uninvested at the end of each day. Cash
+/// reserved for open buys doesn't earn interest, and neither does the system user that runs the market maker.
+#[derive(Clone)]
+pub struct InterestService {
+    db: PgPool,
+    system_user_id: Uuid,
+}
+
+impl InterestService {
+    const ACCRUAL_INTERVAL_SECS: u64 = 60 * 60;
+
+    pub fn new(db: PgPool, system_user_id: Uuid) -> Self {
+        Self { db, system_user_id }
+    }
+
+    #[tracing::instrument(skip(self))]
+    pub async fn get_rate_tiers(&self) -> Result<InterestRateTiers, TradeError> {
+        let tiers = sqlx::query(
+            "SELECT min_balance, annual_rate FROM interest_rate_tiers ORDER BY min_balance",
+        )
+        .fetch_all(&self.db)
+        .await?
+        .iter()
+        .map(|rec| {
+            Ok(InterestRateTier {
+                min_balance: rec.try_get("min_balance")?,
+                annual_rate: rec.try_get("annual_rate")?,
+            })
+        })
+        .collect::<Result<Vec<_>, sqlx::Error>>()?;
+        InterestRateTiers::new(tiers).map_err(TradeError::InvalidInterestRates)
+    }
+
+    /// Replaces every tier. Accruals from then on use the new rates.
+    #[tracing::instrument(skip(self))]
+    pub async fn set_rate_tiers(
+        &self,
+        tiers: Vec<InterestRateTier>,
+    ) -> Result<InterestRateTiers, TradeError> {
+        let tiers = InterestRateTiers::new(tiers).map_err(TradeError::InvalidInterestRates)?;
+        let mut tx = self.db.begin().await?;
+        sqlx::query("DELETE FROM interest_rate_tiers")
+            .execute(&mut *tx)
+            .await?;
+        for tier in tiers.tiers() {
+            sqlx::query(
+                "INSERT INTO interest_rate_tiers (min_balance, annual_rate) VALUES ($1, $2)",
+            )
+            .bind(&tier.min_balance)
+            .bind(&tier.annual_rate)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(tiers)
+    }
+
+    fn end_of(day: NaiveDate) -> DateTime<Utc> {
+        (day + Days::new(1)).and_time(NaiveTime::MIN).and_utc()
+    }
+
+    /// Credits each account one day of interest for `accrual_date` on the available cash it held
+    /// at the end of that day, read from the ledger. Accounts already paid for that day are
+    /// skipped, so the job can rerun safely.
+    #[tracing::instrument(skip(self))]
+    pub async fn accrue_interest(
+        &self,
+        accrual_date: NaiveDate,
+    ) -> Result<InterestAccrualRun, TradeError> {
+        let tiers = self.get_rate_tiers().await?;
+        let balances = sqlx::query(
+            "SELECT a.account_id, COALESCE(SUM(l.amount), 0) AS balance
+            FROM accounts a
+            LEFT JOIN journal_entries e ON e.account_id = a.account_id AND e.created_at < $3
+            LEFT JOIN journal_lines l ON l.entry_id = e.entry_id AND l.account = $4
+            WHERE a.user_id <> $1 AND a.created_at < $3
+                AND NOT EXISTS (SELECT 1 FROM interest_accruals i
+                    WHERE i.account_id = a.account_id AND i.accrual_date = $2)
+            GROUP BY a.account_id
+            HAVING COALESCE(SUM(l.amount), 0) > 0",
+        )
+        .bind(self.system_user_id)
+        .bind(accrual_date)
+        .bind(Self::end_of(accrual_date))
+        .bind(LedgerAccount::UserCash)
+        .fetch_all(&self.db)
+        .await?
+        .iter()
+        .map(|rec| Ok((rec.try_get("account_id")?, rec.try_get("balance")?)))
+        .collect::<Result<Vec<(Uuid, BigDecimal)>, sqlx::Error>>()?;
+
+        let mut run = InterestAccrualRun {
+            accrual_date,
+            accounts_credited: 0,
+            total_interest: BigDecimal::zero(),
+        };
+        for (account_id, balance) in balances {
+            let mut tx = self.db.begin().await?;
+            let amount = tiers.daily_interest(&balance);
+            let accrual_id = Uuid::new_v4();
+            let inserted = sqlx::query(
+                "INSERT INTO interest_accruals (accrual_id, account_id, accrual_date, balance, amount)
+                VALUES ($1, $2, $3, $4, $5)
+                ON CONFLICT (account_id, accrual_date) DO NOTHING",
+            )
+            .bind(accrual_id)
+            .bind(account_id)
+            .bind(accrual_date)
+            .bind(&balance)
+            .bind(&amount)
+            .execute(&mut *tx)
+            .await?
+            .rows_affected();
+            if inserted == 0 {
+                continue;
+            }
+            if amount > BigDecimal::zero() {
+                AccountManagementService::post_journal_entry_in(
+                    &mut tx,
+                    account_id,
+                    JournalEntryType::Interest,
+                    Some(accrual_id),
+                    &[
+                        (LedgerAccount::UserCash, amount.clone()),
+                        (
+                            JournalEntryType::Interest.counter_account(),
+                            -amount.clone(),
+                        ),
+                    ],
+                )
+                .await?;
+                run.accounts_credited += 1;
+                run.total_interest += &amount;
+            }
+            tx.commit().await?;
+        }
+        info!(
+            "Accrued {} interest to {} accounts for {}",
+            run.total_interest, run.accounts_credited, accrual_date
+        );
+        Ok(run)
+    }
+
+    /// Accrues every full day since the last accrual, or just the last full day if interest has
+    /// never been accrued, so days the job missed (e.g. while the server was down) still pay.
+    /// This is what the background job does every run.
+    pub async fn accrue_outstanding_days(&self) -> Result<Vec<InterestAccrualRun>, TradeError> {
+        let yesterday = Utc::now().date_naive() - Days::new(1);
+        let last_accrued: Option<NaiveDate> =
+            sqlx::query_scalar("SELECT MAX(accrual_date) FROM interest_accruals")
+                .fetch_one(&self.db)
+                .await?;
+        let mut day = last_accrued.map_or(yesterday, |last| last + Days::new(1));
+        let mut runs = Vec::new();
+        while day <= yesterday {
+            runs.push(self.accrue_interest(day).await?);
+            day = day + Days::new(1);
+        }
+        Ok(runs)
+    }
+
+    pub fn spawn_accrual_job(&self) -> JoinHandle<Result<(), TradeError>> {
+        info!("Starting interest accrual thread");
+        let service = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(
+                Self::ACCRUAL_INTERVAL_SECS,
+            ));
+            loop {
+                interval.tick().await;
+                if let Err(e) = service.accrue_outstanding_days().await {
+                    warn!(error = ?e, "Interest accrual failed");
+                }
+            }
+        })
+    }
+
+    /// Interest earned per calendar month across all of a user's accounts, newest first.
+    #[tracing::instrument(skip(self))]
+    pub async fn get_monthly_summary(
+        &self,
+        user_id: Uuid,
+        year: Option<i32>,
+    ) -> Result<Vec<MonthlyInterest>, TradeError> {
+        let records = sqlx::query(
+            "SELECT DATE_TRUNC('month', i.accrual_date)::date AS month,
+                COUNT(DISTINCT i.accrual_date) AS days_accrued,
+                ROUND(SUM(i.balance) / COUNT(DISTINCT i.accrual_date), 4) AS average_balance,
+                SUM(i.amount) AS interest
+            FROM interest_accruals i
+            JOIN accounts a ON a.account_id = i.account_id
+            WHERE a.user_id = $1
+                AND ($2::int IS NULL OR EXTRACT(YEAR FROM i.accrual_date) = $2)
+            GROUP BY 1
+            ORDER BY 1 DESC",
+        )
+        .bind(user_id)
+        .bind(year)
+        .fetch_all(&self.db)
+        .await?;
+        records
+            .iter()
+            .map(|rec| {
+                Ok(MonthlyInterest {
+                    month: rec.try_get("month")?,
+                    days_accrued: rec.try_get("days_accrued")?,
+                    average_balance: rec.try_get("average_balance")?,
+                    interest: rec.try_get("interest")?,
+                })
+            })
+            .collect()
+    }
+}
@@ -2,6 +2,7 @@ use std::cmp::min;
 use std::sync::Arc;
 
 use crate::models::errors::trade_error::TradeError;
+use crate::models::ledger::JournalEntryType;
 use crate::models::loan::{Loan, LoanStatus};
 use crate::models::{errors::user_error::UserError, loan::LoanType};
 use crate::services::account_management_service::AccountManagementService;
@@ -75,8 +76,14 @@ impl LoanService {
             .execute(&self.db)
             .await
             .map_err(|e| UserError::DatabaseError(e))?;
+        //loans are paid into and repaid from the default account, which shares the user id
         self.account_management_service
-            .add_user_balance(user_id, &loan.principal)
+            .add_user_balance(
+                user_id,
+                &loan.principal,
+                JournalEntryType::LoanDisbursement,
+                Some(loan.loan_id),
+            )
             .await?;
         Ok(())
     }
@@ -117,12 +124,14 @@ impl LoanService {
         tracing::info!("Accrued interest: {}", accrued_interest);
         tracing::info!("Principal: {}", principal);
         tracing::info!("Actual payment amount: {}", acutal_payment_amount);
-        //reserve and remove from funds
+        //remove from funds
         self.account_management_service
-            .reserve_funds(user_id, &acutal_payment_amount)
-            .await?;
-        self.account_management_service
-            .deduct_user_balance(user_id, &acutal_payment_amount)
+            .debit_user_balance(
+                user_id,
+                &acutal_payment_amount,
+                JournalEntryType::LoanRepayment,
+                Some(loan.loan_id),
+            )
             .await?;
 
         //pay the accrued interest first
@@ -0,0 +1,380 @@
+use std::env;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use alpha_vantage::ApiClient;
+use bigdecimal::{BigDecimal, RoundingMode};
+use chrono::{DateTime, Days, NaiveDate, NaiveDateTime, Utc};
+use futures::future::BoxFuture;
+use num_traits::FromPrimitive;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rand_distr::{Distribution, Normal};
+use serde::Deserialize;
+
+use crate::models::errors::ticker_error::TickerError;
+use crate::models::errors::trade_error::TradeError;
+use crate::models::stock_ticker::Ticker;
+
+const PRICE_SCALE: i64 = 4;
+
+fn price(value: f64) -> Option<BigDecimal> {
+    BigDecimal::from_f64(value).map(|p| p.with_scale_round(PRICE_SCALE, RoundingMode::HalfUp))
+}
+
+/// Accepts RFC 3339 timestamps, `YYYY-MM-DD HH:MM:SS` in UTC and plain dates, which are taken
+/// as midnight UTC.
+fn parse_date(value: &str) -> Option<DateTime<Utc>> {
+    let value = value.trim();
+    if let Ok(date) = DateTime::parse_from_rfc3339(value) {
+        return Some(date.with_timezone(&Utc));
+    }
+    if let Ok(date) = NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S") {
+        return Some(date.and_utc());
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|date| date.and_utc())
+}
+
+/// Where daily prices come from. Bars are returned oldest first.
+pub trait MarketDataProvider: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    fn daily_history<'a>(
+        &'a self,
+        ticker: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<Ticker>, TradeError>>;
+
+    fn latest_quote<'a>(&'a self, ticker: &'a str) -> BoxFuture<'a, Result<Ticker, TradeError>> {
+        Box::pin(async move {
+            self.daily_history(ticker)
+                .await?
+                .pop()
+                .ok_or_else(|| TickerError::InvalidSymbol(ticker.to_string()).into())
+        })
+    }
+}
+
+/// Which provider to use, read from `market_data.provider`: `alpha_vantage` (with
+/// `market_data.api_key`), `files` (with `market_data.directory`) or `synthetic` (with an
+/// optional `market_data.seed`). Without a provider set, prices are synthetic so development
+/// needs no network.
+#[derive(Debug, Clone)]
+pub enum MarketDataConfig {
+    AlphaVantage { api_key: String },
+    Files { directory: PathBuf },
+    Synthetic { seed: u64 },
+}
+
+impl MarketDataConfig {
+    pub fn from_env() -> Result<Self, String> {
+        let setting = |key: &str| env::var(key).ok().filter(|value| !value.trim().is_empty());
+        match setting("market_data.provider").as_deref() {
+            Some("alpha_vantage") => Ok(MarketDataConfig::AlphaVantage {
+                api_key: setting("market_data.api_key")
+                    .ok_or("market_data.api_key is needed for alpha_vantage")?,
+            }),
+            Some("files") => Ok(MarketDataConfig::Files {
+                directory: setting("market_data.directory")
+                    .ok_or("market_data.directory is needed for files")?
+                    .into(),
+            }),
+            Some("synthetic") | None => Ok(MarketDataConfig::Synthetic {
+                seed: match setting("market_data.seed") {
+                    Some(seed) => seed
+                        .parse()
+                        .map_err(|_| format!("market_data.seed {:?} is not a number", seed))?,
+                    None => SyntheticProvider::DEFAULT_SEED,
+                },
+            }),
+            Some(other) => Err(format!("unknown market_data.provider {:?}", other)),
+        }
+    }
+
+    pub fn provider(&self) -> Arc<dyn MarketDataProvider> {
+        match self {
+            MarketDataConfig::AlphaVantage { api_key } => {
+                Arc::new(AlphaVantageProvider::new(api_key))
+            }
+            MarketDataConfig::Files { directory } => Arc::new(FileProvider::new(directory)),
+            MarketDataConfig::Synthetic { seed } => Arc::new(SyntheticProvider::new(*seed)),
+        }
+    }
+}
+
+/// Daily prices from Alpha Vantage, covering roughly the last 100 trading days.
+pub struct AlphaVantageProvider {
+    api_client: ApiClient,
+}
+
+impl AlphaVantageProvider {
+    pub fn new(api_key: &str) -> Self {
+        Self {
+            api_client: alpha_vantage::set_api(api_key, reqwest::Client::new()),
+        }
+    }
+}
+
+impl MarketDataProvider for AlphaVantageProvider {
+    fn name(&self) -> &'static str {
+        "alpha_vantage"
+    }
+
+    fn daily_history<'a>(
+        &'a self,
+        ticker: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<Ticker>, TradeError>> {
+        Box::pin(async move {
+            let response = self
+                .api_client
+                .stock_time(alpha_vantage::stock_time::StockFunction::Daily, ticker)
+                .output_size(alpha_vantage::api::OutputSize::Compact)
+                .json()
+                .await
+                .map_err(TickerError::AlphaVantageError)?;
+            let mut bars = response
+                .data()
+                .iter()
+                .map(|bar| {
+                    Ok(Ticker {
+                        ticker: ticker.to_string(),
+                        date: parse_date(bar.time()).ok_or_else(|| {
+                            TickerError::ApiError(format!("unreadable date {:?}", bar.time()))
+                        })?,
+                        close: price(bar.close()).ok_or_else(|| {
+                            TickerError::ApiError(format!("unreadable close on {}", bar.time()))
+                        })?,
+                        volume: bar.volume().try_into().ok(),
+                        open: price(bar.open()),
+                        high: price(bar.high()),
+                        low: price(bar.low()),
+                    })
+                })
+                .collect::<Result<Vec<_>, TickerError>>()?;
+            if bars.is_empty() {
+                return Err(TickerError::InvalidSymbol(ticker.to_string()).into());
+            }
+            bars.sort_by_key(|bar| bar.date);
+            Ok(bars)
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct FileBar {
+    date: String,
+    open: Option<BigDecimal>,
+    high: Option<BigDecimal>,
+    low: Option<BigDecimal>,
+    close: BigDecimal,
+    volume: Option<i64>,
+}
+
+/// Daily prices kept as files in a directory, one per ticker: `AAPL.csv` with a
+/// `date,open,high,low,close,volume` header (only date and close are required), or `AAPL.json`
+/// holding an array of objects with the same fields.
+pub struct FileProvider {
+    directory: PathBuf,
+}
+
+impl FileProvider {
+    pub fn new(directory: impl AsRef<Path>) -> Self {
+        Self {
+            directory: directory.as_ref().to_path_buf(),
+        }
+    }
+
+    fn parse_csv(ticker: &str, contents: &str) -> Result<Vec<FileBar>, String> {
+        let mut lines = contents
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty());
+        let (_, header) = lines.next().ok_or("the file is empty")?;
+        let columns: Vec<String> = header
+            .split(',')
+            .map(|column| column.trim().to_lowercase())
+            .collect();
+        let column = |name: &str| columns.iter().position(|column| column == name);
+        let (Some(date), Some(close)) = (column("date"), column("close")) else {
+            return Err(format!("{} needs date and close columns", ticker));
+        };
+        let (open, high, low, volume) = (
+            column("open"),
+            column("high"),
+            column("low"),
+            column("volume"),
+        );
+
+        lines
+            .map(|(number, line)| {
+                let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+                let field = |index: Option<usize>| {
+                    index
+                        .and_then(|index| fields.get(index))
+                        .filter(|value| !value.is_empty())
+                };
+                let decimal = |index: Option<usize>| {
+                    field(index)
+                        .map(|value| {
+                            value.parse::<BigDecimal>().map_err(|_| {
+                                format!("line {}: {:?} is not a number", number + 1, value)
+                            })
+                        })
+                        .transpose()
+                };
+                Ok(FileBar {
+                    date: field(Some(date))
+                        .ok_or_else(|| format!("line {}: no date", number + 1))?
+                        .to_string(),
+                    close: decimal(Some(close))?
+                        .ok_or_else(|| format!("line {}: no close", number + 1))?,
+                    open: decimal(open)?,
+                    high: decimal(high)?,
+                    low: decimal(low)?,
+                    volume: field(volume)
+                        .map(|value| {
+                            value.parse::<i64>().map_err(|_| {
+                                format!("line {}: {:?} is not a volume", number + 1, value)
+                            })
+                        })
+                        .transpose()?,
+                })
+            })
+            .collect()
+    }
+}
+
+impl MarketDataProvider for FileProvider {
+    fn name(&self) -> &'static str {
+        "files"
+    }
+
+    fn daily_history<'a>(
+        &'a self,
+        ticker: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<Ticker>, TradeError>> {
+        Box::pin(async move {
+            //the ticker names a file, so it mustn't be able to leave the directory
+            let valid = !ticker.is_empty()
+                && !ticker.starts_with('.')
+                && ticker
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '.' || c == '-');
+            if !valid {
+                return Err(TickerError::InvalidSymbol(ticker.to_string()).into());
+            }
+            let csv = self.directory.join(format!("{}.csv", ticker));
+            let json = self.directory.join(format!("{}.json", ticker));
+            let (path, rows) = if let Ok(contents) = tokio::fs::read_to_string(&csv).await {
+                (csv.clone(), Self::parse_csv(ticker, &contents))
+            } else if let Ok(contents) = tokio::fs::read_to_string(&json).await {
+                (
+                    json.clone(),
+                    serde_json::from_str::<Vec<FileBar>>(&contents).map_err(|e| e.to_string()),
+                )
+            } else {
+                return Err(TickerError::InvalidSymbol(ticker.to_string()).into());
+            };
+            let unreadable =
+                |reason: String| TickerError::ApiError(format!("{}: {}", path.display(), reason));
+            let mut bars = rows
+                .map_err(unreadable)?
+                .into_iter()
+                .map(|row| {
+                    Ok(Ticker {
+                        ticker: ticker.to_string(),
+                        date: parse_date(&row.date)
+                            .ok_or_else(|| unreadable(format!("unreadable date {:?}", row.date)))?,
+                        close: row
+                            .close
+                            .with_scale_round(PRICE_SCALE, RoundingMode::HalfUp),
+                        volume: row.volume,
+                        open: row.open,
+                        high: row.high,
+                        low: row.low,
+                    })
+                })
+                .collect::<Result<Vec<_>, TickerError>>()?;
+            if bars.is_empty() {
+                return Err(TickerError::InvalidSymbol(ticker.to_string()).into());
+            }
+            bars.sort_by_key(|bar| bar.date);
+            bars.dedup_by_key(|bar| bar.date);
+            Ok(bars)
+        })
+    }
+}
+
+/// Made-up daily prices for any ticker: a random walk seeded by the ticker and `seed`, so the
+/// same seed always gives the same prices. The last bar is today.
+pub struct SyntheticProvider {
+    seed: u64,
+    days: u32,
+}
+
+impl SyntheticProvider {
+    pub const DEFAULT_SEED: u64 = 42;
+    pub const DEFAULT_DAYS: u32 = 365;
+    const DAILY_DRIFT: f64 = 0.0003;
+    const DAILY_VOLATILITY: f64 = 0.02;
+
+    pub fn new(seed: u64) -> Self {
+        Self::with_days(seed, Self::DEFAULT_DAYS)
+    }
+
+    pub fn with_days(seed: u64, days: u32) -> Self {
+        Self {
+            seed,
+            days: days.max(1),
+        }
+    }
+
+    /// FNV-1a, which unlike the std hasher is the same on every build.
+    fn ticker_seed(&self, ticker: &str) -> u64 {
+        ticker.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+        }) ^ self.seed
+    }
+
+    pub fn generate(&self, ticker: &str, last_day: NaiveDate) -> Vec<Ticker> {
+        let mut rng = StdRng::seed_from_u64(self.ticker_seed(ticker));
+        let returns =
+            Normal::new(Self::DAILY_DRIFT, Self::DAILY_VOLATILITY).expect("volatility is positive");
+        let mut close: f64 = rng.gen_range(20.0..500.0);
+        let mut bars = Vec::with_capacity(self.days as usize);
+        for offset in (0..self.days).rev() {
+            let Some(day) = last_day.checked_sub_days(Days::new(u64::from(offset))) else {
+                continue;
+            };
+            let open = close;
+            close = open * returns.sample(&mut rng).exp();
+            let high = open.max(close) * (1.0 + rng.gen_range(0.0..0.01));
+            let low = open.min(close) * (1.0 - rng.gen_range(0.0..0.01));
+            bars.push(Ticker {
+                ticker: ticker.to_string(),
+                date: day.and_hms_opt(0, 0, 0).expect("midnight exists").and_utc(),
+                close: price(close).unwrap_or_default(),
+                volume: Some(rng.gen_range(100_000..5_000_000)),
+                open: price(open),
+                high: price(high),
+                low: price(low),
+            });
+        }
+        bars
+    }
+}
+
+impl MarketDataProvider for SyntheticProvider {
+    fn name(&self) -> &'static str {
+        "synthetic"
+    }
+
+    fn daily_history<'a>(
+        &'a self,
+        ticker: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<Ticker>, TradeError>> {
+        Box::pin(async move { Ok(self.generate(ticker, Utc::now().date_naive())) })
+    }
+}
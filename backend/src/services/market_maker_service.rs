@@ -8,8 +8,13 @@ use tracing::info;
 use uuid::Uuid;
 
 use crate::{
-    models::{errors::trade_error::TradeError, order::OrderType},
-    services::{order_management_service::OrderManagementService, ticker_service::TickerService},
+    models::{
+        corporate_action::SplitRatio, errors::trade_error::TradeError, order::OrderType,
+    },
+    services::{
+        alert_service::AlertService, order_management_service::OrderManagementService,
+        ticker_service::TickerService,
+    },
 };
 use std::{collections::HashMap, sync::Arc};
 
@@ -17,8 +22,9 @@ pub struct MarketMakerService {
     db: PgPool,
     ticker_service: Arc<TickerService>,
     order_management_service: Arc<OrderManagementService>,
+    alert_service: Arc<AlertService>,
     acceptable_tickers: Vec<String>,
-    ticker_price_paths: RwLock<HashMap<String, Vec<BigDecimal>>>,
+    ticker_price_paths: Arc<RwLock<HashMap<String, Vec<BigDecimal>>>>,
     market_maker_user_id: Uuid,
 }
 
@@ -32,6 +38,7 @@ impl MarketMakerService {
         db: PgPool,
         ticker_service: Arc<TickerService>,
         order_management_service: Arc<OrderManagementService>,
+        alert_service: Arc<AlertService>,
         acceptable_tickers: Vec<String>,
         user_id: Uuid,
     ) -> Self {
@@ -39,8 +46,9 @@ impl MarketMakerService {
             db,
             ticker_service,
             order_management_service,
+            alert_service,
             acceptable_tickers,
-            ticker_price_paths: RwLock::new(HashMap::new()),
+            ticker_price_paths: Arc::new(RwLock::new(HashMap::new())),
             market_maker_user_id: user_id,
         }
     }
@@ -86,6 +94,20 @@ impl MarketMakerService {
         // self.spawn_worker_thread().await;
         Ok(())
     }
+    /// Rescales the remaining price path after a split so the engine keeps steering the
+    /// price towards the adjusted target.
+    pub async fn rescale_price_path(&self, ticker: &str, ratio: &SplitRatio) {
+        let mut ticker_price_paths = self.ticker_price_paths.write().await;
+        if let Some(path) = ticker_price_paths.get_mut(ticker) {
+            for price in path.iter_mut() {
+                *price = ratio.adjust_price(price);
+            }
+        }
+        drop(ticker_price_paths);
+        //the split restated stock_prices, so any cached quote is in old shares
+        self.ticker_service.invalidate_quote(ticker).await;
+    }
+
     pub async fn generate_market_orders(
         &self,
         ticker: String,
@@ -112,11 +134,12 @@ impl MarketMakerService {
         info!("Starting price engine thread");
         let order_management_service_clone = self.order_management_service.clone();
         let ticker_service_clone = self.ticker_service.clone();
+        let alert_service = self.alert_service.clone();
         let acceptable_tickers = self.acceptable_tickers.clone();
         let user_id = self.market_maker_user_id;
 
         // We clone the Arc to move it into the background thread so it can constantly read live states
-        let price_paths_ref = Arc::new(self.ticker_price_paths.read().await.clone());
+        let price_paths_ref = Arc::clone(&self.ticker_price_paths);
 
         tokio::spawn(async move {
             let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(
@@ -131,13 +154,21 @@ impl MarketMakerService {
                 for ticker in &acceptable_tickers {
                     let default_value = BigDecimal::from(120);
 
-                    let target_price = match price_paths_ref.get(ticker) {
-                        Some(paths) => paths.get(current_time_index).unwrap_or(&default_value),
+                    let target_price = match price_paths_ref.read().await.get(ticker) {
+                        Some(paths) => paths
+                            .get(current_time_index)
+                            .cloned()
+                            .unwrap_or(default_value),
                         None => {
                             tracing::warn!("No price path found for ticker {}", ticker);
-                            &default_value
+                            default_value
                         }
                     };
+                    let target_price = &target_price;
+                    ticker_service_clone.invalidate_quote(ticker).await;
+                    if let Err(e) = alert_service.on_price(ticker, target_price).await {
+                        tracing::warn!("Failed to check price alerts for {}: {:?}", ticker, e);
+                    }
                     let current_price = ticker_service_clone
                         .fetch_latest_price_ticker_from_db(ticker)
                         .await?
@@ -184,7 +215,7 @@ impl MarketMakerService {
         let user_id = self.market_maker_user_id;
 
         // We clone the Arc to move it into the background thread so it can constantly read live states
-        let price_paths_ref = Arc::new(self.ticker_price_paths.read().await.clone());
+        let price_paths_ref = Arc::clone(&self.ticker_price_paths);
 
         tokio::spawn(async move {
             let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(5));
@@ -203,10 +234,14 @@ impl MarketMakerService {
                     .await;
 
                 for order in open_orders {
-                    let target_price = match price_paths_ref.get(&order.ticker) {
-                        Some(paths) => paths.get(current_time_index).unwrap_or(&default_value),
-                        None => &default_value,
+                    let target_price = match price_paths_ref.read().await.get(&order.ticker) {
+                        Some(paths) => paths
+                            .get(current_time_index)
+                            .cloned()
+                            .unwrap_or(default_value.clone()),
+                        None => default_value.clone(),
                     };
+                    let target_price = &target_price;
 
                     // Define acceptable spread (e.g. within 1% of the target price)
                     let max_deviation = target_price.clone()
@@ -1,11 +1,26 @@
 pub mod account_management_service;
+pub mod alert_service;
+pub mod analytics_service;
 pub mod bankruptcy_service;
+pub mod corporate_action_service;
+pub mod export_service;
+pub mod fee_service;
+pub mod idempotency_service;
+pub mod interest_service;
 pub mod loan_service;
+pub mod market_data_provider;
 pub mod market_maker_service;
 pub mod order_management_service;
 pub mod order_matchbook_service;
 pub mod portfolio_management_service;
+pub mod rebalance_service;
+pub mod reconciliation_service;
+pub mod risk_service;
+pub mod simulation_service;
+pub mod snapshot_service;
+pub mod tax_report_service;
 pub mod ticker_service;
 pub mod trade_service;
 pub mod user_service;
 pub mod validation_strategy;
+pub mod watchlist_service;
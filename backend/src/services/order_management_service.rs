@@ -2,19 +2,22 @@ use std::sync::Arc;
 
 use crate::{
     models::{
-        errors::{trade_error::TradeError, user_error::UserError},
+        errors::trade_error::TradeError,
+        history::{HistoryCursor, HistoryQuery, Page},
         order::{Order, OrderStatus, OrderType},
     },
     services::{
-        account_management_service::AccountManagementService,
+        account_management_service::AccountManagementService, fee_service::FeeService,
         order_matchbook_service::OrderMatchbookService,
         portfolio_management_service::PortfolioManagementService, ticker_service::TickerService,
         user_service::UserService,
     },
 };
+use chrono::Utc;
 use sqlx::types::BigDecimal;
 use sqlx::PgPool;
 use sqlx::Row;
+use sqlx::{Postgres, QueryBuilder};
 use tracing::info;
 use uuid::Uuid;
 
@@ -27,6 +30,7 @@ pub struct OrderManagementService {
     pub account_management_service: Arc<AccountManagementService>,
     pub portfolio_management_service: Arc<PortfolioManagementService>,
     pub order_matchbook_service: Arc<OrderMatchbookService>,
+    pub fee_service: Arc<FeeService>,
 }
 
 #[allow(dead_code)]
@@ -38,6 +42,7 @@ impl OrderManagementService {
         account_management_service: Arc<AccountManagementService>,
         portfolio_management_service: Arc<PortfolioManagementService>,
         order_matchbook_service: Arc<OrderMatchbookService>,
+        fee_service: Arc<FeeService>,
     ) -> Self {
         Self {
             db,
@@ -46,6 +51,7 @@ impl OrderManagementService {
             account_management_service,
             portfolio_management_service,
             order_matchbook_service,
+            fee_service,
         }
     }
 
@@ -53,15 +59,30 @@ impl OrderManagementService {
     #[tracing::instrument(skip(self))]
     pub async fn place_order(
         &self,
-        user_id: Uuid,
+        account_id: Uuid,
         ticker: &str,
         quantity: BigDecimal,
         order_type: OrderType,
         _price_buffer: BigDecimal,
         price_per_share: Option<BigDecimal>,
+    ) -> Result<Order, TradeError> {
+        self.place_order_with_lots(account_id, ticker, quantity, order_type, price_per_share, &[])
+            .await
+    }
+
+    /// Like `place_order`, but a sell closes the given tax lots first.
+    #[tracing::instrument(skip(self))]
+    pub async fn place_order_with_lots(
+        &self,
+        account_id: Uuid,
+        ticker: &str,
+        quantity: BigDecimal,
+        order_type: OrderType,
+        price_per_share: Option<BigDecimal>,
+        lot_ids: &[Uuid],
     ) -> Result<Order, TradeError> {
         // TODO : Add atomicity to this function
-        info!("Placing order for user {}", user_id);
+        info!("Placing order for account {}", account_id);
         let order_id = Uuid::new_v4();
         let status = OrderStatus::Pending;
         if quantity < BigDecimal::from(0) {
@@ -72,27 +93,42 @@ impl OrderManagementService {
             Some(price) => price,
             None => {
                 self.trade_service
-                    .fetch_latest_price_ticker_from_db(ticker)
+                    .get_quote(ticker)
                     .await?
                     .close
             }
         };
         let total_purchase_price = &price_per_share * &quantity;
+        if matches!(order_type, OrderType::Buy) && !lot_ids.is_empty() {
+            return Err(TradeError::InvalidLotSelection(
+                "only sell orders close lots".to_string(),
+            ));
+        }
         match order_type {
             OrderType::Buy => {
-                // Reserve funds
+                // Reserve funds, including the most the order can be charged in fees
+                let max_fee = self
+                    .fee_service
+                    .max_order_fee(account_id, &quantity, &price_per_share)
+                    .await?;
                 self.account_management_service
-                    .reserve_funds(user_id, &total_purchase_price)
+                    .reserve_funds(account_id, &(total_purchase_price + max_fee), Some(order_id))
                     .await?;
             }
             OrderType::Sell => {
-                // Check holdings
-                let holdings = self
-                    .portfolio_management_service
-                    .check_holdings(user_id, ticker)
+                // Reserve holdings
+                self.portfolio_management_service
+                    .reserve_holdings(account_id, ticker, &quantity)
                     .await?;
-                if holdings < quantity {
-                    return Err(TradeError::UserError(UserError::InsufficientHoldings));
+                if let Err(e) = self
+                    .portfolio_management_service
+                    .select_lots(account_id, ticker, order_id, lot_ids)
+                    .await
+                {
+                    self.portfolio_management_service
+                        .release_holdings(account_id, ticker, &quantity)
+                        .await?;
+                    return Err(e);
                 }
             }
         }
@@ -100,12 +136,13 @@ impl OrderManagementService {
         //Placing order
         let created_order = Order {
             order_id,
-            user_id,
+            account_id,
             ticker: ticker.to_string(),
             quantity,
             price_per_share,
             order_type,
             status,
+            created_at: Utc::now(),
         };
         self.order_matchbook_service
             .add_order(created_order.clone())
@@ -113,16 +150,17 @@ impl OrderManagementService {
         info!("Order added to orderbook successfully");
         let _rec = sqlx::query(
             "INSERT INTO orders 
-        (order_id, user_id, ticker, quantity, price_per_share, order_type, status) 
-        VALUES ($1, $2, $3, $4, $5, $6::order_type, $7::order_status)",
+        (order_id, account_id, ticker, quantity, price_per_share, order_type, status, created_at) 
+        VALUES ($1, $2, $3, $4, $5, $6::order_type, $7::order_status, $8)",
         )
         .bind(&created_order.order_id)
-        .bind(&created_order.user_id)
+        .bind(&created_order.account_id)
         .bind(&created_order.ticker)
         .bind(&created_order.quantity)
         .bind(&created_order.price_per_share)
         .bind(&created_order.order_type)
         .bind(&created_order.status)
+        .bind(created_order.created_at)
         .execute(&self.db)
         .await
         .map_err(|e| TradeError::DatabaseError(e))?;
@@ -133,11 +171,11 @@ impl OrderManagementService {
     pub async fn get_order_status(
         &self,
         order_id: Uuid,
-        user_id: Uuid,
+        account_id: Uuid,
     ) -> Result<OrderStatus, TradeError> {
-        let rec = sqlx::query("SELECT * FROM orders WHERE order_id = $1 AND user_id = $2")
+        let rec = sqlx::query("SELECT * FROM orders WHERE order_id = $1 AND account_id = $2")
             .bind(order_id)
-            .bind(user_id)
+            .bind(account_id)
             .fetch_one(&self.db)
             .await
             .map_err(|e| TradeError::DatabaseError(e))?;
@@ -148,10 +186,10 @@ impl OrderManagementService {
     }
 
     #[tracing::instrument(skip(self))]
-    pub async fn cancel_order(&self, order_id: Uuid, user_id: Uuid) -> Result<(), TradeError> {
-        let rec = sqlx::query("SELECT * FROM orders WHERE order_id = $1 AND user_id = $2")
+    pub async fn cancel_order(&self, order_id: Uuid, account_id: Uuid) -> Result<(), TradeError> {
+        let rec = sqlx::query("SELECT * FROM orders WHERE order_id = $1 AND account_id = $2")
             .bind(order_id)
-            .bind(user_id)
+            .bind(account_id)
             .fetch_one(&self.db)
             .await
             .map_err(|e| TradeError::DatabaseError(e))?;
@@ -163,20 +201,38 @@ impl OrderManagementService {
         if order_status_str == OrderStatus::Cancelled {
             return Ok(());
         }
-        sqlx::query("UPDATE orders SET status = 'Cancelled' WHERE order_id = $1")
+        let order_type: OrderType = rec.try_get("order_type")?;
+        let remaining_quantity: BigDecimal = rec.try_get("quantity")?;
+        sqlx::query("UPDATE orders SET status = $2 WHERE order_id = $1")
             .bind(order_id)
+            .bind(OrderStatus::Cancelled)
             .execute(&self.db)
             .await
             .map_err(|e| TradeError::DatabaseError(e))?;
         
         self.order_matchbook_service.remove_order(&ticker, order_id).await;
+        //give back the shares or cash held for the unfilled part of the order
+        if order_status_str == OrderStatus::Pending {
+            match order_type {
+                OrderType::Sell => {
+                    self.portfolio_management_service
+                        .release_holdings(account_id, &ticker, &remaining_quantity)
+                        .await?
+                }
+                OrderType::Buy => {
+                    self.account_management_service
+                        .release_reservation(account_id, order_id)
+                        .await?
+                }
+            }
+        }
         Ok(())
     }
 
     #[tracing::instrument(skip(self))]
-    pub async fn get_pending_orders(&self, user_id: Uuid) -> Result<Vec<Order>, TradeError> {
-        let rec = sqlx::query("SELECT * FROM orders WHERE user_id = $1 and status = $2")
-            .bind(user_id)
+    pub async fn get_pending_orders(&self, account_id: Uuid) -> Result<Vec<Order>, TradeError> {
+        let rec = sqlx::query("SELECT * FROM orders WHERE account_id = $1 and status = $2")
+            .bind(account_id)
             .bind(OrderStatus::Pending)
             .fetch_all(&self.db)
             .await
@@ -186,48 +242,99 @@ impl OrderManagementService {
             .map(|r| {
                 Ok(Order {
                     order_id: r.try_get("order_id")?,
-                    user_id: r.try_get("user_id")?,
+                    account_id: r.try_get("account_id")?,
                     ticker: r.try_get("ticker")?,
                     quantity: r.try_get("quantity")?,
                     price_per_share: r.try_get("price_per_share")?,
                     order_type: r.try_get("order_type")?,
                     status: r.try_get("status")?,
+                    created_at: r.try_get("created_at")?,
                 })
             })
             .collect::<Result<Vec<Order>, TradeError>>()?;
         Ok(orders)
     }
 
+    /// Orders in every status, newest first unless `sort=asc` is requested.
     #[tracing::instrument(skip(self))]
-    pub async fn get_order(&self, order_id: Uuid, user_id: Uuid) -> Result<Order, TradeError> {
-        let rec = sqlx::query("SELECT * FROM orders WHERE order_id = $1 AND user_id = $2")
+    pub async fn get_order_history(
+        &self,
+        account_id: Uuid,
+        query: &HistoryQuery,
+    ) -> Result<Page<Order>, TradeError> {
+        let mut builder = QueryBuilder::<Postgres>::new("SELECT * FROM orders WHERE account_id = ");
+        builder.push_bind(account_id);
+        if let Some(status) = &query.status {
+            builder.push(" AND status = ").push_bind(status.clone());
+        }
+        query.push_conditions(&mut builder, "created_at", "order_id")?;
+        let rec = builder.build().fetch_all(&self.db).await?;
+        let orders = rec
+            .into_iter()
+            .map(|r| {
+                Ok(Order {
+                    order_id: r.try_get("order_id")?,
+                    account_id: r.try_get("account_id")?,
+                    ticker: r.try_get("ticker")?,
+                    quantity: r.try_get("quantity")?,
+                    price_per_share: r.try_get("price_per_share")?,
+                    order_type: r.try_get("order_type")?,
+                    status: r.try_get("status")?,
+                    created_at: r.try_get("created_at")?,
+                })
+            })
+            .collect::<Result<Vec<Order>, TradeError>>()?;
+        Ok(query.into_page(orders, |order| HistoryCursor {
+            timestamp: order.created_at,
+            id: order.order_id,
+        }))
+    }
+
+    #[tracing::instrument(skip(self))]
+    pub async fn get_order(&self, order_id: Uuid, account_id: Uuid) -> Result<Order, TradeError> {
+        let rec = sqlx::query("SELECT * FROM orders WHERE order_id = $1 AND account_id = $2")
             .bind(order_id)
-            .bind(user_id)
+            .bind(account_id)
             .fetch_one(&self.db)
             .await
             .map_err(|e| TradeError::DatabaseError(e))?;
         let order = Order {
             order_id: rec.try_get("order_id")?,
-            user_id: rec.try_get("user_id")?,
+            account_id: rec.try_get("account_id")?,
             ticker: rec.try_get("ticker")?,
             quantity: rec.try_get("quantity")?,
             price_per_share: rec.try_get("price_per_share")?,
             order_type: rec.try_get("order_type")?,
             status: rec.try_get("status")?,
+            created_at: rec.try_get("created_at")?,
         };
         Ok(order)
     }
 
-    pub async fn cancel_all_orders(&self, user_id: Uuid) -> Result<(), TradeError> {
-        let pending = self.get_pending_orders(user_id).await?;
-        sqlx::query("UPDATE orders SET status = 'Cancelled' WHERE user_id = $1")
-            .bind(user_id)
+    pub async fn cancel_all_orders(&self, account_id: Uuid) -> Result<(), TradeError> {
+        let pending = self.get_pending_orders(account_id).await?;
+        sqlx::query("UPDATE orders SET status = $2 WHERE account_id = $1 AND status = $3")
+            .bind(account_id)
+            .bind(OrderStatus::Cancelled)
+            .bind(OrderStatus::Pending)
             .execute(&self.db)
             .await
             .map_err(|e| TradeError::DatabaseError(e))?;
 
         for order in pending {
             self.order_matchbook_service.remove_order(&order.ticker, order.order_id).await;
+            match order.order_type {
+                OrderType::Sell => {
+                    self.portfolio_management_service
+                        .release_holdings(account_id, &order.ticker, &order.quantity)
+                        .await?
+                }
+                OrderType::Buy => {
+                    self.account_management_service
+                        .release_reservation(account_id, order.order_id)
+                        .await?
+                }
+            }
         }
         Ok(())
     }
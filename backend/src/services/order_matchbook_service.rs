@@ -4,6 +4,7 @@ use std::{
 };
 
 use bigdecimal::BigDecimal;
+use chrono::Utc;
 use sqlx::PgPool;
 use tokio::{sync::RwLock, task::JoinHandle};
 use tracing::{info, warn};
@@ -12,9 +13,12 @@ use uuid::Uuid;
 use crate::{
     models::{
         errors::trade_error::TradeError,
+        fee::Liquidity,
         order::{Order, OrderType},
     },
-    services::{ticker_service::TickerService, trade_service::TradeService},
+    services::{
+        alert_service::AlertService, ticker_service::TickerService, trade_service::TradeService,
+    },
 };
 
 struct OrderBook {
@@ -47,6 +51,7 @@ pub struct OrderMatchbookService {
     order_books: Arc<RwLock<HashMap<String, OrderBook>>>,
     trade_service: Arc<TradeService>,
     ticker_service: Arc<TickerService>,
+    alert_service: Arc<AlertService>,
 }
 
 impl OrderMatchbookService {
@@ -55,12 +60,14 @@ impl OrderMatchbookService {
         db: PgPool,
         trade_service: Arc<TradeService>,
         ticker_service: Arc<TickerService>,
+        alert_service: Arc<AlertService>,
     ) -> OrderMatchbookService {
         OrderMatchbookService {
             db,
             order_books: Arc::new(RwLock::new(HashMap::new())),
             trade_service,
             ticker_service,
+            alert_service,
         }
     }
 
@@ -100,6 +107,27 @@ impl OrderMatchbookService {
         Ok(())
     }
 
+    /// The ticker's best resting buy and sell, either of which may be missing. Errors if the
+    /// ticker has no book.
+    pub async fn get_best_sale(
+        &self,
+        ticker: &str,
+    ) -> Result<(Option<Order>, Option<Order>), TradeError> {
+        let books = self.order_books.read().await;
+        let order_book = books.get(ticker).ok_or(TradeError::NoMatchForOrder)?;
+        let best_buy = order_book
+            .buys
+            .values()
+            .next_back()
+            .and_then(|orders| orders.first().cloned());
+        let best_sell = order_book
+            .sells
+            .values()
+            .next()
+            .and_then(|orders| orders.first().cloned());
+        Ok((best_buy, best_sell))
+    }
+
     pub async fn get_open_orders(&self) -> Vec<Order> {
         let books = self.order_books.read().await;
         let mut open_orders = Vec::new();
@@ -128,10 +156,65 @@ impl OrderMatchbookService {
         }
     }
 
+    /// Corrects the resting quantity of an order in place so it keeps its time priority.
+    /// Returns false if the order is not in the book.
+    pub async fn set_order_quantity(
+        &self,
+        ticker: &str,
+        order_id: Uuid,
+        quantity: &BigDecimal,
+    ) -> bool {
+        let mut books = self.order_books.write().await;
+        let Some(order_book) = books.get_mut(ticker) else {
+            return false;
+        };
+        for orders in order_book
+            .buys
+            .values_mut()
+            .chain(order_book.sells.values_mut())
+        {
+            if let Some(order) = orders.iter_mut().find(|o| o.order_id == order_id) {
+                order.quantity = quantity.clone();
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Swaps the ticker's resting orders for their rescaled versions after a split. Every price
+    /// level moves by the same ratio, so orders keep their priority. Resting orders missing from
+    /// `rescaled`, such as one placed while the split was being processed, stay as they are.
+    pub async fn rescale_orders(&self, ticker: &str, rescaled: &[Order]) {
+        let rescaled: HashMap<Uuid, &Order> = rescaled
+            .iter()
+            .filter(|o| o.ticker == ticker)
+            .map(|o| (o.order_id, o))
+            .collect();
+        let mut books = self.order_books.write().await;
+        let Some(order_book) = books.get_mut(ticker) else {
+            return;
+        };
+        let rebuild = |side: &BTreeMap<BigDecimal, Vec<Order>>| {
+            let mut levels: BTreeMap<BigDecimal, Vec<Order>> = BTreeMap::new();
+            for order in side.values().flatten() {
+                let order = rescaled.get(&order.order_id).copied().unwrap_or(order);
+                levels
+                    .entry(order.price_per_share.clone())
+                    .or_default()
+                    .push(order.clone());
+            }
+            levels
+        };
+        order_book.buys = rebuild(&order_book.buys);
+        order_book.sells = rebuild(&order_book.sells);
+    }
+
     pub fn create_worker_thread(&self) -> JoinHandle<Result<(), TradeError>> {
         info!("Starting order processor thread");
         let order_books = Arc::clone(&self.order_books);
         let trade_service = Arc::clone(&self.trade_service);
+        let alert_service = Arc::clone(&self.alert_service);
+        let ticker_service = Arc::clone(&self.ticker_service);
 
         tokio::spawn(async move {
             let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(
@@ -140,8 +223,9 @@ impl OrderMatchbookService {
             loop {
                 interval.tick().await;
                 loop {
-                    let mut buy_ids: Vec<(Uuid, BigDecimal, BigDecimal)> = Vec::new();
-                    let mut sell_ids: Vec<(Uuid, BigDecimal, BigDecimal)> = Vec::new();
+                    let mut buy_ids: Vec<(Uuid, BigDecimal, BigDecimal, Liquidity)> = Vec::new();
+                    let mut sell_ids: Vec<(Uuid, BigDecimal, BigDecimal, Liquidity)> = Vec::new();
+                    let mut trade_prices: Vec<(String, Uuid, BigDecimal, BigDecimal)> = Vec::new();
                     {
                         let books = order_books.read().await;
                         info!("Are we even reading the same books? {}", books.len());
@@ -151,8 +235,15 @@ impl OrderMatchbookService {
                                 if best_buy.price_per_share >= best_sell.price_per_share {
                                     let match_quantity = best_buy.quantity.min(best_sell.quantity);
                                     let execution_price = best_sell.price_per_share.clone(); // Market price defined by the limit sell
-                                    buy_ids.push((best_buy.order_id, match_quantity.clone(), execution_price.clone()));
-                                    sell_ids.push((best_sell.order_id, match_quantity, execution_price));
+                                    //whichever order was resting first made the market
+                                    let (buy_liquidity, sell_liquidity) = if best_buy.created_at <= best_sell.created_at {
+                                        (Liquidity::Maker, Liquidity::Taker)
+                                    } else {
+                                        (Liquidity::Taker, Liquidity::Maker)
+                                    };
+                                    trade_prices.push((_ticker.clone(), best_buy.order_id, execution_price.clone(), match_quantity.clone()));
+                                    buy_ids.push((best_buy.order_id, match_quantity.clone(), execution_price.clone(), buy_liquidity));
+                                    sell_ids.push((best_sell.order_id, match_quantity, execution_price, sell_liquidity));
                                 }
                             }
                         }
@@ -173,10 +264,10 @@ impl OrderMatchbookService {
                     let mut failed_buys = Vec::new();
                     let mut failed_sells = Vec::new();
 
-                    for (buy_id, match_quantity, execution_price) in buy_ids {
+                    for (buy_id, match_quantity, execution_price, liquidity) in buy_ids {
                         info!("Executing buy order for user {}", buy_id);
                         match trade_service
-                            .execute_order(buy_id, match_quantity.clone(), execution_price)
+                            .execute_order(buy_id, match_quantity.clone(), execution_price, liquidity)
                             .await
                         {
                             Ok(_) => {
@@ -189,9 +280,9 @@ impl OrderMatchbookService {
                             }
                         }
                     }
-                    for (sell_id, match_quantity, execution_price) in sell_ids {
+                    for (sell_id, match_quantity, execution_price, liquidity) in sell_ids {
                         match trade_service
-                            .execute_order(sell_id, match_quantity.clone(), execution_price)
+                            .execute_order(sell_id, match_quantity.clone(), execution_price, liquidity)
                             .await
                         {
                             Ok(_) => {
@@ -227,6 +318,29 @@ impl OrderMatchbookService {
                             });
                         }
                     }
+
+                    //each match goes into the ticker's bars and becomes its quote, and alerts
+                    //see the price it traded at and each order that filled
+                    let executed_at = Utc::now();
+                    for (ticker, buy_id, execution_price, match_quantity) in &trade_prices {
+                        if successful_buys.contains_key(buy_id) {
+                            if let Err(e) = ticker_service
+                                .record_trade(ticker, execution_price, match_quantity, executed_at)
+                                .await
+                            {
+                                warn!(error = ?e, "Failed to record trade in price bars");
+                                ticker_service.invalidate_quote(ticker).await;
+                            }
+                            if let Err(e) = alert_service.on_price(ticker, execution_price).await {
+                                warn!(error = ?e, "Failed to check price alerts");
+                            }
+                        }
+                    }
+                    for order_id in successful_buys.keys().chain(successful_sells.keys()) {
+                        if let Err(e) = alert_service.on_order_filled(*order_id).await {
+                            warn!(error = ?e, "Failed to check order filled alerts");
+                        }
+                    }
                 }
             }
         })
@@ -1,15 +1,17 @@
 use std::sync::Arc;
 
+use crate::models::allocation::{Holding, PortfolioAllocation};
 use crate::models::errors::trade_error::TradeError;
 use crate::models::errors::user_error::UserError;
 use crate::models::order::OrderType;
-use crate::models::portfolio_ticker::HistoricalStockValue;
 use crate::models::portfolio_ticker::PortfolioTicker;
 use crate::models::stock_ticker::Ticker;
-use crate::models::stock_ticker::TimeFrame;
+use crate::models::tax_lot::{CostBasisMethod, Position, Sale, TaxLot};
 use crate::services::ticker_service::TickerService;
-use chrono::{DateTime, Utc};
+use bigdecimal::RoundingMode;
+use num_traits::Zero;
 use serde::Serialize;
+use sqlx::postgres::PgRow;
 use sqlx::types::BigDecimal;
 use sqlx::PgPool;
 use sqlx::Row;
@@ -25,220 +27,114 @@ impl PortfolioManagementService {
         Self { db, ticker_service }
     }
     #[tracing::instrument(skip(self))]
-    pub async fn get_total_portfolio_value(&self, user_id: Uuid) -> Result<BigDecimal, TradeError> {
-        let portfolio = self.get_portfolio(user_id).await?;
+    pub async fn get_total_portfolio_value(&self, account_id: Uuid) -> Result<BigDecimal, TradeError> {
+        let portfolio = self.get_portfolio(account_id).await?;
         let mut total_portfolio_value = BigDecimal::from(0);
         for portfolio_item in portfolio {
-            total_portfolio_value += portfolio_item.total_profit + portfolio_item.total_money_spent;
+            total_portfolio_value += portfolio_item.market_value;
         }
         Ok(total_portfolio_value)
     }
-    pub async fn get_stock_value_by_timeframe(
-        &self,
-        user_id: Uuid,
-        timeframe: TimeFrame,
-        ticker: &str,
-    ) -> Result<Vec<HistoricalStockValue>, TradeError> {
-        // Step A: Fetch historical ticker data
-        let ticker_data = self
-            .ticker_service
-            .fetch_price_history_ticker_from_db(ticker, timeframe)
-            .await?;
-
-        // Step B: Fetch all filled orders for this user and ticker
-        let orders_rows = sqlx::query(
-            "SELECT quantity, order_type, created_at FROM orders 
-             WHERE user_id = $1 AND ticker = $2 AND status = 'Executed' 
-             ORDER BY created_at ASC",
-        )
-        .bind(user_id)
-        .bind(ticker)
-        .fetch_all(&self.db)
-        .await
-        .map_err(|e| TradeError::UserError(UserError::DatabaseError(e)))?;
-
-        // Step C: The Running Total Algorithm (Optimized with a cursor)
-        let mut historical_values = Vec::new();
-        let mut current_quantity = BigDecimal::from(0);
-        let mut order_idx = 0;
-
-        for ticker_item in ticker_data {
-            // Add or subtract quantities for any trades that happened on or before this day
-            while order_idx < orders_rows.len() {
-                let row = &orders_rows[order_idx];
-                let order_date: DateTime<Utc> =
-                    row.try_get("created_at").unwrap_or_else(|_| Utc::now());
-
-                if order_date <= ticker_item.date {
-                    let order_qty: BigDecimal = row.try_get("quantity").unwrap_or_default();
-                    let order_type: OrderType = row.try_get("order_type").unwrap_or(OrderType::Buy);
-
-                    match order_type {
-                        OrderType::Buy => current_quantity += order_qty,
-                        OrderType::Sell => current_quantity -= order_qty,
-                    }
-                    order_idx += 1;
-                } else {
-                    // This order happened after the current ticker date, wait for the next iteration
-                    break;
-                }
-            }
-
-            let total_value = &current_quantity * &ticker_item.close;
-
-            historical_values.push(HistoricalStockValue {
-                date: ticker_item.date,
-                ticker: ticker_item.ticker.clone(),
-                price_per_share: ticker_item.close.clone(),
-                quantity: current_quantity.clone(),
-                total_value,
-            });
-        }
-
-        Ok(historical_values)
+    /// The account's open positions at the latest prices.
+    #[tracing::instrument(skip(self))]
+    pub async fn get_portfolio(&self, account_id: Uuid) -> Result<Vec<PortfolioTicker>, TradeError> {
+        self.get_positions(account_id, false).await
     }
 
+    /// The account's positions at the latest prices. Closed positions have no shares left but
+    /// keep the P&L realized and the fees paid while they were open.
     #[tracing::instrument(skip(self))]
-    pub async fn get_portfolio_history(
+    pub async fn get_positions(
         &self,
-        user_id: Uuid,
-        timeframe: TimeFrame,
-    ) -> Result<Vec<crate::models::portfolio_ticker::PortfolioHistoryPoint>, TradeError> {
-        let distinct_tickers = sqlx::query(
-            "SELECT DISTINCT ticker FROM orders WHERE user_id = $1 AND status = 'EXECUTED'",
+        account_id: Uuid,
+        include_closed: bool,
+    ) -> Result<Vec<PortfolioTicker>, TradeError> {
+        let database_portfolio = sqlx::query(
+            "SELECT * FROM portfolio WHERE account_id = $1 AND ($2 OR quantity > 0) ORDER BY created_at",
         )
-        .bind(user_id)
+        .bind(account_id)
+        .bind(include_closed)
         .fetch_all(&self.db)
         .await
         .map_err(|e| TradeError::UserError(UserError::DatabaseError(e)))?;
-
-        let mut distinct_tickers_histories = Vec::new();
-        for rec in distinct_tickers {
-            let ticker: String = rec.get("ticker");
-            let ticker_history = self
-                .get_stock_value_by_timeframe(user_id, timeframe, &ticker)
-                .await?;
-            distinct_tickers_histories.push((ticker, ticker_history));
-        }
-
-        use chrono::Timelike;
-        let mut all_normalized_dates = std::collections::BTreeSet::new();
-
-        // Collect all unique normalized dates (set to midnight)
-        for (_, history) in &distinct_tickers_histories {
-            for point in history {
-                let normalized = point
-                    .date
-                    .date_naive()
-                    .and_hms_opt(0, 0, 0)
-                    .unwrap()
-                    .and_utc();
-                all_normalized_dates.insert(normalized);
-            }
-        }
-
-        let mut history = Vec::new();
-        let mut last_known_values: std::collections::HashMap<String, BigDecimal> =
-            std::collections::HashMap::new();
-
-        // 1. Maintain a cursor (index) for each ticker's history so we don't start from 0 every day
-        let mut ticker_cursors: std::collections::HashMap<String, usize> =
-            std::collections::HashMap::new();
-        for (ticker, _) in &distinct_tickers_histories {
-            ticker_cursors.insert(ticker.clone(), 0);
-        }
-
-        // Forward fill algorithm (Optimized with cursors)
-        for date in all_normalized_dates {
-            let mut daily_total = BigDecimal::from(0);
-
-            for (ticker, t_history) in &distinct_tickers_histories {
-                let mut cursor = *ticker_cursors.get(ticker).unwrap_or(&0);
-
-                // Advance the cursor as long as the next point is on or before our global date
-                while cursor < t_history.len() {
-                    let point = &t_history[cursor];
-                    let normalized = point
-                        .date
-                        .date_naive()
-                        .and_hms_opt(0, 0, 0)
-                        .unwrap()
-                        .and_utc();
-                    if normalized <= date {
-                        // We found a valid point, update our last known value
-                        last_known_values.insert(ticker.clone(), point.total_value.clone());
-                        cursor += 1;
-                    } else {
-                        // The point is in the future, wait for the next global date
-                        break;
-                    }
-                }
-
-                // Save the cursor back so we don't start from 0 next time
-                ticker_cursors.insert(ticker.clone(), cursor);
-
-                // Add the last known value of this ticker to the daily total (even if missing today)
-                if let Some(val) = last_known_values.get(ticker) {
-                    daily_total += val;
-                }
-            }
-
-            history.push(crate::models::portfolio_ticker::PortfolioHistoryPoint {
-                date,
-                total_value: daily_total,
-            });
-        }
-
-        // Add the current portfolio value as the very last data point to ensure accuracy to the millisecond
-        let current_total_value = self
-            .get_total_portfolio_value(user_id)
-            .await
-            .unwrap_or(BigDecimal::from(0));
-        history.push(crate::models::portfolio_ticker::PortfolioHistoryPoint {
-            date: chrono::Utc::now(),
-            total_value: current_total_value,
-        });
-
-        Ok(history)
-    }
-
-    #[tracing::instrument(skip(self))]
-    pub async fn get_portfolio(&self, user_id: Uuid) -> Result<Vec<PortfolioTicker>, TradeError> {
-        let database_portfolio = sqlx::query("SELECT * FROM portfolio WHERE user_id = $1")
-            .bind(user_id)
-            .fetch_all(&self.db)
-            .await
-            .map_err(|e| TradeError::UserError(UserError::DatabaseError(e)))?;
-        let mut user_portfolio = Vec::new();
+        let tickers: Vec<String> = database_portfolio
+            .iter()
+            .map(|rec| rec.get("ticker"))
+            .collect();
+        let quotes = self.ticker_service.get_quotes(&tickers).await?;
+        let mut account_portfolio = Vec::new();
         for rec in database_portfolio {
-            let ticker = self
-                .ticker_service
-                .fetch_latest_price_ticker_from_db(rec.get("ticker"))
-                .await?;
-            let quantity = rec.get("quantity");
+            let ticker_name: String = rec.get("ticker");
+            let quantity: BigDecimal = rec.get("quantity");
+            let available_quantity = rec.get("available_quantity");
             let total_money_spent: BigDecimal = rec.get("total_money_spent");
-            let calculated_total_profit = (&quantity * ticker.close) - &total_money_spent;
+            let total_fees: BigDecimal = rec.get("total_fees");
+            let realized_pnl: BigDecimal = rec.get("realized_pnl");
+            let (market_value, average_cost) = if quantity.is_zero() {
+                (BigDecimal::zero(), BigDecimal::zero())
+            } else {
+                let ticker = quotes
+                    .get(&ticker_name)
+                    .ok_or(TradeError::DatabaseError(sqlx::Error::RowNotFound))?;
+                (
+                    &quantity * &ticker.close,
+                    (&total_money_spent / &quantity).with_scale_round(4, RoundingMode::HalfUp),
+                )
+            };
+            let unrealized_pnl = &market_value - &total_money_spent;
+            let calculated_total_profit = &unrealized_pnl + &realized_pnl - &total_fees;
             let portfolio_item = PortfolioTicker {
-                user_id: rec.get("user_id"),
+                account_id: rec.get("account_id"),
                 ticker: rec.get("ticker"),
                 quantity: quantity,
+                available_quantity,
                 total_money_spent: total_money_spent,
+                average_cost,
+                market_value,
+                unrealized_pnl,
+                realized_pnl,
+                total_fees,
                 total_profit: calculated_total_profit,
                 created_at: rec.get("created_at"),
             };
-            user_portfolio.push(portfolio_item);
+            account_portfolio.push(portfolio_item);
         }
-        Ok(user_portfolio)
+        Ok(account_portfolio)
+    }
+
+    /// Splits the account's value across cash, positions, sectors and asset classes at the
+    /// latest prices.
+    #[tracing::instrument(skip(self))]
+    pub async fn get_allocation(&self, account_id: Uuid) -> Result<PortfolioAllocation, TradeError> {
+        let cash: BigDecimal = sqlx::query("SELECT balance FROM accounts WHERE account_id = $1")
+            .bind(account_id)
+            .fetch_optional(&self.db)
+            .await?
+            .ok_or(UserError::AccountNotFound)?
+            .try_get("balance")?;
+        let portfolio = self.get_portfolio(account_id).await?;
+        let tickers: Vec<String> = portfolio.iter().map(|p| p.ticker.clone()).collect();
+        let mut instruments = self.ticker_service.get_instruments(&tickers).await?;
+        let holdings = portfolio
+            .into_iter()
+            .map(|position| Holding {
+                instrument: instruments.remove(&position.ticker),
+                ticker: position.ticker,
+                quantity: position.quantity,
+                market_value: position.market_value,
+            })
+            .collect();
+        Ok(PortfolioAllocation::compute(cash, holdings))
     }
 
     #[tracing::instrument(skip(self))]
     pub async fn check_holdings(
         &self,
-        user_id: Uuid,
+        account_id: Uuid,
         ticker: &str,
     ) -> Result<BigDecimal, TradeError> {
-        let rec = sqlx::query("SELECT * FROM portfolio WHERE user_id = $1 AND ticker = $2")
-            .bind(user_id)
+        let rec = sqlx::query("SELECT * FROM portfolio WHERE account_id = $1 AND ticker = $2")
+            .bind(account_id)
             .bind(ticker)
             .fetch_one(&self.db)
             .await
@@ -246,71 +142,338 @@ impl PortfolioManagementService {
         if rec.is_empty() {
             return Err(TradeError::UserError(UserError::InsufficientHoldings));
         }
-        Ok(rec.get("quantity"))
+        Ok(rec.get("available_quantity"))
+    }
+
+    #[tracing::instrument(skip(self))]
+    pub async fn reserve_holdings(
+        &self,
+        account_id: Uuid,
+        ticker: &str,
+        quantity: &BigDecimal,
+    ) -> Result<(), TradeError> {
+        if quantity <= &BigDecimal::from(0) {
+            return Err(TradeError::InvalidAmount);
+        }
+        //only available quantity is reserved, quantity is deducted from when the order is executed
+        let rows_affected = sqlx::query(
+            "UPDATE portfolio SET available_quantity = available_quantity - $3 WHERE account_id = $1 AND ticker = $2 AND available_quantity >= $3",
+        )
+        .bind(account_id)
+        .bind(ticker)
+        .bind(quantity)
+        .execute(&self.db)
+        .await
+        .map_err(|e| TradeError::UserError(UserError::DatabaseError(e)))?
+        .rows_affected();
+        if rows_affected > 0 {
+            Ok(())
+        } else {
+            Err(TradeError::UserError(UserError::InsufficientHoldings))
+        }
+    }
+
+    #[tracing::instrument(skip(self))]
+    pub async fn release_holdings(
+        &self,
+        account_id: Uuid,
+        ticker: &str,
+        quantity: &BigDecimal,
+    ) -> Result<(), TradeError> {
+        if quantity <= &BigDecimal::from(0) {
+            return Ok(());
+        }
+        //the position may already be gone (e.g. liquidated), in which case there is nothing to release
+        sqlx::query(
+            "UPDATE portfolio SET available_quantity = LEAST(available_quantity + $3, quantity) WHERE account_id = $1 AND ticker = $2",
+        )
+        .bind(account_id)
+        .bind(ticker)
+        .bind(quantity)
+        .execute(&self.db)
+        .await
+        .map_err(|e| TradeError::UserError(UserError::DatabaseError(e)))?;
+        Ok(())
     }
 
+    /// Adds a bought fill to the position and opens a tax lot for it.
     #[tracing::instrument(skip(self))]
     pub async fn add_to_portfolio(
         &self,
-        user_id: Uuid,
+        account_id: Uuid,
         ticker: &str,
         quantity: &BigDecimal,
         total_money_spent: &BigDecimal,
+        order_id: Option<Uuid>,
     ) -> Result<(), TradeError> {
+        let mut tx = self.db.begin().await?;
         let portfolio_id = Uuid::new_v4();
         let _rec = sqlx::query(
-            "INSERT INTO portfolio (portfolio_id, user_id, ticker, quantity, total_money_spent) VALUES ($1, $2, $3, $4, $5)
-            ON CONFLICT (user_id, ticker) DO UPDATE SET quantity = portfolio.quantity + $4, total_money_spent = portfolio.total_money_spent + $5",
+            "INSERT INTO portfolio (portfolio_id, account_id, ticker, quantity, available_quantity, total_money_spent) VALUES ($1, $2, $3, $4, $4, $5)
+            ON CONFLICT (account_id, ticker) DO UPDATE SET quantity = portfolio.quantity + $4, available_quantity = portfolio.available_quantity + $4, total_money_spent = portfolio.total_money_spent + $5",
         )
         .bind(portfolio_id)
-        .bind(user_id)
+        .bind(account_id)
         .bind(ticker)
         .bind(quantity)
         .bind(total_money_spent)
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(
+            "INSERT INTO tax_lots (lot_id, account_id, ticker, order_id, quantity, remaining_quantity, cost_basis)
+            VALUES ($1, $2, $3, $4, $5, $5, $6)",
+        )
+        .bind(Uuid::new_v4())
+        .bind(account_id)
+        .bind(ticker)
+        .bind(order_id)
+        .bind(quantity)
+        .bind(total_money_spent)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Books a fill's fee against the position, including one the fill just closed.
+    #[tracing::instrument(skip(self))]
+    pub async fn add_position_fee(
+        &self,
+        account_id: Uuid,
+        ticker: &str,
+        fee: &BigDecimal,
+    ) -> Result<(), TradeError> {
+        sqlx::query(
+            "UPDATE portfolio SET total_fees = total_fees + $3 WHERE account_id = $1 AND ticker = $2",
+        )
+        .bind(account_id)
+        .bind(ticker)
+        .bind(fee)
         .execute(&self.db)
-        .await
-        .map_err(|e| TradeError::UserError(UserError::DatabaseError(e)))?;
+        .await?;
         Ok(())
     }
 
+    /// Takes `quantity` shares out of the position, closing lots by the account's cost basis
+    /// method and reducing the position's cost by what they cost. A `sale` is recorded in
+    /// realized_gains and its realized P&L returned; shares removed without one (liquidation)
+    /// realize nothing and are recorded as a sell at no price, as nothing was paid for them.
     #[tracing::instrument(skip(self))]
     pub async fn remove_from_portfolio(
         &self,
-        user_id: Uuid,
+        account_id: Uuid,
         ticker: &str,
         quantity: &BigDecimal,
-    ) -> Result<(), TradeError> {
-        let get_rec = sqlx::query("SELECT * FROM portfolio WHERE user_id = $1 AND ticker = $2")
-            .bind(user_id)
-            .bind(ticker)
-            .fetch_one(&self.db)
-            .await
-            .map_err(|e| TradeError::UserError(UserError::DatabaseError(e)))?;
-        if get_rec.is_empty() {
-            return Err(TradeError::UserError(UserError::InsufficientHoldings));
-        }
-        let get_rec_quantity: BigDecimal = get_rec.get("quantity");
+        sale: Option<&Sale>,
+    ) -> Result<BigDecimal, TradeError> {
+        let mut tx = self.db.begin().await?;
+        let get_rec = sqlx::query(
+            "SELECT p.quantity, p.total_money_spent, a.cost_basis_method
+            FROM portfolio p JOIN accounts a ON a.account_id = p.account_id
+            WHERE p.account_id = $1 AND p.ticker = $2 FOR UPDATE OF p",
+        )
+        .bind(account_id)
+        .bind(ticker)
+        .fetch_optional(&mut *tx)
+        .await?
+        .ok_or(UserError::InsufficientHoldings)?;
+        let get_rec_quantity: BigDecimal = get_rec.try_get("quantity")?;
         if get_rec_quantity < *quantity {
             return Err(TradeError::UserError(UserError::InsufficientHoldings));
         }
-        if get_rec_quantity == *quantity {
-            sqlx::query("DELETE FROM portfolio WHERE user_id = $1 AND ticker = $2")
-                .bind(user_id)
+        let total_money_spent: BigDecimal = get_rec.try_get("total_money_spent")?;
+        let method: CostBasisMethod = get_rec.try_get("cost_basis_method")?;
+        let lots = sqlx::query(
+            "SELECT * FROM tax_lots WHERE account_id = $1 AND ticker = $2 AND remaining_quantity > 0
+            FOR UPDATE",
+        )
+        .bind(account_id)
+        .bind(ticker)
+        .fetch_all(&mut *tx)
+        .await?
+        .iter()
+        .map(Self::tax_lot_from_row)
+        .collect::<Result<Vec<_>, _>>()?;
+        let selected: Vec<Uuid> = match sale {
+            Some(sale) => sqlx::query(
+                "SELECT lot_id FROM order_lot_selections WHERE order_id = $1 ORDER BY priority",
+            )
+            .bind(sale.order_id)
+            .fetch_all(&mut *tx)
+            .await?
+            .iter()
+            .map(|rec| rec.try_get("lot_id"))
+            .collect::<Result<_, _>>()?,
+            None => Vec::new(),
+        };
+        let disposals = Position {
+            lots: &lots,
+            quantity: &get_rec_quantity,
+            cost_basis: &total_money_spent,
+        }
+        .dispose(method, &selected, quantity);
+
+        let mut realized_cost = BigDecimal::from(0);
+        let mut realized_pnl = BigDecimal::from(0);
+        for disposal in &disposals {
+            realized_cost += &disposal.cost_basis;
+            if let Some(lot_id) = disposal.lot_id {
+                sqlx::query(
+                    "UPDATE tax_lots SET remaining_quantity = remaining_quantity - $2,
+                        cost_basis = cost_basis - $3
+                    WHERE lot_id = $1",
+                )
+                .bind(lot_id)
+                .bind(&disposal.quantity)
+                .bind(&disposal.lot_cost)
+                .execute(&mut *tx)
+                .await?;
+            }
+            if let Some(sale) = sale {
+                let proceeds = (&disposal.quantity * &sale.price_per_share)
+                    .with_scale_round(4, RoundingMode::HalfUp);
+                realized_pnl += &proceeds - &disposal.cost_basis;
+                sqlx::query(
+                    "INSERT INTO realized_gains (realization_id, account_id, ticker, order_id, lot_id,
+                        acquired_at, quantity, cost_basis, proceeds)
+                    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)",
+                )
+                .bind(Uuid::new_v4())
+                .bind(account_id)
                 .bind(ticker)
-                .execute(&self.db)
-                .await
-                .map_err(|e| TradeError::UserError(UserError::DatabaseError(e)))?;
-        } else {
-            let _rec = sqlx::query(
-                "UPDATE portfolio SET quantity = quantity - $3 WHERE user_id = $1 AND ticker = $2 AND quantity >= $3",
+                .bind(sale.order_id)
+                .bind(disposal.lot_id)
+                .bind(disposal.acquired_at)
+                .bind(&disposal.quantity)
+                .bind(&disposal.cost_basis)
+                .bind(&proceeds)
+                .execute(&mut *tx)
+                .await?;
+            }
+        }
+
+        //a closed position keeps its row, and with it the P&L it realized and the fees it paid
+        //shares sold through an order were already taken out of available_quantity when it was reserved
+        sqlx::query(
+            "UPDATE portfolio SET quantity = quantity - $3, available_quantity = LEAST(available_quantity, quantity - $3),
+                total_money_spent = CASE WHEN quantity = $3 THEN 0 ELSE total_money_spent - $4 END,
+                realized_pnl = realized_pnl + $5
+            WHERE account_id = $1 AND ticker = $2 AND quantity >= $3",
+        )
+        .bind(account_id)
+        .bind(ticker)
+        .bind(quantity)
+        .bind(&realized_cost)
+        .bind(&realized_pnl)
+        .execute(&mut *tx)
+        .await?;
+        //a sale is logged with its fill, a liquidation is logged here so the account's
+        //transactions still add up to what it holds
+        if sale.is_none() {
+            sqlx::query(
+                "INSERT INTO transactions (transaction_id, account_id, ticker, order_type, quantity,
+                    price_per_share, realized_pnl)
+                VALUES ($1, $2, $3, $4, $5, 0, 0)",
             )
-            .bind(user_id)
+            .bind(Uuid::new_v4())
+            .bind(account_id)
             .bind(ticker)
+            .bind(OrderType::Sell)
             .bind(quantity)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(realized_pnl)
+    }
+
+    /// Open lots of the account, oldest first, optionally for one ticker.
+    #[tracing::instrument(skip(self))]
+    pub async fn get_tax_lots(
+        &self,
+        account_id: Uuid,
+        ticker: Option<&str>,
+    ) -> Result<Vec<TaxLot>, TradeError> {
+        let records = sqlx::query(
+            "SELECT * FROM tax_lots
+            WHERE account_id = $1 AND ($2::text IS NULL OR ticker = $2) AND remaining_quantity > 0
+            ORDER BY ticker, acquired_at",
+        )
+        .bind(account_id)
+        .bind(ticker)
+        .fetch_all(&self.db)
+        .await?;
+        records.iter().map(Self::tax_lot_from_row).collect()
+    }
+
+    /// Records the lots a sell order should close first. They must be open lots of the same
+    /// position; accounts on specific-lot identification have to name at least one.
+    #[tracing::instrument(skip(self))]
+    pub async fn select_lots(
+        &self,
+        account_id: Uuid,
+        ticker: &str,
+        order_id: Uuid,
+        lot_ids: &[Uuid],
+    ) -> Result<(), TradeError> {
+        let method: CostBasisMethod =
+            sqlx::query("SELECT cost_basis_method FROM accounts WHERE account_id = $1")
+                .bind(account_id)
+                .fetch_optional(&self.db)
+                .await?
+                .ok_or(UserError::AccountNotFound)?
+                .try_get("cost_basis_method")?;
+        if lot_ids.is_empty() {
+            if method == CostBasisMethod::SpecificLot {
+                return Err(TradeError::InvalidLotSelection(
+                    "this account uses specific-lot identification, so sells must name lots"
+                        .to_string(),
+                ));
+            }
+            return Ok(());
+        }
+        let open_lots: i64 = sqlx::query(
+            "SELECT COUNT(*) FROM tax_lots
+            WHERE lot_id = ANY($1) AND account_id = $2 AND ticker = $3 AND remaining_quantity > 0",
+        )
+        .bind(lot_ids)
+        .bind(account_id)
+        .bind(ticker)
+        .fetch_one(&self.db)
+        .await?
+        .try_get(0)?;
+        let mut distinct = lot_ids.to_vec();
+        distinct.sort();
+        distinct.dedup();
+        if distinct.len() != lot_ids.len() || open_lots != lot_ids.len() as i64 {
+            return Err(TradeError::InvalidLotSelection(format!(
+                "lots must be distinct open lots of your {} position",
+                ticker
+            )));
+        }
+        for (priority, lot_id) in lot_ids.iter().enumerate() {
+            sqlx::query(
+                "INSERT INTO order_lot_selections (order_id, lot_id, priority) VALUES ($1, $2, $3)",
+            )
+            .bind(order_id)
+            .bind(lot_id)
+            .bind(priority as i32)
             .execute(&self.db)
-            .await
-            .map_err(|e| TradeError::UserError(UserError::DatabaseError(e)))?;
+            .await?;
         }
         Ok(())
     }
+
+    fn tax_lot_from_row(rec: &PgRow) -> Result<TaxLot, TradeError> {
+        Ok(TaxLot {
+            lot_id: rec.try_get("lot_id")?,
+            account_id: rec.try_get("account_id")?,
+            ticker: rec.try_get("ticker")?,
+            order_id: rec.try_get("order_id")?,
+            acquired_at: rec.try_get("acquired_at")?,
+            quantity: rec.try_get("quantity")?,
+            remaining_quantity: rec.try_get("remaining_quantity")?,
+            cost_basis: rec.try_get("cost_basis")?,
+        })
+    }
 }
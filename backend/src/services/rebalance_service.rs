@@ -0,0 +1,415 @@
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use bigdecimal::BigDecimal;
+use chrono::{Duration, Utc};
+use num_traits::{One, Zero};
+use sqlx::{PgPool, Row};
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::models::errors::trade_error::TradeError;
+use crate::models::errors::user_error::UserError;
+use crate::models::order::OrderStatus;
+use crate::models::rebalance::{
+    ProposedOrder, RebalanceHolding, RebalancePlan, RebalanceProposal, RebalanceProposalStatus,
+    TargetAllocation, TargetAllocationRequest, TargetWeight,
+};
+use crate::services::order_management_service::OrderManagementService;
+use crate::services::ticker_service::TickerService;
+
+/// Keeps accounts at their target weights: proposes the orders that get them there, places
+/// them as a batch once confirmed, and places them unprompted for accounts that opted in to
+/// auto-rebalancing when they drift too far.
+#[derive(Clone)]
+pub struct RebalanceService {
+    db: PgPool,
+    ticker_service: Arc<TickerService>,
+    order_management_service: Arc<OrderManagementService>,
+}
+
+impl RebalanceService {
+    const PROPOSAL_TTL_MINUTES: i64 = 15;
+    const AUTO_REBALANCE_INTERVAL_SECS: u64 = 15 * 60;
+
+    pub fn new(
+        db: PgPool,
+        ticker_service: Arc<TickerService>,
+        order_management_service: Arc<OrderManagementService>,
+    ) -> Self {
+        Self {
+            db,
+            ticker_service,
+            order_management_service,
+        }
+    }
+
+    #[tracing::instrument(skip(self))]
+    pub async fn get_targets(&self, account_id: Uuid) -> Result<TargetAllocation, TradeError> {
+        let settings = sqlx::query(
+            "SELECT rebalance_drift_threshold, auto_rebalance FROM accounts WHERE account_id = $1",
+        )
+        .bind(account_id)
+        .fetch_optional(&self.db)
+        .await?
+        .ok_or(UserError::AccountNotFound)?;
+        let targets = sqlx::query(
+            "SELECT ticker, weight FROM target_allocations WHERE account_id = $1 ORDER BY ticker",
+        )
+        .bind(account_id)
+        .fetch_all(&self.db)
+        .await?
+        .into_iter()
+        .map(|rec| {
+            Ok(TargetWeight {
+                ticker: rec.try_get("ticker")?,
+                weight: rec.try_get("weight")?,
+            })
+        })
+        .collect::<Result<Vec<_>, TradeError>>()?;
+        let invested: BigDecimal = targets.iter().map(|t| &t.weight).sum();
+        Ok(TargetAllocation {
+            cash_weight: BigDecimal::one() - invested,
+            targets,
+            drift_threshold: settings.try_get("rebalance_drift_threshold")?,
+            auto_rebalance: settings.try_get("auto_rebalance")?,
+        })
+    }
+
+    /// Replaces the account's targets and auto-rebalancing settings.
+    #[tracing::instrument(skip(self))]
+    pub async fn set_targets(
+        &self,
+        account_id: Uuid,
+        request: TargetAllocationRequest,
+    ) -> Result<TargetAllocation, TradeError> {
+        let allocation = TargetAllocation::new(request).map_err(TradeError::InvalidRebalance)?;
+        let mut tx = self.db.begin().await?;
+        let updated = sqlx::query(
+            "UPDATE accounts SET rebalance_drift_threshold = $2, auto_rebalance = $3
+            WHERE account_id = $1",
+        )
+        .bind(account_id)
+        .bind(&allocation.drift_threshold)
+        .bind(allocation.auto_rebalance)
+        .execute(&mut *tx)
+        .await?;
+        if updated.rows_affected() == 0 {
+            return Err(UserError::AccountNotFound.into());
+        }
+        sqlx::query("DELETE FROM target_allocations WHERE account_id = $1")
+            .bind(account_id)
+            .execute(&mut *tx)
+            .await?;
+        for target in &allocation.targets {
+            sqlx::query(
+                "INSERT INTO target_allocations (account_id, ticker, weight) VALUES ($1, $2, $3)",
+            )
+            .bind(account_id)
+            .bind(&target.ticker)
+            .bind(&target.weight)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(allocation)
+    }
+
+    /// Works out the orders that bring the account back to its targets at the latest prices.
+    async fn plan(
+        &self,
+        account_id: Uuid,
+        targets: &[TargetWeight],
+    ) -> Result<RebalancePlan, TradeError> {
+        let account =
+            sqlx::query("SELECT balance, available_balance FROM accounts WHERE account_id = $1")
+                .bind(account_id)
+                .fetch_optional(&self.db)
+                .await?
+                .ok_or(UserError::AccountNotFound)?;
+        let cash: BigDecimal = account.try_get("balance")?;
+        let available_cash: BigDecimal = account.try_get("available_balance")?;
+
+        let mut positions: BTreeMap<String, (BigDecimal, BigDecimal)> = BTreeMap::new();
+        for rec in sqlx::query(
+            "SELECT ticker, quantity, available_quantity FROM portfolio
+            WHERE account_id = $1 AND quantity > 0",
+        )
+        .bind(account_id)
+        .fetch_all(&self.db)
+        .await?
+        {
+            positions.insert(
+                rec.try_get("ticker")?,
+                (rec.try_get("quantity")?, rec.try_get("available_quantity")?),
+            );
+        }
+        for target in targets {
+            positions
+                .entry(target.ticker.clone())
+                .or_insert_with(|| (BigDecimal::zero(), BigDecimal::zero()));
+        }
+
+        let tickers: Vec<String> = positions.keys().cloned().collect();
+        let instruments = self.ticker_service.get_instruments(&tickers).await?;
+        let mut holdings = Vec::with_capacity(positions.len());
+        for (ticker, (quantity, available_quantity)) in positions {
+            let price = self
+                .ticker_service
+                .get_quote(&ticker)
+                .await
+                .map_err(|_| TradeError::InvalidRebalance(format!("{} has no price", ticker)))?
+                .close;
+            holdings.push(RebalanceHolding {
+                lot_size: instruments
+                    .get(&ticker)
+                    .map_or_else(BigDecimal::one, |i| i.lot_size.clone()),
+                ticker,
+                quantity,
+                available_quantity,
+                price,
+            });
+        }
+        let fees = self
+            .order_management_service
+            .fee_service
+            .get_schedule_for_account(account_id)
+            .await?
+            .terms;
+        Ok(RebalancePlan::compute(
+            &cash,
+            &available_cash,
+            &holdings,
+            targets,
+            &fees,
+        ))
+    }
+
+    /// Proposes the orders that rebalance the account, to be placed by `confirm_proposal`.
+    #[tracing::instrument(skip(self))]
+    pub async fn propose(&self, account_id: Uuid) -> Result<RebalanceProposal, TradeError> {
+        let allocation = self.get_targets(account_id).await?;
+        if allocation.targets.is_empty() {
+            return Err(TradeError::InvalidRebalance(
+                "the account has no target allocation".to_string(),
+            ));
+        }
+        let plan = self.plan(account_id, &allocation.targets).await?;
+        self.save_proposal(account_id, plan).await
+    }
+
+    async fn save_proposal(
+        &self,
+        account_id: Uuid,
+        plan: RebalancePlan,
+    ) -> Result<RebalanceProposal, TradeError> {
+        let proposal_id = Uuid::new_v4();
+        let created_at = Utc::now();
+        let expires_at = created_at + Duration::minutes(Self::PROPOSAL_TTL_MINUTES);
+        let mut tx = self.db.begin().await?;
+        sqlx::query(
+            "INSERT INTO rebalance_proposals
+                (proposal_id, account_id, total_value, drift, created_at, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6)",
+        )
+        .bind(proposal_id)
+        .bind(account_id)
+        .bind(&plan.total_value)
+        .bind(&plan.drift)
+        .bind(created_at)
+        .bind(expires_at)
+        .execute(&mut *tx)
+        .await?;
+        for order in &plan.orders {
+            sqlx::query(
+                "INSERT INTO rebalance_proposal_orders (proposal_id, ticker, order_type, quantity,
+                    price_per_share, current_weight, target_weight)
+                VALUES ($1, $2, $3, $4, $5, $6, $7)",
+            )
+            .bind(proposal_id)
+            .bind(&order.ticker)
+            .bind(&order.order_type)
+            .bind(&order.quantity)
+            .bind(&order.price_per_share)
+            .bind(&order.current_weight)
+            .bind(&order.target_weight)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(RebalanceProposal {
+            proposal_id,
+            account_id,
+            status: RebalanceProposalStatus::Proposed,
+            total_value: plan.total_value,
+            drift: plan.drift,
+            orders: plan.orders,
+            created_at,
+            expires_at,
+            submitted_at: None,
+        })
+    }
+
+    #[tracing::instrument(skip(self))]
+    pub async fn get_proposal(
+        &self,
+        account_id: Uuid,
+        proposal_id: Uuid,
+    ) -> Result<RebalanceProposal, TradeError> {
+        let rec = sqlx::query(
+            "SELECT * FROM rebalance_proposals WHERE proposal_id = $1 AND account_id = $2",
+        )
+        .bind(proposal_id)
+        .bind(account_id)
+        .fetch_optional(&self.db)
+        .await?
+        .ok_or(TradeError::RebalanceProposalNotFound)?;
+        let orders = sqlx::query(
+            "SELECT * FROM rebalance_proposal_orders WHERE proposal_id = $1
+            ORDER BY order_type DESC, ticker",
+        )
+        .bind(proposal_id)
+        .fetch_all(&self.db)
+        .await?
+        .into_iter()
+        .map(|rec| {
+            Ok(ProposedOrder {
+                ticker: rec.try_get("ticker")?,
+                order_type: rec.try_get("order_type")?,
+                quantity: rec.try_get("quantity")?,
+                price_per_share: rec.try_get("price_per_share")?,
+                current_weight: rec.try_get("current_weight")?,
+                target_weight: rec.try_get("target_weight")?,
+                order_id: rec.try_get("order_id")?,
+                error: rec.try_get("error")?,
+            })
+        })
+        .collect::<Result<Vec<_>, TradeError>>()?;
+        Ok(RebalanceProposal {
+            proposal_id,
+            account_id,
+            status: rec.try_get("status")?,
+            total_value: rec.try_get("total_value")?,
+            drift: rec.try_get("drift")?,
+            orders,
+            created_at: rec.try_get("created_at")?,
+            expires_at: rec.try_get("expires_at")?,
+            submitted_at: rec.try_get("submitted_at")?,
+        })
+    }
+
+    /// Places the proposal's orders at the prices they were sized at, sells first. An order
+    /// that can't be placed doesn't stop the rest; its error is kept on the proposal. Buys
+    /// funded by sells that haven't filled yet are among those, and are picked up by the next
+    /// proposal.
+    #[tracing::instrument(skip(self))]
+    pub async fn confirm_proposal(
+        &self,
+        account_id: Uuid,
+        proposal_id: Uuid,
+    ) -> Result<RebalanceProposal, TradeError> {
+        //claiming the proposal first means it can only ever be submitted once
+        let claimed = sqlx::query(
+            "UPDATE rebalance_proposals SET status = $3, submitted_at = NOW()
+            WHERE proposal_id = $1 AND account_id = $2 AND status = $4 AND expires_at > NOW()",
+        )
+        .bind(proposal_id)
+        .bind(account_id)
+        .bind(RebalanceProposalStatus::Submitted)
+        .bind(RebalanceProposalStatus::Proposed)
+        .execute(&self.db)
+        .await?;
+        if claimed.rows_affected() == 0 {
+            self.get_proposal(account_id, proposal_id).await?;
+            return Err(TradeError::RebalanceProposalNotPending);
+        }
+
+        let proposal = self.get_proposal(account_id, proposal_id).await?;
+        for order in &proposal.orders {
+            let placed = self
+                .order_management_service
+                .place_order(
+                    account_id,
+                    &order.ticker,
+                    order.quantity.clone(),
+                    order.order_type.clone(),
+                    BigDecimal::zero(),
+                    Some(order.price_per_share.clone()),
+                )
+                .await;
+            let (order_id, error) = match placed {
+                Ok(placed) => (Some(placed.order_id), None),
+                Err(e) => {
+                    warn!(error = ?e, ticker = %order.ticker, "Rebalance order was not placed");
+                    (None, Some(e.to_string()))
+                }
+            };
+            sqlx::query(
+                "UPDATE rebalance_proposal_orders SET order_id = $3, error = $4
+                WHERE proposal_id = $1 AND ticker = $2",
+            )
+            .bind(proposal_id)
+            .bind(&order.ticker)
+            .bind(order_id)
+            .bind(error)
+            .execute(&self.db)
+            .await?;
+        }
+        self.get_proposal(account_id, proposal_id).await
+    }
+
+    /// Rebalances every auto-rebalancing account that has drifted past its threshold. Accounts
+    /// with open orders are left until those settle. Returns how many were rebalanced.
+    #[tracing::instrument(skip(self))]
+    pub async fn auto_rebalance(&self) -> Result<usize, TradeError> {
+        let accounts = sqlx::query(
+            "SELECT a.account_id, a.rebalance_drift_threshold FROM accounts a
+            WHERE a.auto_rebalance AND a.rebalance_drift_threshold IS NOT NULL
+                AND EXISTS (SELECT 1 FROM target_allocations t WHERE t.account_id = a.account_id)
+                AND NOT EXISTS (SELECT 1 FROM orders o
+                    WHERE o.account_id = a.account_id AND o.status IN ($1, $2))",
+        )
+        .bind(OrderStatus::Pending)
+        .bind(OrderStatus::Reserved)
+        .fetch_all(&self.db)
+        .await?;
+        let mut rebalanced = 0;
+        for rec in accounts {
+            let account_id: Uuid = rec.try_get("account_id")?;
+            let threshold: BigDecimal = rec.try_get("rebalance_drift_threshold")?;
+            let allocation = self.get_targets(account_id).await?;
+            let plan = match self.plan(account_id, &allocation.targets).await {
+                Ok(plan) => plan,
+                Err(e) => {
+                    warn!(error = ?e, %account_id, "Could not plan auto-rebalance");
+                    continue;
+                }
+            };
+            if plan.drift <= threshold || plan.orders.is_empty() {
+                continue;
+            }
+            let proposal = self.save_proposal(account_id, plan).await?;
+            self.confirm_proposal(account_id, proposal.proposal_id)
+                .await?;
+            rebalanced += 1;
+        }
+        Ok(rebalanced)
+    }
+
+    pub fn spawn_auto_rebalance_job(&self) -> JoinHandle<Result<(), TradeError>> {
+        info!("Starting auto-rebalance thread");
+        let service = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(
+                Self::AUTO_REBALANCE_INTERVAL_SECS,
+            ));
+            loop {
+                interval.tick().await;
+                if let Err(e) = service.auto_rebalance().await {
+                    warn!(error = ?e, "Auto-rebalance failed");
+                }
+            }
+        })
+    }
+}
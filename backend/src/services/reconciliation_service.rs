@@ -0,0 +1,489 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use bigdecimal::BigDecimal;
+use chrono::Utc;
+use sqlx::PgPool;
+use sqlx::Row;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::models::errors::trade_error::TradeError;
+use crate::models::ledger::{JournalEntryType, LedgerAccount};
+use crate::models::order::{Order, OrderStatus, OrderType};
+use crate::models::reconciliation::{BreakType, ReconciliationBreak, ReconciliationReport};
+use crate::services::order_matchbook_service::OrderMatchbookService;
+use crate::services::trade_service::TradeService;
+
+/// Cross-checks positions, cash and the in-memory order book against the records they are
+/// derived from, and stores every run as a report of breaks.
+#[derive(Clone)]
+pub struct ReconciliationService {
+    db: PgPool,
+    trade_service: Arc<TradeService>,
+    order_matchbook_service: Arc<OrderMatchbookService>,
+    system_user_id: Uuid,
+}
+
+impl ReconciliationService {
+    const RECONCILIATION_INTERVAL_SECS: u64 = 60 * 60;
+    //the matcher updates the database before the book, so a break has to survive a second
+    //look before it is reported or repaired
+    const ORDER_BOOK_CONFIRMATION_DELAY_MILLIS: u64 = 2_000;
+
+    pub fn new(
+        db: PgPool,
+        trade_service: Arc<TradeService>,
+        order_matchbook_service: Arc<OrderMatchbookService>,
+        system_user_id: Uuid,
+    ) -> Self {
+        Self {
+            db,
+            trade_service,
+            order_matchbook_service,
+            system_user_id,
+        }
+    }
+
+    /// Runs every check, repairs order book drift if `auto_repair` is set and stores the report.
+    #[tracing::instrument(skip(self))]
+    pub async fn run(&self, auto_repair: bool) -> Result<ReconciliationReport, TradeError> {
+        let started_at = Utc::now();
+        let mut breaks = Vec::new();
+        breaks.extend(self.check_positions().await?);
+        breaks.extend(self.check_reserved_quantities().await?);
+        breaks.extend(self.check_system_inventory().await?);
+        breaks.extend(self.check_cash().await?);
+        let mut order_book_breaks = self.check_order_book().await?;
+        if auto_repair {
+            self.repair_order_book(&mut order_book_breaks).await?;
+        }
+        breaks.extend(order_book_breaks);
+        let report = ReconciliationReport {
+            report_id: Uuid::new_v4(),
+            auto_repair,
+            started_at,
+            completed_at: Utc::now(),
+            breaks,
+        };
+        self.save_report(&report).await?;
+        if report.breaks.is_empty() {
+            info!("Reconciliation {} found no breaks", report.report_id);
+        } else {
+            warn!(
+                "Reconciliation {} found {} breaks",
+                report.report_id,
+                report.breaks.len()
+            );
+        }
+        Ok(report)
+    }
+
+    #[tracing::instrument(skip(self))]
+    pub async fn get_latest_report(&self) -> Result<Option<ReconciliationReport>, TradeError> {
+        let Some(rec) =
+            sqlx::query("SELECT * FROM reconciliation_reports ORDER BY started_at DESC LIMIT 1")
+                .fetch_optional(&self.db)
+                .await?
+        else {
+            return Ok(None);
+        };
+        let report_id: Uuid = rec.try_get("report_id")?;
+        let break_records = sqlx::query(
+            "SELECT * FROM reconciliation_breaks WHERE report_id = $1 ORDER BY break_type",
+        )
+        .bind(report_id)
+        .fetch_all(&self.db)
+        .await?;
+        let mut breaks = Vec::new();
+        for rec in break_records {
+            breaks.push(ReconciliationBreak {
+                break_type: rec.try_get("break_type")?,
+                account_id: rec.try_get("account_id")?,
+                ticker: rec.try_get("ticker")?,
+                order_id: rec.try_get("order_id")?,
+                expected: rec.try_get("expected")?,
+                actual: rec.try_get("actual")?,
+                repaired: rec.try_get("repaired")?,
+            });
+        }
+        Ok(Some(ReconciliationReport {
+            report_id,
+            auto_repair: rec.try_get("auto_repair")?,
+            started_at: rec.try_get("started_at")?,
+            completed_at: rec.try_get("completed_at")?,
+            breaks,
+        }))
+    }
+
+    pub fn spawn_reconciliation_job(
+        &self,
+        auto_repair: bool,
+    ) -> JoinHandle<Result<(), TradeError>> {
+        info!("Starting reconciliation thread");
+        let service = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(
+                Self::RECONCILIATION_INTERVAL_SECS,
+            ));
+            loop {
+                interval.tick().await;
+                if let Err(e) = service.run(auto_repair).await {
+                    warn!(error = ?e, "Reconciliation run failed");
+                }
+            }
+        })
+    }
+
+    /// Positions of regular accounts must equal the net of their transactions and split
+    /// adjustments. The system user is seeded without transactions and is checked by
+    /// `check_system_inventory`.
+    async fn check_positions(&self) -> Result<Vec<ReconciliationBreak>, TradeError> {
+        let records = sqlx::query(
+            "WITH net AS (
+                SELECT account_id, ticker, SUM(quantity) AS quantity FROM (
+                    SELECT account_id, ticker,
+                        CASE WHEN order_type = $2 THEN quantity ELSE -quantity END AS quantity
+                    FROM transactions
+                    UNION ALL
+                    SELECT account_id, ticker, quantity_after - quantity_before
+                    FROM corporate_action_adjustments
+                ) movements
+                GROUP BY account_id, ticker
+            )
+            SELECT COALESCE(p.account_id, n.account_id) AS account_id,
+                COALESCE(p.ticker, n.ticker) AS ticker,
+                COALESCE(n.quantity, 0) AS expected, COALESCE(p.quantity, 0) AS actual
+            FROM portfolio p FULL OUTER JOIN net n
+                ON n.account_id = p.account_id AND n.ticker = p.ticker
+            WHERE COALESCE(p.account_id, n.account_id) <> $1
+                AND COALESCE(n.quantity, 0) <> COALESCE(p.quantity, 0)",
+        )
+        .bind(self.system_user_id)
+        .bind(OrderType::Buy)
+        .fetch_all(&self.db)
+        .await?;
+        records
+            .into_iter()
+            .map(|rec| {
+                Ok(ReconciliationBreak {
+                    break_type: BreakType::PositionQuantity,
+                    account_id: rec.try_get("account_id")?,
+                    ticker: rec.try_get("ticker")?,
+                    order_id: None,
+                    expected: rec.try_get("expected")?,
+                    actual: rec.try_get("actual")?,
+                    repaired: false,
+                })
+            })
+            .collect()
+    }
+
+    /// Shares held back from `available_quantity` must match the account's pending sells.
+    async fn check_reserved_quantities(&self) -> Result<Vec<ReconciliationBreak>, TradeError> {
+        let records = sqlx::query(
+            "WITH pending AS (
+                SELECT account_id, ticker, SUM(quantity) AS quantity
+                FROM orders WHERE status = $1 AND order_type = $2
+                GROUP BY account_id, ticker
+            )
+            SELECT COALESCE(p.account_id, s.account_id) AS account_id,
+                COALESCE(p.ticker, s.ticker) AS ticker,
+                COALESCE(s.quantity, 0) AS expected,
+                COALESCE(p.quantity - p.available_quantity, 0) AS actual
+            FROM portfolio p FULL OUTER JOIN pending s
+                ON s.account_id = p.account_id AND s.ticker = p.ticker
+            WHERE COALESCE(s.quantity, 0) <> COALESCE(p.quantity - p.available_quantity, 0)",
+        )
+        .bind(OrderStatus::Pending)
+        .bind(OrderType::Sell)
+        .fetch_all(&self.db)
+        .await?;
+        records
+            .into_iter()
+            .map(|rec| {
+                Ok(ReconciliationBreak {
+                    break_type: BreakType::ReservedQuantity,
+                    account_id: rec.try_get("account_id")?,
+                    ticker: rec.try_get("ticker")?,
+                    order_id: None,
+                    expected: rec.try_get("expected")?,
+                    actual: rec.try_get("actual")?,
+                    repaired: false,
+                })
+            })
+            .collect()
+    }
+
+    /// The system user's seed inventory is not recorded as transactions, so what it holds
+    /// minus its net trades is the inventory it was seeded with, which can never be negative.
+    async fn check_system_inventory(&self) -> Result<Vec<ReconciliationBreak>, TradeError> {
+        let records = sqlx::query(
+            "SELECT ticker, seeded FROM (
+                SELECT p.ticker, p.quantity - COALESCE(SUM(m.quantity), 0) AS seeded
+                FROM portfolio p LEFT JOIN (
+                    SELECT account_id, ticker,
+                        CASE WHEN order_type = $2 THEN quantity ELSE -quantity END AS quantity
+                    FROM transactions
+                    UNION ALL
+                    SELECT account_id, ticker, quantity_after - quantity_before
+                    FROM corporate_action_adjustments
+                ) m ON m.account_id = p.account_id AND m.ticker = p.ticker
+                WHERE p.account_id = $1
+                GROUP BY p.ticker, p.quantity
+            ) inventory
+            WHERE seeded < 0",
+        )
+        .bind(self.system_user_id)
+        .bind(OrderType::Buy)
+        .fetch_all(&self.db)
+        .await?;
+        records
+            .into_iter()
+            .map(|rec| {
+                Ok(ReconciliationBreak {
+                    break_type: BreakType::SystemInventory,
+                    account_id: Some(self.system_user_id),
+                    ticker: rec.try_get("ticker")?,
+                    order_id: None,
+                    expected: BigDecimal::from(0),
+                    actual: rec.try_get("seeded")?,
+                    repaired: false,
+                })
+            })
+            .collect()
+    }
+
+    /// `balance` must equal deposits and other non-trade cash movements plus the net trade cash
+    /// flow from `transactions`, and `available_balance` must equal the ledger's USER_CASH.
+    /// Accounts that predate the ledger were opened with their balance at the time, which
+    /// already includes their earlier trades, so only trades after the opening entry count.
+    async fn check_cash(&self) -> Result<Vec<ReconciliationBreak>, TradeError> {
+        let records = sqlx::query(
+            "WITH ledger AS (
+                SELECT e.account_id,
+                    SUM(l.amount) FILTER (WHERE e.entry_type NOT IN ($1, $2)) AS non_trade,
+                    SUM(l.amount) FILTER (WHERE l.account = $3) AS cash
+                FROM journal_entries e JOIN journal_lines l ON l.entry_id = e.entry_id
+                WHERE l.account IN ($3, $4)
+                GROUP BY e.account_id
+            ), opened AS (
+                SELECT account_id, MIN(created_at) AS opened_at
+                FROM journal_entries WHERE entry_type = $6
+                GROUP BY account_id
+            ), trades AS (
+                SELECT t.account_id, SUM(CASE WHEN t.order_type = $5
+                    THEN -ROUND(t.quantity * t.price_per_share, 4)
+                    ELSE ROUND(t.quantity * t.price_per_share, 4) END) AS cash_flow
+                FROM transactions t LEFT JOIN opened o ON o.account_id = t.account_id
+                WHERE o.opened_at IS NULL OR t.executed_at > o.opened_at
+                GROUP BY t.account_id
+            )
+            SELECT a.account_id, a.balance, a.available_balance,
+                COALESCE(g.non_trade, 0) + COALESCE(t.cash_flow, 0) AS expected_balance,
+                COALESCE(g.cash, 0) AS expected_available
+            FROM accounts a
+                LEFT JOIN ledger g ON g.account_id = a.account_id
+                LEFT JOIN trades t ON t.account_id = a.account_id
+            WHERE a.balance <> COALESCE(g.non_trade, 0) + COALESCE(t.cash_flow, 0)
+                OR a.available_balance <> COALESCE(g.cash, 0)",
+        )
+        .bind(JournalEntryType::TradeBuy)
+        .bind(JournalEntryType::TradeSell)
+        .bind(LedgerAccount::UserCash)
+        .bind(LedgerAccount::UserReserved)
+        .bind(OrderType::Buy)
+        .bind(JournalEntryType::OpeningBalance)
+        .fetch_all(&self.db)
+        .await?;
+        let mut breaks = Vec::new();
+        for rec in records {
+            let account_id: Uuid = rec.try_get("account_id")?;
+            let balance: BigDecimal = rec.try_get("balance")?;
+            let expected_balance: BigDecimal = rec.try_get("expected_balance")?;
+            let available_balance: BigDecimal = rec.try_get("available_balance")?;
+            let expected_available: BigDecimal = rec.try_get("expected_available")?;
+            if balance != expected_balance {
+                breaks.push(ReconciliationBreak {
+                    break_type: BreakType::CashBalance,
+                    account_id: Some(account_id),
+                    ticker: None,
+                    order_id: None,
+                    expected: expected_balance,
+                    actual: balance,
+                    repaired: false,
+                });
+            }
+            if available_balance != expected_available {
+                breaks.push(ReconciliationBreak {
+                    break_type: BreakType::AvailableCash,
+                    account_id: Some(account_id),
+                    ticker: None,
+                    order_id: None,
+                    expected: expected_available,
+                    actual: available_balance,
+                    repaired: false,
+                });
+            }
+        }
+        Ok(breaks)
+    }
+
+    async fn check_order_book(&self) -> Result<Vec<ReconciliationBreak>, TradeError> {
+        let first = Self::diff_order_book(
+            &self.trade_service.get_pending_orders().await?,
+            &self.order_matchbook_service.get_open_orders().await,
+        );
+        if first.is_empty() {
+            return Ok(first);
+        }
+        tokio::time::sleep(tokio::time::Duration::from_millis(
+            Self::ORDER_BOOK_CONFIRMATION_DELAY_MILLIS,
+        ))
+        .await;
+        let second = Self::diff_order_book(
+            &self.trade_service.get_pending_orders().await?,
+            &self.order_matchbook_service.get_open_orders().await,
+        );
+        Ok(Self::confirmed_breaks(&first, second))
+    }
+
+    /// Compares PENDING rows (expected) against the orders resting in the book (actual).
+    pub fn diff_order_book(pending: &[Order], resting: &[Order]) -> Vec<ReconciliationBreak> {
+        let resting_by_id: HashMap<Uuid, &Order> = resting
+            .iter()
+            .map(|order| (order.order_id, order))
+            .collect();
+        let pending_by_id: HashMap<Uuid, &Order> = pending
+            .iter()
+            .map(|order| (order.order_id, order))
+            .collect();
+        let mut breaks = Vec::new();
+        for order in pending {
+            let break_type = match resting_by_id.get(&order.order_id) {
+                None => BreakType::MissingFromOrderBook,
+                Some(resting) if resting.quantity != order.quantity => BreakType::OrderBookQuantity,
+                Some(_) => continue,
+            };
+            breaks.push(ReconciliationBreak {
+                break_type,
+                account_id: Some(order.account_id),
+                ticker: Some(order.ticker.clone()),
+                order_id: Some(order.order_id),
+                expected: order.quantity.clone(),
+                actual: resting_by_id
+                    .get(&order.order_id)
+                    .map(|resting| resting.quantity.clone())
+                    .unwrap_or_default(),
+                repaired: false,
+            });
+        }
+        for order in resting {
+            if !pending_by_id.contains_key(&order.order_id) {
+                breaks.push(ReconciliationBreak {
+                    break_type: BreakType::StaleInOrderBook,
+                    account_id: Some(order.account_id),
+                    ticker: Some(order.ticker.clone()),
+                    order_id: Some(order.order_id),
+                    expected: BigDecimal::from(0),
+                    actual: order.quantity.clone(),
+                    repaired: false,
+                });
+            }
+        }
+        breaks
+    }
+
+    /// Keeps the breaks from the second look that were also seen in the first.
+    pub fn confirmed_breaks(
+        first: &[ReconciliationBreak],
+        second: Vec<ReconciliationBreak>,
+    ) -> Vec<ReconciliationBreak> {
+        second
+            .into_iter()
+            .filter(|candidate| {
+                first.iter().any(|seen| {
+                    seen.break_type == candidate.break_type && seen.order_id == candidate.order_id
+                })
+            })
+            .collect()
+    }
+
+    async fn repair_order_book(
+        &self,
+        breaks: &mut [ReconciliationBreak],
+    ) -> Result<(), TradeError> {
+        for order_break in breaks.iter_mut() {
+            let (Some(order_id), Some(ticker)) = (order_break.order_id, order_break.ticker.clone())
+            else {
+                continue;
+            };
+            order_break.repaired = match order_break.break_type {
+                BreakType::MissingFromOrderBook => {
+                    let order = self.trade_service.get_order(order_id).await?;
+                    //it may have been filled or cancelled since it was checked
+                    if order.status == OrderStatus::Pending {
+                        self.order_matchbook_service.add_order(order).await?;
+                        true
+                    } else {
+                        false
+                    }
+                }
+                BreakType::StaleInOrderBook => {
+                    self.order_matchbook_service
+                        .remove_order(&ticker, order_id)
+                        .await;
+                    true
+                }
+                BreakType::OrderBookQuantity => {
+                    self.order_matchbook_service
+                        .set_order_quantity(&ticker, order_id, &order_break.expected)
+                        .await
+                }
+                _ => false,
+            };
+            if order_break.repaired {
+                info!(
+                    "Repaired {} for order {} in the order book",
+                    order_break.break_type, order_id
+                );
+            }
+        }
+        Ok(())
+    }
+
+    async fn save_report(&self, report: &ReconciliationReport) -> Result<(), TradeError> {
+        let mut tx = self.db.begin().await?;
+        sqlx::query(
+            "INSERT INTO reconciliation_reports (report_id, auto_repair, started_at, completed_at)
+            VALUES ($1, $2, $3, $4)",
+        )
+        .bind(report.report_id)
+        .bind(report.auto_repair)
+        .bind(report.started_at)
+        .bind(report.completed_at)
+        .execute(&mut *tx)
+        .await?;
+        for report_break in &report.breaks {
+            sqlx::query(
+                "INSERT INTO reconciliation_breaks
+                (break_id, report_id, break_type, account_id, ticker, order_id, expected, actual, repaired)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)",
+            )
+            .bind(Uuid::new_v4())
+            .bind(report.report_id)
+            .bind(report_break.break_type)
+            .bind(report_break.account_id)
+            .bind(&report_break.ticker)
+            .bind(report_break.order_id)
+            .bind(&report_break.expected)
+            .bind(&report_break.actual)
+            .bind(report_break.repaired)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+}
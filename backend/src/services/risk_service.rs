@@ -0,0 +1,130 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use bigdecimal::BigDecimal;
+use chrono::{Days, NaiveDate, Utc};
+use num_traits::ToPrimitive;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use sqlx::{PgPool, Row};
+use uuid::Uuid;
+
+use crate::models::errors::trade_error::TradeError;
+use crate::models::risk::{ReturnHistory, RiskParameters, RiskReport};
+use crate::services::portfolio_management_service::PortfolioManagementService;
+
+/// Value-at-risk and expected shortfall of accounts' current positions, from the daily closes
+/// of the tickers they hold.
+pub struct RiskService {
+    db: PgPool,
+    portfolio_service: Arc<PortfolioManagementService>,
+}
+
+impl RiskService {
+    pub fn new(db: PgPool, portfolio_service: Arc<PortfolioManagementService>) -> Self {
+        Self {
+            db,
+            portfolio_service,
+        }
+    }
+
+    #[tracing::instrument(skip(self))]
+    pub async fn get_risk(
+        &self,
+        account_id: Uuid,
+        parameters: &RiskParameters,
+    ) -> Result<RiskReport, TradeError> {
+        let exposures = self
+            .portfolio_service
+            .get_portfolio(account_id)
+            .await?
+            .into_iter()
+            .map(|position| (position.ticker, position.market_value))
+            .collect();
+        self.risk_of(exposures, parameters).await
+    }
+
+    /// Risk of holding `exposures`, the value in each ticker, whether or not an account does.
+    pub async fn risk_of(
+        &self,
+        exposures: Vec<(String, BigDecimal)>,
+        parameters: &RiskParameters,
+    ) -> Result<RiskReport, TradeError> {
+        parameters
+            .validate()
+            .map_err(TradeError::InvalidRiskParameters)?;
+        let exposures = exposures
+            .into_iter()
+            .map(|(ticker, value)| (ticker, value.to_f64().unwrap_or_default()))
+            .collect();
+        let history = self
+            .return_history(exposures, parameters.lookback_days)
+            .await?;
+        let mut rng = match parameters.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        //a Monte Carlo run takes long enough to stall the other requests on this worker
+        let parameters = parameters.clone();
+        match tokio::task::spawn_blocking(move || history.report(&parameters, &mut rng)).await {
+            Ok(report) => Ok(report),
+            Err(e) => std::panic::resume_unwind(e.into_panic()),
+        }
+    }
+
+    /// Daily returns over the last `lookback_days` for the days every ticker has a close.
+    pub async fn return_history(
+        &self,
+        exposures: Vec<(String, f64)>,
+        lookback_days: u32,
+    ) -> Result<ReturnHistory, TradeError> {
+        let tickers: Vec<String> = exposures.iter().map(|(ticker, _)| ticker.clone()).collect();
+        let since = Utc::now().date_naive() - Days::new(lookback_days as u64);
+        let mut closes: HashMap<String, BTreeMap<NaiveDate, f64>> = HashMap::new();
+        let price_records = sqlx::query(
+            "SELECT DISTINCT ON (ticker, (date AT TIME ZONE 'UTC')::date)
+                ticker, (date AT TIME ZONE 'UTC')::date AS day, close
+            FROM stock_prices
+            WHERE ticker = ANY($1) AND date <= NOW() AND close > 0
+                AND (date AT TIME ZONE 'UTC')::date >= $2
+            ORDER BY ticker, (date AT TIME ZONE 'UTC')::date, date DESC",
+        )
+        .bind(&tickers)
+        .bind(since)
+        .fetch_all(&self.db)
+        .await?;
+        for rec in price_records {
+            let close: BigDecimal = rec.try_get("close")?;
+            closes
+                .entry(rec.try_get("ticker")?)
+                .or_default()
+                .insert(rec.try_get("day")?, close.to_f64().unwrap_or_default());
+        }
+
+        let days: Vec<NaiveDate> = match closes.get(tickers.first().map_or("", |t| t.as_str())) {
+            Some(first) => first
+                .keys()
+                .filter(|day| {
+                    tickers
+                        .iter()
+                        .all(|t| closes.get(t).is_some_and(|c| c.contains_key(day)))
+                })
+                .copied()
+                .collect(),
+            None => Vec::new(),
+        };
+        let returns = days
+            .windows(2)
+            .map(|pair| {
+                tickers
+                    .iter()
+                    .map(|ticker| closes[ticker][&pair[1]] / closes[ticker][&pair[0]] - 1.0)
+                    .collect()
+            })
+            .collect();
+        Ok(ReturnHistory {
+            exposures: exposures.into_iter().map(|(_, value)| value).collect(),
+            returns,
+        })
+    }
+}
@@ -0,0 +1,207 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+
+use bigdecimal::BigDecimal;
+use num_traits::Zero;
+use rand::Rng;
+use sqlx::{PgPool, Row};
+use uuid::Uuid;
+
+use crate::models::errors::trade_error::TradeError;
+use crate::models::errors::user_error::UserError;
+use crate::models::simulation::{
+    PortfolioProjection, ProjectedHolding, SimulatedFill, SimulationRequest, SimulationResult,
+};
+use crate::models::tax_lot::TaxLot;
+use crate::services::account_management_service::AccountManagementService;
+use crate::services::bankruptcy_service::BankruptcyService;
+use crate::services::fee_service::FeeService;
+use crate::services::loan_service::LoanService;
+use crate::services::portfolio_management_service::PortfolioManagementService;
+use crate::services::risk_service::RiskService;
+
+/// Projects what hypothetical orders would do to an account without placing them. Nothing is
+/// written: the account is read once and the orders are filled against a copy of it.
+pub struct SimulationService {
+    db: PgPool,
+    portfolio_service: Arc<PortfolioManagementService>,
+    account_management_service: Arc<AccountManagementService>,
+    fee_service: Arc<FeeService>,
+    risk_service: Arc<RiskService>,
+    loan_service: Arc<LoanService>,
+}
+
+impl SimulationService {
+    pub fn new(
+        db: PgPool,
+        portfolio_service: Arc<PortfolioManagementService>,
+        account_management_service: Arc<AccountManagementService>,
+        fee_service: Arc<FeeService>,
+        risk_service: Arc<RiskService>,
+        loan_service: Arc<LoanService>,
+    ) -> Self {
+        Self {
+            db,
+            portfolio_service,
+            account_management_service,
+            fee_service,
+            risk_service,
+            loan_service,
+        }
+    }
+
+    /// Fills the orders in turn, each fully at its price and charged its largest possible fee,
+    /// and compares the account before and after.
+    #[tracing::instrument(skip(self))]
+    pub async fn simulate(
+        &self,
+        account_id: Uuid,
+        mut request: SimulationRequest,
+    ) -> Result<SimulationResult, TradeError> {
+        if request.orders.is_empty() {
+            return Err(TradeError::InvalidSimulation(
+                "at least one order is needed".to_string(),
+            ));
+        }
+        let account = sqlx::query(
+            "SELECT user_id, balance, available_balance, cost_basis_method FROM accounts
+            WHERE account_id = $1",
+        )
+        .bind(account_id)
+        .fetch_optional(&self.db)
+        .await?
+        .ok_or(UserError::AccountNotFound)?;
+        let user_id: Uuid = account.try_get("user_id")?;
+
+        let mut lots: HashMap<String, Vec<TaxLot>> = HashMap::new();
+        for lot in self
+            .portfolio_service
+            .get_tax_lots(account_id, None)
+            .await?
+        {
+            lots.entry(lot.ticker.clone()).or_default().push(lot);
+        }
+        let mut holdings = BTreeMap::new();
+        for position in self.portfolio_service.get_portfolio(account_id).await? {
+            if position.quantity.is_zero() {
+                continue;
+            }
+            holdings.insert(
+                position.ticker.clone(),
+                ProjectedHolding {
+                    price: &position.market_value / &position.quantity,
+                    lots: lots.remove(&position.ticker).unwrap_or_default(),
+                    quantity: position.quantity,
+                    available_quantity: position.available_quantity,
+                    cost_basis: position.total_money_spent,
+                },
+            );
+        }
+        let mut projection = PortfolioProjection {
+            account_id,
+            cost_basis_method: account.try_get("cost_basis_method")?,
+            cash: account.try_get("balance")?,
+            available_cash: account.try_get("available_balance")?,
+            holdings,
+            realized_pnl: BigDecimal::zero(),
+        };
+        let before = projection.clone();
+
+        let mut fills = Vec::with_capacity(request.orders.len());
+        for order in request.orders.drain(..) {
+            let ticker = order.ticker.trim().to_uppercase();
+            let price_per_share = match order.price_per_share {
+                Some(price) => price,
+                None => match projection.holdings.get(&ticker) {
+                    Some(holding) => holding.price.clone(),
+                    None => {
+                        self.portfolio_service
+                            .ticker_service
+                            .get_quote(&ticker)
+                            .await
+                            .map_err(|_| {
+                                TradeError::InvalidSimulation(format!("{} has no price", ticker))
+                            })?
+                            .close
+                    }
+                },
+            };
+            let fill = SimulatedFill {
+                estimated_fee: self
+                    .fee_service
+                    .max_order_fee(account_id, &order.quantity, &price_per_share)
+                    .await?,
+                ticker,
+                order_type: order.order_type,
+                quantity: order.quantity,
+                price_per_share,
+            };
+            projection
+                .apply(&fill)
+                .map_err(TradeError::InvalidSimulation)?;
+            fills.push(fill);
+        }
+
+        //the same draws for both runs, so a Monte Carlo difference comes from the orders
+        let mut risk = request.risk;
+        risk.seed = Some(risk.seed.unwrap_or_else(|| rand::thread_rng().gen()));
+        let risk_before = self.risk_service.risk_of(before.exposures(), &risk).await?;
+        let risk_after = self
+            .risk_service
+            .risk_of(projection.exposures(), &risk)
+            .await?;
+
+        let (before_state, after_state) = (before.state(), projection.state());
+        let (credit_score_before, credit_score_after) =
+            match self.loan_service.get_loan(user_id).await {
+                Ok(loan) => {
+                    let (principal, interest) = loan.get_current_balance();
+                    let liabilities = principal + interest;
+                    //the user's other accounts count towards the score as they are
+                    let mut other_assets = BigDecimal::zero();
+                    for other in self
+                        .account_management_service
+                        .list_accounts(user_id)
+                        .await?
+                        .into_iter()
+                        .filter(|other| other.account_id != account_id)
+                    {
+                        other_assets += other.available_balance;
+                        other_assets += self
+                            .portfolio_service
+                            .get_total_portfolio_value(other.account_id)
+                            .await?;
+                    }
+                    let score = |available_cash: &BigDecimal, positions_value: &BigDecimal| {
+                        BankruptcyService::calculate_credit_score(
+                            &other_assets + available_cash + positions_value,
+                            liabilities.clone(),
+                        )
+                    };
+                    (
+                        Some(score(
+                            &before_state.available_cash,
+                            &before_state.positions_value,
+                        )),
+                        Some(score(
+                            &after_state.available_cash,
+                            &after_state.positions_value,
+                        )),
+                    )
+                }
+                Err(UserError::UserDoesNotHaveLoan) => (None, None),
+                Err(e) => return Err(e.into()),
+            };
+
+        Ok(SimulationResult {
+            orders: fills,
+            before: before_state,
+            after: after_state,
+            realized_pnl: projection.realized_pnl,
+            risk_before,
+            risk_after,
+            credit_score_before,
+            credit_score_after,
+        })
+    }
+}
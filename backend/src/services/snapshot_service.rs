@@ -0,0 +1,527 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::Arc;
+
+use bigdecimal::{BigDecimal, One, RoundingMode, Zero};
+use chrono::{DateTime, Days, NaiveDate, NaiveTime, Utc};
+use sqlx::{PgPool, Postgres, Row, Transaction};
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::models::corporate_action::CorporateActionStatus;
+use crate::models::errors::trade_error::TradeError;
+use crate::models::errors::user_error::UserError;
+use crate::models::ledger::LedgerAccount;
+use crate::models::order::OrderType;
+use crate::models::portfolio_ticker::PortfolioHistoryPoint;
+use crate::models::snapshot::{PortfolioSnapshot, SnapshotPosition, SnapshotRun};
+use crate::models::stock_ticker::TimeFrame;
+use crate::services::portfolio_management_service::PortfolioManagementService;
+
+/// A split processed at `processed_at` that turned `old_shares` of `ticker` into `new_shares`.
+struct Split {
+    ticker: String,
+    processed_at: DateTime<Utc>,
+    ratio: BigDecimal,
+}
+
+/// Keeps an end-of-day snapshot of every account so history is read rather than rebuilt.
+/// Only today is valued live.
+#[derive(Clone)]
+pub struct SnapshotService {
+    db: PgPool,
+    portfolio_service: Arc<PortfolioManagementService>,
+    system_user_id: Uuid,
+}
+
+impl SnapshotService {
+    const SNAPSHOT_INTERVAL_SECS: u64 = 60 * 60;
+    const SCALE: i64 = 4;
+
+    pub fn new(
+        db: PgPool,
+        portfolio_service: Arc<PortfolioManagementService>,
+        system_user_id: Uuid,
+    ) -> Self {
+        Self {
+            db,
+            portfolio_service,
+            system_user_id,
+        }
+    }
+
+    fn end_of(day: NaiveDate) -> DateTime<Utc> {
+        (day + Days::new(1)).and_time(NaiveTime::MIN).and_utc()
+    }
+
+    /// Rebuilds what the account held and was worth at the end of each day from `from` to `to`
+    /// out of the ledger, its trades and the price history, without saving anything.
+    pub async fn rebuild(
+        &self,
+        account_id: Uuid,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<PortfolioSnapshot>, TradeError> {
+        let cash_changes: BTreeMap<NaiveDate, BigDecimal> = sqlx::query(
+            "SELECT (e.created_at AT TIME ZONE 'UTC')::date AS day, SUM(l.amount) AS amount
+            FROM journal_entries e JOIN journal_lines l ON l.entry_id = e.entry_id
+            WHERE e.account_id = $1 AND l.account IN ($2, $3)
+                AND e.created_at < $4
+            GROUP BY 1",
+        )
+        .bind(account_id)
+        .bind(LedgerAccount::UserCash)
+        .bind(LedgerAccount::UserReserved)
+        .bind(Self::end_of(to))
+        .fetch_all(&self.db)
+        .await?
+        .iter()
+        .map(|rec| Ok((rec.try_get("day")?, rec.try_get("amount")?)))
+        .collect::<Result<_, sqlx::Error>>()?;
+
+        let trades = sqlx::query(
+            "SELECT ticker, order_type, quantity, executed_at FROM transactions
+            WHERE account_id = $1 AND executed_at < $2 ORDER BY executed_at",
+        )
+        .bind(account_id)
+        .bind(Self::end_of(to))
+        .fetch_all(&self.db)
+        .await?
+        .iter()
+        .map(|rec| {
+            let quantity: BigDecimal = rec.try_get("quantity")?;
+            let signed = match rec.try_get("order_type")? {
+                OrderType::Buy => quantity,
+                OrderType::Sell => -quantity,
+            };
+            Ok((
+                rec.try_get::<String, _>("ticker")?,
+                rec.try_get::<DateTime<Utc>, _>("executed_at")?,
+                signed,
+            ))
+        })
+        .collect::<Result<Vec<_>, sqlx::Error>>()?;
+        let tickers: Vec<String> = trades
+            .iter()
+            .map(|(ticker, _, _)| ticker.clone())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+
+        let splits = sqlx::query(
+            "SELECT ticker, processed_at, new_shares, old_shares FROM corporate_actions
+            WHERE status = $1 AND new_shares IS NOT NULL AND ticker = ANY($2)",
+        )
+        .bind(CorporateActionStatus::Processed)
+        .bind(&tickers)
+        .fetch_all(&self.db)
+        .await?
+        .iter()
+        .map(|rec| {
+            Ok(Split {
+                ticker: rec.try_get("ticker")?,
+                processed_at: rec.try_get("processed_at")?,
+                ratio: BigDecimal::from(rec.try_get::<i32, _>("new_shares")?)
+                    / BigDecimal::from(rec.try_get::<i32, _>("old_shares")?),
+            })
+        })
+        .collect::<Result<Vec<_>, sqlx::Error>>()?;
+        //how much the splits of a ticker between two moments multiplied its share count by
+        let split_factor = |ticker: &str, after: DateTime<Utc>, before: Option<DateTime<Utc>>| {
+            splits
+                .iter()
+                .filter(|split| {
+                    split.ticker == ticker
+                        && split.processed_at > after
+                        && before.is_none_or(|before| split.processed_at < before)
+                })
+                .fold(BigDecimal::one(), |factor, split| factor * &split.ratio)
+        };
+
+        //stock_prices is restated in today's shares whenever a split is processed
+        let mut closes: HashMap<String, BTreeMap<NaiveDate, BigDecimal>> = HashMap::new();
+        let price_records = sqlx::query(
+            "SELECT DISTINCT ON (ticker, (date AT TIME ZONE 'UTC')::date)
+                ticker, (date AT TIME ZONE 'UTC')::date AS day, close
+            FROM stock_prices
+            WHERE ticker = ANY($1) AND date < $2 AND date <= NOW() AND close IS NOT NULL
+            ORDER BY ticker, (date AT TIME ZONE 'UTC')::date, date DESC",
+        )
+        .bind(&tickers)
+        .bind(Self::end_of(to))
+        .fetch_all(&self.db)
+        .await?;
+        for rec in price_records {
+            closes
+                .entry(rec.try_get("ticker")?)
+                .or_default()
+                .insert(rec.try_get("day")?, rec.try_get("close")?);
+        }
+
+        let mut cash: BigDecimal = cash_changes.range(..from).map(|(_, amount)| amount).sum();
+        let mut snapshots = Vec::new();
+        for day in from.iter_days().take_while(|day| *day <= to) {
+            if let Some(change) = cash_changes.get(&day) {
+                cash += change;
+            }
+            let end = Self::end_of(day);
+            let mut positions = Vec::new();
+            for ticker in &tickers {
+                let quantity: BigDecimal = trades
+                    .iter()
+                    .filter(|(t, executed_at, _)| t == ticker && *executed_at < end)
+                    .map(|(_, executed_at, signed)| {
+                        signed * split_factor(ticker, *executed_at, Some(end))
+                    })
+                    .sum::<BigDecimal>()
+                    .with_scale_round(Self::SCALE, RoundingMode::HalfUp);
+                if quantity <= BigDecimal::zero() {
+                    continue;
+                }
+                let price = closes
+                    .get(ticker)
+                    .and_then(|c| c.range(..=day).next_back())
+                    .map(|(_, close)| close * split_factor(ticker, end, None))
+                    .unwrap_or_default()
+                    .with_scale_round(Self::SCALE, RoundingMode::HalfUp);
+                positions.push(SnapshotPosition {
+                    ticker: ticker.clone(),
+                    market_value: (&quantity * &price)
+                        .with_scale_round(Self::SCALE, RoundingMode::HalfUp),
+                    quantity,
+                    price,
+                });
+            }
+            snapshots.push(Self::snapshot(account_id, day, cash.clone(), positions));
+        }
+        Ok(snapshots)
+    }
+
+    fn snapshot(
+        account_id: Uuid,
+        snapshot_date: NaiveDate,
+        cash: BigDecimal,
+        positions: Vec<SnapshotPosition>,
+    ) -> PortfolioSnapshot {
+        let positions_value: BigDecimal = positions.iter().map(|p| &p.market_value).sum();
+        PortfolioSnapshot {
+            account_id,
+            snapshot_date,
+            total_value: &cash + &positions_value,
+            cash,
+            positions_value,
+            positions,
+        }
+    }
+
+    /// The account as it stands right now, valued at the latest prices.
+    pub async fn live_snapshot(&self, account_id: Uuid) -> Result<PortfolioSnapshot, TradeError> {
+        let cash: BigDecimal = sqlx::query(
+            "SELECT COALESCE(SUM(l.amount), 0) AS cash
+            FROM journal_entries e JOIN journal_lines l ON l.entry_id = e.entry_id
+            WHERE e.account_id = $1 AND l.account IN ($2, $3)",
+        )
+        .bind(account_id)
+        .bind(LedgerAccount::UserCash)
+        .bind(LedgerAccount::UserReserved)
+        .fetch_one(&self.db)
+        .await?
+        .try_get("cash")?;
+        let positions = self
+            .portfolio_service
+            .get_portfolio(account_id)
+            .await?
+            .into_iter()
+            .map(|position| SnapshotPosition {
+                price: (&position.market_value / &position.quantity)
+                    .with_scale_round(Self::SCALE, RoundingMode::HalfUp),
+                ticker: position.ticker,
+                quantity: position.quantity,
+                market_value: position.market_value,
+            })
+            .collect();
+        Ok(Self::snapshot(
+            account_id,
+            Utc::now().date_naive(),
+            cash,
+            positions,
+        ))
+    }
+
+    async fn insert_snapshot(
+        tx: &mut Transaction<'_, Postgres>,
+        snapshot: &PortfolioSnapshot,
+    ) -> Result<bool, TradeError> {
+        let inserted = sqlx::query(
+            "INSERT INTO portfolio_snapshots
+                (account_id, snapshot_date, cash, positions_value, total_value)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (account_id, snapshot_date) DO NOTHING",
+        )
+        .bind(snapshot.account_id)
+        .bind(snapshot.snapshot_date)
+        .bind(&snapshot.cash)
+        .bind(&snapshot.positions_value)
+        .bind(&snapshot.total_value)
+        .execute(&mut **tx)
+        .await?
+        .rows_affected();
+        if inserted == 0 {
+            return Ok(false);
+        }
+        for position in &snapshot.positions {
+            sqlx::query(
+                "INSERT INTO portfolio_snapshot_positions
+                    (account_id, snapshot_date, ticker, quantity, price, market_value)
+                VALUES ($1, $2, $3, $4, $5, $6)",
+            )
+            .bind(snapshot.account_id)
+            .bind(snapshot.snapshot_date)
+            .bind(&position.ticker)
+            .bind(&position.quantity)
+            .bind(&position.price)
+            .bind(&position.market_value)
+            .execute(&mut **tx)
+            .await?;
+        }
+        Ok(true)
+    }
+
+    /// Accounts open by the end of `day`, or just `account_id` if given. The system user that
+    /// runs the market maker has no history worth keeping.
+    async fn accounts_open_on(
+        &self,
+        day: NaiveDate,
+        account_id: Option<Uuid>,
+    ) -> Result<Vec<(Uuid, NaiveDate)>, TradeError> {
+        sqlx::query(
+            "SELECT account_id, (created_at AT TIME ZONE 'UTC')::date AS opened_on FROM accounts
+            WHERE user_id <> $1 AND created_at < $2 AND ($3::uuid IS NULL OR account_id = $3)",
+        )
+        .bind(self.system_user_id)
+        .bind(Self::end_of(day))
+        .bind(account_id)
+        .fetch_all(&self.db)
+        .await?
+        .iter()
+        .map(|rec| Ok((rec.try_get("account_id")?, rec.try_get("opened_on")?)))
+        .collect::<Result<_, sqlx::Error>>()
+        .map_err(TradeError::from)
+    }
+
+    /// Snapshots every account at the end of `day`. Accounts already snapshotted that day are
+    /// left alone, so the job can rerun safely.
+    #[tracing::instrument(skip(self))]
+    pub async fn take_snapshots(&self, day: NaiveDate) -> Result<SnapshotRun, TradeError> {
+        let mut run = SnapshotRun {
+            accounts_snapshotted: 0,
+            snapshots_written: 0,
+        };
+        for (account_id, _) in self.accounts_open_on(day, None).await? {
+            let exists = sqlx::query(
+                "SELECT 1 FROM portfolio_snapshots WHERE account_id = $1 AND snapshot_date = $2",
+            )
+            .bind(account_id)
+            .bind(day)
+            .fetch_optional(&self.db)
+            .await?
+            .is_some();
+            if exists {
+                continue;
+            }
+            let snapshots = self.rebuild(account_id, day, day).await?;
+            let mut tx = self.db.begin().await?;
+            for snapshot in &snapshots {
+                if Self::insert_snapshot(&mut tx, snapshot).await? {
+                    run.snapshots_written += 1;
+                }
+            }
+            tx.commit().await?;
+            run.accounts_snapshotted += 1;
+        }
+        info!(
+            "Wrote {} portfolio snapshots for {}",
+            run.snapshots_written, day
+        );
+        Ok(run)
+    }
+
+    /// Snapshots the last full day, which is what the background job does every run.
+    pub async fn snapshot_previous_day(&self) -> Result<SnapshotRun, TradeError> {
+        self.take_snapshots(Utc::now().date_naive() - Days::new(1))
+            .await
+    }
+
+    pub fn spawn_snapshot_job(&self) -> JoinHandle<Result<(), TradeError>> {
+        info!("Starting portfolio snapshot thread");
+        let service = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(
+                Self::SNAPSHOT_INTERVAL_SECS,
+            ));
+            loop {
+                interval.tick().await;
+                if let Err(e) = service.snapshot_previous_day().await {
+                    warn!(error = ?e, "Portfolio snapshot failed");
+                }
+            }
+        })
+    }
+
+    /// Fills in every missing snapshot from the day each account opened to yesterday, for all
+    /// accounts or just `account_id`. Used to seed history for accounts older than the job.
+    #[tracing::instrument(skip(self))]
+    pub async fn backfill(&self, account_id: Option<Uuid>) -> Result<SnapshotRun, TradeError> {
+        let yesterday = Utc::now().date_naive() - Days::new(1);
+        let mut run = SnapshotRun {
+            accounts_snapshotted: 0,
+            snapshots_written: 0,
+        };
+        for (account_id, opened_on) in self.accounts_open_on(yesterday, account_id).await? {
+            let taken: BTreeSet<NaiveDate> = self
+                .stored_snapshots(account_id, opened_on, yesterday)
+                .await?
+                .into_iter()
+                .map(|snapshot| snapshot.snapshot_date)
+                .collect();
+            let Some(first_missing) = opened_on
+                .iter_days()
+                .take_while(|day| *day <= yesterday)
+                .find(|day| !taken.contains(day))
+            else {
+                continue;
+            };
+            let mut tx = self.db.begin().await?;
+            for snapshot in self.rebuild(account_id, first_missing, yesterday).await? {
+                if !taken.contains(&snapshot.snapshot_date)
+                    && Self::insert_snapshot(&mut tx, &snapshot).await?
+                {
+                    run.snapshots_written += 1;
+                }
+            }
+            tx.commit().await?;
+            run.accounts_snapshotted += 1;
+        }
+        info!(
+            "Backfilled {} portfolio snapshots across {} accounts",
+            run.snapshots_written, run.accounts_snapshotted
+        );
+        Ok(run)
+    }
+
+    /// Stored snapshots without their positions.
+    async fn stored_snapshots(
+        &self,
+        account_id: Uuid,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<PortfolioSnapshot>, TradeError> {
+        sqlx::query(
+            "SELECT snapshot_date, cash, positions_value, total_value FROM portfolio_snapshots
+            WHERE account_id = $1 AND snapshot_date BETWEEN $2 AND $3
+            ORDER BY snapshot_date",
+        )
+        .bind(account_id)
+        .bind(from)
+        .bind(to)
+        .fetch_all(&self.db)
+        .await?
+        .iter()
+        .map(|rec| {
+            Ok(PortfolioSnapshot {
+                account_id,
+                snapshot_date: rec.try_get("snapshot_date")?,
+                cash: rec.try_get("cash")?,
+                positions_value: rec.try_get("positions_value")?,
+                total_value: rec.try_get("total_value")?,
+                positions: Vec::new(),
+            })
+        })
+        .collect::<Result<_, sqlx::Error>>()
+        .map_err(TradeError::from)
+    }
+
+    /// One snapshot per day from `from` to `to`. Past days come from the stored snapshots, or
+    /// are rebuilt if the job missed them, and today is valued live. Past days have no positions.
+    pub async fn daily_snapshots(
+        &self,
+        account_id: Uuid,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<PortfolioSnapshot>, TradeError> {
+        let today = Utc::now().date_naive();
+        let last_past_day = to.min(today - Days::new(1));
+        let mut snapshots: BTreeMap<NaiveDate, PortfolioSnapshot> = BTreeMap::new();
+        if from <= last_past_day {
+            snapshots.extend(
+                self.stored_snapshots(account_id, from, last_past_day)
+                    .await?
+                    .into_iter()
+                    .map(|snapshot| (snapshot.snapshot_date, snapshot)),
+            );
+            let missing: Vec<NaiveDate> = from
+                .iter_days()
+                .take_while(|day| *day <= last_past_day)
+                .filter(|day| !snapshots.contains_key(day))
+                .collect();
+            if let (Some(first), Some(last)) = (missing.first(), missing.last()) {
+                for mut snapshot in self.rebuild(account_id, *first, *last).await? {
+                    snapshot.positions.clear();
+                    snapshots.entry(snapshot.snapshot_date).or_insert(snapshot);
+                }
+            }
+        }
+        if from <= today && today <= to {
+            snapshots.insert(today, self.live_snapshot(account_id).await?);
+        }
+        Ok(snapshots.into_values().collect())
+    }
+
+    /// First day of `timeframe` that the account was open.
+    pub async fn timeframe_start(
+        &self,
+        account_id: Uuid,
+        timeframe: TimeFrame,
+    ) -> Result<NaiveDate, TradeError> {
+        let opened_at: DateTime<Utc> =
+            sqlx::query("SELECT created_at FROM accounts WHERE account_id = $1")
+                .bind(account_id)
+                .fetch_optional(&self.db)
+                .await?
+                .ok_or(UserError::AccountNotFound)?
+                .try_get("created_at")?;
+        let today = Utc::now().date_naive();
+        Ok(match timeframe.lookback_days() {
+            Some(days) => (today - Days::new(days as u64 - 1)).max(opened_at.date_naive()),
+            None => opened_at.date_naive(),
+        })
+    }
+
+    /// The account's value at the end of each day of `timeframe` since it opened, ending with
+    /// its value right now.
+    #[tracing::instrument(skip(self))]
+    pub async fn get_portfolio_history(
+        &self,
+        account_id: Uuid,
+        timeframe: TimeFrame,
+    ) -> Result<Vec<PortfolioHistoryPoint>, TradeError> {
+        let from = self.timeframe_start(account_id, timeframe).await?;
+        let now = Utc::now();
+        let today = now.date_naive();
+        Ok(self
+            .daily_snapshots(account_id, from, today)
+            .await?
+            .into_iter()
+            .map(|snapshot| PortfolioHistoryPoint {
+                date: if snapshot.snapshot_date == today {
+                    now
+                } else {
+                    snapshot.snapshot_date.and_time(NaiveTime::MIN).and_utc()
+                },
+                cash: snapshot.cash,
+                positions_value: snapshot.positions_value,
+                total_value: snapshot.total_value,
+            })
+            .collect())
+    }
+}
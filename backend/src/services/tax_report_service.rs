@@ -0,0 +1,83 @@
+use chrono::{Duration, NaiveDate, NaiveTime};
+use sqlx::{PgPool, Row};
+use uuid::Uuid;
+
+use crate::models::errors::trade_error::TradeError;
+use crate::models::order::OrderType;
+use crate::models::tax_report::{Disposal, Purchase, TaxReport};
+
+/// Realized capital gains per tax year, from the lots each sell fill closed and the buy fills
+/// in `transactions` that may make a loss a wash sale.
+pub struct TaxReportService {
+    db: PgPool,
+}
+
+impl TaxReportService {
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+
+    #[tracing::instrument(skip(self))]
+    pub async fn get_report(&self, account_id: Uuid, year: i32) -> Result<TaxReport, TradeError> {
+        let year_start = |year: i32| {
+            NaiveDate::from_ymd_opt(year, 1, 1)
+                .map(|day| day.and_time(NaiveTime::MIN).and_utc())
+                .ok_or_else(|| TradeError::InvalidTaxYear(year.to_string()))
+        };
+        let from = year_start(year)?;
+        let to = year_start(year + 1)?;
+
+        let disposals = sqlx::query(
+            "SELECT g.ticker, g.order_id, g.lot_id, l.order_id AS lot_order_id, g.acquired_at,
+                g.realized_at, g.quantity, g.proceeds, g.cost_basis
+            FROM realized_gains g LEFT JOIN tax_lots l ON l.lot_id = g.lot_id
+            WHERE g.account_id = $1 AND g.realized_at >= $2 AND g.realized_at < $3
+            ORDER BY g.realized_at, g.ticker",
+        )
+        .bind(account_id)
+        .bind(from)
+        .bind(to)
+        .fetch_all(&self.db)
+        .await?
+        .into_iter()
+        .map(|rec| {
+            Ok(Disposal {
+                ticker: rec.try_get("ticker")?,
+                order_id: rec.try_get("order_id")?,
+                lot_id: rec.try_get("lot_id")?,
+                lot_order_id: rec.try_get("lot_order_id")?,
+                acquired_at: rec.try_get("acquired_at")?,
+                disposed_at: rec.try_get("realized_at")?,
+                quantity: rec.try_get("quantity")?,
+                proceeds: rec.try_get("proceeds")?,
+                cost_basis: rec.try_get("cost_basis")?,
+            })
+        })
+        .collect::<Result<Vec<_>, TradeError>>()?;
+
+        let window = Duration::days(TaxReport::WASH_SALE_DAYS);
+        let purchases = sqlx::query(
+            "SELECT ticker, order_id, executed_at, quantity FROM transactions
+            WHERE account_id = $1 AND order_type = $2 AND executed_at >= $3 AND executed_at < $4
+            ORDER BY executed_at",
+        )
+        .bind(account_id)
+        .bind(OrderType::Buy)
+        .bind(from - window)
+        .bind(to + window)
+        .fetch_all(&self.db)
+        .await?
+        .into_iter()
+        .map(|rec| {
+            Ok(Purchase {
+                ticker: rec.try_get("ticker")?,
+                order_id: rec.try_get("order_id")?,
+                executed_at: rec.try_get("executed_at")?,
+                quantity: rec.try_get("quantity")?,
+            })
+        })
+        .collect::<Result<Vec<_>, TradeError>>()?;
+
+        Ok(TaxReport::compute(account_id, year, disposals, &purchases))
+    }
+}
@@ -1,17 +1,18 @@
 use crate::models::errors::ticker_error::TickerError;
 use crate::models::errors::trade_error::TradeError;
-use crate::models::stock_ticker::Ticker;
+use crate::models::instrument::{Instrument, InstrumentDetails};
+use crate::models::price_bar::{BarInterval, PriceBar};
 use crate::models::stock_ticker::TimeFrame;
-use alpha_vantage::ApiClient;
+use crate::models::stock_ticker::{QuoteCacheStats, Ticker};
+use crate::services::market_data_provider::MarketDataProvider;
 use bigdecimal::BigDecimal;
-use chrono::Utc;
-use num_traits::FromPrimitive;
+use chrono::{DateTime, Utc};
 use num_traits::Zero;
 use sqlx::PgPool;
 use sqlx::Row;
 use std::collections::HashMap;
-use std::str::FromStr;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
 use tracing::debug;
@@ -19,18 +20,159 @@ use tracing::info;
 
 #[derive(Clone)]
 pub struct TickerService {
-    pub api_client: Arc<ApiClient>,
+    pub market_data: Arc<dyn MarketDataProvider>,
     pub mock_db: PgPool,
     pub cache: Arc<RwLock<HashMap<String, (Ticker, Instant)>>>,
+    cache_ttl: Duration,
+    /// Bumped for a ticker whenever its quote is invalidated, so a read that was already in
+    /// flight doesn't put the old quote back in the cache.
+    generations: Arc<Mutex<HashMap<String, u64>>>,
+    cache_hits: Arc<AtomicU64>,
+    cache_misses: Arc<AtomicU64>,
+}
+
+fn ticker_from_row(rec: &sqlx::postgres::PgRow) -> Result<Ticker, sqlx::Error> {
+    Ok(Ticker {
+        ticker: rec.try_get("ticker")?,
+        date: rec.try_get("date")?,
+        close: rec.try_get("close")?,
+        volume: rec.try_get("volume")?,
+        open: rec.try_get("open")?,
+        high: rec.try_get("high")?,
+        low: rec.try_get("low")?,
+    })
 }
 
 #[allow(dead_code)]
 impl TickerService {
-    pub fn new(api_key: &str, mock_db: PgPool) -> Self {
+    /// How long a quote is served from memory. Trades and market maker price moves drop the
+    /// ticker's quote straight away, so this only bounds staleness from writes made elsewhere.
+    pub const QUOTE_CACHE_TTL: Duration = Duration::from_secs(30);
+    const MAX_PRICE_BARS: i64 = 1000;
+
+    pub fn new(market_data: Arc<dyn MarketDataProvider>, mock_db: PgPool) -> Self {
+        Self::with_cache_ttl(market_data, mock_db, Self::QUOTE_CACHE_TTL)
+    }
+
+    pub fn with_cache_ttl(
+        market_data: Arc<dyn MarketDataProvider>,
+        mock_db: PgPool,
+        cache_ttl: Duration,
+    ) -> Self {
         Self {
-            api_client: Arc::new(alpha_vantage::set_api(api_key, reqwest::Client::new())),
+            market_data,
             mock_db,
             cache: Arc::new(RwLock::new(HashMap::new())),
+            cache_ttl,
+            generations: Arc::new(Mutex::new(HashMap::new())),
+            cache_hits: Arc::new(AtomicU64::new(0)),
+            cache_misses: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    /// The latest quote for `ticker`, from the cache while it is fresh.
+    pub async fn get_quote(&self, ticker: &str) -> Result<Ticker, TradeError> {
+        if let Some(quote) = self.cached_quote(ticker).await {
+            return Ok(quote);
+        }
+        let generation = self.generation(ticker);
+        let quote = self.fetch_latest_price_ticker_from_db(ticker).await?;
+        let mut cache = self.cache.write().await;
+        if self.generation(ticker) == generation {
+            cache.insert(ticker.to_string(), (quote.clone(), Instant::now()));
+        }
+        Ok(quote)
+    }
+
+    /// The latest quotes for `tickers`, keyed by ticker, reading whatever is not cached in one
+    /// query. Tickers without any prices are left out.
+    pub async fn get_quotes(&self, tickers: &[String]) -> Result<HashMap<String, Ticker>, TradeError> {
+        let mut quotes = HashMap::new();
+        let mut missing = Vec::new();
+        for ticker in tickers {
+            if quotes.contains_key(ticker) || missing.contains(ticker) {
+                continue;
+            }
+            match self.cached_quote(ticker).await {
+                Some(quote) => {
+                    quotes.insert(ticker.clone(), quote);
+                }
+                None => missing.push(ticker.clone()),
+            }
+        }
+        if missing.is_empty() {
+            return Ok(quotes);
+        }
+
+        let generations: HashMap<&str, u64> = missing
+            .iter()
+            .map(|ticker| (ticker.as_str(), self.generation(ticker)))
+            .collect();
+        let rows = sqlx::query(
+            "SELECT DISTINCT ON (ticker) * FROM stock_prices WHERE ticker = ANY($1)
+            ORDER BY ticker, date DESC",
+        )
+        .bind(&missing)
+        .fetch_all(&self.mock_db)
+        .await?;
+        let now = Instant::now();
+        let mut cache = self.cache.write().await;
+        for rec in &rows {
+            let quote = ticker_from_row(rec)?;
+            if generations.get(quote.ticker.as_str()) == Some(&self.generation(&quote.ticker)) {
+                cache.insert(quote.ticker.clone(), (quote.clone(), now));
+            }
+            quotes.insert(quote.ticker.clone(), quote);
+        }
+        Ok(quotes)
+    }
+
+    async fn cached_quote(&self, ticker: &str) -> Option<Ticker> {
+        let cached = self
+            .cache
+            .read()
+            .await
+            .get(ticker)
+            .filter(|(_, cached_at)| cached_at.elapsed() < self.cache_ttl)
+            .map(|(quote, _)| quote.clone());
+        let counter = if cached.is_some() {
+            &self.cache_hits
+        } else {
+            &self.cache_misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+        cached
+    }
+
+    fn generation(&self, ticker: &str) -> u64 {
+        let generations = self.generations.lock().unwrap();
+        generations.get(ticker).copied().unwrap_or_default()
+    }
+
+    /// Drops the cached quote for `ticker` after its price has changed. Reads already in flight
+    /// see the generation change and leave the cache alone.
+    pub async fn invalidate_quote(&self, ticker: &str) {
+        let mut cache = self.cache.write().await;
+        cache.remove(ticker);
+        *self
+            .generations
+            .lock()
+            .unwrap()
+            .entry(ticker.to_string())
+            .or_default() += 1;
+    }
+
+    pub async fn cache_stats(&self) -> QuoteCacheStats {
+        let cache = self.cache.read().await;
+        QuoteCacheStats {
+            entries: cache.len(),
+            fresh_entries: cache
+                .values()
+                .filter(|(_, cached_at)| cached_at.elapsed() < self.cache_ttl)
+                .count(),
+            hits: self.cache_hits.load(Ordering::Relaxed),
+            misses: self.cache_misses.load(Ordering::Relaxed),
+            ttl_secs: self.cache_ttl.as_secs(),
         }
     }
     pub async fn fetch_latest_price_ticker_from_db(
@@ -38,23 +180,113 @@ impl TickerService {
         ticker: &str,
     ) -> Result<Ticker, TradeError> {
         debug!("Fetching ticker from DB");
-        let stock = sqlx::query("SELECT * FROM stock_prices WHERE ticker = $1 ORDER BY date DESC")
+        let rec = sqlx::query(
+            "SELECT * FROM stock_prices WHERE ticker = $1 ORDER BY date DESC LIMIT 1",
+        )
+        .bind(ticker)
+        .fetch_one(&self.mock_db)
+        .await?;
+        Ok(ticker_from_row(&rec)?)
+    }
+
+    /// Folds a fill into the ticker's bars at every interval and into today's row of
+    /// `stock_prices`, so the trade becomes the latest quote.
+    pub async fn record_trade(
+        &self,
+        ticker: &str,
+        price: &BigDecimal,
+        quantity: &BigDecimal,
+        executed_at: DateTime<Utc>,
+    ) -> Result<(), TradeError> {
+        let mut tx = self.mock_db.begin().await?;
+        let mut day_close = price.clone();
+        for interval in BarInterval::ALL {
+            //a fill that lands late doesn't take over the close from a later one
+            let rec = sqlx::query(
+                "INSERT INTO price_bars (ticker, interval, bucket_start, open, high, low, close,
+                    volume, trade_count, last_trade_at)
+                VALUES ($1, $2, $3, $4, $4, $4, $4, $5, 1, $6)
+                ON CONFLICT (ticker, interval, bucket_start) DO UPDATE SET
+                    high = GREATEST(price_bars.high, EXCLUDED.high),
+                    low = LEAST(price_bars.low, EXCLUDED.low),
+                    close = CASE WHEN EXCLUDED.last_trade_at >= price_bars.last_trade_at
+                        THEN EXCLUDED.close ELSE price_bars.close END,
+                    volume = price_bars.volume + EXCLUDED.volume,
+                    trade_count = price_bars.trade_count + 1,
+                    last_trade_at = GREATEST(price_bars.last_trade_at, EXCLUDED.last_trade_at)
+                RETURNING close",
+            )
             .bind(ticker)
-            .fetch_one(&self.mock_db)
-            .await
-            .and_then(|rec| {
-                Ok(Ticker {
+            .bind(interval)
+            .bind(interval.bucket_start(executed_at))
+            .bind(price)
+            .bind(quantity)
+            .bind(executed_at)
+            .fetch_one(&mut *tx)
+            .await?;
+            if interval == BarInterval::Day {
+                day_close = rec.try_get("close")?;
+            }
+        }
+        //stock_prices keeps whole share volumes and one row per day, which may already hold
+        //the provider's bar for today. Its close follows the daily bar's
+        sqlx::query(
+            "INSERT INTO stock_prices (ticker, date, close, volume, open, high, low)
+            VALUES ($1, $2, $3, ROUND($4)::BIGINT, $5, $5, $5)
+            ON CONFLICT (ticker, date) DO UPDATE SET
+                close = EXCLUDED.close,
+                volume = COALESCE(stock_prices.volume, 0) + EXCLUDED.volume,
+                open = COALESCE(stock_prices.open, EXCLUDED.open),
+                high = GREATEST(stock_prices.high, EXCLUDED.high),
+                low = LEAST(stock_prices.low, EXCLUDED.low)",
+        )
+        .bind(ticker)
+        .bind(BarInterval::Day.bucket_start(executed_at))
+        .bind(&day_close)
+        .bind(quantity)
+        .bind(price)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        self.invalidate_quote(ticker).await;
+        Ok(())
+    }
+
+    /// The ticker's latest `limit` bars at `interval`, oldest first.
+    pub async fn get_price_bars(
+        &self,
+        ticker: &str,
+        interval: BarInterval,
+        limit: i64,
+    ) -> Result<Vec<PriceBar>, TradeError> {
+        let rows = sqlx::query(
+            "SELECT * FROM (
+                SELECT * FROM price_bars WHERE ticker = $1 AND interval = $2
+                ORDER BY bucket_start DESC LIMIT $3
+            ) latest ORDER BY bucket_start ASC",
+        )
+        .bind(ticker)
+        .bind(interval)
+        .bind(limit.clamp(1, Self::MAX_PRICE_BARS))
+        .fetch_all(&self.mock_db)
+        .await?;
+        rows.iter()
+            .map(|rec| {
+                Ok(PriceBar {
                     ticker: rec.try_get("ticker")?,
-                    date: rec.try_get("date")?,
-                    close: rec.try_get("close")?,
-                    volume: rec.try_get("volume")?,
+                    interval: rec.try_get("interval")?,
+                    bucket_start: rec.try_get("bucket_start")?,
                     open: rec.try_get("open")?,
                     high: rec.try_get("high")?,
                     low: rec.try_get("low")?,
+                    close: rec.try_get("close")?,
+                    volume: rec.try_get("volume")?,
+                    trade_count: rec.try_get("trade_count")?,
+                    last_trade_at: rec.try_get("last_trade_at")?,
                 })
             })
-            .map_err(|e| TradeError::DatabaseError(e));
-        return stock;
+            .collect::<Result<_, sqlx::Error>>()
+            .map_err(TradeError::from)
     }
 
     pub async fn fetch_price_history_ticker_from_db(
@@ -97,50 +329,61 @@ impl TickerService {
             .fetch_all(&self.mock_db)
             .await
             .and_then(|rows| {
-                rows.iter()
-                    .map(|rec| {
-                        Ok(Ticker {
-                            ticker: rec.try_get("ticker")?,
-                            date: rec.try_get("date")?,
-                            close: rec.try_get("close")?,
-                            volume: rec.try_get("volume")?,
-                            open: rec.try_get("open")?,
-                            high: rec.try_get("high")?,
-                            low: rec.try_get("low")?,
-                        })
-                    })
-                    .collect()
+                rows.iter().map(ticker_from_row).collect()
             })
             .map_err(|e| TradeError::DatabaseError(e));
 
         return stocks;
     }
 
-    pub async fn fetch_ticker_from_api(&self, ticker: &str) -> Result<Ticker, TradeError> {
-        debug!("Fetching ticker from API");
-        let api_response = self
-            .api_client
-            .stock_time(alpha_vantage::stock_time::StockFunction::Daily, ticker)
-            .output_size(alpha_vantage::api::OutputSize::Compact)
-            .json()
-            .await
-            .map_err(|e| TradeError::TickerError(TickerError::AlphaVantageError(e)))?;
-        let api_data = api_response.data();
-        if let Some(latest) = api_data.first() {
-            let ticker = Ticker {
-                ticker: ticker.to_string(),
-                date: chrono::DateTime::from_str(latest.time()).unwrap_or(Utc::now()),
-                close: BigDecimal::from_f64(latest.close()).unwrap_or(BigDecimal::from(120)),
-                volume: latest.volume().try_into().ok(),
-                open: Some(BigDecimal::from_f64(latest.open()).unwrap_or(BigDecimal::from(120))),
-                high: Some(BigDecimal::from_f64(latest.high()).unwrap_or(BigDecimal::from(120))),
-                low: Some(BigDecimal::from_f64(latest.low()).unwrap_or(BigDecimal::from(120))),
-            };
-            return Ok(ticker);
+    pub async fn fetch_ticker_from_provider(&self, ticker: &str) -> Result<Ticker, TradeError> {
+        debug!("Fetching ticker from {}", self.market_data.name());
+        self.market_data.latest_quote(ticker).await
+    }
+
+    /// Pulls the ticker's daily history from the market data provider into `stock_prices`,
+    /// overwriting any bars already stored for the same dates. Returns the number of bars.
+    pub async fn import_price_history(&self, ticker: &str) -> Result<usize, TradeError> {
+        let bars = self.market_data.daily_history(ticker).await?;
+        sqlx::query(
+            "INSERT INTO stock_prices (ticker, date, close, volume, open, high, low)
+            SELECT $1, * FROM UNNEST($2::timestamptz[], $3::numeric[], $4::bigint[],
+                $5::numeric[], $6::numeric[], $7::numeric[])
+            ON CONFLICT (ticker, date) DO UPDATE SET close = EXCLUDED.close,
+                volume = EXCLUDED.volume, open = EXCLUDED.open, high = EXCLUDED.high,
+                low = EXCLUDED.low",
+        )
+        .bind(ticker)
+        .bind(bars.iter().map(|bar| bar.date).collect::<Vec<_>>())
+        .bind(bars.iter().map(|bar| bar.close.clone()).collect::<Vec<_>>())
+        .bind(bars.iter().map(|bar| bar.volume).collect::<Vec<_>>())
+        .bind(bars.iter().map(|bar| bar.open.clone()).collect::<Vec<_>>())
+        .bind(bars.iter().map(|bar| bar.high.clone()).collect::<Vec<_>>())
+        .bind(bars.iter().map(|bar| bar.low.clone()).collect::<Vec<_>>())
+        .execute(&self.mock_db)
+        .await?;
+        self.invalidate_quote(ticker).await;
+        info!(
+            "Imported {} bars for {} from {}",
+            bars.len(),
+            ticker,
+            self.market_data.name()
+        );
+        Ok(bars.len())
+    }
+
+    /// Imports history for whichever of `tickers` have no prices yet, so a fresh database has
+    /// something to quote and start the market from.
+    pub async fn seed_price_history(&self, tickers: &[String]) -> Result<(), TradeError> {
+        let priced: Vec<String> =
+            sqlx::query_scalar("SELECT DISTINCT ticker FROM stock_prices WHERE ticker = ANY($1)")
+                .bind(tickers)
+                .fetch_all(&self.mock_db)
+                .await?;
+        for ticker in tickers.iter().filter(|ticker| !priced.contains(ticker)) {
+            self.import_price_history(ticker).await?;
         }
-        Err(TradeError::TickerError(TickerError::InvalidSymbol(
-            ticker.to_string(),
-        )))
+        Ok(())
     }
 
     pub async fn get_active_stocks(&self) -> Vec<String> {
@@ -155,4 +398,68 @@ impl TickerService {
             })
             .unwrap_or_default()
     }
+
+    fn instrument_from_row(rec: &sqlx::postgres::PgRow) -> Result<Instrument, sqlx::Error> {
+        Ok(Instrument {
+            ticker: rec.try_get("ticker")?,
+            name: rec.try_get("name")?,
+            asset_class: rec.try_get("asset_class")?,
+            sector: rec.try_get("sector")?,
+            lot_size: rec.try_get("lot_size")?,
+            updated_at: rec.try_get("updated_at")?,
+        })
+    }
+
+    pub async fn get_instrument(&self, ticker: &str) -> Result<Instrument, TradeError> {
+        let rec = sqlx::query("SELECT * FROM instruments WHERE ticker = $1")
+            .bind(ticker)
+            .fetch_optional(&self.mock_db)
+            .await?
+            .ok_or_else(|| TickerError::InvalidSymbol(ticker.to_string()))?;
+        Ok(Self::instrument_from_row(&rec)?)
+    }
+
+    /// Metadata of whichever of `tickers` have any, keyed by ticker.
+    pub async fn get_instruments(
+        &self,
+        tickers: &[String],
+    ) -> Result<HashMap<String, Instrument>, TradeError> {
+        sqlx::query("SELECT * FROM instruments WHERE ticker = ANY($1)")
+            .bind(tickers)
+            .fetch_all(&self.mock_db)
+            .await?
+            .iter()
+            .map(|rec| {
+                let instrument = Self::instrument_from_row(rec)?;
+                Ok((instrument.ticker.clone(), instrument))
+            })
+            .collect::<Result<_, sqlx::Error>>()
+            .map_err(TradeError::from)
+    }
+
+    pub async fn upsert_instrument(
+        &self,
+        ticker: &str,
+        details: &InstrumentDetails,
+    ) -> Result<Instrument, TradeError> {
+        if details.lot_size <= BigDecimal::zero() {
+            return Err(TradeError::InvalidAmount);
+        }
+        let rec = sqlx::query(
+            "INSERT INTO instruments (ticker, name, asset_class, sector, lot_size)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (ticker) DO UPDATE SET name = EXCLUDED.name,
+                asset_class = EXCLUDED.asset_class, sector = EXCLUDED.sector,
+                lot_size = EXCLUDED.lot_size, updated_at = NOW()
+            RETURNING *",
+        )
+        .bind(ticker)
+        .bind(&details.name)
+        .bind(details.asset_class)
+        .bind(&details.sector)
+        .bind(&details.lot_size)
+        .fetch_one(&self.mock_db)
+        .await?;
+        Ok(Self::instrument_from_row(&rec)?)
+    }
 }
@@ -1,10 +1,13 @@
 use crate::{
     models::{
         errors::trade_error::TradeError,
+        fee::{Fill, Liquidity},
+        ledger::JournalEntryType,
         order::{Order, OrderStatus, OrderType},
+        tax_lot::Sale,
     },
     services::{
-        account_management_service::AccountManagementService,
+        account_management_service::AccountManagementService, fee_service::FeeService,
         portfolio_management_service::PortfolioManagementService, ticker_service::TickerService,
     },
 };
@@ -20,6 +23,7 @@ pub struct TradeService {
     pub ticker_service: Arc<TickerService>,
     pub account_management_service: Arc<AccountManagementService>,
     pub portfolio_management_service: Arc<PortfolioManagementService>,
+    pub fee_service: Arc<FeeService>,
 }
 #[allow(dead_code)]
 impl TradeService {
@@ -29,12 +33,14 @@ impl TradeService {
         ticker_service: Arc<TickerService>,
         account_management_service: Arc<AccountManagementService>,
         portfolio_management_service: Arc<PortfolioManagementService>,
+        fee_service: Arc<FeeService>,
     ) -> Self {
         Self {
             db,
             ticker_service,
             account_management_service,
             portfolio_management_service,
+            fee_service,
         }
     }
 
@@ -47,12 +53,13 @@ impl TradeService {
             .map_err(|e| TradeError::DatabaseError(e))?;
         Ok(Order {
             order_id: rec.try_get("order_id")?,
-            user_id: rec.try_get("user_id")?,
+            account_id: rec.try_get("account_id")?,
             ticker: rec.try_get("ticker")?,
             quantity: rec.try_get("quantity")?,
             price_per_share: rec.try_get("price_per_share")?,
             order_type: rec.try_get("order_type")?,
             status: rec.try_get("status")?,
+            created_at: rec.try_get("created_at")?,
         })
     }
     #[tracing::instrument(skip(self))]
@@ -61,6 +68,7 @@ impl TradeService {
         order_id: Uuid,
         fullfilment_quantity: BigDecimal,
         execution_price: BigDecimal,
+        liquidity: Liquidity,
     ) -> Result<(), TradeError> {
         let order = self.get_order(order_id).await?;
         //validation checks
@@ -73,30 +81,74 @@ impl TradeService {
         }
         //TODO: refactor price getting here
         let total_purchase_price = &execution_price * &fullfilment_quantity;
-        match order.order_type {
+        let fee = self
+            .fee_service
+            .fee_for_fill(
+                order.order_id,
+                order.account_id,
+                Fill {
+                    liquidity,
+                    quantity: fullfilment_quantity.clone(),
+                    notional: total_purchase_price.clone(),
+                },
+            )
+            .await?;
+        let realized_pnl = match order.order_type {
             OrderType::Buy => {
                 self.account_management_service
-                    .deduct_user_balance(order.user_id, &total_purchase_price)
+                    .deduct_user_balance(
+                        order.account_id,
+                        &total_purchase_price,
+                        JournalEntryType::TradeBuy,
+                        Some(order.order_id),
+                    )
                     .await?;
                 self.portfolio_management_service
                     .add_to_portfolio(
-                        order.user_id,
+                        order.account_id,
                         &order.ticker,
-                        &order.quantity,
+                        &fullfilment_quantity,
                         &total_purchase_price,
+                        Some(order.order_id),
                     )
                     .await?;
+                None
             }
             OrderType::Sell => {
-                self.portfolio_management_service
-                    .remove_from_portfolio(order.user_id, &order.ticker, &order.quantity)
+                let sale = Sale {
+                    order_id: order.order_id,
+                    price_per_share: execution_price.clone(),
+                };
+                let realized_pnl = self
+                    .portfolio_management_service
+                    .remove_from_portfolio(
+                        order.account_id,
+                        &order.ticker,
+                        &fullfilment_quantity,
+                        Some(&sale),
+                    )
                     .await?;
                 self.account_management_service
-                    .add_user_balance(order.user_id, &total_purchase_price)
+                    .add_user_balance(
+                        order.account_id,
+                        &total_purchase_price,
+                        JournalEntryType::TradeSell,
+                        Some(order.order_id),
+                    )
                     .await?;
+                Some(realized_pnl)
             }
-        }
-        self.log_transaction(&order, &fullfilment_quantity, &execution_price).await?;
+        };
+        self.settle_fee(&order, &fee).await?;
+        self.log_transaction(
+            &order,
+            &fullfilment_quantity,
+            &execution_price,
+            liquidity,
+            &fee,
+            realized_pnl.as_ref(),
+        )
+        .await?;
         if fullfilment_quantity < order.quantity {
             sqlx::query("UPDATE orders SET quantity = $2, status = $3 WHERE order_id = $1")
                 .bind(order_id)
@@ -113,10 +165,60 @@ impl TradeService {
                 .execute(&self.db)
                 .await
                 .map_err(|e| TradeError::DatabaseError(e))?;
+            //price improvement and the unused part of the fee estimate go back to the account
+            if let OrderType::Buy = order.order_type {
+                self.account_management_service
+                    .release_reservation(order.account_id, order.order_id)
+                    .await?;
+            }
         }
         Ok(())
     }
 
+    /// Charges a fill's fee as its own journal entry, or pays out a maker rebate. Buys pay from
+    /// the cash reserved for the order, sells from the proceeds.
+    async fn settle_fee(&self, order: &Order, fee: &BigDecimal) -> Result<(), TradeError> {
+        let zero = BigDecimal::from(0);
+        if fee < &zero {
+            self.account_management_service
+                .add_user_balance(
+                    order.account_id,
+                    &-fee.clone(),
+                    JournalEntryType::Fee,
+                    Some(order.order_id),
+                )
+                .await?;
+        } else if fee > &zero {
+            match order.order_type {
+                OrderType::Buy => {
+                    self.account_management_service
+                        .deduct_user_balance(
+                            order.account_id,
+                            fee,
+                            JournalEntryType::Fee,
+                            Some(order.order_id),
+                        )
+                        .await?
+                }
+                OrderType::Sell => {
+                    self.account_management_service
+                        .debit_user_balance(
+                            order.account_id,
+                            fee,
+                            JournalEntryType::Fee,
+                            Some(order.order_id),
+                        )
+                        .await?
+                }
+            }
+        } else {
+            return Ok(());
+        }
+        self.portfolio_management_service
+            .add_position_fee(order.account_id, &order.ticker, fee)
+            .await
+    }
+
     #[tracing::instrument(skip(self))]
     pub async fn get_pending_orders(&self) -> Result<Vec<Order>, TradeError> {
         let rec = sqlx::query(
@@ -130,12 +232,13 @@ impl TradeService {
         for rec in rec {
             orders.push(Order {
                 order_id: rec.try_get("order_id")?,
-                user_id: rec.try_get("user_id")?,
+                account_id: rec.try_get("account_id")?,
                 ticker: rec.try_get("ticker")?,
                 quantity: rec.try_get("quantity")?,
                 price_per_share: rec.try_get("price_per_share")?,
                 order_type: rec.try_get("order_type")?,
                 status: rec.try_get("status")?,
+                created_at: rec.try_get("created_at")?,
             });
         }
         Ok(orders)
@@ -173,16 +276,20 @@ impl TradeService {
     // }
 
     #[tracing::instrument(skip(self))]
-    async fn log_transaction(&self, order: &Order, fullfilment_quantity: &BigDecimal, execution_price: &BigDecimal) -> Result<(), TradeError> {
+    async fn log_transaction(&self, order: &Order, fullfilment_quantity: &BigDecimal, execution_price: &BigDecimal, liquidity: Liquidity, fee: &BigDecimal, realized_pnl: Option<&BigDecimal>) -> Result<(), TradeError> {
         sqlx::query(
-            "INSERT INTO transactions (transaction_id, user_id, ticker, order_type, quantity, price_per_share) 
-            VALUES ($1, $2, $3, $4, $5, $6)")
+            "INSERT INTO transactions (transaction_id, account_id, ticker, order_type, quantity, price_per_share, order_id, liquidity, fee, realized_pnl) 
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)")
             .bind(uuid::Uuid::new_v4())
-            .bind(&order.user_id)
+            .bind(&order.account_id)
             .bind(&order.ticker)
             .bind(&order.order_type)
             .bind(fullfilment_quantity)
             .bind(execution_price)
+            .bind(order.order_id)
+            .bind(liquidity)
+            .bind(fee)
+            .bind(realized_pnl)
             .execute(&self.db)
             .await
             .map_err(|e| TradeError::DatabaseError(e))?;
@@ -4,6 +4,7 @@ use crate::authentication::basic_client::Auth0LoginResponse;
 use crate::authentication::basic_client::AuthorizationClient;
 use crate::models::errors::trade_error::TradeError;
 use crate::models::errors::user_error::UserError;
+use crate::models::ledger::JournalEntryType;
 use crate::services::account_management_service::AccountManagementService;
 use crate::services::portfolio_management_service::PortfolioManagementService;
 use bigdecimal::BigDecimal;
@@ -27,6 +28,8 @@ pub struct UserService {
 impl UserService {
     //need to implement getters
     const SESSION_LENGTH: i64 = 30;
+    //the market maker provides liquidity and is not charged commission
+    const SYSTEM_FEE_TIER: &'static str = "COMMISSION_FREE";
     pub fn new(
         db: PgPool,
         account_management_service: Arc<AccountManagementService>,
@@ -54,7 +57,7 @@ impl UserService {
             INSERT INTO users (user_id, auth_user_id, username, email) 
             VALUES ($1, $2, $3, $4)
             ON CONFLICT (auth_user_id) DO UPDATE SET username = EXCLUDED.username, email = EXCLUDED.email
-            RETURNING user_id
+            RETURNING user_id, (xmax = 0) AS inserted
             ",
         )
         .bind(user_id)
@@ -67,6 +70,13 @@ impl UserService {
         let inserted_user_id: Uuid = row
             .try_get("user_id")
             .map_err(|e| UserError::DatabaseError(e))?;
+        //xmax is only zero when the row was freshly inserted rather than updated
+        let inserted: bool = row.try_get("inserted")?;
+        if inserted {
+            self.account_management_service
+                .open_account(inserted_user_id)
+                .await?;
+        }
         Ok(inserted_user_id)
     }
 
@@ -79,8 +89,18 @@ impl UserService {
         self.upsert_user(system_user_id, "system", "system", "system@system.com")
             .await?;
         info!("System user upserted with user_id: {}", system_user_id);
+        sqlx::query("UPDATE users SET fee_tier = $2 WHERE user_id = $1")
+            .bind(system_user_id)
+            .bind(Self::SYSTEM_FEE_TIER)
+            .execute(&self.user_db)
+            .await?;
         self.account_management_service
-            .add_user_balance(system_user_id, &BigDecimal::from(100000000))
+            .add_user_balance(
+                system_user_id,
+                &BigDecimal::from(100000000),
+                JournalEntryType::Deposit,
+                None,
+            )
             .await?;
         info!("System user balance added for user_id: {}", system_user_id);
         for ticker_id in ticker_ids {
@@ -91,11 +111,23 @@ impl UserService {
                     &ticker_id,
                     &BigDecimal::from(100000000),
                     &BigDecimal::zero(),
+                    None,
                 )
                 .await?;
         }
         Ok(system_user_id)
     }
+    #[tracing::instrument(skip(self))]
+    pub async fn is_admin(&self, user_id: Uuid) -> Result<bool, UserError> {
+        let rec = sqlx::query("SELECT is_admin FROM users WHERE user_id = $1")
+            .bind(user_id)
+            .fetch_optional(&self.user_db)
+            .await?;
+        match rec {
+            Some(rec) => Ok(rec.try_get("is_admin")?),
+            None => Err(UserError::NotFound),
+        }
+    }
     //should this function exist?
     #[tracing::instrument(skip(self))]
     pub async fn get_user_uuid(&self, username: &str) -> Result<Uuid, UserError> {
@@ -0,0 +1,298 @@
+use sqlx::postgres::PgRow;
+use sqlx::{PgPool, Postgres, Row, Transaction};
+use uuid::Uuid;
+
+use crate::models::errors::trade_error::TradeError;
+use crate::models::watchlist::{
+    Watchlist, WatchlistOrder, WatchlistQuote, WatchlistQuotes, WatchlistRequest,
+};
+
+/// Users' named lists of tickers, and quotes for everything on a list at once.
+pub struct WatchlistService {
+    db: PgPool,
+}
+
+impl WatchlistService {
+    pub const MAX_WATCHLISTS: i64 = 20;
+
+    pub fn new(db: PgPool) -> Self {
+        Self { db }
+    }
+
+    fn watchlist_from_row(rec: &PgRow) -> Result<Watchlist, TradeError> {
+        Ok(Watchlist {
+            watchlist_id: rec.try_get("watchlist_id")?,
+            name: rec.try_get("name")?,
+            position: rec.try_get("position")?,
+            tickers: rec.try_get("tickers")?,
+            created_at: rec.try_get("created_at")?,
+        })
+    }
+
+    /// A second list with the same name is the only way writing a list can conflict.
+    fn name_taken(name: &str) -> impl FnOnce(sqlx::Error) -> TradeError + '_ {
+        move |e| match e.as_database_error() {
+            Some(db_error) if db_error.is_unique_violation() => {
+                TradeError::InvalidWatchlist(format!("a watchlist named {} already exists", name))
+            }
+            _ => TradeError::DatabaseError(e),
+        }
+    }
+
+    #[tracing::instrument(skip(self))]
+    pub async fn list_watchlists(&self, user_id: Uuid) -> Result<Vec<Watchlist>, TradeError> {
+        sqlx::query(
+            "SELECT w.watchlist_id, w.name, w.position, w.created_at,
+                COALESCE(array_agg(i.ticker ORDER BY i.position)
+                    FILTER (WHERE i.ticker IS NOT NULL), '{}') AS tickers
+            FROM watchlists w LEFT JOIN watchlist_items i ON i.watchlist_id = w.watchlist_id
+            WHERE w.user_id = $1
+            GROUP BY w.watchlist_id
+            ORDER BY w.position, w.created_at",
+        )
+        .bind(user_id)
+        .fetch_all(&self.db)
+        .await?
+        .iter()
+        .map(Self::watchlist_from_row)
+        .collect()
+    }
+
+    #[tracing::instrument(skip(self))]
+    pub async fn get_watchlist(
+        &self,
+        user_id: Uuid,
+        watchlist_id: Uuid,
+    ) -> Result<Watchlist, TradeError> {
+        let rec = sqlx::query(
+            "SELECT w.watchlist_id, w.name, w.position, w.created_at,
+                COALESCE(array_agg(i.ticker ORDER BY i.position)
+                    FILTER (WHERE i.ticker IS NOT NULL), '{}') AS tickers
+            FROM watchlists w LEFT JOIN watchlist_items i ON i.watchlist_id = w.watchlist_id
+            WHERE w.user_id = $1 AND w.watchlist_id = $2
+            GROUP BY w.watchlist_id",
+        )
+        .bind(user_id)
+        .bind(watchlist_id)
+        .fetch_optional(&self.db)
+        .await?
+        .ok_or(TradeError::WatchlistNotFound)?;
+        Self::watchlist_from_row(&rec)
+    }
+
+    /// Adds a list after the user's existing ones.
+    #[tracing::instrument(skip(self))]
+    pub async fn create_watchlist(
+        &self,
+        user_id: Uuid,
+        request: WatchlistRequest,
+    ) -> Result<Watchlist, TradeError> {
+        let request = request.normalise().map_err(TradeError::InvalidWatchlist)?;
+        let mut tx = self.db.begin().await?;
+        //serialises list creation per user so the count and position can't race
+        sqlx::query("SELECT user_id FROM users WHERE user_id = $1 FOR UPDATE")
+            .bind(user_id)
+            .execute(&mut *tx)
+            .await?;
+        let rec = sqlx::query(
+            "SELECT COUNT(*) AS lists, COALESCE(MAX(position) + 1, 0) AS next_position
+            FROM watchlists WHERE user_id = $1",
+        )
+        .bind(user_id)
+        .fetch_one(&mut *tx)
+        .await?;
+        let lists: i64 = rec.try_get("lists")?;
+        if lists >= Self::MAX_WATCHLISTS {
+            return Err(TradeError::InvalidWatchlist(format!(
+                "a user can have at most {} watchlists",
+                Self::MAX_WATCHLISTS
+            )));
+        }
+        let watchlist_id = Uuid::new_v4();
+        sqlx::query(
+            "INSERT INTO watchlists (watchlist_id, user_id, name, position) VALUES ($1, $2, $3, $4)",
+        )
+        .bind(watchlist_id)
+        .bind(user_id)
+        .bind(&request.name)
+        .bind(rec.try_get::<i32, _>("next_position")?)
+        .execute(&mut *tx)
+        .await
+        .map_err(Self::name_taken(&request.name))?;
+        Self::write_tickers(&mut tx, watchlist_id, &request.tickers).await?;
+        tx.commit().await?;
+        self.get_watchlist(user_id, watchlist_id).await
+    }
+
+    /// Renames the list and replaces its tickers with `request.tickers`, in that order.
+    #[tracing::instrument(skip(self))]
+    pub async fn update_watchlist(
+        &self,
+        user_id: Uuid,
+        watchlist_id: Uuid,
+        request: WatchlistRequest,
+    ) -> Result<Watchlist, TradeError> {
+        let request = request.normalise().map_err(TradeError::InvalidWatchlist)?;
+        let mut tx = self.db.begin().await?;
+        let updated =
+            sqlx::query("UPDATE watchlists SET name = $3 WHERE watchlist_id = $1 AND user_id = $2")
+                .bind(watchlist_id)
+                .bind(user_id)
+                .bind(&request.name)
+                .execute(&mut *tx)
+                .await
+                .map_err(Self::name_taken(&request.name))?;
+        if updated.rows_affected() == 0 {
+            return Err(TradeError::WatchlistNotFound);
+        }
+        sqlx::query("DELETE FROM watchlist_items WHERE watchlist_id = $1")
+            .bind(watchlist_id)
+            .execute(&mut *tx)
+            .await?;
+        Self::write_tickers(&mut tx, watchlist_id, &request.tickers).await?;
+        tx.commit().await?;
+        self.get_watchlist(user_id, watchlist_id).await
+    }
+
+    async fn write_tickers(
+        tx: &mut Transaction<'_, Postgres>,
+        watchlist_id: Uuid,
+        tickers: &[String],
+    ) -> Result<(), TradeError> {
+        sqlx::query(
+            "INSERT INTO watchlist_items (watchlist_id, ticker, position)
+            SELECT $1, ticker, position::int - 1
+            FROM UNNEST($2::text[]) WITH ORDINALITY AS t(ticker, position)",
+        )
+        .bind(watchlist_id)
+        .bind(tickers)
+        .execute(&mut **tx)
+        .await?;
+        Ok(())
+    }
+
+    #[tracing::instrument(skip(self))]
+    pub async fn delete_watchlist(
+        &self,
+        user_id: Uuid,
+        watchlist_id: Uuid,
+    ) -> Result<(), TradeError> {
+        let deleted =
+            sqlx::query("DELETE FROM watchlists WHERE watchlist_id = $1 AND user_id = $2")
+                .bind(watchlist_id)
+                .bind(user_id)
+                .execute(&self.db)
+                .await?;
+        if deleted.rows_affected() == 0 {
+            return Err(TradeError::WatchlistNotFound);
+        }
+        Ok(())
+    }
+
+    /// Puts the user's lists in the given order. Every list must be named exactly once.
+    #[tracing::instrument(skip(self))]
+    pub async fn reorder_watchlists(
+        &self,
+        user_id: Uuid,
+        order: WatchlistOrder,
+    ) -> Result<Vec<Watchlist>, TradeError> {
+        let mut tx = self.db.begin().await?;
+        let mut current: Vec<Uuid> =
+            sqlx::query_scalar("SELECT watchlist_id FROM watchlists WHERE user_id = $1 FOR UPDATE")
+                .bind(user_id)
+                .fetch_all(&mut *tx)
+                .await?;
+        let mut requested = order.watchlist_ids.clone();
+        current.sort();
+        requested.sort();
+        if current != requested {
+            return Err(TradeError::InvalidWatchlist(
+                "the order must list each of your watchlists once".to_string(),
+            ));
+        }
+        sqlx::query(
+            "UPDATE watchlists w SET position = o.position::int - 1
+            FROM UNNEST($2::uuid[]) WITH ORDINALITY AS o(watchlist_id, position)
+            WHERE w.watchlist_id = o.watchlist_id AND w.user_id = $1",
+        )
+        .bind(user_id)
+        .bind(&order.watchlist_ids)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        self.list_watchlists(user_id).await
+    }
+
+    /// Quotes every ticker on the list in one query: the latest price, the change since the
+    /// previous day's last close, the latest day's volume and a daily-close sparkline.
+    #[tracing::instrument(skip(self))]
+    pub async fn get_quotes(
+        &self,
+        user_id: Uuid,
+        watchlist_id: Uuid,
+    ) -> Result<WatchlistQuotes, TradeError> {
+        let records = sqlx::query(
+            "SELECT w.name, i.ticker, latest.date, latest.close, previous.close AS previous_close,
+                day_volume.volume, COALESCE(spark.closes, '{}') AS sparkline
+            FROM watchlists w
+            LEFT JOIN watchlist_items i ON i.watchlist_id = w.watchlist_id
+            LEFT JOIN LATERAL (
+                SELECT date, close FROM stock_prices
+                WHERE ticker = i.ticker AND date <= NOW() AND close IS NOT NULL
+                ORDER BY date DESC LIMIT 1
+            ) latest ON TRUE
+            LEFT JOIN LATERAL (
+                SELECT close FROM stock_prices
+                WHERE ticker = i.ticker AND close IS NOT NULL
+                    AND date < date_trunc('day', latest.date, 'UTC')
+                ORDER BY date DESC LIMIT 1
+            ) previous ON TRUE
+            LEFT JOIN LATERAL (
+                SELECT SUM(volume)::bigint AS volume FROM stock_prices
+                WHERE ticker = i.ticker AND date <= latest.date
+                    AND date >= date_trunc('day', latest.date, 'UTC')
+            ) day_volume ON TRUE
+            LEFT JOIN LATERAL (
+                SELECT array_agg(d.close ORDER BY d.day) AS closes FROM (
+                    SELECT DISTINCT ON ((date AT TIME ZONE 'UTC')::date)
+                        (date AT TIME ZONE 'UTC')::date AS day, close
+                    FROM stock_prices
+                    WHERE ticker = i.ticker AND close IS NOT NULL AND date <= NOW()
+                        AND date >= date_trunc('day', NOW(), 'UTC') - make_interval(days => $3 - 1)
+                    ORDER BY (date AT TIME ZONE 'UTC')::date, date DESC
+                ) d
+            ) spark ON TRUE
+            WHERE w.watchlist_id = $1 AND w.user_id = $2
+            ORDER BY i.position",
+        )
+        .bind(watchlist_id)
+        .bind(user_id)
+        .bind(WatchlistQuote::SPARKLINE_DAYS)
+        .fetch_all(&self.db)
+        .await?;
+        let name: String = records
+            .first()
+            .ok_or(TradeError::WatchlistNotFound)?
+            .try_get("name")?;
+        let mut quotes = Vec::with_capacity(records.len());
+        for rec in &records {
+            //an empty list still comes back as one row, without a ticker
+            let Some(ticker) = rec.try_get::<Option<String>, _>("ticker")? else {
+                continue;
+            };
+            quotes.push(WatchlistQuote::new(
+                ticker,
+                rec.try_get("close")?,
+                rec.try_get("date")?,
+                rec.try_get("previous_close")?,
+                rec.try_get("volume")?,
+                rec.try_get("sparkline")?,
+            ));
+        }
+        Ok(WatchlistQuotes {
+            watchlist_id,
+            name,
+            quotes,
+        })
+    }
+}
@@ -0,0 +1,185 @@
+mod common;
+
+use std::sync::Arc;
+
+use axum::{
+    body::{to_bytes, Body},
+    http::{Request, StatusCode},
+    Router,
+};
+use backend::app_state::AppState;
+use backend::models::errors::trade_error::TradeError;
+use backend::models::errors::user_error::UserError;
+use backend::models::ledger::JournalEntryType;
+use backend::routes::router::create_router;
+use backend::services::account_management_service::AccountManagementService;
+use backend::services::market_data_provider::SyntheticProvider;
+use chrono::{Duration, Utc};
+use common::{create_user, dec, setup_db};
+use sqlx::PgPool;
+use tower::util::ServiceExt;
+use uuid::Uuid;
+
+/// A session for the user, as the login callback would create it.
+async fn sign_in(pool: &PgPool, user_id: Uuid) -> Uuid {
+    let session_id = Uuid::new_v4();
+    sqlx::query("INSERT INTO sessions (session_id, user_id, expires_at) VALUES ($1, $2, $3)")
+        .bind(session_id)
+        .bind(user_id)
+        .bind(Utc::now() + Duration::hours(1))
+        .execute(pool)
+        .await
+        .unwrap();
+    session_id
+}
+
+/// `GET /account` in the session, optionally naming the account in `X-Account-Id`.
+async fn get_account(
+    app: &Router,
+    session_id: Uuid,
+    account_id: Option<&str>,
+) -> (StatusCode, serde_json::Value) {
+    let mut request = Request::builder()
+        .uri("/account")
+        .header("Cookie", format!("session_id={session_id}"));
+    if let Some(account_id) = account_id {
+        request = request.header("X-Account-Id", account_id);
+    }
+    let response = app
+        .clone()
+        .oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .unwrap();
+    let status = response.status();
+    let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    (status, serde_json::from_slice(&body).unwrap_or_default())
+}
+
+#[tokio::test]
+async fn test_requests_act_on_the_account_in_the_header() {
+    let pool = setup_db().await;
+    let state = AppState::new(
+        pool.clone(),
+        Arc::new(SyntheticProvider::new(1)),
+        Uuid::new_v4(),
+    );
+    let app = create_router(state.clone()).with_state(state.clone());
+    let (user_id, someone_else) = (create_user(&pool).await, create_user(&pool).await);
+    let sub_account = state
+        .account_management_service
+        .create_account(user_id, "day trading")
+        .await
+        .unwrap();
+    state
+        .account_management_service
+        .add_user_balance(
+            sub_account.account_id,
+            &dec("250"),
+            JournalEntryType::Deposit,
+            None,
+        )
+        .await
+        .unwrap();
+    let session_id = sign_in(&pool, user_id).await;
+
+    let (status, body) = get_account(&app, session_id, None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["account_id"], user_id.to_string());
+    assert_eq!(
+        dec(body["available_balance"].as_str().unwrap()),
+        dec("1000000")
+    );
+
+    let (status, body) =
+        get_account(&app, session_id, Some(&sub_account.account_id.to_string())).await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(body["account_id"], sub_account.account_id.to_string());
+    assert_eq!(dec(body["available_balance"].as_str().unwrap()), dec("250"));
+
+    let (status, _) = get_account(&app, session_id, Some(&someone_else.to_string())).await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+    let (status, _) = get_account(&app, session_id, Some("not-an-account")).await;
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn test_transfers_move_cash_between_accounts_atomically() {
+    let pool = setup_db().await;
+    let service = AccountManagementService::new(pool.clone());
+    let (user_id, someone_else) = (create_user(&pool).await, create_user(&pool).await);
+    let long_term = service.create_account(user_id, "long-term").await.unwrap();
+    assert!(matches!(
+        service.create_account(user_id, " long-term ").await,
+        Err(UserError::AccountNameAlreadyExists)
+    ));
+    let cash = |account_id| {
+        let service = &service;
+        async move {
+            service
+                .get_account(user_id, account_id)
+                .await
+                .unwrap()
+                .available_balance
+        }
+    };
+
+    let transfer = service
+        .transfer_funds(user_id, user_id, long_term.account_id, &dec("400"))
+        .await
+        .unwrap();
+    assert_eq!(cash(user_id).await, dec("999600"));
+    assert_eq!(cash(long_term.account_id).await, dec("400"));
+    let legs: Vec<Uuid> = sqlx::query_scalar(
+        "SELECT account_id FROM journal_entries WHERE reference_id = $1 ORDER BY account_id",
+    )
+    .bind(transfer.transfer_id)
+    .fetch_all(&pool)
+    .await
+    .unwrap();
+    let mut accounts = vec![user_id, long_term.account_id];
+    accounts.sort();
+    assert_eq!(legs, accounts);
+
+    assert!(matches!(
+        service
+            .transfer_funds(user_id, long_term.account_id, user_id, &dec("400.01"))
+            .await,
+        Err(TradeError::UserError(UserError::InsufficientFunds))
+    ));
+    assert!(matches!(
+        service
+            .transfer_funds(user_id, user_id, someone_else, &dec("1"))
+            .await,
+        Err(TradeError::UserError(UserError::AccountNotFound))
+    ));
+    assert_eq!(cash(user_id).await, dec("999600"));
+    assert_eq!(cash(long_term.account_id).await, dec("400"));
+    assert_eq!(
+        service.list_accounts(user_id).await.unwrap().len(),
+        2,
+        "the default account and the sub-account"
+    );
+}
+
+#[tokio::test]
+async fn test_opening_an_account_twice_funds_it_once() {
+    let pool = setup_db().await;
+    let service = AccountManagementService::new(pool.clone());
+    let user_id = create_user(&pool).await;
+
+    service.open_account(user_id).await.unwrap();
+    let account = service.get_account(user_id, user_id).await.unwrap();
+    assert_eq!(
+        (account.balance, account.available_balance),
+        (dec("1000000"), dec("1000000"))
+    );
+    let openings: i64 = sqlx::query_scalar(
+        "SELECT COUNT(*) FROM journal_entries WHERE account_id = $1 AND entry_type = $2",
+    )
+    .bind(user_id)
+    .bind(JournalEntryType::OpeningBalance)
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!(openings, 1);
+}
@@ -0,0 +1,237 @@
+mod common;
+
+use backend::models::alert::{Alert, AlertCondition, AlertRequest, PriceUpdate};
+use backend::models::errors::trade_error::TradeError;
+use backend::models::order::OrderType;
+use backend::services::alert_service::AlertService;
+use chrono::Utc;
+use common::{create_user, dec, filled_order, setup_db, trade_service, unique_ticker};
+use uuid::Uuid;
+
+fn request(condition: AlertCondition, threshold: Option<&str>) -> AlertRequest {
+    AlertRequest {
+        ticker: " msft".to_string(),
+        condition,
+        threshold: threshold.map(dec),
+        recurring: false,
+    }
+}
+
+fn alert(condition: AlertCondition, threshold: &str) -> Alert {
+    Alert {
+        alert_id: Uuid::new_v4(),
+        ticker: "MSFT".to_string(),
+        condition,
+        threshold: Some(dec(threshold)),
+        recurring: true,
+        active: true,
+        armed: true,
+        trigger_count: 0,
+        created_at: Utc::now(),
+        last_triggered_at: None,
+    }
+}
+
+/// MSFT at `price` after closing at 100 yesterday, with 3,000 shares traded today against a
+/// daily average of 1,000.
+fn update(price: &str) -> PriceUpdate {
+    PriceUpdate {
+        ticker: "MSFT".to_string(),
+        price: dec(price),
+        previous_close: Some(dec("100")),
+        day_volume: Some(3000),
+        average_volume: Some(dec("1000")),
+    }
+}
+
+#[test]
+fn normalise_checks_the_threshold_suits_the_condition() {
+    let normalised = request(AlertCondition::PriceAbove, Some("105.1234567"))
+        .normalise()
+        .unwrap();
+    assert_eq!(normalised.ticker, "MSFT");
+    assert_eq!(normalised.threshold, Some(dec("105.123457")));
+
+    assert!(request_fails(AlertCondition::PriceBelow, None));
+    assert!(request_fails(AlertCondition::PercentMove, Some("-5")));
+    assert!(request_fails(AlertCondition::VolumeSpike, Some("1")));
+    assert!(request_fails(AlertCondition::OrderFilled, Some("1")));
+    assert!(request(AlertCondition::OrderFilled, None)
+        .normalise()
+        .is_ok());
+}
+
+fn request_fails(condition: AlertCondition, threshold: Option<&str>) -> bool {
+    request(condition, threshold).normalise().is_err()
+}
+
+#[test]
+fn price_alerts_hold_at_and_beyond_their_level() {
+    let above = alert(AlertCondition::PriceAbove, "105");
+    assert!(above.check(&update("104.99")).is_none());
+    assert!(above.check(&update("105")).is_some());
+    let below = alert(AlertCondition::PriceBelow, "95");
+    assert!(below.check(&update("95.01")).is_none());
+    assert!(below.check(&update("94")).is_some());
+}
+
+#[test]
+fn percent_move_holds_either_way_from_the_previous_close() {
+    let move_alert = alert(AlertCondition::PercentMove, "5");
+    assert!(move_alert.check(&update("104.99")).is_none());
+    assert_eq!(
+        move_alert.check(&update("94.5")).unwrap(),
+        "MSFT has moved -5.50% today to 94.5"
+    );
+    let mut no_history = update("80");
+    no_history.previous_close = None;
+    assert!(move_alert.check(&no_history).is_none());
+}
+
+#[test]
+fn volume_spike_compares_today_with_the_daily_average() {
+    assert!(alert(AlertCondition::VolumeSpike, "3")
+        .check(&update("100"))
+        .is_some());
+    assert!(alert(AlertCondition::VolumeSpike, "3.5")
+        .check(&update("100"))
+        .is_none());
+    assert!(alert(AlertCondition::OrderFilled, "1")
+        .check(&update("100"))
+        .is_none());
+}
+
+/// An alert on `ticker` for `user_id`, created through the service.
+async fn alert_on(
+    service: &AlertService,
+    user_id: Uuid,
+    ticker: &str,
+    condition: AlertCondition,
+    threshold: Option<&str>,
+    recurring: bool,
+) -> Alert {
+    service
+        .create_alert(
+            user_id,
+            AlertRequest {
+                ticker: ticker.to_string(),
+                condition,
+                threshold: threshold.map(dec),
+                recurring,
+            },
+        )
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn price_alerts_notify_once_per_crossing() {
+    let pool = setup_db().await;
+    let service = AlertService::new(pool.clone());
+    let (user_id, someone_else) = (create_user(&pool).await, create_user(&pool).await);
+    let ticker = unique_ticker();
+    let recurring = alert_on(
+        &service,
+        user_id,
+        &ticker,
+        AlertCondition::PriceAbove,
+        Some("100"),
+        true,
+    )
+    .await;
+    let one_shot = alert_on(
+        &service,
+        user_id,
+        &ticker,
+        AlertCondition::PriceBelow,
+        Some("90"),
+        false,
+    )
+    .await;
+
+    assert_eq!(service.on_price(&ticker, &dec("101")).await.unwrap(), 1);
+    assert_eq!(
+        service.on_price(&ticker, &dec("102")).await.unwrap(),
+        0,
+        "not armed again yet"
+    );
+    assert_eq!(service.on_price(&ticker, &dec("95")).await.unwrap(), 0);
+    assert_eq!(
+        service.on_price(&ticker, &dec("100")).await.unwrap(),
+        1,
+        "armed again below the level"
+    );
+    assert_eq!(service.on_price(&ticker, &dec("89")).await.unwrap(), 1);
+    assert_eq!(service.on_price(&ticker, &dec("80")).await.unwrap(), 0);
+
+    let alerts = service.list_alerts(user_id).await.unwrap();
+    let state = |alert_id| {
+        let alert = alerts
+            .iter()
+            .find(|alert| alert.alert_id == alert_id)
+            .unwrap();
+        (alert.active, alert.trigger_count)
+    };
+    assert_eq!(state(recurring.alert_id), (true, 2));
+    assert_eq!(state(one_shot.alert_id), (false, 1));
+
+    let inbox = service.get_notifications(user_id, true, 10).await.unwrap();
+    assert_eq!(inbox.unread, 3);
+    assert_eq!(inbox.notifications[0].price, Some(dec("89")));
+    let newest = inbox.notifications[0].notification_id;
+    assert!(matches!(
+        service.mark_read(someone_else, newest).await,
+        Err(TradeError::NotificationNotFound)
+    ));
+    service.mark_read(user_id, newest).await.unwrap();
+    assert_eq!(
+        service
+            .get_notifications(user_id, true, 10)
+            .await
+            .unwrap()
+            .unread,
+        2
+    );
+    service.mark_all_read(user_id).await.unwrap();
+    let inbox = service.get_notifications(user_id, false, 10).await.unwrap();
+    assert_eq!((inbox.unread, inbox.notifications.len()), (0, 3));
+
+    assert!(matches!(
+        service.delete_alert(someone_else, recurring.alert_id).await,
+        Err(TradeError::AlertNotFound)
+    ));
+    service
+        .delete_alert(user_id, recurring.alert_id)
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn order_filled_alerts_notify_the_orders_owner_once() {
+    let pool = setup_db().await;
+    let trades = trade_service(&pool);
+    let service = AlertService::new(pool.clone());
+    let user_id = create_user(&pool).await;
+    let ticker = unique_ticker();
+    alert_on(
+        &service,
+        user_id,
+        &ticker,
+        AlertCondition::OrderFilled,
+        None,
+        true,
+    )
+    .await;
+
+    let order_id = filled_order(&trades, user_id, &ticker, OrderType::Buy, "10", "25").await;
+    assert_eq!(service.on_order_filled(order_id).await.unwrap(), 1);
+    assert_eq!(service.on_order_filled(order_id).await.unwrap(), 0);
+
+    let inbox = service.get_notifications(user_id, true, 10).await.unwrap();
+    assert_eq!(inbox.unread, 1);
+    assert_eq!(inbox.notifications[0].order_id, Some(order_id));
+    assert_eq!(
+        inbox.notifications[0].message,
+        format!("Your buy order for 10 {ticker} was filled at an average of 25")
+    );
+}
@@ -0,0 +1,137 @@
+mod common;
+
+use backend::models::allocation::{Holding, PortfolioAllocation};
+use backend::models::instrument::{AssetClass, Instrument, InstrumentDetails};
+use backend::models::order::OrderType;
+use bigdecimal::BigDecimal;
+use chrono::Utc;
+use common::{create_user, dec, filled_order, set_price, setup_db, trade_service, unique_ticker};
+
+fn holding(ticker: &str, market_value: &str, sector: Option<&str>) -> Holding {
+    Holding {
+        ticker: ticker.to_string(),
+        quantity: dec("1"),
+        market_value: dec(market_value),
+        instrument: sector.map(|sector| Instrument {
+            ticker: ticker.to_string(),
+            name: ticker.to_string(),
+            asset_class: AssetClass::Equity,
+            sector: Some(sector.to_string()),
+            lot_size: dec("1"),
+            updated_at: Utc::now(),
+        }),
+    }
+}
+
+#[test]
+fn test_allocation_weights_and_groups_add_up_with_cash() {
+    let allocation = PortfolioAllocation::compute(
+        dec("500"),
+        vec![
+            holding("MSFT", "250", Some("Information Technology")),
+            holding("XYZ", "100", None),
+            holding("AAPL", "150", Some("Information Technology")),
+        ],
+    );
+    assert_eq!(allocation.total_value, dec("1000"));
+    assert_eq!(allocation.cash_weight, dec("0.5"));
+    let largest = allocation.largest_position.unwrap();
+    assert_eq!(largest.ticker, "MSFT");
+    assert_eq!(largest.weight, dec("0.25"));
+    assert_eq!(allocation.positions[2].ticker, "XYZ");
+
+    assert_eq!(allocation.by_sector[0].name, "Information Technology");
+    assert_eq!(allocation.by_sector[0].weight, dec("0.4"));
+    assert_eq!(
+        allocation.by_sector[1].name,
+        PortfolioAllocation::UNCLASSIFIED
+    );
+    assert_eq!(allocation.by_asset_class[0].name, "Equity");
+    assert_eq!(allocation.by_asset_class[0].market_value, dec("400"));
+    //0.5² + 0.3² + 0.2² of the 500 invested
+    assert_eq!(allocation.herfindahl_index, dec("0.38"));
+}
+
+#[test]
+fn test_allocation_of_an_all_cash_account() {
+    let allocation = PortfolioAllocation::compute(dec("100"), Vec::new());
+    assert_eq!(allocation.cash_weight, dec("1"));
+    assert_eq!(allocation.herfindahl_index, dec("0"));
+    assert!(allocation.largest_position.is_none());
+    assert!(allocation.by_sector.is_empty());
+}
+
+#[tokio::test]
+async fn test_allocation_values_positions_at_the_latest_prices() {
+    let pool = setup_db().await;
+    let trade_service = trade_service(&pool);
+    let account_id = create_user(&pool).await;
+    trade_service
+        .fee_service
+        .set_user_tier(account_id, "COMMISSION_FREE")
+        .await
+        .unwrap();
+    let (classified, unclassified) = (unique_ticker(), unique_ticker());
+    trade_service
+        .ticker_service
+        .upsert_instrument(
+            &classified,
+            &InstrumentDetails {
+                name: "Classified Inc.".to_string(),
+                asset_class: AssetClass::Equity,
+                sector: Some("Industrials".to_string()),
+                lot_size: dec("1"),
+            },
+        )
+        .await
+        .unwrap();
+    filled_order(
+        &trade_service,
+        account_id,
+        &classified,
+        OrderType::Buy,
+        "10",
+        "100",
+    )
+    .await;
+    filled_order(
+        &trade_service,
+        account_id,
+        &unclassified,
+        OrderType::Buy,
+        "20",
+        "50",
+    )
+    .await;
+    set_price(&trade_service, &classified, "150").await;
+    set_price(&trade_service, &unclassified, "25").await;
+
+    let allocation = trade_service
+        .portfolio_management_service
+        .get_allocation(account_id)
+        .await
+        .unwrap();
+    //accounts open with 1,000,000 and 2,000 of it went on the shares
+    assert_eq!(allocation.cash, dec("998000"));
+    assert_eq!(allocation.total_value, dec("1000000"));
+    let positions: Vec<(&str, BigDecimal)> = allocation
+        .positions
+        .iter()
+        .map(|p| (p.ticker.as_str(), p.market_value.clone()))
+        .collect();
+    assert_eq!(
+        positions,
+        vec![
+            (classified.as_str(), dec("1500")),
+            (unclassified.as_str(), dec("500"))
+        ]
+    );
+    assert_eq!(allocation.positions[0].weight, dec("0.0015"));
+    assert_eq!(allocation.by_sector[0].name, "Industrials");
+    assert_eq!(
+        allocation.by_asset_class[1].name,
+        PortfolioAllocation::UNCLASSIFIED
+    );
+    //0.75² + 0.25² of the 2,000 invested
+    assert_eq!(allocation.herfindahl_index, dec("0.625"));
+}
@@ -0,0 +1,107 @@
+use backend::models::analytics::{
+    Benchmark, BenchmarkComparison, DailyReturn, DailyValuation, PerformanceAnalytics,
+};
+use chrono::NaiveDate;
+
+fn day(d: u32, value: f64, net_flow: f64) -> DailyValuation {
+    DailyValuation {
+        date: NaiveDate::from_ymd_opt(2026, 3, d).unwrap(),
+        value,
+        net_flow,
+    }
+}
+
+fn close_to(actual: f64, expected: f64) -> bool {
+    (actual - expected).abs() < 1e-9
+}
+
+#[test]
+fn test_time_weighted_return_ignores_deposits() {
+    //up 10%, then a 1,000 deposit, then down 10%
+    let days = [day(1, 1100.0, 0.0), day(2, 1890.0, 1000.0)];
+    let analytics = PerformanceAnalytics::compute(1000.0, &days, 0.0).unwrap();
+    assert!(close_to(analytics.time_weighted_return.unwrap(), -0.01));
+    assert!(close_to(analytics.net_flows, 1000.0));
+    assert!(close_to(analytics.best_day.unwrap().daily_return, 0.1));
+    assert!(close_to(analytics.worst_day.unwrap().daily_return, -0.1));
+    //most of the money was in for the losing day, so it lost more than the account's returns did
+    let mwr = analytics.money_weighted_return.unwrap();
+    assert!((mwr - -0.0729).abs() < 1e-4);
+}
+
+#[test]
+fn test_max_drawdown_runs_from_peak_to_trough() {
+    let days = [
+        day(1, 120.0, 0.0),
+        day(2, 90.0, 0.0),
+        day(3, 60.0, 0.0),
+        day(4, 100.0, 0.0),
+    ];
+    let drawdown = PerformanceAnalytics::compute(100.0, &days, 0.0)
+        .unwrap()
+        .max_drawdown
+        .unwrap();
+    assert!(close_to(drawdown.depth, 0.5));
+    assert_eq!(drawdown.peak_date, days[0].date);
+    assert_eq!(drawdown.trough_date, days[2].date);
+}
+
+#[test]
+fn test_statistics_need_enough_history() {
+    let analytics = PerformanceAnalytics::compute(100.0, &[day(1, 101.0, 0.0)], 0.0).unwrap();
+    assert!(close_to(analytics.time_weighted_return.unwrap(), 0.01));
+    assert!(analytics.annualised_volatility.is_none());
+    assert!(analytics.sharpe_ratio.is_none());
+    //an empty account has no returns at all
+    let empty = PerformanceAnalytics::compute(0.0, &[day(1, 0.0, 0.0)], 0.0).unwrap();
+    assert!(empty.time_weighted_return.is_none());
+    assert!(empty.max_drawdown.is_none());
+}
+
+#[test]
+fn test_benchmark_parses_tickers_and_scales_weights() {
+    let basket = Benchmark::parse("aapl:3, MSFT:1").unwrap();
+    assert_eq!(
+        basket.tickers(),
+        vec!["AAPL".to_string(), "MSFT".to_string()]
+    );
+    assert!(close_to(basket.weights()[0].weight, 0.75));
+    assert!(close_to(
+        Benchmark::parse("SPY").unwrap().weights()[0].weight,
+        1.0
+    ));
+    assert!(Benchmark::parse("AAPL:0").is_err());
+    assert!(Benchmark::parse("AAPL,AAPL").is_err());
+    assert!(Benchmark::parse("AAPL:x").is_err());
+}
+
+#[test]
+fn test_benchmark_comparison_of_a_leveraged_account() {
+    let date = |d: u32| NaiveDate::from_ymd_opt(2026, 3, d).unwrap();
+    //the account moves twice as far as the benchmark every day
+    let levels = [
+        (date(1), 100.0),
+        (date(2), 110.0),
+        (date(3), 99.0),
+        (date(4), 104.94),
+    ];
+    let returns: Vec<DailyReturn> = levels
+        .windows(2)
+        .map(|pair| DailyReturn {
+            date: pair[1].0,
+            daily_return: 2.0 * (pair[1].1 / pair[0].1 - 1.0),
+        })
+        .collect();
+    let comparison =
+        BenchmarkComparison::compute(Benchmark::parse("SPY").unwrap(), &returns, &levels).unwrap();
+    assert!(close_to(comparison.beta.unwrap(), 2.0));
+    assert!(close_to(comparison.alpha.unwrap(), 0.0));
+    assert!(close_to(comparison.benchmark_return, 0.0494));
+    //1.2 * 0.8 * 1.12
+    assert!(close_to(comparison.portfolio_return.unwrap(), 0.0752));
+    assert!(close_to(comparison.excess_return.unwrap(), 0.0258));
+    assert_eq!(comparison.series.len(), 3);
+    assert!(close_to(comparison.series[0].benchmark, 1.1));
+    assert!(close_to(comparison.series[0].portfolio, 1.2));
+    assert!(comparison.tracking_error.unwrap() > 0.0);
+}
@@ -0,0 +1,199 @@
+//! Helpers shared by the integration tests. Not every test crate uses all of them.
+#![allow(dead_code)]
+
+use std::env;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use backend::authentication::basic_client::AuthorizationClient;
+use backend::models::fee::Liquidity;
+use backend::models::order::{OrderStatus, OrderType};
+use backend::services::account_management_service::AccountManagementService;
+use backend::services::alert_service::AlertService;
+use backend::services::fee_service::FeeService;
+use backend::services::market_data_provider::SyntheticProvider;
+use backend::services::order_management_service::OrderManagementService;
+use backend::services::order_matchbook_service::OrderMatchbookService;
+use backend::services::portfolio_management_service::PortfolioManagementService;
+use backend::services::ticker_service::TickerService;
+use backend::services::trade_service::TradeService;
+use backend::services::user_service::UserService;
+use bigdecimal::BigDecimal;
+use chrono::Utc;
+use dotenv::dotenv;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub fn dec(value: &str) -> BigDecimal {
+    BigDecimal::from_str(value).unwrap()
+}
+
+pub async fn setup_db() -> PgPool {
+    dotenv().ok();
+    let db_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+    PgPool::connect(&db_url)
+        .await
+        .expect("Failed to connect to DB")
+}
+
+/// A new user and their default account, whose id is the user id, opened with the starting
+/// balance the way registration does.
+pub async fn create_user(pool: &PgPool) -> Uuid {
+    let user_id = Uuid::new_v4();
+    sqlx::query(
+        "INSERT INTO users (user_id, auth_user_id, username, email) VALUES ($1, $2, $3, $4)",
+    )
+    .bind(user_id)
+    .bind(format!("auth0|{}", user_id))
+    .bind(format!("user_{}", user_id))
+    .bind(format!("user_{}@example.com", user_id))
+    .execute(pool)
+    .await
+    .unwrap();
+    AccountManagementService::new(pool.clone())
+        .open_account(user_id)
+        .await
+        .unwrap();
+    user_id
+}
+
+/// The trade service and everything a fill goes through, on `pool`.
+pub fn trade_service(pool: &PgPool) -> TradeService {
+    let ticker_service = Arc::new(TickerService::new(
+        Arc::new(SyntheticProvider::new(1)),
+        pool.clone(),
+    ));
+    TradeService::new(
+        pool.clone(),
+        ticker_service.clone(),
+        Arc::new(AccountManagementService::new(pool.clone())),
+        Arc::new(PortfolioManagementService::new(
+            pool.clone(),
+            ticker_service,
+        )),
+        Arc::new(FeeService::new(pool.clone())),
+    )
+}
+
+/// Order placement on top of `trade_service`, with a book of its own. Needs the auth settings
+/// in the environment, like the server does.
+pub fn order_management_service(trade_service: &TradeService) -> OrderManagementService {
+    let db = trade_service.db.clone();
+    let order_matchbook_service = Arc::new(OrderMatchbookService::new(
+        db.clone(),
+        Arc::new(trade_service.clone()),
+        trade_service.ticker_service.clone(),
+        Arc::new(AlertService::new(db.clone())),
+    ));
+    let user_service = Arc::new(UserService::new(
+        db.clone(),
+        trade_service.account_management_service.clone(),
+        trade_service.portfolio_management_service.clone(),
+        Arc::new(AuthorizationClient::new()),
+    ));
+    OrderManagementService::new(
+        db,
+        user_service,
+        trade_service.ticker_service.clone(),
+        trade_service.account_management_service.clone(),
+        trade_service.portfolio_management_service.clone(),
+        order_matchbook_service,
+        trade_service.fee_service.clone(),
+    )
+}
+
+/// A pending order written straight to the orders table, with its cash (and worst-case fee) or
+/// shares reserved the way placing it would, but kept out of the in-memory book.
+pub async fn pending_order(
+    trade_service: &TradeService,
+    account_id: Uuid,
+    ticker: &str,
+    order_type: OrderType,
+    quantity: &str,
+    price_per_share: &str,
+) -> Uuid {
+    let order_id = Uuid::new_v4();
+    let (quantity, price_per_share) = (dec(quantity), dec(price_per_share));
+    match order_type {
+        OrderType::Buy => {
+            let max_fee = trade_service
+                .fee_service
+                .max_order_fee(account_id, &quantity, &price_per_share)
+                .await
+                .unwrap();
+            trade_service
+                .account_management_service
+                .reserve_funds(
+                    account_id,
+                    &(&quantity * &price_per_share + max_fee),
+                    Some(order_id),
+                )
+                .await
+                .unwrap();
+        }
+        OrderType::Sell => trade_service
+            .portfolio_management_service
+            .reserve_holdings(account_id, ticker, &quantity)
+            .await
+            .unwrap(),
+    }
+    sqlx::query(
+        "INSERT INTO orders (order_id, account_id, ticker, quantity, price_per_share, order_type, status)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)",
+    )
+    .bind(order_id)
+    .bind(account_id)
+    .bind(ticker)
+    .bind(&quantity)
+    .bind(&price_per_share)
+    .bind(order_type)
+    .bind(OrderStatus::Pending)
+    .execute(&trade_service.db)
+    .await
+    .unwrap();
+    order_id
+}
+
+/// An order filled in full at its own price, as the taker.
+pub async fn filled_order(
+    trade_service: &TradeService,
+    account_id: Uuid,
+    ticker: &str,
+    order_type: OrderType,
+    quantity: &str,
+    price_per_share: &str,
+) -> Uuid {
+    let order_id = pending_order(
+        trade_service,
+        account_id,
+        ticker,
+        order_type,
+        quantity,
+        price_per_share,
+    )
+    .await;
+    trade_service
+        .execute_order(
+            order_id,
+            dec(quantity),
+            dec(price_per_share),
+            Liquidity::Taker,
+        )
+        .await
+        .unwrap();
+    order_id
+}
+
+/// A ticker no other test trades, so its positions and prices are the test's own.
+pub fn unique_ticker() -> String {
+    format!("T{}", &Uuid::new_v4().simple().to_string()[..6]).to_uppercase()
+}
+
+/// Makes `price` the ticker's latest quote, as a trade at that price would.
+pub async fn set_price(trade_service: &TradeService, ticker: &str, price: &str) {
+    trade_service
+        .ticker_service
+        .record_trade(ticker, &dec(price), &dec("1"), Utc::now())
+        .await
+        .unwrap();
+}
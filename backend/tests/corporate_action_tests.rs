@@ -0,0 +1,283 @@
+mod common;
+
+use std::str::FromStr;
+use std::sync::Arc;
+
+use backend::models::corporate_action::{CorporateActionType, NewCorporateAction, SplitRatio};
+use backend::models::order::OrderType;
+use backend::services::alert_service::AlertService;
+use backend::services::corporate_action_service::CorporateActionService;
+use backend::services::market_maker_service::MarketMakerService;
+use backend::services::order_matchbook_service::OrderMatchbookService;
+use backend::services::trade_service::TradeService;
+use bigdecimal::BigDecimal;
+use chrono::{NaiveDate, Utc};
+use common::{
+    create_user, dec, filled_order, order_management_service, pending_order, set_price, setup_db,
+    trade_service, unique_ticker,
+};
+use uuid::Uuid;
+
+fn new_action(
+    action_type: CorporateActionType,
+    dividend_per_share: Option<&str>,
+    split_ratio: Option<(i32, i32)>,
+) -> NewCorporateAction {
+    NewCorporateAction {
+        ticker: "AAPL".to_string(),
+        action_type,
+        effective_date: NaiveDate::from_ymd_opt(2030, 1, 2).unwrap(),
+        dividend_per_share: dividend_per_share.map(|d| BigDecimal::from_str(d).unwrap()),
+        split_ratio: split_ratio.map(|(new_shares, old_shares)| SplitRatio {
+            new_shares,
+            old_shares,
+        }),
+    }
+}
+
+#[test]
+fn test_split_ratio_rescales_quantity_and_price() {
+    let two_for_one = SplitRatio {
+        new_shares: 2,
+        old_shares: 1,
+    };
+    assert_eq!(
+        two_for_one.adjust_quantity(&BigDecimal::from(15)),
+        BigDecimal::from(30)
+    );
+    assert_eq!(
+        two_for_one.adjust_price(&BigDecimal::from_str("101.25").unwrap()),
+        BigDecimal::from_str("50.625").unwrap()
+    );
+
+    //rounded down to 4 places so a rescaled buy never costs more than its reservation
+    let one_for_three = SplitRatio {
+        new_shares: 1,
+        old_shares: 3,
+    };
+    assert_eq!(
+        one_for_three.adjust_quantity(&BigDecimal::from(10)),
+        BigDecimal::from_str("3.3333").unwrap()
+    );
+    assert_eq!(
+        one_for_three.adjust_price(&BigDecimal::from_str("10.5").unwrap()),
+        BigDecimal::from_str("31.5").unwrap()
+    );
+}
+
+#[test]
+fn test_validate_accepts_well_formed_actions() {
+    for action in [
+        new_action(CorporateActionType::Dividend, Some("0.24"), None),
+        new_action(CorporateActionType::Split, None, Some((3, 2))),
+        new_action(CorporateActionType::ReverseSplit, None, Some((1, 10))),
+    ] {
+        assert!(CorporateActionService::validate(&action).is_ok());
+    }
+}
+
+#[test]
+fn test_validate_rejects_mismatched_terms() {
+    for action in [
+        new_action(CorporateActionType::Dividend, Some("0"), None),
+        new_action(CorporateActionType::Dividend, None, Some((2, 1))),
+        new_action(CorporateActionType::Split, None, Some((1, 2))),
+        new_action(CorporateActionType::Split, Some("1"), Some((2, 1))),
+        new_action(CorporateActionType::ReverseSplit, None, Some((2, 1))),
+        new_action(CorporateActionType::ReverseSplit, None, None),
+    ] {
+        assert!(CorporateActionService::validate(&action).is_err());
+    }
+}
+
+/// Schedules the action on `ticker` for today and processes it, along with anything else due.
+async fn apply(trade_service: &TradeService, ticker: &str, action: NewCorporateAction) {
+    let db = trade_service.db.clone();
+    let alert_service = Arc::new(AlertService::new(db.clone()));
+    let corporate_action_service = CorporateActionService::new(
+        db.clone(),
+        Arc::new(OrderMatchbookService::new(
+            db.clone(),
+            Arc::new(trade_service.clone()),
+            trade_service.ticker_service.clone(),
+            alert_service.clone(),
+        )),
+        Arc::new(MarketMakerService::new(
+            db.clone(),
+            trade_service.ticker_service.clone(),
+            Arc::new(order_management_service(trade_service)),
+            alert_service,
+            Vec::new(),
+            Uuid::new_v4(),
+        )),
+    );
+    let created_by = create_user(&db).await;
+    let scheduled = corporate_action_service
+        .schedule_action(
+            created_by,
+            NewCorporateAction {
+                ticker: ticker.to_string(),
+                effective_date: Utc::now().date_naive(),
+                ..action
+            },
+        )
+        .await
+        .unwrap();
+    corporate_action_service
+        .process_due_actions()
+        .await
+        .unwrap();
+    let processed = corporate_action_service
+        .list_actions(Some(ticker))
+        .await
+        .unwrap();
+    assert!(processed
+        .iter()
+        .any(|action| action.action_id == scheduled.action_id && action.processed_at.is_some()));
+}
+
+#[tokio::test]
+async fn test_a_dividend_credits_each_holders_cash_through_the_ledger() {
+    let pool = setup_db().await;
+    let trade_service = trade_service(&pool);
+    let account_id = create_user(&pool).await;
+    let ticker = unique_ticker();
+    filled_order(
+        &trade_service,
+        account_id,
+        &ticker,
+        OrderType::Buy,
+        "10",
+        "100",
+    )
+    .await;
+    set_price(&trade_service, &ticker, "100").await;
+    let accounts = &trade_service.account_management_service;
+    let before = accounts.get_ledger_balance(account_id).await.unwrap();
+
+    apply(
+        &trade_service,
+        &ticker,
+        new_action(CorporateActionType::Dividend, Some("0.25"), None),
+    )
+    .await;
+
+    let after = accounts.get_ledger_balance(account_id).await.unwrap();
+    assert_eq!(&after.cash - &before.cash, dec("2.5"));
+    assert_eq!(
+        accounts.get_user_balance(account_id).await.unwrap(),
+        after.cash
+    );
+}
+
+#[tokio::test]
+async fn test_a_split_rescales_positions_lots_and_pending_orders() {
+    let pool = setup_db().await;
+    let trade_service = trade_service(&pool);
+    let account_id = create_user(&pool).await;
+    let ticker = unique_ticker();
+    filled_order(
+        &trade_service,
+        account_id,
+        &ticker,
+        OrderType::Buy,
+        "10",
+        "100",
+    )
+    .await;
+    set_price(&trade_service, &ticker, "100").await;
+    let sell = pending_order(
+        &trade_service,
+        account_id,
+        &ticker,
+        OrderType::Sell,
+        "4",
+        "120",
+    )
+    .await;
+    let buy = pending_order(
+        &trade_service,
+        account_id,
+        &ticker,
+        OrderType::Buy,
+        "3",
+        "90",
+    )
+    .await;
+
+    apply(
+        &trade_service,
+        &ticker,
+        new_action(CorporateActionType::Split, None, Some((2, 1))),
+    )
+    .await;
+
+    let portfolio = trade_service
+        .portfolio_management_service
+        .get_portfolio(account_id)
+        .await
+        .unwrap();
+    let position = portfolio.iter().find(|item| item.ticker == ticker).unwrap();
+    assert_eq!(position.quantity, dec("20"));
+    //the shares held for the pending sell double with it
+    assert_eq!(position.available_quantity, dec("12"));
+    let lots = trade_service
+        .portfolio_management_service
+        .get_tax_lots(account_id, Some(&ticker))
+        .await
+        .unwrap();
+    assert_eq!(lots.len(), 1);
+    assert_eq!(lots[0].quantity, dec("20"));
+    assert_eq!(lots[0].remaining_quantity, dec("20"));
+    for (order_id, quantity, price) in [(sell, "8", "60"), (buy, "6", "45")] {
+        let order = trade_service.get_order(order_id).await.unwrap();
+        assert_eq!(order.quantity, dec(quantity));
+        assert_eq!(order.price_per_share, dec(price));
+    }
+}
+
+#[tokio::test]
+async fn test_a_reverse_split_that_rounds_a_position_away_closes_it() {
+    let pool = setup_db().await;
+    let trade_service = trade_service(&pool);
+    let account_id = create_user(&pool).await;
+    let ticker = unique_ticker();
+    //a tenth of it is below the four decimal places quantities are stored with
+    filled_order(
+        &trade_service,
+        account_id,
+        &ticker,
+        OrderType::Buy,
+        "0.0005",
+        "100",
+    )
+    .await;
+    set_price(&trade_service, &ticker, "100").await;
+
+    apply(
+        &trade_service,
+        &ticker,
+        new_action(CorporateActionType::ReverseSplit, None, Some((1, 10))),
+    )
+    .await;
+
+    let portfolio_management_service = &trade_service.portfolio_management_service;
+    assert!(!portfolio_management_service
+        .get_portfolio(account_id)
+        .await
+        .unwrap()
+        .iter()
+        .any(|item| item.ticker == ticker));
+    let positions = portfolio_management_service
+        .get_positions(account_id, true)
+        .await
+        .unwrap();
+    let closed = positions.iter().find(|item| item.ticker == ticker).unwrap();
+    assert_eq!(closed.quantity, dec("0"));
+    assert_eq!(closed.available_quantity, dec("0"));
+    assert!(portfolio_management_service
+        .get_tax_lots(account_id, Some(&ticker))
+        .await
+        .unwrap()
+        .is_empty());
+}
@@ -0,0 +1,44 @@
+use backend::models::order::OrderType;
+use backend::services::export_service::{ActivityExportRow, ExportService};
+use bigdecimal::BigDecimal;
+use chrono::{TimeZone, Utc};
+use std::str::FromStr;
+use uuid::Uuid;
+
+fn trade_row() -> ActivityExportRow {
+    ActivityExportRow {
+        occurred_at: Utc.with_ymd_and_hms(2026, 1, 2, 3, 4, 5).unwrap(),
+        activity_type: "TRADE".to_string(),
+        reference_id: Uuid::nil(),
+        ticker: Some("AAPL".to_string()),
+        side: Some(OrderType::Buy),
+        quantity: Some(BigDecimal::from(3)),
+        price_per_share: Some(BigDecimal::from_str("101.3333").unwrap()),
+        fee: Some(BigDecimal::from_str("1.5").unwrap()),
+        amount: BigDecimal::from_str("-305.4999").unwrap(),
+    }
+}
+
+#[test]
+fn test_format_decimal_is_fixed_point() {
+    let large = BigDecimal::from_str("1E+7").unwrap();
+    assert_eq!(ExportService::format_decimal(&large, 2), "10000000.00");
+    let half = BigDecimal::from_str("0.125").unwrap();
+    assert_eq!(ExportService::format_decimal(&half, 2), "0.12");
+}
+
+#[test]
+fn test_csv_line() {
+    assert_eq!(
+        ExportService::to_csv_line(&trade_row()),
+        "2026-01-02T03:04:05+00:00,TRADE,00000000-0000-0000-0000-000000000000,AAPL,BUY,3.0000,101.3333,1.5000,-305.50\r\n"
+    );
+}
+
+#[test]
+fn test_ofx_trade_is_buystock() {
+    let ofx = ExportService::to_ofx_transaction(&trade_row());
+    assert!(ofx.starts_with("<BUYSTOCK><INVBUY>"));
+    assert!(ofx.contains("<DTTRADE>20260102030405</DTTRADE>"));
+    assert!(ofx.contains("<FEES>1.5000</FEES><TOTAL>-305.50</TOTAL>"));
+}
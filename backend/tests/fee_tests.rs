@@ -0,0 +1,147 @@
+mod common;
+
+use backend::models::fee::{FeeTerms, Fill, Liquidity};
+use backend::models::order::OrderType;
+use bigdecimal::BigDecimal;
+use common::{create_user, dec, pending_order, setup_db, trade_service};
+
+fn fill(liquidity: Liquidity, quantity: &str, price: &str) -> Fill {
+    Fill {
+        liquidity,
+        quantity: dec(quantity),
+        notional: dec(quantity) * dec(price),
+    }
+}
+
+#[test]
+fn test_order_fee_applies_minimum_once_per_order() {
+    let per_share = FeeTerms {
+        per_share_fee: dec("0.005"),
+        minimum_fee: dec("1.00"),
+        ..Default::default()
+    };
+    assert_eq!(
+        per_share.order_fee(&[fill(Liquidity::Taker, "10", "100")]),
+        dec("1.0000")
+    );
+    assert_eq!(
+        per_share.order_fee(&[
+            fill(Liquidity::Taker, "300", "100"),
+            fill(Liquidity::Maker, "100", "100"),
+        ]),
+        dec("2.0000")
+    );
+    assert_eq!(per_share.order_fee(&[]), dec("0"));
+}
+
+#[test]
+fn test_order_fee_pays_maker_rebates() {
+    let tiered = FeeTerms {
+        flat_fee: dec("0.10"),
+        taker_rate: dec("0.0005"),
+        maker_rate: dec("-0.0002"),
+        minimum_fee: dec("0.50"),
+        ..Default::default()
+    };
+    //0.10 + 0.0005 * 10_000 = 5.10
+    assert_eq!(
+        tiered.order_fee(&[fill(Liquidity::Taker, "100", "100")]),
+        dec("5.1000")
+    );
+    //0.10 - 0.0002 * 10_000 = -1.90, rebates are not lifted to the minimum
+    assert_eq!(
+        tiered.order_fee(&[fill(Liquidity::Maker, "100", "100")]),
+        dec("-1.9000")
+    );
+}
+
+#[test]
+fn test_max_order_fee_covers_any_fill_sequence() {
+    let tiered = FeeTerms {
+        flat_fee: dec("0.10"),
+        per_share_fee: dec("0.001"),
+        taker_rate: dec("0.0005"),
+        maker_rate: dec("-0.0002"),
+        minimum_fee: dec("0.50"),
+    };
+    let reserved = tiered.max_order_fee(&dec("100"), &dec("10000"));
+    for fills in [
+        vec![fill(Liquidity::Taker, "100", "100")],
+        vec![
+            fill(Liquidity::Maker, "40", "99.5"),
+            fill(Liquidity::Taker, "60", "100"),
+        ],
+    ] {
+        assert!(tiered.order_fee(&fills) <= reserved);
+    }
+}
+
+#[test]
+fn test_validate_rejects_negative_charges() {
+    assert!(FeeTerms::default().validate().is_ok());
+    let negative_minimum = FeeTerms {
+        minimum_fee: dec("-1"),
+        ..Default::default()
+    };
+    assert!(negative_minimum.validate().is_err());
+    let maker_above_taker = FeeTerms {
+        maker_rate: dec("0.001"),
+        ..Default::default()
+    };
+    assert!(maker_above_taker.validate().is_err());
+}
+
+#[tokio::test]
+async fn test_fills_are_charged_their_share_of_the_order_fee() {
+    let pool = setup_db().await;
+    let trade_service = trade_service(&pool);
+    let account_id = create_user(&pool).await;
+    trade_service
+        .fee_service
+        .set_user_tier(account_id, "STANDARD")
+        .await
+        .unwrap();
+    let order_id = pending_order(
+        &trade_service,
+        account_id,
+        "MSFT",
+        OrderType::Buy,
+        "400",
+        "10",
+    )
+    .await;
+
+    //the first fill alone is under the 1.00 minimum, the second brings 400 shares to 2.00
+    for quantity in ["100", "300"] {
+        trade_service
+            .execute_order(order_id, dec(quantity), dec("10"), Liquidity::Taker)
+            .await
+            .unwrap();
+    }
+    let fees: Vec<BigDecimal> =
+        sqlx::query_scalar("SELECT fee FROM transactions WHERE order_id = $1 ORDER BY quantity")
+            .bind(order_id)
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+    assert_eq!(fees, vec![dec("1"), dec("1")]);
+
+    let balance = trade_service
+        .account_management_service
+        .get_ledger_balance(account_id)
+        .await
+        .unwrap();
+    //accounts open with 1,000,000
+    assert_eq!(balance.cash, dec("995998"));
+    assert_eq!(balance.reserved, dec("0"));
+    //fees are booked against the position apart from what the shares cost
+    let (cost, fees): (BigDecimal, BigDecimal) = sqlx::query_as(
+        "SELECT total_money_spent, total_fees FROM portfolio WHERE account_id = $1 AND ticker = $2",
+    )
+    .bind(account_id)
+    .bind("MSFT")
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!((cost, fees), (dec("4000"), dec("2")));
+}
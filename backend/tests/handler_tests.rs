@@ -4,11 +4,18 @@ use axum::{
 };
 use backend::app_state::AppState;
 use backend::routes::router::create_router;
+use backend::services::market_data_provider::SyntheticProvider;
 use dotenv::dotenv;
 
 use sqlx::PgPool;
 use std::env;
+use std::sync::Arc;
 use tower::util::ServiceExt; // for `oneshot`
+use uuid::Uuid;
+
+fn app_state(pool: PgPool) -> AppState {
+    AppState::new(pool, Arc::new(SyntheticProvider::new(1)), Uuid::new_v4())
+}
 
 async fn setup_app() -> axum::Router {
     dotenv().ok();
@@ -18,17 +25,11 @@ async fn setup_app() -> axum::Router {
     let pool = match PgPool::connect(&db_url).await {
         Ok(pool) => pool,
         Err(_) => {
-            return create_router(AppState::new(
-                PgPool::connect_lazy(&db_url).unwrap(),
-                "mock",
-            ))
-            .with_state(AppState::new(
-                PgPool::connect_lazy(&db_url).unwrap(),
-                "mock",
-            ))
+            return create_router(app_state(PgPool::connect_lazy(&db_url).unwrap()))
+                .with_state(app_state(PgPool::connect_lazy(&db_url).unwrap()))
         }
     };
-    let app_state = AppState::new(pool, "mock");
+    let app_state = app_state(pool);
     create_router(app_state.clone()).with_state(app_state)
 }
 
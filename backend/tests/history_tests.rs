@@ -0,0 +1,44 @@
+use backend::models::history::{HistoryCursor, HistoryQuery};
+use chrono::Utc;
+use uuid::Uuid;
+
+#[test]
+fn test_cursor_round_trip() {
+    let cursor = HistoryCursor {
+        timestamp: chrono::DateTime::from_timestamp_micros(Utc::now().timestamp_micros()).unwrap(),
+        id: Uuid::new_v4(),
+    };
+    let decoded = HistoryCursor::decode(&cursor.encode()).unwrap();
+    assert_eq!(decoded, cursor);
+}
+
+#[test]
+fn test_invalid_cursor() {
+    assert!(HistoryCursor::decode("not-a-cursor").is_err());
+    assert!(HistoryCursor::decode("123_not-a-uuid").is_err());
+}
+
+#[test]
+fn test_into_page_sets_next_cursor_only_when_more_rows() {
+    let query = HistoryQuery {
+        limit: Some(2),
+        ..Default::default()
+    };
+    let ids: Vec<Uuid> = (0..3).map(|_| Uuid::new_v4()).collect();
+    let now = Utc::now();
+    let page = query.into_page(ids.clone(), |id| HistoryCursor {
+        timestamp: now,
+        id: *id,
+    });
+    assert_eq!(page.items.len(), 2);
+    assert_eq!(
+        HistoryCursor::decode(&page.next_cursor.unwrap()).unwrap().id,
+        ids[1]
+    );
+
+    let page = query.into_page(ids[..2].to_vec(), |id| HistoryCursor {
+        timestamp: now,
+        id: *id,
+    });
+    assert!(page.next_cursor.is_none());
+}
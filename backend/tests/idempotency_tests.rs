@@ -0,0 +1,169 @@
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    middleware::from_fn_with_state,
+    routing::post,
+    Extension, Router,
+};
+use backend::app_state::AppState;
+use backend::models::account::AccountId;
+use backend::models::errors::trade_error::TradeError;
+use backend::models::idempotency::IdempotencyOutcome;
+use backend::routes::middleware::idempotency_middleware;
+use backend::services::idempotency_service::IdempotencyService;
+use backend::services::market_data_provider::SyntheticProvider;
+use dotenv::dotenv;
+use sqlx::PgPool;
+use std::env;
+use std::sync::Arc;
+use std::time::Duration;
+use tower::util::ServiceExt;
+use uuid::Uuid;
+
+async fn setup_db() -> PgPool {
+    dotenv().ok();
+    let db_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
+    PgPool::connect(&db_url)
+        .await
+        .expect("Failed to connect to DB")
+}
+
+async fn create_user(pool: &PgPool) -> Uuid {
+    let user_id = Uuid::new_v4();
+    sqlx::query(
+        "INSERT INTO users (user_id, auth_user_id, username, email) VALUES ($1, $2, $3, $4)",
+    )
+    .bind(user_id)
+    .bind(format!("auth0|{}", user_id))
+    .bind(format!("user_{}", user_id))
+    .bind(format!("user_{}@example.com", user_id))
+    .execute(pool)
+    .await
+    .unwrap();
+    user_id
+}
+
+async fn key_exists(pool: &PgPool, user_id: Uuid, key: &str) -> bool {
+    sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM idempotency_keys WHERE user_id = $1 AND idempotency_key = $2)",
+    )
+    .bind(user_id)
+    .bind(key)
+    .fetch_one(pool)
+    .await
+    .unwrap()
+}
+
+#[test]
+fn test_hash_request_is_stable() {
+    let first =
+        IdempotencyService::hash_request(Uuid::nil(), "POST", "/orders", b"{\"quantity\":1}");
+    let second =
+        IdempotencyService::hash_request(Uuid::nil(), "POST", "/orders", b"{\"quantity\":1}");
+    assert_eq!(first, second);
+    assert_eq!(first.len(), 64);
+}
+
+#[test]
+fn test_hash_request_changes_with_request() {
+    let original =
+        IdempotencyService::hash_request(Uuid::nil(), "POST", "/orders", b"{\"quantity\":1}");
+    assert_ne!(
+        original,
+        IdempotencyService::hash_request(Uuid::nil(), "POST", "/orders", b"{\"quantity\":2}")
+    );
+    assert_ne!(
+        original,
+        IdempotencyService::hash_request(Uuid::nil(), "DELETE", "/orders", b"{\"quantity\":1}")
+    );
+    assert_ne!(
+        original,
+        IdempotencyService::hash_request(Uuid::max(), "POST", "/orders", b"{\"quantity\":1}")
+    );
+}
+
+#[tokio::test]
+async fn test_only_an_unrenewed_claim_is_taken_over() {
+    let pool = setup_db().await;
+    let service = IdempotencyService::new(pool.clone());
+    let user_id = create_user(&pool).await;
+    let hash = IdempotencyService::hash_request(user_id, "POST", "/orders", b"{}");
+
+    assert!(matches!(
+        service.begin(user_id, "order-1", &hash).await,
+        Ok(IdempotencyOutcome::Execute)
+    ));
+    assert!(matches!(
+        service.begin(user_id, "order-1", &hash).await,
+        Err(TradeError::IdempotentRequestInProgress)
+    ));
+
+    //a request that is still running keeps renewing its lease, however long ago it started
+    sqlx::query(
+        "UPDATE idempotency_keys SET created_at = NOW() - INTERVAL '5 minutes'
+        WHERE user_id = $1 AND idempotency_key = $2",
+    )
+    .bind(user_id)
+    .bind("order-1")
+    .execute(&pool)
+    .await
+    .unwrap();
+    service.renew(user_id, "order-1").await.unwrap();
+    assert!(matches!(
+        service.begin(user_id, "order-1", &hash).await,
+        Err(TradeError::IdempotentRequestInProgress)
+    ));
+
+    //the first request died without a response long enough ago for its lease to run out
+    sqlx::query(
+        "UPDATE idempotency_keys SET renewed_at = NOW() - INTERVAL '5 minutes'
+        WHERE user_id = $1 AND idempotency_key = $2",
+    )
+    .bind(user_id)
+    .bind("order-1")
+    .execute(&pool)
+    .await
+    .unwrap();
+    assert!(matches!(
+        service.begin(user_id, "order-1", &hash).await,
+        Ok(IdempotencyOutcome::Execute)
+    ));
+}
+
+#[tokio::test]
+async fn test_dropped_request_releases_its_key() {
+    let pool = setup_db().await;
+    let user_id = create_user(&pool).await;
+    let state = AppState::new(
+        pool.clone(),
+        Arc::new(SyntheticProvider::new(1)),
+        Uuid::new_v4(),
+    );
+    let app = Router::new()
+        .route("/slow", post(std::future::pending::<StatusCode>))
+        .route_layer(from_fn_with_state(state.clone(), idempotency_middleware))
+        .layer(Extension(AccountId(user_id)))
+        .layer(Extension(user_id))
+        .with_state(state);
+
+    let request = Request::builder()
+        .method("POST")
+        .uri("/slow")
+        .header("Idempotency-Key", "slow-1")
+        .body(Body::empty())
+        .unwrap();
+    //the client gives up before the handler finishes
+    assert!(
+        tokio::time::timeout(Duration::from_millis(200), app.oneshot(request))
+            .await
+            .is_err()
+    );
+
+    for _ in 0..50 {
+        if !key_exists(&pool, user_id, "slow-1").await {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("the idempotency key was not released");
+}
@@ -0,0 +1,165 @@
+mod common;
+
+use backend::models::interest::{InterestRateTier, InterestRateTiers};
+use backend::models::ledger::{JournalEntryType, LedgerAccount};
+use backend::services::interest_service::InterestService;
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Days, NaiveDate, Utc};
+use common::{dec, setup_db};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+fn tier(min_balance: &str, annual_rate: &str) -> InterestRateTier {
+    InterestRateTier {
+        min_balance: dec(min_balance),
+        annual_rate: dec(annual_rate),
+    }
+}
+
+#[test]
+fn test_daily_interest_applies_each_rate_to_its_band() {
+    let tiers = InterestRateTiers::new(vec![tier("10000", "0.0365"), tier("0", "0.0073")]).unwrap();
+    //7.3% of 1000 over 365 days
+    assert_eq!(tiers.daily_interest(&dec("1000")), dec("0.0200"));
+    //the first 10,000 at 0.73% and the 5,000 above it at 3.65%
+    assert_eq!(tiers.daily_interest(&dec("15000")), dec("0.7000"));
+    assert_eq!(tiers.daily_interest(&dec("0")), dec("0"));
+}
+
+#[test]
+fn test_daily_interest_rounds_down_and_skips_balances_below_first_tier() {
+    let tiers = InterestRateTiers::new(vec![tier("500", "0.01")]).unwrap();
+    assert_eq!(tiers.daily_interest(&dec("400")), dec("0"));
+    //500 * 0.01 / 365 = 0.0136986...
+    assert_eq!(tiers.daily_interest(&dec("1000")), dec("0.0136"));
+    assert_eq!(
+        InterestRateTiers::default().daily_interest(&dec("1000")),
+        dec("0")
+    );
+}
+
+#[test]
+fn test_rate_tiers_reject_negative_and_duplicate_tiers() {
+    assert!(InterestRateTiers::new(vec![tier("0", "-0.01")]).is_err());
+    assert!(InterestRateTiers::new(vec![tier("-1", "0.01")]).is_err());
+    assert!(InterestRateTiers::new(vec![tier("0", "0.01"), tier("0.0", "0.02")]).is_err());
+}
+
+/// An account opened at `opened` and paid `deposits` at the given times, written straight
+/// into the ledger so they can lie in the past.
+async fn account_with_deposits(
+    pool: &PgPool,
+    opened: DateTime<Utc>,
+    deposits: &[(DateTime<Utc>, &str)],
+) -> Uuid {
+    let account_id = Uuid::new_v4();
+    sqlx::query(
+        "INSERT INTO users (user_id, auth_user_id, username, email) VALUES ($1, $2, $3, $4)",
+    )
+    .bind(account_id)
+    .bind(format!("auth0|{}", account_id))
+    .bind(format!("user_{}", account_id))
+    .bind(format!("user_{}@example.com", account_id))
+    .execute(pool)
+    .await
+    .unwrap();
+    sqlx::query(
+        "INSERT INTO accounts (account_id, user_id, name, created_at) VALUES ($1, $1, 'default', $2)",
+    )
+    .bind(account_id)
+    .bind(opened)
+    .execute(pool)
+    .await
+    .unwrap();
+    for (at, amount) in deposits {
+        let entry_id = Uuid::new_v4();
+        sqlx::query(
+            "INSERT INTO journal_entries (entry_id, account_id, entry_type, created_at)
+            VALUES ($1, $2, $3, $4)",
+        )
+        .bind(entry_id)
+        .bind(account_id)
+        .bind(JournalEntryType::Deposit)
+        .bind(at)
+        .execute(pool)
+        .await
+        .unwrap();
+        for (account, amount) in [
+            (LedgerAccount::UserCash, dec(amount)),
+            (LedgerAccount::ExternalBank, -dec(amount)),
+        ] {
+            sqlx::query(
+                "INSERT INTO journal_lines (line_id, entry_id, account, amount)
+                VALUES ($1, $2, $3, $4)",
+            )
+            .bind(Uuid::new_v4())
+            .bind(entry_id)
+            .bind(account)
+            .bind(amount)
+            .execute(pool)
+            .await
+            .unwrap();
+        }
+    }
+    account_id
+}
+
+async fn accrued_balance(pool: &PgPool, account_id: Uuid, day: NaiveDate) -> Option<BigDecimal> {
+    sqlx::query_scalar(
+        "SELECT balance FROM interest_accruals WHERE account_id = $1 AND accrual_date = $2",
+    )
+    .bind(account_id)
+    .bind(day)
+    .fetch_optional(pool)
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn test_each_day_accrues_on_its_end_of_day_balance() {
+    let pool = setup_db().await;
+    let service = InterestService::new(pool.clone(), Uuid::nil());
+    let first = Utc::now().date_naive() - Days::new(20);
+    let second = first + Days::new(1);
+    let noon = |day: NaiveDate| day.and_hms_opt(12, 0, 0).unwrap().and_utc();
+    let account_id = account_with_deposits(
+        &pool,
+        noon(first - Days::new(1)),
+        &[(noon(first), "1000"), (noon(second), "9000")],
+    )
+    .await;
+
+    for day in [first - Days::new(1), first, second, second] {
+        service.accrue_interest(day).await.unwrap();
+    }
+    assert_eq!(
+        accrued_balance(&pool, account_id, first - Days::new(1)).await,
+        None,
+        "nothing to pay on before the first deposit"
+    );
+    assert_eq!(
+        accrued_balance(&pool, account_id, first).await,
+        Some(dec("1000"))
+    );
+    assert_eq!(
+        accrued_balance(&pool, account_id, second).await,
+        Some(dec("10000"))
+    );
+
+    let tiers = service.get_rate_tiers().await.unwrap();
+    let paid: BigDecimal = sqlx::query_scalar(
+        "SELECT COALESCE(SUM(l.amount), 0) FROM journal_entries e
+        JOIN journal_lines l ON l.entry_id = e.entry_id
+        WHERE e.account_id = $1 AND e.entry_type = $2 AND l.account = $3",
+    )
+    .bind(account_id)
+    .bind(JournalEntryType::Interest)
+    .bind(LedgerAccount::UserCash)
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!(
+        paid,
+        tiers.daily_interest(&dec("1000")) + tiers.daily_interest(&dec("10000"))
+    );
+}
@@ -0,0 +1,215 @@
+mod common;
+
+use backend::models::errors::trade_error::TradeError;
+use backend::models::errors::user_error::UserError;
+use backend::models::ledger::JournalEntryType;
+use backend::services::account_management_service::AccountManagementService;
+use bigdecimal::BigDecimal;
+use common::{create_user, dec, setup_db};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// The ledger's cash and reserved totals for the account next to its cached balances.
+async fn balances(
+    service: &AccountManagementService,
+    user_id: Uuid,
+) -> ((BigDecimal, BigDecimal), (BigDecimal, BigDecimal)) {
+    let ledger = service.get_ledger_balance(user_id).await.unwrap();
+    let account = service.get_account(user_id, user_id).await.unwrap();
+    (
+        (ledger.cash.clone(), &ledger.cash + &ledger.reserved),
+        (account.available_balance, account.balance),
+    )
+}
+
+/// How many of the account's journal entries have lines that don't sum to zero.
+async fn unbalanced_entries(pool: &PgPool, account_id: Uuid) -> i64 {
+    sqlx::query_scalar(
+        "SELECT COUNT(*) FROM (
+            SELECT e.entry_id FROM journal_entries e
+            JOIN journal_lines l ON l.entry_id = e.entry_id
+            WHERE e.account_id = $1
+            GROUP BY e.entry_id
+            HAVING SUM(l.amount) <> 0
+        ) unbalanced",
+    )
+    .bind(account_id)
+    .fetch_one(pool)
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn test_every_change_is_a_balanced_entry_mirrored_by_the_cache() {
+    let pool = setup_db().await;
+    let service = AccountManagementService::new(pool.clone());
+    let user_id = create_user(&pool).await;
+    let order_id = Uuid::new_v4();
+
+    service
+        .add_user_balance(user_id, &dec("500"), JournalEntryType::Deposit, None)
+        .await
+        .unwrap();
+    service
+        .reserve_funds(user_id, &dec("200"), Some(order_id))
+        .await
+        .unwrap();
+    service
+        .deduct_user_balance(
+            user_id,
+            &dec("150"),
+            JournalEntryType::TradeBuy,
+            Some(order_id),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        balances(&service, user_id).await,
+        (
+            (dec("1000300"), dec("1000350")),
+            (dec("1000300"), dec("1000350"))
+        )
+    );
+    service
+        .release_reservation(user_id, order_id)
+        .await
+        .unwrap();
+    service
+        .release_reservation(user_id, order_id)
+        .await
+        .unwrap();
+    service
+        .debit_user_balance(user_id, &dec("350"), JournalEntryType::Withdrawal, None)
+        .await
+        .unwrap();
+    assert_eq!(
+        balances(&service, user_id).await,
+        (
+            (dec("1000000"), dec("1000000")),
+            (dec("1000000"), dec("1000000"))
+        )
+    );
+
+    let entries: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM journal_entries WHERE account_id = $1")
+            .bind(user_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert!(matches!(
+        service
+            .debit_user_balance(
+                user_id,
+                &dec("1000000.01"),
+                JournalEntryType::Withdrawal,
+                None
+            )
+            .await,
+        Err(TradeError::UserError(UserError::InsufficientFunds))
+    ));
+    let after: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM journal_entries WHERE account_id = $1")
+            .bind(user_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!(after, entries, "a refused change posts nothing");
+    assert_eq!(entries, 6);
+    assert_eq!(unbalanced_entries(&pool, user_id).await, 0);
+
+    let rewrite = sqlx::query(
+        "UPDATE journal_lines SET amount = amount * 2
+        WHERE entry_id IN (SELECT entry_id FROM journal_entries WHERE account_id = $1)",
+    )
+    .bind(user_id)
+    .execute(&pool)
+    .await;
+    assert!(rewrite.is_err(), "the ledger is append-only");
+}
+
+#[tokio::test]
+async fn test_cash_movements_carry_their_type_and_running_balance() {
+    let pool = setup_db().await;
+    let service = AccountManagementService::new(pool.clone());
+    let user_id = create_user(&pool).await;
+    let (loan_id, order_id) = (Uuid::new_v4(), Uuid::new_v4());
+
+    service
+        .add_user_balance(user_id, &dec("500"), JournalEntryType::Deposit, None)
+        .await
+        .unwrap();
+    service
+        .reserve_funds(user_id, &dec("300"), Some(order_id))
+        .await
+        .unwrap();
+    service
+        .add_user_balance(
+            user_id,
+            &dec("2000"),
+            JournalEntryType::LoanDisbursement,
+            Some(loan_id),
+        )
+        .await
+        .unwrap();
+    service
+        .debit_user_balance(
+            user_id,
+            &dec("750"),
+            JournalEntryType::LoanRepayment,
+            Some(loan_id),
+        )
+        .await
+        .unwrap();
+    service
+        .debit_user_balance(user_id, &dec("200"), JournalEntryType::Withdrawal, None)
+        .await
+        .unwrap();
+    service.reset_user_balance(user_id).await.unwrap();
+
+    let movements: Vec<_> = service
+        .get_cash_movements(user_id)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|movement| {
+            (
+                movement.movement_type,
+                movement.reference_id,
+                movement.amount,
+                movement.running_balance,
+            )
+        })
+        .collect();
+    assert_eq!(
+        movements,
+        vec![
+            (
+                JournalEntryType::OpeningBalance,
+                None,
+                dec("1000000"),
+                dec("1000000")
+            ),
+            (JournalEntryType::Deposit, None, dec("500"), dec("1000500")),
+            (
+                JournalEntryType::LoanDisbursement,
+                Some(loan_id),
+                dec("2000"),
+                dec("1002500")
+            ),
+            (
+                JournalEntryType::LoanRepayment,
+                Some(loan_id),
+                dec("-750"),
+                dec("1001750")
+            ),
+            (
+                JournalEntryType::Withdrawal,
+                None,
+                dec("-200"),
+                dec("1001550")
+            ),
+            (JournalEntryType::Reset, None, dec("-901550"), dec("100000")),
+        ],
+        "reservations move cash within the account and are left out"
+    );
+}
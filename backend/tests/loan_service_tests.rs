@@ -1,13 +1,10 @@
 use backend::models::loan::LoanStatus;
 use backend::services::account_management_service::AccountManagementService;
 use backend::services::loan_service::LoanService;
-use backend::services::portfolio_management_service::PortfolioManagementService;
-use backend::services::ticker_service::TickerService;
 use bigdecimal::BigDecimal;
 use dotenv::dotenv;
 use sqlx::PgPool;
 use std::env;
-use std::str::FromStr;
 use std::sync::Arc;
 use uuid::Uuid;
 
@@ -23,11 +20,6 @@ async fn setup_db() -> PgPool {
 async fn test_loan_request() {
     let pool = setup_db().await;
     let account_service = Arc::new(AccountManagementService::new(pool.clone()));
-    let ticker_service = Arc::new(TickerService::new("mock", pool.clone()));
-    let portfolio_service = Arc::new(PortfolioManagementService::new(
-        pool.clone(),
-        ticker_service,
-    ));
 
     let loan_service = LoanService::new(pool.clone(), account_service.clone());
 
@@ -45,6 +37,8 @@ async fn test_loan_request() {
     .execute(&pool)
     .await
     .unwrap();
+    // loans are paid into the user's default account
+    account_service.open_account(user_id).await.unwrap();
 
     // Initialize balance
 
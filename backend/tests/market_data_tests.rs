@@ -0,0 +1,107 @@
+mod common;
+
+use std::path::PathBuf;
+
+use backend::models::errors::ticker_error::TickerError;
+use backend::models::errors::trade_error::TradeError;
+use backend::services::market_data_provider::{
+    FileProvider, MarketDataProvider, SyntheticProvider,
+};
+use chrono::NaiveDate;
+use common::dec;
+use uuid::Uuid;
+
+/// A fresh directory holding the given files.
+fn price_directory(files: &[(&str, &str)]) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("market-data-{}", Uuid::new_v4()));
+    std::fs::create_dir_all(&directory).unwrap();
+    for (name, contents) in files {
+        std::fs::write(directory.join(name), contents).unwrap();
+    }
+    directory
+}
+
+#[test]
+fn synthetic_prices_are_repeatable_and_consistent() {
+    let last_day = NaiveDate::from_ymd_opt(2026, 10, 19).unwrap();
+    let provider = SyntheticProvider::with_days(7, 30);
+    let bars = provider.generate("MSFT", last_day);
+
+    assert_eq!(bars.len(), 30);
+    assert_eq!(bars.last().unwrap().date.date_naive(), last_day);
+    assert!(bars.windows(2).all(|pair| pair[0].date < pair[1].date));
+    for bar in &bars {
+        let (high, low) = (bar.high.clone().unwrap(), bar.low.clone().unwrap());
+        assert!(bar.close > 0);
+        assert!(low <= bar.close && bar.close <= high);
+        assert!(low <= *bar.open.as_ref().unwrap() && *bar.open.as_ref().unwrap() <= high);
+    }
+
+    let again = provider.generate("MSFT", last_day);
+    let closes = |bars: &[backend::models::stock_ticker::Ticker]| {
+        bars.iter().map(|bar| bar.close.clone()).collect::<Vec<_>>()
+    };
+    assert_eq!(closes(&bars), closes(&again));
+    assert_ne!(
+        closes(&bars),
+        closes(&provider.generate("AAPL", last_day)),
+        "each ticker gets its own path"
+    );
+    assert_ne!(
+        closes(&bars),
+        closes(&SyntheticProvider::with_days(8, 30).generate("MSFT", last_day)),
+        "the seed changes the path"
+    );
+}
+
+#[tokio::test]
+async fn file_provider_reads_csv_and_json() {
+    let directory = price_directory(&[
+        (
+            "MSFT.csv",
+            "Date,Open,High,Low,Close,Volume\n\
+             2026-10-16,101,103,100,102.5,1200\n\
+             2026-10-15,99,101.5,98,100.25,900\n",
+        ),
+        (
+            "AAPL.json",
+            r#"[{"date": "2026-10-16T00:00:00Z", "close": "180.1"},
+                {"date": "2026-10-17", "close": 181, "volume": 50}]"#,
+        ),
+    ]);
+    let provider = FileProvider::new(&directory);
+
+    let msft = provider.daily_history("MSFT").await.unwrap();
+    assert_eq!(msft.len(), 2);
+    assert_eq!(msft[0].close, dec("100.25"));
+    assert_eq!(msft[1].high, Some(dec("103")));
+    assert_eq!(msft[1].volume, Some(1200));
+
+    let aapl = provider.latest_quote("AAPL").await.unwrap();
+    assert_eq!(aapl.close, dec("181"));
+    assert_eq!(aapl.volume, Some(50));
+    assert_eq!(aapl.open, None);
+
+    std::fs::remove_dir_all(directory).unwrap();
+}
+
+#[tokio::test]
+async fn file_provider_rejects_unknown_and_unreadable_tickers() {
+    let directory = price_directory(&[("BAD.csv", "date,close\n2026-10-16,abc\n")]);
+    let provider = FileProvider::new(&directory);
+
+    assert!(matches!(
+        provider.daily_history("GOOGL").await,
+        Err(TradeError::TickerError(TickerError::InvalidSymbol(_)))
+    ));
+    assert!(matches!(
+        provider.daily_history("../BAD").await,
+        Err(TradeError::TickerError(TickerError::InvalidSymbol(_)))
+    ));
+    assert!(matches!(
+        provider.daily_history("BAD").await,
+        Err(TradeError::TickerError(TickerError::ApiError(_)))
+    ));
+
+    std::fs::remove_dir_all(directory).unwrap();
+}
@@ -1,11 +1,24 @@
+mod common;
+
 use backend::authentication::basic_client::AuthorizationClient;
+use backend::models::errors::trade_error::TradeError;
+use backend::models::errors::user_error::UserError;
 use backend::models::order::{OrderStatus, OrderType};
 use backend::services::account_management_service::AccountManagementService;
+use backend::services::alert_service::AlertService;
+use backend::services::fee_service::FeeService;
+use backend::services::market_data_provider::SyntheticProvider;
 use backend::services::order_management_service::OrderManagementService;
+use backend::services::order_matchbook_service::OrderMatchbookService;
 use backend::services::portfolio_management_service::PortfolioManagementService;
 use backend::services::ticker_service::TickerService;
+use backend::services::trade_service::TradeService;
 use backend::services::user_service::UserService;
 use bigdecimal::BigDecimal;
+use common::{
+    create_user, dec, filled_order, order_management_service, set_price, trade_service,
+    unique_ticker,
+};
 use dotenv::dotenv;
 use sqlx::PgPool;
 use std::env;
@@ -25,7 +38,10 @@ async fn setup_db() -> PgPool {
 async fn test_order_placement() {
     let pool = setup_db().await;
     let account_service = Arc::new(AccountManagementService::new(pool.clone()));
-    let ticker_service = Arc::new(TickerService::new("mock", pool.clone()));
+    let ticker_service = Arc::new(TickerService::new(
+        Arc::new(SyntheticProvider::new(1)),
+        pool.clone(),
+    ));
     let portfolio_service = Arc::new(PortfolioManagementService::new(
         pool.clone(),
         ticker_service.clone(),
@@ -38,12 +54,29 @@ async fn test_order_placement() {
         auth_client,
     ));
 
+    let fee_service = Arc::new(FeeService::new(pool.clone()));
+    let trade_service = Arc::new(TradeService::new(
+        pool.clone(),
+        ticker_service.clone(),
+        account_service.clone(),
+        portfolio_service.clone(),
+        fee_service.clone(),
+    ));
+    let order_matchbook_service = Arc::new(OrderMatchbookService::new(
+        pool.clone(),
+        trade_service,
+        ticker_service.clone(),
+        Arc::new(AlertService::new(pool.clone())),
+    ));
+
     let oms = OrderManagementService::new(
         pool.clone(),
         user_service.clone(),
         ticker_service.clone(),
         account_service.clone(),
         portfolio_service.clone(),
+        order_matchbook_service,
+        fee_service,
     );
 
     // Create a test user
@@ -57,8 +90,12 @@ async fn test_order_placement() {
         .unwrap();
     let user_id = user_service.get_user_uuid(&username).await.unwrap();
 
-    // Place an order
+    // Place an order at the market price
     let symbol = "AAPL";
+    ticker_service
+        .seed_price_history(&[symbol.to_string()])
+        .await
+        .unwrap();
     let quantity = BigDecimal::from_str("10").unwrap();
     let price_buffer = BigDecimal::from(0);
 
@@ -69,13 +106,82 @@ async fn test_order_placement() {
             quantity.clone(),
             OrderType::Buy,
             price_buffer,
+            None,
         )
         .await;
     assert!(order.is_ok());
     let order = order.unwrap();
 
-    assert_eq!(order.user_id, user_id);
+    assert_eq!(order.account_id, user_id);
     assert_eq!(order.ticker, symbol);
     assert_eq!(order.quantity, quantity);
     assert_eq!(order.status, OrderStatus::Pending);
 }
+
+#[tokio::test]
+async fn test_sell_orders_reserve_shares_until_cancelled() {
+    let pool = setup_db().await;
+    let trade_service = trade_service(&pool);
+    let oms = order_management_service(&trade_service);
+    let account_id = create_user(&pool).await;
+    let ticker = unique_ticker();
+    filled_order(
+        &trade_service,
+        account_id,
+        &ticker,
+        OrderType::Buy,
+        "100",
+        "10",
+    )
+    .await;
+    set_price(&trade_service, &ticker, "10").await;
+    let position = || async {
+        let portfolio = trade_service
+            .portfolio_management_service
+            .get_portfolio(account_id)
+            .await
+            .unwrap();
+        let position = portfolio
+            .iter()
+            .find(|position| position.ticker == ticker)
+            .unwrap();
+        (
+            position.quantity.clone(),
+            position.available_quantity.clone(),
+        )
+    };
+
+    let sell = |quantity: &str| {
+        oms.place_order(
+            account_id,
+            &ticker,
+            dec(quantity),
+            OrderType::Sell,
+            BigDecimal::from(0),
+            Some(dec("50")),
+        )
+    };
+    let order = sell("60").await.unwrap();
+    assert_eq!(position().await, (dec("100"), dec("40")));
+    assert!(matches!(
+        sell("50").await,
+        Err(TradeError::UserError(UserError::InsufficientHoldings))
+    ));
+    sell("40").await.unwrap();
+    assert_eq!(position().await, (dec("100"), dec("0")));
+
+    oms.cancel_order(order.order_id, account_id).await.unwrap();
+    assert_eq!(position().await, (dec("100"), dec("60")));
+    assert_eq!(
+        oms.get_order_status(order.order_id, account_id)
+            .await
+            .unwrap(),
+        OrderStatus::Cancelled
+    );
+    oms.cancel_order(order.order_id, account_id).await.unwrap();
+    assert_eq!(
+        position().await,
+        (dec("100"), dec("60")),
+        "released only once"
+    );
+}
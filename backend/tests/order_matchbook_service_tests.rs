@@ -1,19 +1,54 @@
+use std::sync::Arc;
+
 use backend::models::order::{Order, OrderStatus, OrderType};
+use backend::services::account_management_service::AccountManagementService;
+use backend::services::alert_service::AlertService;
+use backend::services::fee_service::FeeService;
+use backend::services::market_data_provider::SyntheticProvider;
 use backend::services::order_matchbook_service::OrderMatchbookService;
+use backend::services::portfolio_management_service::PortfolioManagementService;
+use backend::services::ticker_service::TickerService;
+use backend::services::trade_service::TradeService;
 use bigdecimal::BigDecimal;
 use num_traits::FromPrimitive;
 use sqlx::PgPool;
 use uuid::Uuid;
 
+/// A matchbook whose services are never asked to reach the database.
+fn matchbook_service(db: PgPool) -> OrderMatchbookService {
+    let ticker_service = Arc::new(TickerService::new(
+        Arc::new(SyntheticProvider::new(1)),
+        db.clone(),
+    ));
+    let portfolio_service = Arc::new(PortfolioManagementService::new(
+        db.clone(),
+        ticker_service.clone(),
+    ));
+    let trade_service = Arc::new(TradeService::new(
+        db.clone(),
+        ticker_service.clone(),
+        Arc::new(AccountManagementService::new(db.clone())),
+        portfolio_service,
+        Arc::new(FeeService::new(db.clone())),
+    ));
+    OrderMatchbookService::new(
+        db.clone(),
+        trade_service,
+        ticker_service,
+        Arc::new(AlertService::new(db)),
+    )
+}
+
 fn create_dummy_order(ticker: &str, price: f64, order_type: OrderType) -> Order {
     Order {
         order_id: Uuid::new_v4(),
-        user_id: Uuid::new_v4(),
+        account_id: Uuid::new_v4(),
         ticker: ticker.to_string(),
         quantity: BigDecimal::from(10),
         price_per_share: BigDecimal::from_f64(price).unwrap(),
         order_type,
         status: OrderStatus::Pending,
+        created_at: chrono::Utc::now(),
     }
 }
 
@@ -28,7 +63,7 @@ async fn test_add_order_and_get_best_sale() {
     // We can try to use `sqlx::PgPool::connect_lazy` which doesn't check connection immediately.
 
     let db = PgPool::connect_lazy("postgres://localhost/dummy").unwrap();
-    let service = OrderMatchbookService::new(db);
+    let service = matchbook_service(db);
 
     let ticker = "AAPL";
 
@@ -62,8 +97,38 @@ async fn test_add_order_and_get_best_sale() {
 #[tokio::test]
 async fn test_empty_book() {
     let db = PgPool::connect_lazy("postgres://localhost/dummy").unwrap();
-    let service = OrderMatchbookService::new(db);
+    let service = matchbook_service(db);
     // Expect error because book doesn't exist
     let result = service.get_best_sale("UNKNOWN").await;
     assert!(result.is_err());
 }
+
+#[tokio::test]
+async fn test_rescale_orders_keeps_orders_missing_from_the_rescaled_list() {
+    let db = PgPool::connect_lazy("postgres://localhost/dummy").unwrap();
+    let service = matchbook_service(db);
+
+    let rescaled_buy = create_dummy_order("AAPL", 100.0, OrderType::Buy);
+    let late_sell = create_dummy_order("AAPL", 120.0, OrderType::Sell);
+    let other_ticker = create_dummy_order("MSFT", 300.0, OrderType::Buy);
+    for order in [&rescaled_buy, &late_sell, &other_ticker] {
+        service.add_order(order.clone()).await.unwrap();
+    }
+
+    //a 2:1 split halves the price and doubles the quantity of the order it read back
+    let mut split_buy = rescaled_buy.clone();
+    split_buy.price_per_share = BigDecimal::from(50);
+    split_buy.quantity = BigDecimal::from(20);
+    service.rescale_orders("AAPL", &[split_buy]).await;
+
+    let (best_buy, best_sell) = service.get_best_sale("AAPL").await.unwrap();
+    let best_buy = best_buy.unwrap();
+    assert_eq!(best_buy.order_id, rescaled_buy.order_id);
+    assert_eq!(best_buy.price_per_share, BigDecimal::from(50));
+    assert_eq!(best_buy.quantity, BigDecimal::from(20));
+    assert_eq!(best_sell.unwrap().order_id, late_sell.order_id);
+
+    let (msft_buy, _) = service.get_best_sale("MSFT").await.unwrap();
+    assert_eq!(msft_buy.unwrap().price_per_share, other_ticker.price_per_share);
+    assert_eq!(service.get_open_orders().await.len(), 3);
+}
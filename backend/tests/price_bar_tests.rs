@@ -0,0 +1,27 @@
+use backend::models::price_bar::BarInterval;
+use chrono::{DateTime, TimeZone, Utc};
+
+fn at(hour: u32, minute: u32, second: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2026, 10, 19, hour, minute, second).unwrap()
+}
+
+#[test]
+fn trades_fall_into_aligned_buckets() {
+    let trade = at(14, 37, 42);
+    assert_eq!(BarInterval::Minute.bucket_start(trade), at(14, 37, 0));
+    assert_eq!(BarInterval::FiveMinutes.bucket_start(trade), at(14, 35, 0));
+    assert_eq!(BarInterval::FifteenMinutes.bucket_start(trade), at(14, 30, 0));
+    assert_eq!(BarInterval::Hour.bucket_start(trade), at(14, 0, 0));
+    assert_eq!(BarInterval::Day.bucket_start(trade), at(0, 0, 0));
+}
+
+#[test]
+fn bucket_boundaries_start_a_new_bar() {
+    for interval in BarInterval::ALL {
+        let start = interval.bucket_start(at(23, 59, 59));
+        assert_eq!(interval.bucket_start(start), start);
+        let next = start + chrono::Duration::seconds(interval.seconds());
+        assert_eq!(interval.bucket_start(next), next);
+        assert!(start <= at(23, 59, 59) && at(23, 59, 59) < next);
+    }
+}
@@ -0,0 +1,293 @@
+mod common;
+
+use std::sync::Arc;
+
+use backend::models::errors::trade_error::TradeError;
+use backend::models::fee::FeeTerms;
+use backend::models::order::{OrderStatus, OrderType};
+use backend::models::rebalance::{
+    RebalanceHolding, RebalancePlan, RebalanceProposalStatus, TargetAllocation,
+    TargetAllocationRequest, TargetWeight,
+};
+use backend::services::rebalance_service::RebalanceService;
+use bigdecimal::BigDecimal;
+use common::{
+    create_user, dec, filled_order, order_management_service, set_price, setup_db, trade_service,
+    unique_ticker,
+};
+
+fn target(ticker: &str, weight: &str) -> TargetWeight {
+    TargetWeight {
+        ticker: ticker.to_string(),
+        weight: dec(weight),
+    }
+}
+
+fn holding(ticker: &str, quantity: &str, price: &str, lot_size: &str) -> RebalanceHolding {
+    RebalanceHolding {
+        ticker: ticker.to_string(),
+        quantity: dec(quantity),
+        available_quantity: dec(quantity),
+        price: dec(price),
+        lot_size: dec(lot_size),
+    }
+}
+
+#[test]
+fn test_rebalance_sells_first_and_rounds_to_lots() {
+    //10,000 in AAPL and 0 in MSFT, aiming for 40% AAPL, 30% MSFT and 30% cash
+    let holdings = [
+        holding("AAPL", "100", "100", "1"),
+        holding("MSFT", "0", "30", "10"),
+    ];
+    let targets = [target("AAPL", "0.4"), target("MSFT", "0.3")];
+    let plan = RebalancePlan::compute(
+        &dec("0"),
+        &dec("0"),
+        &holdings,
+        &targets,
+        &FeeTerms::default(),
+    );
+    assert_eq!(plan.total_value, dec("10000"));
+    assert_eq!(plan.drift, dec("0.6"));
+    assert_eq!(plan.orders.len(), 2);
+
+    let sell = &plan.orders[0];
+    assert!(matches!(sell.order_type, OrderType::Sell));
+    assert_eq!(sell.ticker, "AAPL");
+    assert_eq!(sell.quantity, dec("60"));
+    //3,000 buys 100 MSFT, a whole number of lots of 10
+    let buy = &plan.orders[1];
+    assert!(matches!(buy.order_type, OrderType::Buy));
+    assert_eq!(buy.quantity, dec("100"));
+    assert_eq!(buy.target_weight, dec("0.3"));
+}
+
+#[test]
+fn test_rebalance_scales_buys_to_available_cash() {
+    //cash is 1,000 but 400 of it is reserved by an open order
+    let holdings = [
+        holding("AAPL", "0", "10", "5"),
+        holding("MSFT", "0", "10", "1"),
+    ];
+    let targets = [target("AAPL", "0.5"), target("MSFT", "0.5")];
+    let plan = RebalancePlan::compute(
+        &dec("1000"),
+        &dec("600"),
+        &holdings,
+        &targets,
+        &FeeTerms::default(),
+    );
+    let bought: BigDecimal = plan
+        .orders
+        .iter()
+        .map(|o| &o.quantity * &o.price_per_share)
+        .sum();
+    assert!(bought <= dec("600"));
+    assert_eq!(plan.orders[0].quantity, dec("30"));
+    assert_eq!(plan.orders[1].quantity, dec("30"));
+
+    //positions without a target are sold in full, lots or not
+    let plan = RebalancePlan::compute(
+        &dec("0"),
+        &dec("0"),
+        &[holding("GOOGL", "7.5", "10", "10")],
+        &[],
+        &FeeTerms::default(),
+    );
+    assert_eq!(plan.orders[0].quantity, dec("7.5"));
+}
+
+#[test]
+fn test_rebalance_leaves_room_for_each_buys_fee() {
+    //the standard tier: half a cent a share, at least 1 an order
+    let standard = FeeTerms {
+        per_share_fee: dec("0.005"),
+        minimum_fee: dec("1"),
+        ..Default::default()
+    };
+    let holdings = [
+        holding("AAPL", "0", "1", "1"),
+        holding("GOOGL", "0", "1", "1"),
+        holding("MSFT", "0", "1", "1"),
+    ];
+    let targets = [
+        target("AAPL", "0.333333"),
+        target("GOOGL", "0.333333"),
+        target("MSFT", "0.333333"),
+    ];
+    let plan = RebalancePlan::compute(&dec("1000"), &dec("100"), &holdings, &targets, &standard);
+    //97 shares and three minimum fees use up the 100, a 98th share would not leave room
+    let bought: BigDecimal = plan.orders.iter().map(|o| &o.quantity).sum();
+    assert_eq!(bought, dec("97"));
+    let cost: BigDecimal = plan
+        .orders
+        .iter()
+        .map(|o| {
+            let notional = &o.quantity * &o.price_per_share;
+            standard.max_order_fee(&o.quantity, &notional) + notional
+        })
+        .sum();
+    assert!(cost <= dec("100"));
+}
+
+#[test]
+fn test_target_allocation_validation() {
+    let request = |targets: Vec<TargetWeight>, threshold: Option<&str>, auto: bool| {
+        TargetAllocation::new(TargetAllocationRequest {
+            targets,
+            drift_threshold: threshold.map(dec),
+            auto_rebalance: auto,
+        })
+    };
+    let allocation = request(
+        vec![target("msft", "0.3"), target("AAPL", "0.4")],
+        None,
+        false,
+    )
+    .unwrap();
+    assert_eq!(allocation.targets[1].ticker, "MSFT");
+    assert_eq!(allocation.cash_weight, dec("0.3"));
+
+    assert!(request(
+        vec![target("AAPL", "0.6"), target("MSFT", "0.5")],
+        None,
+        false
+    )
+    .is_err());
+    assert!(request(
+        vec![target("AAPL", "0.5"), target("aapl", "0.1")],
+        None,
+        false
+    )
+    .is_err());
+    assert!(request(vec![target("AAPL", "0")], None, false).is_err());
+    assert!(request(vec![target("AAPL", "0.5")], None, true).is_err());
+    assert!(request(vec![target("AAPL", "0.5")], Some("1.5"), true).is_err());
+    assert!(request(vec![target("AAPL", "0.5")], Some("0.05"), true).is_ok());
+}
+
+#[tokio::test]
+async fn test_confirmed_proposal_places_its_orders_once() {
+    let pool = setup_db().await;
+    let trade_service = trade_service(&pool);
+    let account_id = create_user(&pool).await;
+    trade_service
+        .fee_service
+        .set_user_tier(account_id, "COMMISSION_FREE")
+        .await
+        .unwrap();
+    let (held, wanted) = (unique_ticker(), unique_ticker());
+    set_price(&trade_service, &held, "100").await;
+    set_price(&trade_service, &wanted, "50").await;
+    filled_order(
+        &trade_service,
+        account_id,
+        &held,
+        OrderType::Buy,
+        "100",
+        "100",
+    )
+    .await;
+    let rebalance_service = RebalanceService::new(
+        pool.clone(),
+        trade_service.ticker_service.clone(),
+        Arc::new(order_management_service(&trade_service)),
+    );
+    //the account is worth 1,000,000, 10,000 of it in shares it wants to split evenly
+    rebalance_service
+        .set_targets(
+            account_id,
+            TargetAllocationRequest {
+                targets: vec![target(&held, "0.005"), target(&wanted, "0.005")],
+                drift_threshold: None,
+                auto_rebalance: false,
+            },
+        )
+        .await
+        .unwrap();
+
+    let proposal = rebalance_service.propose(account_id).await.unwrap();
+    let orders: Vec<(&str, String, BigDecimal)> = proposal
+        .orders
+        .iter()
+        .map(|o| {
+            (
+                o.ticker.as_str(),
+                o.order_type.to_string(),
+                o.quantity.clone(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        orders,
+        vec![
+            (held.as_str(), "SELL".to_string(), dec("50")),
+            (wanted.as_str(), "BUY".to_string(), dec("100")),
+        ]
+    );
+
+    let submitted = rebalance_service
+        .confirm_proposal(account_id, proposal.proposal_id)
+        .await
+        .unwrap();
+    assert_eq!(submitted.status, RebalanceProposalStatus::Submitted);
+    for order in &submitted.orders {
+        assert_eq!(order.error, None);
+        let placed = trade_service
+            .get_order(order.order_id.unwrap())
+            .await
+            .unwrap();
+        assert_eq!(
+            (placed.ticker, placed.quantity, placed.status),
+            (
+                order.ticker.clone(),
+                order.quantity.clone(),
+                OrderStatus::Pending
+            )
+        );
+    }
+    assert!(matches!(
+        rebalance_service
+            .confirm_proposal(account_id, proposal.proposal_id)
+            .await,
+        Err(TradeError::RebalanceProposalNotPending)
+    ));
+}
+
+#[tokio::test]
+async fn test_proposal_on_the_standard_tier_can_be_placed_with_its_fees() {
+    let pool = setup_db().await;
+    let trade_service = trade_service(&pool);
+    let account_id = create_user(&pool).await;
+    let wanted = unique_ticker();
+    set_price(&trade_service, &wanted, "50").await;
+    let rebalance_service = RebalanceService::new(
+        pool.clone(),
+        trade_service.ticker_service.clone(),
+        Arc::new(order_management_service(&trade_service)),
+    );
+    //all 1,000,000 of the account in one ticker leaves nothing over for the fee
+    rebalance_service
+        .set_targets(
+            account_id,
+            TargetAllocationRequest {
+                targets: vec![target(&wanted, "1")],
+                drift_threshold: None,
+                auto_rebalance: false,
+            },
+        )
+        .await
+        .unwrap();
+
+    let proposal = rebalance_service.propose(account_id).await.unwrap();
+    assert_eq!(proposal.orders.len(), 1);
+    //19,998 shares cost 999,900 and reserve a fee of 99.99 on top
+    assert_eq!(proposal.orders[0].quantity, dec("19998"));
+    let submitted = rebalance_service
+        .confirm_proposal(account_id, proposal.proposal_id)
+        .await
+        .unwrap();
+    assert_eq!(submitted.orders[0].error, None);
+    assert!(submitted.orders[0].order_id.is_some());
+}
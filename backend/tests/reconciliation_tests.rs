@@ -0,0 +1,201 @@
+mod common;
+
+use std::sync::Arc;
+
+use backend::models::ledger::JournalEntryType;
+use backend::models::order::{Order, OrderStatus, OrderType};
+use backend::models::reconciliation::{BreakType, ReconciliationBreak};
+use backend::services::alert_service::AlertService;
+use backend::services::order_matchbook_service::OrderMatchbookService;
+use backend::services::reconciliation_service::ReconciliationService;
+use backend::services::trade_service::TradeService;
+use bigdecimal::BigDecimal;
+use chrono::{Duration, Utc};
+use common::{
+    create_user, dec, filled_order, pending_order, setup_db, trade_service, unique_ticker,
+};
+use uuid::Uuid;
+
+fn order(quantity: i32) -> Order {
+    Order {
+        order_id: Uuid::new_v4(),
+        account_id: Uuid::new_v4(),
+        ticker: "AAPL".to_string(),
+        quantity: BigDecimal::from(quantity),
+        price_per_share: BigDecimal::from(100),
+        order_type: OrderType::Buy,
+        status: OrderStatus::Pending,
+        created_at: Utc::now(),
+    }
+}
+
+#[test]
+fn test_diff_order_book_in_sync() {
+    let pending = vec![order(5), order(10)];
+    let breaks = ReconciliationService::diff_order_book(&pending, &pending.clone());
+    assert!(breaks.is_empty());
+}
+
+#[test]
+fn test_diff_order_book_finds_each_break_type() {
+    let missing = order(5);
+    let stale = order(7);
+    let drifted = order(10);
+    let mut resting_drifted = drifted.clone();
+    resting_drifted.quantity = BigDecimal::from(4);
+
+    let breaks = ReconciliationService::diff_order_book(
+        &[missing.clone(), drifted.clone()],
+        &[stale.clone(), resting_drifted],
+    );
+    assert_eq!(breaks.len(), 3);
+    let find = |order_id| {
+        breaks
+            .iter()
+            .find(|b| b.order_id == Some(order_id))
+            .unwrap()
+    };
+    assert_eq!(
+        find(missing.order_id).break_type,
+        BreakType::MissingFromOrderBook
+    );
+    assert_eq!(find(missing.order_id).actual, BigDecimal::from(0));
+    assert_eq!(find(stale.order_id).break_type, BreakType::StaleInOrderBook);
+    assert_eq!(find(stale.order_id).expected, BigDecimal::from(0));
+    assert_eq!(
+        find(drifted.order_id).break_type,
+        BreakType::OrderBookQuantity
+    );
+    assert_eq!(find(drifted.order_id).expected, BigDecimal::from(10));
+    assert_eq!(find(drifted.order_id).actual, BigDecimal::from(4));
+}
+
+#[test]
+fn test_confirmed_breaks_drops_transient_drift() {
+    let transient = order(5);
+    let persistent = order(7);
+    let first = ReconciliationService::diff_order_book(&[transient, persistent.clone()], &[]);
+    let second = ReconciliationService::diff_order_book(std::slice::from_ref(&persistent), &[]);
+    let confirmed = ReconciliationService::confirmed_breaks(&first, second);
+    assert_eq!(confirmed.len(), 1);
+    assert_eq!(confirmed[0].order_id, Some(persistent.order_id));
+}
+
+/// A reconciliation over `trade_service` and a book of its own, returning the breaks it found
+/// for `account_id` alone, since other tests share the database.
+async fn account_breaks(
+    trade_service: &TradeService,
+    order_matchbook_service: Arc<OrderMatchbookService>,
+    account_id: Uuid,
+) -> Vec<ReconciliationBreak> {
+    let reconciliation_service = ReconciliationService::new(
+        trade_service.db.clone(),
+        Arc::new(trade_service.clone()),
+        order_matchbook_service,
+        Uuid::new_v4(),
+    );
+    reconciliation_service
+        .run(false)
+        .await
+        .unwrap()
+        .breaks
+        .into_iter()
+        .filter(|found| found.account_id == Some(account_id))
+        .collect()
+}
+
+fn order_book(trade_service: &TradeService) -> Arc<OrderMatchbookService> {
+    let db = trade_service.db.clone();
+    Arc::new(OrderMatchbookService::new(
+        db.clone(),
+        Arc::new(trade_service.clone()),
+        trade_service.ticker_service.clone(),
+        Arc::new(AlertService::new(db)),
+    ))
+}
+
+#[tokio::test]
+async fn test_a_deposit_trades_and_fees_reconcile_without_breaks() {
+    let pool = setup_db().await;
+    let trade_service = trade_service(&pool);
+    let order_matchbook_service = order_book(&trade_service);
+    let account_id = create_user(&pool).await;
+    let ticker = unique_ticker();
+    trade_service
+        .account_management_service
+        .add_user_balance(account_id, &dec("5000"), JournalEntryType::Deposit, None)
+        .await
+        .unwrap();
+    //both fills pay the standard tier's fees
+    filled_order(
+        &trade_service,
+        account_id,
+        &ticker,
+        OrderType::Buy,
+        "10",
+        "100",
+    )
+    .await;
+    filled_order(
+        &trade_service,
+        account_id,
+        &ticker,
+        OrderType::Sell,
+        "4",
+        "110",
+    )
+    .await;
+    //and a resting order on each side holds back shares and cash
+    for (order_type, quantity, price) in
+        [(OrderType::Sell, "3", "120"), (OrderType::Buy, "2", "90")]
+    {
+        let order_id = pending_order(
+            &trade_service,
+            account_id,
+            &ticker,
+            order_type,
+            quantity,
+            price,
+        )
+        .await;
+        order_matchbook_service
+            .add_order(trade_service.get_order(order_id).await.unwrap())
+            .await
+            .unwrap();
+    }
+
+    let breaks = account_breaks(&trade_service, order_matchbook_service, account_id).await;
+    assert!(breaks.is_empty(), "{:?}", breaks);
+}
+
+#[tokio::test]
+async fn test_trades_from_before_the_ledger_are_not_counted_against_cash() {
+    let pool = setup_db().await;
+    let trade_service = trade_service(&pool);
+    let account_id = create_user(&pool).await;
+    //a buy from before the account's opening balance, which already paid for it
+    sqlx::query(
+        "INSERT INTO transactions
+        (transaction_id, account_id, ticker, order_type, quantity, price_per_share, executed_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)",
+    )
+    .bind(Uuid::new_v4())
+    .bind(account_id)
+    .bind(unique_ticker())
+    .bind(OrderType::Buy)
+    .bind(dec("5"))
+    .bind(dec("100"))
+    .bind(Utc::now() - Duration::days(1))
+    .execute(&pool)
+    .await
+    .unwrap();
+
+    let breaks = account_breaks(&trade_service, order_book(&trade_service), account_id).await;
+    assert!(
+        !breaks
+            .iter()
+            .any(|found| found.break_type == BreakType::CashBalance),
+        "{:?}",
+        breaks
+    );
+}
@@ -0,0 +1,140 @@
+mod common;
+
+use backend::models::errors::trade_error::TradeError;
+use backend::models::order::OrderType;
+use backend::models::risk::{ReturnHistory, RiskMeasure, RiskParameters, VarMethod};
+use backend::services::risk_service::RiskService;
+use chrono::{Days, NaiveTime, Utc};
+use common::{create_user, filled_order, setup_db, trade_service, unique_ticker};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+fn close_to(actual: f64, expected: f64, tolerance: f64) -> bool {
+    (actual - expected).abs() <= tolerance
+}
+
+#[test]
+fn test_risk_measure_takes_the_tail_of_the_losses() {
+    //the worst 5% of 100 outcomes are the five losses of 10 to 50
+    let mut pnl: Vec<f64> = (1..=95).map(f64::from).collect();
+    pnl.extend([-10.0, -20.0, -30.0, -40.0, -50.0]);
+    let measure = RiskMeasure::from_pnl(pnl, 0.95).unwrap();
+    assert_eq!(measure.value_at_risk, 10.0);
+    assert_eq!(measure.expected_shortfall, 30.0);
+    assert!(RiskMeasure::from_pnl(Vec::new(), 0.95).is_none());
+}
+
+#[test]
+fn test_historical_simulation_replays_returns_against_positions() {
+    //1,000 in one ticker that falls 5% one day in twenty
+    let returns: Vec<Vec<f64>> = (0..20)
+        .map(|day| vec![if day == 7 { -0.05 } else { 0.001 }])
+        .collect();
+    let history = ReturnHistory {
+        exposures: vec![1000.0],
+        returns,
+    };
+    let (one_day, ten_day) = history.historical(0.95);
+    let one_day = one_day.unwrap();
+    assert!(close_to(one_day.value_at_risk, 50.0, 1e-9));
+    assert!(close_to(one_day.expected_shortfall, 50.0, 1e-9));
+    //every ten-day window but the last three includes the fall
+    assert!(ten_day.unwrap().value_at_risk > 40.0);
+}
+
+#[test]
+fn test_monte_carlo_matches_the_normal_quantile_and_handles_correlated_tickers() {
+    //two tickers moving in lockstep, ±1% a day
+    let returns: Vec<Vec<f64>> = (0..250)
+        .map(|day| {
+            let r = if day % 2 == 0 { 0.01 } else { -0.01 };
+            vec![r, r]
+        })
+        .collect();
+    let history = ReturnHistory {
+        exposures: vec![600.0, 400.0],
+        returns,
+    };
+    let mut rng = StdRng::seed_from_u64(7);
+    let (one_day, ten_day) = history.monte_carlo(0.95, 20_000, &mut rng);
+    //1.645 standard deviations of 1% on 1,000
+    let one_day = one_day.unwrap();
+    assert!(close_to(one_day.value_at_risk, 16.45, 1.0));
+    assert!(one_day.expected_shortfall > one_day.value_at_risk);
+    assert!(close_to(
+        ten_day.unwrap().value_at_risk,
+        16.45 * 10f64.sqrt(),
+        3.0
+    ));
+}
+
+#[tokio::test]
+async fn test_historical_risk_of_an_accounts_positions() {
+    let pool = setup_db().await;
+    let trade_service = trade_service(&pool);
+    let account_id = create_user(&pool).await;
+    let ticker = unique_ticker();
+    //three weeks of closes at 100 that drop to 90 once, ten days ago
+    let today = Utc::now().date_naive();
+    for days_ago in 0..=20u64 {
+        sqlx::query("INSERT INTO stock_prices (ticker, date, close) VALUES ($1, $2, $3)")
+            .bind(&ticker)
+            .bind(
+                (today - Days::new(days_ago))
+                    .and_time(NaiveTime::MIN)
+                    .and_utc(),
+            )
+            .bind(if days_ago > 10 { 100 } else { 90 })
+            .execute(&pool)
+            .await
+            .unwrap();
+    }
+    filled_order(
+        &trade_service,
+        account_id,
+        &ticker,
+        OrderType::Buy,
+        "10",
+        "100",
+    )
+    .await;
+
+    let risk_service = RiskService::new(
+        pool.clone(),
+        trade_service.portfolio_management_service.clone(),
+    );
+    let report = risk_service
+        .get_risk(
+            account_id,
+            &RiskParameters {
+                lookback_days: 30,
+                ..Default::default()
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(report.observations, 20);
+    assert!(close_to(report.positions_value, 900.0, 1e-9));
+    //the worst day in twenty is the 10% drop, on what the shares are worth now
+    assert!(close_to(report.one_day.value_at_risk, 90.0, 1e-9));
+    assert!(close_to(report.one_day.expected_shortfall, 90.0, 1e-9));
+
+    let simulated = RiskParameters {
+        method: VarMethod::MonteCarlo,
+        lookback_days: 30,
+        simulations: RiskParameters::MAX_SIMULATIONS,
+        seed: Some(7),
+        ..Default::default()
+    };
+    let report = risk_service.get_risk(account_id, &simulated).await.unwrap();
+    assert_eq!(report.observations, 20);
+    assert!(report.one_day.value_at_risk > 0.0);
+    let too_many = RiskParameters {
+        simulations: RiskParameters::MAX_SIMULATIONS + 1,
+        ..simulated
+    };
+    assert!(matches!(
+        risk_service.get_risk(account_id, &too_many).await,
+        Err(TradeError::InvalidRiskParameters(_))
+    ));
+}
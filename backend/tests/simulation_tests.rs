@@ -0,0 +1,197 @@
+mod common;
+
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+use backend::models::order::OrderType;
+use backend::models::simulation::{
+    PortfolioProjection, ProjectedHolding, SimulatedFill, SimulatedOrder, SimulationRequest,
+};
+use backend::models::tax_lot::{CostBasisMethod, TaxLot};
+use backend::services::loan_service::LoanService;
+use backend::services::risk_service::RiskService;
+use backend::services::simulation_service::SimulationService;
+use chrono::{TimeZone, Utc};
+use common::{create_user, dec, filled_order, set_price, setup_db, trade_service, unique_ticker};
+use uuid::Uuid;
+
+fn lot(account_id: Uuid, day: u32, quantity: &str, cost_basis: &str) -> TaxLot {
+    TaxLot {
+        lot_id: Uuid::new_v4(),
+        account_id,
+        ticker: "MSFT".to_string(),
+        order_id: None,
+        acquired_at: Utc.with_ymd_and_hms(2026, 3, day, 12, 0, 0).unwrap(),
+        quantity: dec(quantity),
+        remaining_quantity: dec(quantity),
+        cost_basis: dec(cost_basis),
+    }
+}
+
+/// 1,000 in cash and 20 MSFT bought as 10 at 100 then 10 at 200, now priced at 150.
+fn projection(method: CostBasisMethod) -> PortfolioProjection {
+    let account_id = Uuid::new_v4();
+    let mut holdings = BTreeMap::new();
+    holdings.insert(
+        "MSFT".to_string(),
+        ProjectedHolding {
+            quantity: dec("20"),
+            available_quantity: dec("20"),
+            cost_basis: dec("3000"),
+            price: dec("150"),
+            lots: vec![
+                lot(account_id, 1, "10", "1000"),
+                lot(account_id, 2, "10", "2000"),
+            ],
+        },
+    );
+    PortfolioProjection {
+        account_id,
+        cost_basis_method: method,
+        cash: dec("1000"),
+        available_cash: dec("1000"),
+        holdings,
+        realized_pnl: dec("0"),
+    }
+}
+
+fn fill(ticker: &str, order_type: OrderType, quantity: &str, price: &str) -> SimulatedFill {
+    SimulatedFill {
+        ticker: ticker.to_string(),
+        order_type,
+        quantity: dec(quantity),
+        price_per_share: dec(price),
+        estimated_fee: dec("1"),
+    }
+}
+
+#[test]
+fn test_sell_closes_lots_by_the_accounts_method() {
+    let mut fifo = projection(CostBasisMethod::Fifo);
+    fifo.apply(&fill("MSFT", OrderType::Sell, "10", "150"))
+        .unwrap();
+    assert_eq!(fifo.realized_pnl, dec("500"));
+    assert_eq!(fifo.cash, dec("2499"));
+    let state = fifo.state();
+    assert_eq!(state.positions[0].average_cost, dec("200"));
+    assert_eq!(state.total_value, dec("3999"));
+
+    let mut lifo = projection(CostBasisMethod::Lifo);
+    lifo.apply(&fill("MSFT", OrderType::Sell, "10", "150"))
+        .unwrap();
+    assert_eq!(lifo.realized_pnl, dec("-500"));
+    assert_eq!(lifo.state().positions[0].average_cost, dec("100"));
+}
+
+#[test]
+fn test_buy_adds_a_position_and_spends_cash() {
+    let mut projection = projection(CostBasisMethod::Fifo);
+    projection
+        .apply(&fill("AAPL", OrderType::Buy, "4", "200"))
+        .unwrap();
+    assert_eq!(projection.available_cash, dec("199"));
+    let state = projection.state();
+    assert_eq!(state.positions.len(), 2);
+    assert_eq!(state.positions[0].ticker, "AAPL");
+    assert_eq!(state.positions[0].weight, dec("0.20005"));
+    assert_eq!(state.total_value, dec("3999"));
+}
+
+#[test]
+fn test_orders_the_account_cannot_place_are_rejected() {
+    let mut projection = projection(CostBasisMethod::Fifo);
+    assert!(projection
+        .apply(&fill("AAPL", OrderType::Buy, "10", "100"))
+        .is_err());
+    assert!(projection
+        .apply(&fill("MSFT", OrderType::Sell, "21", "150"))
+        .is_err());
+    assert!(projection
+        .apply(&fill("GOOGL", OrderType::Sell, "1", "150"))
+        .is_err());
+    //selling everything closes the position
+    projection
+        .apply(&fill("MSFT", OrderType::Sell, "20", "150"))
+        .unwrap();
+    assert!(projection.state().positions.is_empty());
+    assert_eq!(projection.realized_pnl, dec("0"));
+}
+
+#[tokio::test]
+async fn test_simulation_projects_the_account_without_touching_it() {
+    let pool = setup_db().await;
+    let trade_service = trade_service(&pool);
+    let account_id = create_user(&pool).await;
+    trade_service
+        .fee_service
+        .set_user_tier(account_id, "COMMISSION_FREE")
+        .await
+        .unwrap();
+    let ticker = unique_ticker();
+    filled_order(
+        &trade_service,
+        account_id,
+        &ticker,
+        OrderType::Buy,
+        "10",
+        "100",
+    )
+    .await;
+    filled_order(
+        &trade_service,
+        account_id,
+        &ticker,
+        OrderType::Buy,
+        "10",
+        "200",
+    )
+    .await;
+    set_price(&trade_service, &ticker, "150").await;
+    let portfolio_service = trade_service.portfolio_management_service.clone();
+    let account_service = trade_service.account_management_service.clone();
+    let simulation_service = SimulationService::new(
+        pool.clone(),
+        portfolio_service.clone(),
+        account_service.clone(),
+        trade_service.fee_service.clone(),
+        Arc::new(RiskService::new(pool.clone(), portfolio_service.clone())),
+        Arc::new(LoanService::new(pool.clone(), account_service.clone())),
+    );
+
+    //sold at the latest price, first in first out
+    let result = simulation_service
+        .simulate(
+            account_id,
+            SimulationRequest {
+                orders: vec![SimulatedOrder {
+                    ticker: ticker.to_lowercase(),
+                    order_type: OrderType::Sell,
+                    quantity: dec("10"),
+                    price_per_share: None,
+                }],
+                risk: Default::default(),
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(result.orders[0].price_per_share, dec("150"));
+    assert_eq!(result.realized_pnl, dec("500"));
+    assert_eq!(&result.after.cash - &result.before.cash, dec("1500"));
+    assert_eq!(result.before.positions_value, dec("3000"));
+    assert_eq!(result.after.positions[0].average_cost, dec("200"));
+    assert_eq!(result.credit_score_before, None);
+
+    let balance = account_service
+        .get_ledger_balance(account_id)
+        .await
+        .unwrap();
+    assert_eq!(balance.cash, dec("997000"));
+    assert_eq!(
+        portfolio_service
+            .get_tax_lots(account_id, Some(&ticker))
+            .await
+            .unwrap()
+            .len(),
+        2
+    );
+}
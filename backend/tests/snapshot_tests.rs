@@ -0,0 +1,221 @@
+mod common;
+
+use backend::models::ledger::{JournalEntryType, LedgerAccount};
+use backend::models::order::OrderType;
+use backend::models::snapshot::PortfolioSnapshot;
+use backend::services::snapshot_service::SnapshotService;
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Days, Duration, NaiveDate, NaiveTime, Utc};
+use common::{create_user, dec, filled_order, set_price, setup_db, trade_service, unique_ticker};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+fn noon(day: NaiveDate) -> DateTime<Utc> {
+    day.and_time(NaiveTime::from_hms_opt(12, 0, 0).unwrap())
+        .and_utc()
+}
+
+/// A journal entry moving `amount` into the account's cash at `at`, posted straight to the
+/// ledger so it can be dated in the past.
+async fn post_entry(
+    pool: &PgPool,
+    account_id: Uuid,
+    entry_type: JournalEntryType,
+    at: DateTime<Utc>,
+    amount: BigDecimal,
+) {
+    let entry_id = Uuid::new_v4();
+    sqlx::query(
+        "INSERT INTO journal_entries (entry_id, account_id, entry_type, created_at)
+        VALUES ($1, $2, $3, $4)",
+    )
+    .bind(entry_id)
+    .bind(account_id)
+    .bind(entry_type)
+    .bind(at)
+    .execute(pool)
+    .await
+    .unwrap();
+    for (account, amount) in [
+        (LedgerAccount::UserCash, amount.clone()),
+        (entry_type.counter_account(), -amount),
+    ] {
+        sqlx::query(
+            "INSERT INTO journal_lines (line_id, entry_id, account, amount) VALUES ($1, $2, $3, $4)",
+        )
+        .bind(Uuid::new_v4())
+        .bind(entry_id)
+        .bind(account)
+        .bind(amount)
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+}
+
+/// An account opened three days ago with no cash, which deposited 5,000 the day after and
+/// bought 10 shares at 100 yesterday. The ticker closed at 90 and then 110.
+async fn account_with_history(pool: &PgPool) -> (Uuid, String, NaiveDate) {
+    let account_id = create_user(pool).await;
+    let ticker = unique_ticker();
+    let opened = Utc::now().date_naive() - Days::new(3);
+    sqlx::query("UPDATE accounts SET created_at = $2 WHERE account_id = $1")
+        .bind(account_id)
+        .bind(noon(opened))
+        .execute(pool)
+        .await
+        .unwrap();
+    let (deposited, bought) = (opened + Days::new(1), opened + Days::new(2));
+    post_entry(
+        pool,
+        account_id,
+        JournalEntryType::Deposit,
+        noon(deposited),
+        dec("5000"),
+    )
+    .await;
+    post_entry(
+        pool,
+        account_id,
+        JournalEntryType::TradeBuy,
+        noon(bought),
+        dec("-1000"),
+    )
+    .await;
+    sqlx::query(
+        "INSERT INTO transactions
+            (transaction_id, account_id, ticker, order_type, quantity, price_per_share, executed_at)
+        VALUES ($1, $2, $3, $4, 10, 100, $5)",
+    )
+    .bind(Uuid::new_v4())
+    .bind(account_id)
+    .bind(&ticker)
+    .bind(OrderType::Buy)
+    .bind(noon(bought))
+    .execute(pool)
+    .await
+    .unwrap();
+    for (day, close) in [(deposited, 90), (bought, 110)] {
+        sqlx::query("INSERT INTO stock_prices (ticker, date, close) VALUES ($1, $2, $3)")
+            .bind(&ticker)
+            .bind(noon(day) + Duration::hours(4))
+            .bind(close)
+            .execute(pool)
+            .await
+            .unwrap();
+    }
+    (account_id, ticker, opened)
+}
+
+#[tokio::test]
+async fn test_backfill_stores_what_rebuild_computes() {
+    let pool = setup_db().await;
+    let service = SnapshotService::new(
+        pool.clone(),
+        trade_service(&pool).portfolio_management_service,
+        Uuid::new_v4(),
+    );
+    let (account_id, ticker, opened) = account_with_history(&pool).await;
+    let yesterday = Utc::now().date_naive() - Days::new(1);
+
+    //the opening balance was posted today, so it is not in any past day
+    let rebuilt = service
+        .rebuild(account_id, opened, yesterday)
+        .await
+        .unwrap();
+    let totals = |snapshots: &[PortfolioSnapshot]| {
+        snapshots
+            .iter()
+            .map(|s| (s.snapshot_date, s.cash.clone(), s.total_value.clone()))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        totals(&rebuilt),
+        vec![
+            (opened, dec("0"), dec("0")),
+            (opened + Days::new(1), dec("5000"), dec("5000")),
+            (yesterday, dec("4000"), dec("5100")),
+        ]
+    );
+
+    let run = service.backfill(Some(account_id)).await.unwrap();
+    assert_eq!((run.accounts_snapshotted, run.snapshots_written), (1, 3));
+    let run = service.backfill(Some(account_id)).await.unwrap();
+    assert_eq!((run.accounts_snapshotted, run.snapshots_written), (0, 0));
+
+    //a day the job missed is filled in without touching the others
+    sqlx::query("DELETE FROM portfolio_snapshots WHERE account_id = $1 AND snapshot_date = $2")
+        .bind(account_id)
+        .bind(opened + Days::new(1))
+        .execute(&pool)
+        .await
+        .unwrap();
+    let run = service.backfill(Some(account_id)).await.unwrap();
+    assert_eq!((run.accounts_snapshotted, run.snapshots_written), (1, 1));
+
+    let stored = service
+        .daily_snapshots(account_id, opened, yesterday)
+        .await
+        .unwrap();
+    assert_eq!(totals(&stored), totals(&rebuilt));
+    let positions: Vec<(String, BigDecimal, BigDecimal, BigDecimal)> = sqlx::query_as(
+        "SELECT ticker, quantity, price, market_value FROM portfolio_snapshot_positions
+        WHERE account_id = $1 AND snapshot_date = $2",
+    )
+    .bind(account_id)
+    .bind(yesterday)
+    .fetch_all(&pool)
+    .await
+    .unwrap();
+    assert_eq!(
+        positions,
+        vec![(ticker, dec("10"), dec("110"), dec("1100"))]
+    );
+}
+
+#[tokio::test]
+async fn test_rebuild_drops_a_liquidated_position() {
+    let pool = setup_db().await;
+    let trade_service = trade_service(&pool);
+    let service = SnapshotService::new(
+        pool.clone(),
+        trade_service.portfolio_management_service.clone(),
+        Uuid::new_v4(),
+    );
+    let account_id = create_user(&pool).await;
+    let ticker = unique_ticker();
+    filled_order(
+        &trade_service,
+        account_id,
+        &ticker,
+        OrderType::Buy,
+        "10",
+        "100",
+    )
+    .await;
+    set_price(&trade_service, &ticker, "100").await;
+    let today = Utc::now().date_naive();
+    let rebuilt = service.rebuild(account_id, today, today).await.unwrap();
+    assert_eq!(rebuilt[0].positions.len(), 1);
+
+    //bankruptcy takes the shares without selling them
+    trade_service
+        .portfolio_management_service
+        .remove_from_portfolio(account_id, &ticker, &dec("10"), None)
+        .await
+        .unwrap();
+
+    let rebuilt = service.rebuild(account_id, today, today).await.unwrap();
+    assert!(rebuilt[0].positions.is_empty());
+    //the liquidation is logged as a sell at no price, which the rebuild nets off
+    let liquidated: (BigDecimal, BigDecimal) = sqlx::query_as(
+        "SELECT quantity, price_per_share FROM transactions
+        WHERE account_id = $1 AND order_type = $2",
+    )
+    .bind(account_id)
+    .bind(OrderType::Sell)
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!(liquidated, (dec("10"), dec("0")));
+}
@@ -0,0 +1,300 @@
+mod common;
+
+use backend::models::fee::Liquidity;
+use backend::models::order::OrderType;
+use backend::models::tax_lot::{CostBasisMethod, Position, TaxLot};
+use bigdecimal::BigDecimal;
+use chrono::{Duration, Utc};
+use common::{
+    create_user, dec, filled_order, pending_order, set_price, setup_db, trade_service,
+    unique_ticker,
+};
+use uuid::Uuid;
+
+//lots bought a day apart, oldest first
+fn lots(lots: &[(&str, &str)]) -> Vec<TaxLot> {
+    let start = Utc::now() - Duration::days(30);
+    lots.iter()
+        .enumerate()
+        .map(|(i, (quantity, cost_basis))| TaxLot {
+            lot_id: Uuid::new_v4(),
+            account_id: Uuid::nil(),
+            ticker: "AAPL".to_string(),
+            order_id: None,
+            acquired_at: start + Duration::days(i as i64),
+            quantity: dec(quantity),
+            remaining_quantity: dec(quantity),
+            cost_basis: dec(cost_basis),
+        })
+        .collect()
+}
+
+fn realized(
+    position: &Position,
+    method: CostBasisMethod,
+    selected: &[Uuid],
+    quantity: &str,
+) -> BigDecimal {
+    position
+        .dispose(method, selected, &dec(quantity))
+        .iter()
+        .map(|disposal| &disposal.cost_basis)
+        .sum()
+}
+
+#[test]
+fn test_fifo_and_lifo_close_oldest_and_newest_lots_first() {
+    let lots = lots(&[("10", "1000"), ("10", "2000")]);
+    let position = Position {
+        lots: &lots,
+        quantity: &dec("20"),
+        cost_basis: &dec("3000"),
+    };
+    assert_eq!(
+        realized(&position, CostBasisMethod::Fifo, &[], "15"),
+        dec("2000")
+    );
+    assert_eq!(
+        realized(&position, CostBasisMethod::Lifo, &[], "15"),
+        dec("2500")
+    );
+
+    let disposals = position.dispose(CostBasisMethod::Fifo, &[], &dec("15"));
+    assert_eq!(disposals.len(), 2);
+    assert_eq!(disposals[0].lot_id, Some(lots[0].lot_id));
+    assert_eq!(disposals[1].quantity, dec("5"));
+    assert_eq!(disposals[1].lot_cost, dec("1000.0000"));
+}
+
+#[test]
+fn test_average_cost_realizes_position_average_but_reduces_lots_at_their_own_cost() {
+    let lots = lots(&[("10", "1000"), ("10", "2000")]);
+    let position = Position {
+        lots: &lots,
+        quantity: &dec("20"),
+        cost_basis: &dec("3000"),
+    };
+    let disposals = position.dispose(CostBasisMethod::AverageCost, &[], &dec("5"));
+    assert_eq!(disposals.len(), 1);
+    assert_eq!(disposals[0].cost_basis, dec("750.0000"));
+    assert_eq!(disposals[0].lot_cost, dec("500.0000"));
+}
+
+#[test]
+fn test_selected_lots_go_first_and_closing_realizes_whole_cost() {
+    let lots = lots(&[("10", "1000"), ("10", "2000"), ("10", "3000")]);
+    let position = Position {
+        lots: &lots,
+        quantity: &dec("30.5"),
+        cost_basis: &dec("6100"),
+    };
+    let selected = [lots[2].lot_id, lots[1].lot_id];
+    let disposals = position.dispose(CostBasisMethod::SpecificLot, &selected, &dec("12"));
+    assert_eq!(disposals[0].lot_id, Some(lots[2].lot_id));
+    assert_eq!(disposals[1].lot_id, Some(lots[1].lot_id));
+    assert_eq!(
+        realized(&position, CostBasisMethod::SpecificLot, &selected, "12"),
+        dec("3400")
+    );
+
+    //the half share no lot covers (e.g. from split rounding) is closed at the position's cost
+    let disposals = position.dispose(CostBasisMethod::Fifo, &[], &dec("30.5"));
+    assert_eq!(disposals.last().unwrap().lot_id, None);
+    assert_eq!(
+        realized(&position, CostBasisMethod::Fifo, &[], "30.5"),
+        dec("6100")
+    );
+}
+
+#[tokio::test]
+async fn test_sells_close_lots_by_the_accounts_method() {
+    let pool = setup_db().await;
+    let trade_service = trade_service(&pool);
+    let account_id = create_user(&pool).await;
+    trade_service
+        .fee_service
+        .set_user_tier(account_id, "COMMISSION_FREE")
+        .await
+        .unwrap();
+    let account_service = &trade_service.account_management_service;
+    let portfolio_service = &trade_service.portfolio_management_service;
+    account_service
+        .set_cost_basis_method(account_id, CostBasisMethod::Lifo)
+        .await
+        .unwrap();
+    filled_order(
+        &trade_service,
+        account_id,
+        "AAPL",
+        OrderType::Buy,
+        "10",
+        "100",
+    )
+    .await;
+    filled_order(
+        &trade_service,
+        account_id,
+        "AAPL",
+        OrderType::Buy,
+        "10",
+        "200",
+    )
+    .await;
+    let bought = portfolio_service
+        .get_tax_lots(account_id, Some("AAPL"))
+        .await
+        .unwrap();
+    let (older, newer) = (bought[0].lot_id, bought[1].lot_id);
+
+    //last in, first out: the 200 lot goes first
+    let lifo_sale = filled_order(
+        &trade_service,
+        account_id,
+        "AAPL",
+        OrderType::Sell,
+        "5",
+        "300",
+    )
+    .await;
+
+    //then name the older lot explicitly
+    account_service
+        .set_cost_basis_method(account_id, CostBasisMethod::SpecificLot)
+        .await
+        .unwrap();
+    let specific_sale = pending_order(
+        &trade_service,
+        account_id,
+        "AAPL",
+        OrderType::Sell,
+        "5",
+        "300",
+    )
+    .await;
+    portfolio_service
+        .select_lots(account_id, "AAPL", specific_sale, &[older])
+        .await
+        .unwrap();
+    trade_service
+        .execute_order(specific_sale, dec("5"), dec("300"), Liquidity::Taker)
+        .await
+        .unwrap();
+
+    for (order_id, lot_id, cost_basis, realized_pnl) in [
+        (lifo_sale, newer, "1000", "500"),
+        (specific_sale, older, "500", "1000"),
+    ] {
+        let (realized_lot, realized_cost): (Option<Uuid>, BigDecimal) =
+            sqlx::query_as("SELECT lot_id, cost_basis FROM realized_gains WHERE order_id = $1")
+                .bind(order_id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(
+            (realized_lot, realized_cost),
+            (Some(lot_id), dec(cost_basis))
+        );
+        let pnl: BigDecimal =
+            sqlx::query_scalar("SELECT realized_pnl FROM transactions WHERE order_id = $1")
+                .bind(order_id)
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(pnl, dec(realized_pnl));
+    }
+
+    let left: Vec<(BigDecimal, BigDecimal)> = portfolio_service
+        .get_tax_lots(account_id, Some("AAPL"))
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|lot| (lot.remaining_quantity, lot.cost_basis))
+        .collect();
+    assert_eq!(left, vec![(dec("5"), dec("500")), (dec("5"), dec("1000"))]);
+    let (cost, realized_pnl): (BigDecimal, BigDecimal) = sqlx::query_as(
+        "SELECT total_money_spent, realized_pnl FROM portfolio WHERE account_id = $1 AND ticker = $2",
+    )
+    .bind(account_id)
+    .bind("AAPL")
+    .fetch_one(&pool)
+    .await
+    .unwrap();
+    assert_eq!((cost, realized_pnl), (dec("1500"), dec("1500")));
+}
+
+#[tokio::test]
+async fn test_a_closed_position_keeps_its_realized_pnl_and_fees() {
+    let pool = setup_db().await;
+    let trade_service = trade_service(&pool);
+    let portfolio_service = &trade_service.portfolio_management_service;
+    let account_id = create_user(&pool).await;
+    let ticker = unique_ticker();
+    //the default tier charges its one dollar minimum on each fill, closing one included
+    filled_order(
+        &trade_service,
+        account_id,
+        &ticker,
+        OrderType::Buy,
+        "10",
+        "100",
+    )
+    .await;
+    filled_order(
+        &trade_service,
+        account_id,
+        &ticker,
+        OrderType::Sell,
+        "10",
+        "120",
+    )
+    .await;
+
+    assert!(portfolio_service
+        .get_portfolio(account_id)
+        .await
+        .unwrap()
+        .is_empty());
+    let positions = portfolio_service
+        .get_positions(account_id, true)
+        .await
+        .unwrap();
+    let closed = &positions[0];
+    assert_eq!(
+        (
+            &closed.quantity,
+            &closed.total_money_spent,
+            &closed.average_cost
+        ),
+        (&dec("0"), &dec("0"), &dec("0"))
+    );
+    assert_eq!(
+        (
+            &closed.realized_pnl,
+            &closed.total_fees,
+            &closed.total_profit
+        ),
+        (&dec("200"), &dec("2"), &dec("198"))
+    );
+
+    //buying back in reopens the same position
+    filled_order(
+        &trade_service,
+        account_id,
+        &ticker,
+        OrderType::Buy,
+        "5",
+        "110",
+    )
+    .await;
+    set_price(&trade_service, &ticker, "110").await;
+    let reopened = portfolio_service.get_portfolio(account_id).await.unwrap();
+    assert_eq!(
+        (
+            &reopened[0].quantity,
+            &reopened[0].available_quantity,
+            &reopened[0].average_cost
+        ),
+        (&dec("5"), &dec("5"), &dec("110"))
+    );
+    assert_eq!(reopened[0].realized_pnl, dec("200"));
+}
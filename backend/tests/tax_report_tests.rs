@@ -0,0 +1,173 @@
+mod common;
+
+use backend::models::order::OrderType;
+use backend::models::tax_report::{Disposal, HoldingPeriod, Purchase, TaxReport};
+use backend::services::tax_report_service::TaxReportService;
+use chrono::{DateTime, Datelike, TimeZone, Utc};
+use common::{create_user, dec, filled_order, setup_db, trade_service, unique_ticker};
+use uuid::Uuid;
+
+fn at(year: i32, month: u32, day: u32) -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(year, month, day, 12, 0, 0).unwrap()
+}
+
+fn disposal(acquired_at: DateTime<Utc>, disposed_at: DateTime<Utc>, proceeds: &str) -> Disposal {
+    Disposal {
+        ticker: "MSFT".to_string(),
+        order_id: Uuid::new_v4(),
+        lot_id: Some(Uuid::new_v4()),
+        lot_order_id: None,
+        acquired_at: Some(acquired_at),
+        disposed_at,
+        quantity: dec("10"),
+        proceeds: dec(proceeds),
+        cost_basis: dec("1000"),
+    }
+}
+
+fn purchase(executed_at: DateTime<Utc>, quantity: &str) -> Purchase {
+    Purchase {
+        ticker: "MSFT".to_string(),
+        order_id: Some(Uuid::new_v4()),
+        executed_at,
+        quantity: dec(quantity),
+    }
+}
+
+#[test]
+fn test_holding_period_is_long_term_after_a_year() {
+    let bought = at(2025, 3, 1);
+    assert_eq!(
+        HoldingPeriod::of(Some(bought), at(2026, 3, 1)),
+        HoldingPeriod::ShortTerm
+    );
+    assert_eq!(
+        HoldingPeriod::of(Some(bought), at(2026, 3, 2)),
+        HoldingPeriod::LongTerm
+    );
+    assert_eq!(
+        HoldingPeriod::of(None, at(2030, 1, 1)),
+        HoldingPeriod::ShortTerm
+    );
+}
+
+#[test]
+fn test_report_splits_short_and_long_term_gains() {
+    let report = TaxReport::compute(
+        Uuid::new_v4(),
+        2026,
+        vec![
+            disposal(at(2026, 1, 5), at(2026, 6, 1), "1200"),
+            disposal(at(2024, 1, 5), at(2026, 2, 1), "1500"),
+        ],
+        &[],
+    );
+    assert_eq!(report.lots[0].disposed_at, at(2026, 2, 1));
+    assert_eq!(report.long_term.gain, dec("500"));
+    assert_eq!(report.short_term.gain, dec("200"));
+    assert_eq!(report.total.proceeds, dec("2700"));
+    assert!(report.lots.iter().all(|lot| !lot.wash_sale));
+
+    let csv = report.to_csv();
+    let lines: Vec<&str> = csv.split("\r\n").collect();
+    assert!(lines[0].starts_with("ticker,order_id,lot_id,acquired_at"));
+    assert!(lines[1].contains(",500.0000,LONG_TERM,false,0.0000,500.0000"));
+}
+
+#[test]
+fn test_loss_with_repurchase_is_flagged_as_wash_sale() {
+    //sold 10 at a loss of 200, bought 4 back 20 days later and 10 more after 40 days
+    let report = TaxReport::compute(
+        Uuid::new_v4(),
+        2026,
+        vec![
+            disposal(at(2026, 1, 5), at(2026, 6, 1), "800"),
+            disposal(at(2026, 1, 5), at(2026, 6, 2), "900"),
+        ],
+        &[
+            purchase(at(2026, 6, 21), "4"),
+            purchase(at(2026, 7, 11), "10"),
+        ],
+    );
+    let first = &report.lots[0];
+    assert!(first.wash_sale);
+    assert_eq!(first.disallowed_loss, dec("80"));
+    assert_eq!(first.adjusted_gain, dec("-120"));
+    //the 4 replacement shares were used up by the first sale
+    assert!(!report.lots[1].wash_sale);
+    assert_eq!(report.total.disallowed_loss, dec("80"));
+    assert_eq!(report.total.adjusted_gain, dec("-220"));
+}
+
+#[tokio::test]
+async fn test_report_reads_the_lots_a_sell_closed() {
+    let pool = setup_db().await;
+    let trade_service = trade_service(&pool);
+    let account_id = create_user(&pool).await;
+    trade_service
+        .fee_service
+        .set_user_tier(account_id, "COMMISSION_FREE")
+        .await
+        .unwrap();
+    let ticker = unique_ticker();
+    let bought = filled_order(
+        &trade_service,
+        account_id,
+        &ticker,
+        OrderType::Buy,
+        "10",
+        "100",
+    )
+    .await;
+    let sold = filled_order(
+        &trade_service,
+        account_id,
+        &ticker,
+        OrderType::Sell,
+        "10",
+        "80",
+    )
+    .await;
+    //4 shares bought back straight away replace part of the loss; the buy that opened the lot
+    //doesn't, though it was within 30 days of the sale too
+    filled_order(
+        &trade_service,
+        account_id,
+        &ticker,
+        OrderType::Buy,
+        "4",
+        "85",
+    )
+    .await;
+
+    let year = Utc::now().year();
+    let report = TaxReportService::new(pool.clone())
+        .get_report(account_id, year)
+        .await
+        .unwrap();
+    assert_eq!(report.lots.len(), 1);
+    let lot = &report.lots[0];
+    assert_eq!((lot.order_id, lot.ticker.as_str()), (sold, ticker.as_str()));
+    assert_eq!(lot.holding_period, HoldingPeriod::ShortTerm);
+    assert_eq!(
+        (lot.proceeds.clone(), lot.cost_basis.clone()),
+        (dec("800"), dec("1000"))
+    );
+    assert!(lot.wash_sale);
+    assert_eq!(lot.disallowed_loss, dec("80"));
+    assert_eq!(report.short_term.adjusted_gain, dec("-120"));
+
+    let lot_order: Option<Uuid> =
+        sqlx::query_scalar("SELECT order_id FROM tax_lots WHERE lot_id = $1")
+            .bind(lot.lot_id)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+    assert_eq!(lot_order, Some(bought));
+    assert!(TaxReportService::new(pool)
+        .get_report(account_id, year - 1)
+        .await
+        .unwrap()
+        .lots
+        .is_empty());
+}
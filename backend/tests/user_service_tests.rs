@@ -1,6 +1,7 @@
 use backend::authentication::basic_client::AuthorizationClient;
 use backend::services::account_management_service::AccountManagementService;
 use backend::services::portfolio_management_service::PortfolioManagementService;
+use backend::services::market_data_provider::SyntheticProvider;
 use backend::services::ticker_service::TickerService;
 use backend::services::user_service::UserService;
 use dotenv::dotenv;
@@ -21,7 +22,7 @@ async fn setup_db() -> PgPool {
 async fn test_user_service_registration() {
     let pool = setup_db().await;
     let account_service = Arc::new(AccountManagementService::new(pool.clone()));
-    let ticker_service = Arc::new(TickerService::new("mock", pool.clone()));
+    let ticker_service = Arc::new(TickerService::new(Arc::new(SyntheticProvider::new(1)), pool.clone()));
     let portfolio_service = Arc::new(PortfolioManagementService::new(
         pool.clone(),
         ticker_service.clone(),
@@ -46,7 +47,7 @@ async fn test_user_service_registration() {
 
     // Verify user exists and has balance account
     let user_id = service.get_user_uuid(&username).await.unwrap();
-    let balance = sqlx::query("SELECT balance FROM users WHERE user_id = $1")
+    let balance = sqlx::query("SELECT balance FROM accounts WHERE account_id = $1")
         .bind(user_id)
         .fetch_one(&pool)
         .await;
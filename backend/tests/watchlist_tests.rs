@@ -0,0 +1,166 @@
+mod common;
+
+use backend::models::errors::trade_error::TradeError;
+use backend::models::watchlist::{WatchlistOrder, WatchlistQuote, WatchlistRequest};
+use backend::services::watchlist_service::WatchlistService;
+use chrono::{Days, NaiveTime, Utc};
+use common::{create_user, dec, setup_db, unique_ticker};
+
+fn request(name: &str, tickers: &[&str]) -> WatchlistRequest {
+    WatchlistRequest {
+        name: name.to_string(),
+        tickers: tickers.iter().map(|t| t.to_string()).collect(),
+    }
+}
+
+#[test]
+fn normalise_trims_the_name_and_uppercases_tickers_in_order() {
+    let request = request("  Tech ", &["msft", " aapl", "GOOGL"])
+        .normalise()
+        .unwrap();
+    assert_eq!(request.name, "Tech");
+    assert_eq!(request.tickers, vec!["MSFT", "AAPL", "GOOGL"]);
+}
+
+#[test]
+fn normalise_rejects_blank_names_and_repeated_or_empty_tickers() {
+    assert!(request("   ", &["MSFT"]).normalise().is_err());
+    assert!(request("Tech", &["MSFT", "msft"]).normalise().is_err());
+    assert!(request("Tech", &["MSFT", " "]).normalise().is_err());
+    let too_many: Vec<String> = (0..=WatchlistRequest::MAX_TICKERS)
+        .map(|i| format!("T{}", i))
+        .collect();
+    let too_many: Vec<&str> = too_many.iter().map(String::as_str).collect();
+    assert!(request("Tech", &too_many).normalise().is_err());
+}
+
+#[test]
+fn quote_measures_the_day_change_against_the_previous_close() {
+    let quote = WatchlistQuote::new(
+        "MSFT".to_string(),
+        Some(dec("103")),
+        Some(Utc::now()),
+        Some(dec("98.5")),
+        Some(1200),
+        vec![dec("97"), dec("98.5"), dec("103")],
+    );
+    assert_eq!(quote.day_change, Some(dec("4.5")));
+    assert_eq!(quote.day_change_percent, Some(dec("4.57")));
+
+    let unpriced = WatchlistQuote::new("NEW".to_string(), None, None, None, None, Vec::new());
+    assert_eq!(unpriced.day_change, None);
+    assert_eq!(unpriced.day_change_percent, None);
+}
+
+#[tokio::test]
+async fn watchlists_keep_their_order_and_stay_with_their_owner() {
+    let pool = setup_db().await;
+    let service = WatchlistService::new(pool.clone());
+    let (user_id, someone_else) = (create_user(&pool).await, create_user(&pool).await);
+
+    let tech = service
+        .create_watchlist(user_id, request("Tech", &["msft", "aapl"]))
+        .await
+        .unwrap();
+    let energy = service
+        .create_watchlist(user_id, request("Energy", &[]))
+        .await
+        .unwrap();
+    assert_eq!((tech.position, energy.position), (0, 1));
+    assert_eq!(tech.tickers, vec!["MSFT", "AAPL"]);
+    assert!(matches!(
+        service
+            .create_watchlist(user_id, request(" Tech ", &[]))
+            .await,
+        Err(TradeError::InvalidWatchlist(_))
+    ));
+
+    let energy = service
+        .update_watchlist(
+            user_id,
+            energy.watchlist_id,
+            request("Energy", &["xom", "cvx"]),
+        )
+        .await
+        .unwrap();
+    assert_eq!(energy.tickers, vec!["XOM", "CVX"]);
+    let names: Vec<String> = service
+        .reorder_watchlists(
+            user_id,
+            WatchlistOrder {
+                watchlist_ids: vec![energy.watchlist_id, tech.watchlist_id],
+            },
+        )
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|list| list.name)
+        .collect();
+    assert_eq!(names, vec!["Energy", "Tech"]);
+    assert!(matches!(
+        service
+            .reorder_watchlists(
+                user_id,
+                WatchlistOrder {
+                    watchlist_ids: vec![energy.watchlist_id],
+                },
+            )
+            .await,
+        Err(TradeError::InvalidWatchlist(_))
+    ));
+
+    assert!(service
+        .list_watchlists(someone_else)
+        .await
+        .unwrap()
+        .is_empty());
+    assert!(matches!(
+        service
+            .delete_watchlist(someone_else, tech.watchlist_id)
+            .await,
+        Err(TradeError::WatchlistNotFound)
+    ));
+    service
+        .delete_watchlist(user_id, tech.watchlist_id)
+        .await
+        .unwrap();
+    assert_eq!(service.list_watchlists(user_id).await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn watchlist_quotes_come_from_the_price_history() {
+    let pool = setup_db().await;
+    let service = WatchlistService::new(pool.clone());
+    let user_id = create_user(&pool).await;
+    let (priced, unpriced) = (unique_ticker(), unique_ticker());
+    let today = Utc::now().date_naive();
+    for (day, close, volume) in [(today - Days::new(1), 100, 10), (today, 110, 25)] {
+        sqlx::query(
+            "INSERT INTO stock_prices (ticker, date, close, volume) VALUES ($1, $2, $3, $4)",
+        )
+        .bind(&priced)
+        .bind(day.and_time(NaiveTime::MIN).and_utc())
+        .bind(close)
+        .bind(volume as i64)
+        .execute(&pool)
+        .await
+        .unwrap();
+    }
+    let watchlist = service
+        .create_watchlist(user_id, request("Mixed", &[&priced, &unpriced]))
+        .await
+        .unwrap();
+
+    let quotes = service
+        .get_quotes(user_id, watchlist.watchlist_id)
+        .await
+        .unwrap();
+    assert_eq!(quotes.name, "Mixed");
+    let quote = &quotes.quotes[0];
+    assert_eq!(quote.price, Some(dec("110")));
+    assert_eq!(quote.day_change_percent, Some(dec("10")));
+    assert_eq!(quote.volume, Some(25));
+    assert_eq!(quote.sparkline, vec![dec("100"), dec("110")]);
+    assert_eq!(quotes.quotes[1].ticker, unpriced);
+    assert_eq!(quotes.quotes[1].price, None);
+}
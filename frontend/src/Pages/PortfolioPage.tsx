@@ -13,7 +13,7 @@ import type { PortfolioResponse } from "../types/Portfolio_Response";
 import { useNavigate } from "react-router-dom";
 
 const defaultPortfolioResponse: PortfolioResponse = {
-    user_id: "guest",
+    account_id: "guest",
     portfolio: []
 };
 
@@ -1,13 +1,37 @@
 import axios from 'axios';
 import type { AccountBalanceResponse } from '../types/AccountBalanceResponse';
-import type { Transaction } from '@/types/TransactionResponse';
+import type { AccountActivityResponse, Transaction } from '@/types/TransactionResponse';
+import type { HistoryQuery, Page } from '@/types/HistoryPage';
 export const fetchAccountBalance = async (): Promise<AccountBalanceResponse> => {
     const { data } = await axios.get('/api/account');
     return data;
 };
 
-export const fetchTransactionHistory = async (): Promise<Transaction[]> => {
+export const fetchAccountActivity = async (): Promise<AccountActivityResponse> => {
     const { data } = await axios.get('/api/account/transactions');
-    console.log(data)
     return data;
 };
+
+export const fetchTransactionHistory = async (): Promise<Transaction[]> => {
+    const activity = await fetchAccountActivity();
+    return activity.trades;
+};
+
+export const fetchTransactionHistoryPage = async (query: HistoryQuery = {}): Promise<Page<Transaction>> => {
+    const { data } = await axios.get('/api/account/transactions/history', { params: query });
+    return data;
+};
+
+// Fetched through axios rather than a plain link so the active account header is sent.
+export const downloadActivityExport = async (format: 'csv' | 'ofx', from?: string, to?: string): Promise<void> => {
+    const { data } = await axios.get('/api/account/transactions/export', {
+        params: { format, from, to },
+        responseType: 'blob',
+    });
+    const url = URL.createObjectURL(data);
+    const link = document.createElement('a');
+    link.href = url;
+    link.download = `activity.${format}`;
+    link.click();
+    URL.revokeObjectURL(url);
+};
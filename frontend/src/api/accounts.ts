@@ -0,0 +1,34 @@
+import axios from 'axios';
+import type { Account, CashTransfer } from '@/types/Account';
+
+export const fetchAccounts = async (): Promise<Account[]> => {
+    const { data } = await axios.get('/api/accounts');
+    return data;
+};
+
+export const createAccount = async (name: string): Promise<Account> => {
+    const { data } = await axios.post('/api/accounts', { name });
+    return data;
+};
+
+export const transferFunds = async (
+    fromAccountId: string,
+    toAccountId: string,
+    amount: string,
+): Promise<CashTransfer> => {
+    const { data } = await axios.post('/api/accounts/transfers', {
+        from_account_id: fromAccountId,
+        to_account_id: toAccountId,
+        amount,
+    });
+    return data;
+};
+
+// Every later request acts on this account; null switches back to the default account.
+export const setActiveAccount = (accountId: string | null): void => {
+    if (accountId) {
+        axios.defaults.headers.common['X-Account-Id'] = accountId;
+    } else {
+        delete axios.defaults.headers.common['X-Account-Id'];
+    }
+};
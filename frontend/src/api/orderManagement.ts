@@ -1,12 +1,22 @@
 import axios from 'axios';
 import type { PlaceOrderRequest } from '../types/PlaceOrderRequest';
 import type { Order } from '@/types/PendingOrdersResponse';
+import type { HistoryQuery, Page } from '@/types/HistoryPage';
 
-export const placeOrder = async (order: PlaceOrderRequest): Promise<void> => {
-    await axios.post('/api/orders', order);
+// reuse the same key when retrying so the backend does not place the order twice
+export const placeOrder = async (
+    order: PlaceOrderRequest,
+    idempotencyKey: string = crypto.randomUUID(),
+): Promise<void> => {
+    await axios.post('/api/orders', order, { headers: { 'Idempotency-Key': idempotencyKey } });
 };
 
 export const fetchOrderHistory = async (): Promise<Order[]> => {
     const { data } = await axios.get('/api/orders');
     return data;
-};
\ No newline at end of file
+};
+
+export const fetchOrderHistoryPage = async (query: HistoryQuery = {}): Promise<Page<Order>> => {
+    const { data } = await axios.get('/api/orders/history', { params: query });
+    return data;
+};
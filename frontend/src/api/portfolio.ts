@@ -1,5 +1,6 @@
 import axios from 'axios';
 import type { PortfolioResponse } from '../types/Portfolio_Response';
+import type { BenchmarkComparison } from '../types/PortfolioAnalytics';
 
 export const fetchPortfolio = async (): Promise<PortfolioResponse> => {
     console.log("Fetching portfolio...");
@@ -14,16 +15,34 @@ export const fetchPortfolio = async (): Promise<PortfolioResponse> => {
 };
 
 export interface PortfolioHistoryPoint {
-    date: string;
-    total_value: string;
+    date: string; // midnight of each past day, then now
+    cash: string; // available and reserved cash
+    positions_value: string;
+    total_value: string; // cash + positions_value
 }
 
-export const fetchPortfolioHistory = async (timeframe: string): Promise<PortfolioHistoryPoint[]> => {
+export interface PortfolioHistoryResponse {
+    points: PortfolioHistoryPoint[];
+    benchmark: BenchmarkComparison | null;
+}
+
+// benchmark is a ticker, e.g. "SPY", or a weighted basket, e.g. "AAPL:0.6,MSFT:0.4"
+export const fetchPortfolioHistoryWithBenchmark = async (
+    timeframe: string,
+    benchmark?: string,
+): Promise<PortfolioHistoryResponse> => {
     try {
-        const { data } = await axios.get(`/api/portfolio/history?timeframe=${timeframe}`);
+        const params = new URLSearchParams({ timeframe });
+        if (benchmark) params.set('benchmark', benchmark);
+        const { data } = await axios.get(`/api/portfolio/history?${params}`);
         return data;
     } catch (error) {
         console.error("Error fetching portfolio history:", error);
         throw error;
     }
 }
+
+export const fetchPortfolioHistory = async (timeframe: string): Promise<PortfolioHistoryPoint[]> => {
+    const { points } = await fetchPortfolioHistoryWithBenchmark(timeframe);
+    return points;
+}
@@ -23,7 +23,8 @@ export const HoldingsGrid = ({ portfolio }: HoldingsGridProps) => {
                 <Text as="h3" className="text-xl font-bold text-gray-800">Holdings</Text>
             </div>
             <div className="grid grid-cols-1 md:grid-cols-2 lg:grid-cols-3 gap-6">
-                {portfolio.map((item) => {
+                {/* closed positions are only in the list for their realized P&L */}
+                {portfolio.filter((item) => Number(item.quantity) > 0).map((item) => {
                     const currentValue = Number(item.total_money_spent) + Number(item.total_profit);
                     const profit = Number(item.total_profit);
                     const isProfit = profit >= 0;
@@ -0,0 +1,19 @@
+export type CostBasisMethod = 'Fifo' | 'Lifo' | 'AverageCost' | 'SpecificLot';
+
+export interface Account {
+    account_id: string;
+    user_id: string;
+    name: string;
+    balance: string;
+    available_balance: string;
+    cost_basis_method: CostBasisMethod;
+    is_default: boolean;
+    created_at: string;
+}
+
+export interface CashTransfer {
+    transfer_id: string;
+    from_account_id: string;
+    to_account_id: string;
+    amount: string;
+}
@@ -1,5 +1,5 @@
 export interface AccountBalanceResponse {
-    user_id: string;
+    account_id: string;
     balance: string;
     available_balance: string;
 }
@@ -0,0 +1,44 @@
+// decimals are sent as strings; dates are RFC 3339
+export type AlertCondition =
+    | "PriceAbove"
+    | "PriceBelow"
+    | "PercentMove" // threshold is a percentage either way from the previous close
+    | "VolumeSpike" // threshold is a multiple of the average daily volume
+    | "OrderFilled"; // no threshold
+
+export interface AlertRequest {
+    ticker: string;
+    condition: AlertCondition;
+    threshold: string | null;
+    recurring: boolean;
+}
+
+export interface Alert {
+    alert_id: string;
+    ticker: string;
+    condition: AlertCondition;
+    threshold: string | null;
+    recurring: boolean;
+    active: boolean; // false once a one-shot alert has triggered
+    armed: boolean; // false until a triggered recurring alert's condition stops holding
+    trigger_count: number;
+    created_at: string;
+    last_triggered_at: string | null;
+}
+
+export interface Notification {
+    notification_id: string;
+    alert_id: string | null;
+    ticker: string;
+    condition: AlertCondition;
+    message: string;
+    price: string | null;
+    order_id: string | null;
+    created_at: string;
+    read_at: string | null;
+}
+
+export interface NotificationInbox {
+    unread: number;
+    notifications: Notification[]; // newest first
+}
@@ -0,0 +1,15 @@
+export interface HistoryQuery {
+    ticker?: string;
+    side?: 'Buy' | 'Sell';
+    status?: 'PENDING' | 'RESERVED' | 'EXECUTED' | 'CANCELLED';
+    from?: string; // ISO date-time, inclusive
+    to?: string; // ISO date-time, exclusive
+    sort?: 'asc' | 'desc';
+    cursor?: string;
+    limit?: number;
+}
+
+export interface Page<T> {
+    items: T[];
+    next_cursor: string | null;
+}
@@ -2,10 +2,11 @@
 
 export interface Order {
     order_id: string;
-    user_id: string;
+    account_id: string;
     ticker: string;
     quantity: string;
     price_per_share: string;
     order_type: string;
     status: string;
+    created_at: string;
 }
\ No newline at end of file
@@ -0,0 +1,28 @@
+export type AssetClass = 'Equity' | 'Etf' | 'Fund' | 'Bond' | 'Commodity' | 'Crypto';
+
+export interface PositionAllocation {
+    ticker: string;
+    quantity: string; // BigDecimal
+    market_value: string; // BigDecimal
+    weight: string; // share of total value, cash included
+    asset_class: AssetClass | null; // null when the ticker has no instrument metadata
+    sector: string | null;
+}
+
+export interface AllocationGroup {
+    name: string; // "Unclassified" for holdings without metadata
+    market_value: string;
+    weight: string;
+}
+
+// Weights are of total value, so positions (or either grouping) plus cash_weight sum to 1.
+export interface PortfolioAllocation {
+    total_value: string;
+    cash: string;
+    cash_weight: string;
+    positions: PositionAllocation[]; // largest first
+    largest_position: PositionAllocation | null;
+    herfindahl_index: string; // concentration of the invested value, 1 = a single position
+    by_sector: AllocationGroup[];
+    by_asset_class: AllocationGroup[];
+}
@@ -0,0 +1,52 @@
+export interface DailyReturn {
+    date: string; // NaiveDate, YYYY-MM-DD
+    return: number;
+}
+
+export interface Drawdown {
+    depth: number; // fraction of the peak lost
+    peak_date: string;
+    trough_date: string;
+}
+
+// Returns are for the whole period, volatility and ratios are annualised.
+// Statistics the period has too little history for are null.
+export interface PortfolioAnalytics {
+    start_date: string;
+    end_date: string;
+    starting_value: number;
+    ending_value: number;
+    net_flows: number; // deposits less withdrawals and transfers out
+    time_weighted_return: number | null;
+    money_weighted_return: number | null;
+    annualised_volatility: number | null;
+    sharpe_ratio: number | null;
+    sortino_ratio: number | null;
+    max_drawdown: Drawdown | null;
+    best_day: DailyReturn | null;
+    worst_day: DailyReturn | null;
+}
+
+export interface BenchmarkWeight {
+    ticker: string;
+    weight: number; // weights sum to 1
+}
+
+// growth of 1 invested at the start of the period
+export interface BenchmarkPoint {
+    date: string;
+    portfolio: number;
+    benchmark: number;
+}
+
+// alpha and tracking error are annualised
+export interface BenchmarkComparison {
+    benchmark: BenchmarkWeight[];
+    series: BenchmarkPoint[];
+    portfolio_return: number | null;
+    benchmark_return: number;
+    excess_return: number | null;
+    alpha: number | null;
+    beta: number | null;
+    tracking_error: number | null;
+}
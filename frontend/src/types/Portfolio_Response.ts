@@ -1,13 +1,19 @@
 export interface PortfolioTicker {
-    user_id: string;
+    account_id: string;
     ticker: string;
     quantity: string; // BigDecimal is typically serialized as a string to preserve precision
-    total_money_spent: string; // BigDecimal
-    total_profit: string; // BigDecimal
+    available_quantity: string; // quantity not held against open sell orders
+    total_money_spent: string; // cost basis of the shares still held
+    average_cost: string; // BigDecimal
+    market_value: string; // BigDecimal
+    unrealized_pnl: string; // market value less cost basis
+    realized_pnl: string; // proceeds less cost basis of shares sold
+    total_fees: string; // commissions paid on the position, net of rebates
+    total_profit: string; // realized + unrealized, net of fees
     created_at: string; // DateTime<Utc> ISO string
 }
 
 export interface PortfolioResponse {
-    user_id: string;
+    account_id: string;
     portfolio: PortfolioTicker[];
 }
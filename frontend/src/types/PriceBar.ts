@@ -0,0 +1,20 @@
+// decimals are sent as strings; dates are RFC 3339
+export type BarInterval =
+    | "minute"
+    | "five_minutes"
+    | "fifteen_minutes"
+    | "hour"
+    | "day";
+
+export interface PriceBar {
+    ticker: string;
+    interval: BarInterval;
+    bucket_start: string;
+    open: string;
+    high: string;
+    low: string;
+    close: string;
+    volume: string;
+    trade_count: number;
+    last_trade_at: string;
+}
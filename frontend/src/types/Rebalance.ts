@@ -0,0 +1,37 @@
+import { OrderType } from "./OrderType";
+
+// decimals are sent as strings; weights are fractions of the account's total value
+export interface TargetWeight {
+    ticker: string;
+    weight: string;
+}
+
+export interface TargetAllocation {
+    targets: TargetWeight[];
+    cash_weight: string; // what the ticker weights leave over
+    drift_threshold: string | null;
+    auto_rebalance: boolean;
+}
+
+export interface ProposedOrder {
+    ticker: string;
+    order_type: OrderType;
+    quantity: string;
+    price_per_share: string;
+    current_weight: string;
+    target_weight: string;
+    order_id: string | null; // set once the proposal is submitted
+    error: string | null; // why the order could not be placed
+}
+
+export interface RebalanceProposal {
+    proposal_id: string;
+    account_id: string;
+    status: "Proposed" | "Submitted";
+    total_value: string;
+    drift: string;
+    orders: ProposedOrder[];
+    created_at: string;
+    expires_at: string;
+    submitted_at: string | null;
+}
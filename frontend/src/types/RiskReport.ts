@@ -0,0 +1,17 @@
+export type VarMethod = 'historical' | 'monte_carlo';
+
+// losses are positive amounts of money
+export interface RiskMeasure {
+    value_at_risk: number;
+    expected_shortfall: number;
+}
+
+export interface RiskReport {
+    method: VarMethod;
+    confidence: number; // e.g. 0.95
+    lookback_days: number;
+    observations: number; // days of returns available for every held ticker
+    positions_value: number;
+    one_day: RiskMeasure;
+    ten_day: RiskMeasure | null; // null when the window holds fewer than ten days of returns
+}
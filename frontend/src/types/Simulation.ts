@@ -0,0 +1,56 @@
+import { OrderType } from "./OrderType";
+import { RiskReport, VarMethod } from "./RiskReport";
+
+// decimals are sent as strings
+export interface SimulatedOrder {
+    ticker: string;
+    order_type: OrderType;
+    quantity: string;
+    price_per_share?: string; // defaults to the latest close
+}
+
+export interface SimulationRequest {
+    orders: SimulatedOrder[];
+    risk?: {
+        method?: VarMethod;
+        confidence?: number;
+        lookback_days?: number;
+        simulations?: number;
+        seed?: number;
+    };
+}
+
+export interface SimulatedFill {
+    ticker: string;
+    order_type: OrderType;
+    quantity: string;
+    price_per_share: string;
+    estimated_fee: string; // the most the order can be charged
+}
+
+export interface PositionState {
+    ticker: string;
+    quantity: string;
+    average_cost: string;
+    market_value: string;
+    weight: string; // share of total value, cash included
+}
+
+export interface PortfolioState {
+    cash: string;
+    available_cash: string;
+    positions_value: string;
+    total_value: string;
+    positions: PositionState[];
+}
+
+export interface SimulationResult {
+    orders: SimulatedFill[];
+    before: PortfolioState;
+    after: PortfolioState;
+    realized_pnl: string;
+    risk_before: RiskReport;
+    risk_after: RiskReport;
+    credit_score_before: string | null; // null when there is no loan
+    credit_score_after: string | null;
+}
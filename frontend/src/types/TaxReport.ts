@@ -0,0 +1,35 @@
+// decimals are sent as strings; dates are RFC 3339
+export type HoldingPeriod = "ShortTerm" | "LongTerm";
+
+export interface ClosedLot {
+    ticker: string;
+    order_id: string; // the sell order
+    lot_id: string | null;
+    acquired_at: string | null;
+    disposed_at: string;
+    quantity: string;
+    proceeds: string;
+    cost_basis: string;
+    gain: string;
+    holding_period: HoldingPeriod;
+    wash_sale: boolean; // shares of the ticker were bought within 30 days of a loss
+    disallowed_loss: string;
+    adjusted_gain: string; // gain with the disallowed loss added back
+}
+
+export interface GainTotals {
+    proceeds: string;
+    cost_basis: string;
+    gain: string;
+    disallowed_loss: string;
+    adjusted_gain: string;
+}
+
+export interface TaxReport {
+    account_id: string;
+    year: number;
+    lots: ClosedLot[];
+    short_term: GainTotals;
+    long_term: GainTotals;
+    total: GainTotals;
+}
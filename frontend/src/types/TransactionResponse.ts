@@ -2,10 +2,43 @@
 
 export interface Transaction {
     transaction_id: string;
-    user_id: string;
+    account_id: string;
     ticker: string;
     quantity: string;
     price_per_share: string;
     order_type: string;
+    order_id: string | null;
+    liquidity: 'Maker' | 'Taker' | null;
+    fee: string;
+    realized_pnl: string | null; // sells only
     executed_at: string;
-}
\ No newline at end of file
+}
+export type CashMovementType =
+    | 'OpeningBalance'
+    | 'Deposit'
+    | 'Withdrawal'
+    | 'TradeBuy'
+    | 'TradeSell'
+    | 'Fee'
+    | 'LoanDisbursement'
+    | 'LoanRepayment'
+    | 'Reset'
+    | 'Transfer'
+    | 'Dividend'
+    | 'Interest';
+
+export interface CashMovement {
+    entry_id: string;
+    account_id: string;
+    movement_type: CashMovementType;
+    reference_id: string | null;
+    amount: string;
+    running_balance: string;
+    created_at: string;
+}
+
+export interface AccountActivityResponse {
+    account_id: string;
+    trades: Transaction[];
+    cash_movements: CashMovement[];
+}
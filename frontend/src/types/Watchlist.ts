@@ -0,0 +1,35 @@
+// decimals are sent as strings; dates are RFC 3339
+export interface Watchlist {
+    watchlist_id: string;
+    name: string;
+    position: number;
+    tickers: string[];
+    created_at: string;
+}
+
+export interface WatchlistRequest {
+    name: string;
+    tickers: string[]; // in display order; replaces the list's tickers on update
+}
+
+export interface WatchlistOrder {
+    watchlist_ids: string[]; // every watchlist of the user, once each
+}
+
+// price fields are null for tickers without prices
+export interface WatchlistQuote {
+    ticker: string;
+    price: string | null;
+    quoted_at: string | null;
+    previous_close: string | null;
+    day_change: string | null;
+    day_change_percent: string | null;
+    volume: number | null; // traded on the latest day
+    sparkline: string[]; // one close per day, oldest first
+}
+
+export interface WatchlistQuotes {
+    watchlist_id: string;
+    name: string;
+    quotes: WatchlistQuote[];
+}